@@ -20,6 +20,25 @@ fn bench_find(b: &mut Bencher) {
     });
 }
 
+#[bench]
+fn bench_find_byte(b: &mut Bencher) {
+    let mut buf = ByteBuf::with_capacity(SIZE);
+    buf.append(u8s::filling(0, SIZE), u8s::append_fill).unwrap();
+    b.iter(|| {
+        buf.find(b"\n");
+    });
+}
+
+#[bench]
+fn bench_find_any(b: &mut Bencher) {
+    let mut buf = ByteBuf::with_capacity(SIZE);
+    buf.append(u8s::filling(0, SIZE), u8s::append_fill).unwrap();
+    let needles: [&[u8]; 2] = [b"\r\n", b"\n"];
+    b.iter(|| {
+        buf.find_any(&needles);
+    });
+}
+
 #[bench]
 fn bench_windows(b: &mut Bencher) {
     let mut buf = ByteBuf::with_capacity(SIZE);
@@ -0,0 +1,373 @@
+extern crate structopt;
+#[macro_use]
+extern crate structopt_derive;
+
+extern crate env_logger;
+#[macro_use]
+extern crate log;
+
+extern crate futures;
+extern crate ruyi;
+
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+
+use futures::{Async, AsyncSink, Future, Poll, Sink, Stream};
+use structopt::StructOpt;
+
+use ruyi::IntoTask;
+use ruyi::Task;
+use ruyi::net::tcp::{split, RecvHalf, SendHalf};
+use ruyi::proto::{Duplex, Framed};
+use ruyi::proto::mqtt::{self, reason, ConnAck, MqttCodec, Packet, Property, Protocol, Publish,
+                        SubAck, Subscription, Will};
+use ruyi::reactor::PeriodicTimer;
+use ruyi::service::tcp;
+use ruyi::service::tcp::server::{Handler, Session, ToHandler};
+use ruyi::sync::spsc::{self, SyncSender};
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "mqtt_broker", about = "A program that implements an MQTT broker.")]
+struct Opt {
+    #[structopt(short = "p", long = "port", help = "Listening port to bind",
+                default_value = "1883")]
+    port: u16,
+}
+
+// The number of messages queued for a client before more are dropped.
+const QUEUE_LEN: usize = 1024;
+
+struct Client {
+    conn: usize,
+    subscriptions: Vec<Subscription>,
+    tx: SyncSender<Publish>,
+}
+
+// The clients connected, shared by all connections as the broker runs a
+// single worker.
+#[derive(Default)]
+struct Broker {
+    clients: HashMap<String, Client>,
+    next_conn: usize,
+}
+
+impl Broker {
+    fn route(&self, publish: &Publish, from: &str) {
+        for (id, client) in &self.clients {
+            // The highest QoS of the subscriptions matching the topic.
+            let sub = client
+                .subscriptions
+                .iter()
+                .filter(|s| mqtt::topic_matches(&s.filter, &publish.topic))
+                .filter(|s| !(s.no_local && id == from))
+                .max_by_key(|s| s.qos);
+            let sub = match sub {
+                Some(sub) => sub,
+                None => continue,
+            };
+            let mut p = publish.clone();
+            p.dup = false;
+            p.qos = ::std::cmp::min(publish.qos, sub.qos);
+            p.retain = sub.retain_as_published && publish.retain;
+            p.packet_id = 0;
+            p.properties.retain(|p| match *p {
+                Property::TopicAlias(..) | Property::SubscriptionIdentifier(..) => false,
+                _ => true,
+            });
+            if client.tx.try_send(p).is_err() {
+                warn!("Dropped a message to {}", id);
+            }
+        }
+    }
+}
+
+enum Step {
+    Connecting,
+    Connected(String),
+    Closing,
+}
+
+struct Connection {
+    conn: usize,
+    framed: Framed<Duplex<RecvHalf<Session>, SendHalf<Session>>, MqttCodec>,
+    routed: Option<spsc::Recv<Publish>>,
+    session: mqtt::Session,
+    broker: Rc<RefCell<Broker>>,
+    out: VecDeque<Packet>,
+    ticks: PeriodicTimer,
+    will: Option<Will>,
+    step: Step,
+}
+
+fn protocol_error(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl Connection {
+    fn connect(&mut self, connect: mqtt::Connect) -> io::Result<()> {
+        let mut properties = Vec::new();
+        let client_id = match connect.client_id.is_empty() {
+            true => {
+                let id = format!("auto-{}", self.conn);
+                if connect.protocol == Protocol::V5 {
+                    properties.push(Property::AssignedClientIdentifier(id.clone()));
+                }
+                id
+            }
+            false => connect.client_id,
+        };
+        let (tx, rx) = spsc::sync_channel(QUEUE_LEN)?;
+        self.routed = Some(rx.recv()?);
+        // A client connecting again takes over, and the previous connection
+        // ends as its queue is dropped.
+        let client = Client {
+            conn: self.conn,
+            subscriptions: Vec::new(),
+            tx,
+        };
+        self.broker
+            .borrow_mut()
+            .clients
+            .insert(client_id.clone(), client);
+        info!("{} connected", client_id);
+        self.session.set_keep_alive(connect.keep_alive);
+        self.will = connect.will;
+        self.out.push_back(Packet::ConnAck(ConnAck {
+            session_present: false,
+            code: reason::SUCCESS,
+            properties,
+        }));
+        self.step = Step::Connected(client_id);
+        Ok(())
+    }
+
+    fn subscribe(&mut self, client_id: &str, subscribe: mqtt::Subscribe) {
+        let v5 = self.framed.codec().protocol() == Protocol::V5;
+        let mut broker = self.broker.borrow_mut();
+        let client = match broker.clients.get_mut(client_id) {
+            Some(client) => client,
+            None => return,
+        };
+        let mut codes = Vec::with_capacity(subscribe.subscriptions.len());
+        for sub in subscribe.subscriptions {
+            if !mqtt::is_valid_filter(&sub.filter) {
+                codes.push(match v5 {
+                    true => reason::TOPIC_FILTER_INVALID,
+                    false => reason::V3_SUBSCRIBE_FAILURE,
+                });
+                continue;
+            }
+            codes.push(sub.qos as u8);
+            client.subscriptions.retain(|s| s.filter != sub.filter);
+            client.subscriptions.push(sub);
+        }
+        self.out.push_back(Packet::SubAck(SubAck {
+            packet_id: subscribe.packet_id,
+            codes,
+            properties: Vec::new(),
+        }));
+    }
+
+    fn unsubscribe(&mut self, client_id: &str, unsubscribe: mqtt::Unsubscribe) {
+        let mut broker = self.broker.borrow_mut();
+        let client = match broker.clients.get_mut(client_id) {
+            Some(client) => client,
+            None => return,
+        };
+        let mut codes = Vec::with_capacity(unsubscribe.filters.len());
+        for filter in unsubscribe.filters {
+            let n = client.subscriptions.len();
+            client.subscriptions.retain(|s| s.filter != filter);
+            codes.push(match client.subscriptions.len() < n {
+                true => reason::SUCCESS,
+                false => reason::NO_SUBSCRIPTION_EXISTED,
+            });
+        }
+        self.out.push_back(Packet::UnsubAck(SubAck {
+            packet_id: unsubscribe.packet_id,
+            codes,
+            properties: Vec::new(),
+        }));
+    }
+
+    fn handle(&mut self, packet: Packet) -> io::Result<()> {
+        let client_id = match self.step {
+            Step::Connecting => match packet {
+                Packet::Connect(connect) => return self.connect(connect),
+                _ => return Err(protocol_error("Expected CONNECT")),
+            },
+            Step::Connected(ref client_id) => client_id.clone(),
+            Step::Closing => return Ok(()),
+        };
+        debug!("{} sent {}", client_id, packet);
+        match packet {
+            Packet::Connect(..) => return Err(protocol_error("Unexpected CONNECT")),
+            Packet::Subscribe(subscribe) => self.subscribe(&client_id, subscribe),
+            Packet::Unsubscribe(unsubscribe) => self.unsubscribe(&client_id, unsubscribe),
+            Packet::Disconnect(disconnect) => {
+                if disconnect.code != reason::DISCONNECT_WITH_WILL {
+                    self.will = None;
+                }
+                self.step = Step::Closing;
+            }
+            packet => {
+                let (delivered, reply) = self.session.recv(packet);
+                if let Some(Packet::Publish(publish)) = delivered {
+                    if !mqtt::is_valid_topic(&publish.topic) {
+                        return Err(protocol_error("Invalid topic name"));
+                    }
+                    self.broker.borrow().route(&publish, &client_id);
+                }
+                self.out.extend(reply);
+            }
+        }
+        Ok(())
+    }
+
+    fn poll_flush(&mut self) -> Poll<(), io::Error> {
+        while let Some(packet) = self.out.pop_front() {
+            if let AsyncSink::NotReady(packet) = self.framed.start_send(packet)? {
+                self.out.push_front(packet);
+                return Ok(Async::NotReady);
+            }
+            self.session.sent();
+        }
+        self.framed.poll_complete()
+    }
+}
+
+impl Future for Connection {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<(), io::Error> {
+        loop {
+            if let Step::Closing = self.step {
+                return self.poll_flush();
+            }
+            match self.framed.poll()? {
+                Async::Ready(Some(packet)) => self.handle(packet)?,
+                Async::Ready(None) => return Ok(Async::Ready(())),
+                Async::NotReady => break,
+            }
+        }
+        if let Some(ref mut routed) = self.routed {
+            loop {
+                match routed.poll()? {
+                    Async::Ready(Some(publish)) => {
+                        let packet = self.session.publish(publish)?;
+                        self.out.push_back(packet);
+                    }
+                    Async::Ready(None) => {
+                        info!("Session taken over");
+                        return Ok(Async::Ready(()));
+                    }
+                    Async::NotReady => break,
+                }
+            }
+        }
+        while let Ok(Async::Ready(Some(()))) = self.ticks.poll() {
+            if self.session.is_expired() {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "Keep alive timed out"));
+            }
+        }
+        self.poll_flush()?;
+        Ok(Async::NotReady)
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let client_id = match self.step {
+            Step::Connected(ref client_id) => client_id,
+            _ => return,
+        };
+        let mut broker = self.broker.borrow_mut();
+        if broker.clients.get(client_id).map(|c| c.conn) == Some(self.conn) {
+            broker.clients.remove(client_id);
+            info!("{} disconnected", client_id);
+        }
+        if let Some(will) = self.will.take() {
+            let mut publish = Publish::new(&will.topic, will.qos, will.payload.into());
+            publish.retain = will.retain;
+            broker.route(&publish, client_id);
+        }
+    }
+}
+
+struct Mqtt {
+    broker: Rc<RefCell<Broker>>,
+}
+
+impl Mqtt {
+    fn task(&mut self, session: Session) -> io::Result<Task> {
+        session.as_ref().set_nodelay(true)?;
+        let peer = session.peer_addr()?;
+        let (r, w) = split(session)?;
+        let conn = {
+            let mut broker = self.broker.borrow_mut();
+            broker.next_conn += 1;
+            broker.next_conn
+        };
+        let conn = Connection {
+            conn,
+            framed: MqttCodec::new().frames(Duplex::new(r, w)),
+            routed: None,
+            session: mqtt::Session::new(),
+            broker: self.broker.clone(),
+            out: VecDeque::new(),
+            ticks: PeriodicTimer::new(Duration::from_secs(1), Duration::from_secs(1)),
+            will: None,
+            step: Step::Connecting,
+        };
+        Ok(conn.map_err(move |e| warn!("{}: {}", peer, e)).into_task())
+    }
+}
+
+impl Handler for Mqtt {
+    fn handle(&mut self, session: Session) -> Option<Task> {
+        match self.task(session) {
+            Ok(t) => Some(t),
+            Err(e) => {
+                error!("{}", e);
+                None
+            }
+        }
+    }
+}
+
+// Creates the handler of the worker, which owns the state of the broker.
+struct ToMqtt;
+
+impl ToHandler for ToMqtt {
+    type Handler = Mqtt;
+
+    fn to_handler(&self) -> Mqtt {
+        Mqtt {
+            broker: Rc::new(RefCell::new(Broker::default())),
+        }
+    }
+}
+
+fn main() {
+    let opt = Opt::from_args();
+
+    // Initialize logger
+    env_logger::init().unwrap();
+
+    ruyi::net::init();
+
+    // All clients are served by one worker to share their subscriptions.
+    match tcp::Server::with_handler(ToMqtt)
+        .port(opt.port)
+        .num_of_workers(1)
+        .start()
+    {
+        Ok(()) => thread::park(),
+        Err(e) => error!("{}", e),
+    }
+}
@@ -0,0 +1,408 @@
+//! `#[derive(RuyiEncode, RuyiDecode)]` for binary message structs.
+//!
+//! The derived impls implement `ruyi::buf::codec::{Encode, Decode}`, so a
+//! message is written with `buf.append(&msg, codec::encode)` and read back
+//! with `buf.read(codec::decode)`. Fields are encoded in declaration order.
+//!
+//! Attributes, on the struct or on a field:
+//!
+//! * `#[ruyi(be)]`, `#[ruyi(le)]`, `#[ruyi(varint)]` - number encoding.
+//!   On the struct it is the default for all multi-byte number fields.
+//! * `#[ruyi(len = "u8")]` - a `Vec<T>` prefixed by its number of elements;
+//!   one of `u8`, `u16be`, `u16le`, `u32be`, `u32le` or `varint`.
+//! * `#[ruyi(fixed = 16)]` - exactly that many raw bytes, for `[u8; N]` or
+//!   `Vec<u8>` fields.
+//! * `#[ruyi(skip)]` - not encoded; decoded as `Default::default()`.
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use syn::{Data, DeriveInput, Error, Fields, Index, Lit, Meta, NestedMeta, Type};
+
+#[proc_macro_derive(RuyiEncode, attributes(ruyi))]
+pub fn derive_encode(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    expand_encode(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+#[proc_macro_derive(RuyiDecode, attributes(ruyi))]
+pub fn derive_decode(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    expand_decode(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Num {
+    Raw,
+    Be,
+    Le,
+    Varint,
+}
+
+#[derive(Clone, Copy)]
+enum Len {
+    U8,
+    U16be,
+    U16le,
+    U32be,
+    U32le,
+    Varint,
+}
+
+#[derive(Clone, Copy)]
+struct Attrs {
+    num: Option<Num>,
+    len: Option<Len>,
+    fixed: Option<usize>,
+    skip: bool,
+}
+
+impl Attrs {
+    fn parse(attrs: &[syn::Attribute]) -> Result<Self, Error> {
+        let mut parsed = Attrs {
+            num: None,
+            len: None,
+            fixed: None,
+            skip: false,
+        };
+        for attr in attrs {
+            if !attr.path.is_ident("ruyi") {
+                continue;
+            }
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                meta => return Err(Error::new_spanned(meta, "expected #[ruyi(...)]")),
+            };
+            for nested in list.nested {
+                match nested {
+                    NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("be") => {
+                        parsed.num = Some(Num::Be)
+                    }
+                    NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("le") => {
+                        parsed.num = Some(Num::Le)
+                    }
+                    NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("varint") => {
+                        parsed.num = Some(Num::Varint)
+                    }
+                    NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("skip") => {
+                        parsed.skip = true
+                    }
+                    NestedMeta::Meta(Meta::NameValue(ref nv)) if nv.path.is_ident("len") => {
+                        parsed.len = Some(match nv.lit {
+                            Lit::Str(ref s) => match s.value().as_str() {
+                                "u8" => Len::U8,
+                                "u16be" => Len::U16be,
+                                "u16le" => Len::U16le,
+                                "u32be" => Len::U32be,
+                                "u32le" => Len::U32le,
+                                "varint" => Len::Varint,
+                                _ => return Err(Error::new_spanned(&nv.lit, "unknown length prefix")),
+                            },
+                            _ => return Err(Error::new_spanned(&nv.lit, "expected a string")),
+                        })
+                    }
+                    NestedMeta::Meta(Meta::NameValue(ref nv)) if nv.path.is_ident("fixed") => {
+                        parsed.fixed = Some(match nv.lit {
+                            Lit::Int(ref n) => n.base10_parse()?,
+                            _ => return Err(Error::new_spanned(&nv.lit, "expected an integer")),
+                        })
+                    }
+                    other => return Err(Error::new_spanned(other, "unknown ruyi attribute")),
+                }
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+struct Field {
+    // `name` or `0`
+    member: TokenStream2,
+    ty: Type,
+    attrs: Attrs,
+    // number encoding of the struct
+    default_num: Num,
+}
+
+impl Field {
+    // Number encoding of `ty`, the field type or its element type.
+    fn num_of(&self, ty: &Type) -> Num {
+        match self.attrs.num {
+            Some(num) => num,
+            None if is_wide_num(ty) => self.default_num,
+            None => Num::Raw,
+        }
+    }
+}
+
+fn fields_of(input: &DeriveInput) -> Result<(Vec<Field>, &Fields), Error> {
+    let data = match input.data {
+        Data::Struct(ref data) => data,
+        _ => {
+            return Err(Error::new(
+                Span::call_site(),
+                "RuyiEncode and RuyiDecode only support structs",
+            ))
+        }
+    };
+    let default_num = Attrs::parse(&input.attrs)?.num.unwrap_or(Num::Raw);
+    let mut fields = Vec::new();
+    for (i, f) in data.fields.iter().enumerate() {
+        let member = match f.ident {
+            Some(ref ident) => quote!(#ident),
+            None => {
+                let index = Index::from(i);
+                quote!(#index)
+            }
+        };
+        let attrs = Attrs::parse(&f.attrs)?;
+        if attrs.len.is_some() && attrs.fixed.is_some() {
+            return Err(Error::new_spanned(f, "`len` and `fixed` are mutually exclusive"));
+        }
+        fields.push(Field {
+            member,
+            ty: f.ty.clone(),
+            attrs,
+            default_num,
+        });
+    }
+    Ok((fields, &data.fields))
+}
+
+fn add_bounds(input: &DeriveInput, bound: TokenStream2) -> syn::Generics {
+    let mut generics = input.generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(syn::parse2(bound.clone()).unwrap());
+    }
+    generics
+}
+
+// Element type of `Vec<T>`
+fn vec_elem(ty: &Type) -> Option<&Type> {
+    if let Type::Path(ref p) = *ty {
+        let seg = p.path.segments.last()?;
+        if seg.ident == "Vec" {
+            if let syn::PathArguments::AngleBracketed(ref args) = seg.arguments {
+                if let Some(syn::GenericArgument::Type(ref t)) = args.args.first() {
+                    return Some(t);
+                }
+            }
+        }
+    }
+    None
+}
+
+fn is_u8(ty: &Type) -> bool {
+    match *ty {
+        Type::Path(ref p) => p.path.is_ident("u8"),
+        _ => false,
+    }
+}
+
+const WIDE_NUMS: &[&str] = &[
+    "u16", "i16", "u32", "i32", "u64", "i64", "u128", "i128", "f32", "f64",
+];
+
+fn is_wide_num(ty: &Type) -> bool {
+    match *ty {
+        Type::Path(ref p) => WIDE_NUMS.iter().any(|t| p.path.is_ident(t)),
+        _ => false,
+    }
+}
+
+fn wrapper(num: Num) -> Option<TokenStream2> {
+    match num {
+        Num::Raw => None,
+        Num::Be => Some(quote!(::ruyi::buf::codec::Be)),
+        Num::Le => Some(quote!(::ruyi::buf::codec::Le)),
+        Num::Varint => Some(quote!(::ruyi::buf::codec::Varint)),
+    }
+}
+
+fn len_codec(len: Len) -> (TokenStream2, TokenStream2, TokenStream2) {
+    match len {
+        Len::U8 => (quote!(u8), quote!(::ruyi::buf::codec::u8), quote!(::std::u8::MAX)),
+        Len::U16be => (
+            quote!(u16),
+            quote!(::ruyi::buf::codec::u16::big_endian),
+            quote!(::std::u16::MAX),
+        ),
+        Len::U16le => (
+            quote!(u16),
+            quote!(::ruyi::buf::codec::u16::little_endian),
+            quote!(::std::u16::MAX),
+        ),
+        Len::U32be => (
+            quote!(u32),
+            quote!(::ruyi::buf::codec::u32::big_endian),
+            quote!(::std::u32::MAX),
+        ),
+        Len::U32le => (
+            quote!(u32),
+            quote!(::ruyi::buf::codec::u32::little_endian),
+            quote!(::std::u32::MAX),
+        ),
+        Len::Varint => (
+            quote!(u32),
+            quote!(::ruyi::buf::codec::u32::varint),
+            quote!(::std::u32::MAX),
+        ),
+    }
+}
+
+// Expression encoding the value behind the reference `v`.
+fn encode_value(v: TokenStream2, num: Num) -> TokenStream2 {
+    match wrapper(num) {
+        Some(w) => quote!(::ruyi::buf::codec::Encode::encode(&#w(*#v), chain)?),
+        None => quote!(::ruyi::buf::codec::Encode::encode(#v, chain)?),
+    }
+}
+
+fn decode_value(ty: &Type, num: Num) -> TokenStream2 {
+    match wrapper(num) {
+        Some(w) => quote!(<#w<#ty> as ::ruyi::buf::codec::Decode>::decode(chain)?.0),
+        None => quote!(<#ty as ::ruyi::buf::codec::Decode>::decode(chain)?),
+    }
+}
+
+fn encode_field(f: &Field) -> Result<TokenStream2, Error> {
+    let member = &f.member;
+    if f.attrs.skip {
+        return Ok(quote!());
+    }
+    if let Some(n) = f.attrs.fixed {
+        return Ok(quote! {
+            if self.#member.len() != #n {
+                return Err(());
+            }
+            n += ::ruyi::buf::codec::u8s::append(&self.#member[..], chain)?;
+        });
+    }
+    if let Some(len) = f.attrs.len {
+        let elem = vec_elem(&f.ty)
+            .ok_or_else(|| Error::new_spanned(&f.ty, "`len` requires a `Vec<T>` field"))?;
+        let (len_ty, codec, max) = len_codec(len);
+        let prefix = quote! {
+            if self.#member.len() > #max as usize {
+                return Err(());
+            }
+            n += #codec::append(self.#member.len() as #len_ty, chain)?;
+        };
+        return Ok(if is_u8(elem) {
+            quote! {
+                #prefix
+                n += ::ruyi::buf::codec::u8s::append(&self.#member, chain)?;
+            }
+        } else {
+            let e = encode_value(quote!(v), f.num_of(elem));
+            quote! {
+                #prefix
+                for v in self.#member.iter() {
+                    n += #e;
+                }
+            }
+        });
+    }
+    let e = encode_value(quote!(&self.#member), f.num_of(&f.ty));
+    Ok(quote!(n += #e;))
+}
+
+fn decode_field(f: &Field) -> Result<TokenStream2, Error> {
+    let ty = &f.ty;
+    if f.attrs.skip {
+        return Ok(quote!(::std::default::Default::default()));
+    }
+    if let Some(n) = f.attrs.fixed {
+        return Ok(match *ty {
+            Type::Array(..) => quote! {{
+                let bytes = ::ruyi::buf::codec::u8s::read_exact(chain, #n)?;
+                let mut a = [0u8; #n];
+                a.copy_from_slice(&bytes);
+                a
+            }},
+            _ => quote!(::ruyi::buf::codec::u8s::read_exact(chain, #n)?),
+        });
+    }
+    if let Some(len) = f.attrs.len {
+        let elem = vec_elem(ty)
+            .ok_or_else(|| Error::new_spanned(ty, "`len` requires a `Vec<T>` field"))?;
+        let (_, codec, _) = len_codec(len);
+        return Ok(if is_u8(elem) {
+            quote! {{
+                let len = #codec::read(chain)? as usize;
+                if chain.len() < len {
+                    return Err(::ruyi::buf::Error::Underflow);
+                }
+                ::ruyi::buf::codec::u8s::read_exact(chain, len)?
+            }}
+        } else {
+            let d = decode_value(elem, f.num_of(elem));
+            quote! {{
+                let len = #codec::read(chain)? as usize;
+                let mut v = Vec::with_capacity(::std::cmp::min(len, chain.len()));
+                for _ in 0..len {
+                    v.push(#d);
+                }
+                v
+            }}
+        });
+    }
+    Ok(decode_value(ty, f.num_of(ty)))
+}
+
+fn expand_encode(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let (fields, _) = fields_of(input)?;
+    let name = &input.ident;
+    let generics = add_bounds(input, quote!(::ruyi::buf::codec::Encode));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let mut body = Vec::with_capacity(fields.len());
+    for f in &fields {
+        body.push(encode_field(f)?);
+    }
+    Ok(quote! {
+        impl #impl_generics ::ruyi::buf::codec::Encode for #name #ty_generics #where_clause {
+            #[allow(unused_mut)]
+            fn encode(&self, chain: &mut ::ruyi::buf::Appender) -> Result<usize, ()> {
+                let mut n = 0;
+                #(#body)*
+                Ok(n)
+            }
+        }
+    })
+}
+
+fn expand_decode(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let (fields, shape) = fields_of(input)?;
+    let name = &input.ident;
+    let generics = add_bounds(input, quote!(::ruyi::buf::codec::Decode));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let mut values = Vec::with_capacity(fields.len());
+    for f in &fields {
+        values.push(decode_field(f)?);
+    }
+    let ctor = match *shape {
+        Fields::Named(..) => {
+            let members = fields.iter().map(|f| &f.member);
+            quote!(#name { #(#members: #values),* })
+        }
+        Fields::Unnamed(..) => quote!(#name(#(#values),*)),
+        Fields::Unit => quote!(#name),
+    };
+    Ok(quote! {
+        impl #impl_generics ::ruyi::buf::codec::Decode for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn decode(chain: &mut ::ruyi::buf::ReadIter) -> Result<Self, ::ruyi::buf::Error> {
+                Ok(#ctor)
+            }
+        }
+    })
+}
@@ -0,0 +1,115 @@
+extern crate ruyi;
+#[macro_use]
+extern crate ruyi_derive;
+
+use ruyi::buf::{ByteBuf, Error};
+use ruyi::buf::codec::{self, u16, u32, u8, Be};
+
+#[derive(Debug, PartialEq, RuyiEncode, RuyiDecode)]
+#[ruyi(be)]
+struct Header {
+    magic: u32,
+    #[ruyi(le)]
+    version: u16,
+    #[ruyi(varint)]
+    seq: u64,
+    flags: u8,
+}
+
+#[derive(Debug, PartialEq, RuyiEncode, RuyiDecode)]
+struct Packet {
+    header: Header,
+    #[ruyi(len = "u16be")]
+    payload: Vec<u8>,
+    #[ruyi(len = "varint", be)]
+    acks: Vec<u32>,
+    #[ruyi(fixed = 4)]
+    digest: [u8; 4],
+    #[ruyi(fixed = 2)]
+    trailer: Vec<u8>,
+    tag: Option<Be<u16>>,
+    #[ruyi(skip)]
+    cached: usize,
+}
+
+#[derive(Debug, PartialEq, RuyiEncode, RuyiDecode)]
+struct Pair<T>(T, #[ruyi(len = "u8")] Vec<T>);
+
+#[derive(Debug, PartialEq, RuyiEncode, RuyiDecode)]
+struct Empty;
+
+#[derive(Debug, PartialEq, RuyiEncode, RuyiDecode)]
+struct Blob(#[ruyi(len = "u32be")] Vec<u8>);
+
+fn packet() -> Packet {
+    Packet {
+        header: Header {
+            magic: 0xCAFEBABE,
+            version: 0x0102,
+            seq: 300,
+            flags: 0x80,
+        },
+        payload: b"hello".to_vec(),
+        acks: vec![1, 0x01020304],
+        digest: [1, 2, 3, 4],
+        trailer: vec![0xFF, 0xFE],
+        tag: Some(Be(7)),
+        cached: 0,
+    }
+}
+
+#[test]
+fn derive_layout() {
+    let mut buf = ByteBuf::with_growth(1);
+    let n = buf.append(&packet(), codec::encode).unwrap();
+    assert_eq!(n, buf.len());
+    assert_eq!(n, (4 + 2 + 2 + 1) + (2 + 5) + (1 + 8) + 4 + 2 + 3);
+
+    assert_eq!(0xCAFEBABE, buf.get(0, u32::big_endian::get).unwrap());
+    assert_eq!(0x0102, buf.get(4, u16::little_endian::get).unwrap());
+    assert_eq!(300, buf.get(6, u32::varint::get).unwrap());
+    assert_eq!(0x80, buf.get(8, u8::get).unwrap());
+    assert_eq!(5, buf.get(9, u16::big_endian::get).unwrap());
+    assert_eq!(2, buf.get(16, u8::get).unwrap());
+    assert_eq!(0x01020304, buf.get(21, u32::big_endian::get).unwrap());
+}
+
+#[test]
+fn derive_round_trip() {
+    let mut msg = packet();
+    msg.cached = 42;
+    let mut buf = ByteBuf::with_growth(1);
+    buf.append(&msg, codec::encode).unwrap();
+    buf.append(&Pair(Be(3u16), vec![Be(4), Be(5)]), codec::encode)
+        .unwrap();
+    buf.append(&Empty, codec::encode).unwrap();
+
+    let decoded: Packet = buf.read(codec::decode).unwrap();
+    msg.cached = 0;
+    assert_eq!(msg, decoded);
+
+    let pair: Pair<Be<u16>> = buf.read(codec::decode).unwrap();
+    assert_eq!(Pair(Be(3), vec![Be(4), Be(5)]), pair);
+
+    let empty: Empty = buf.read(codec::decode).unwrap();
+    assert_eq!(Empty, empty);
+    assert!(buf.is_empty());
+}
+
+#[test]
+fn derive_bad_fixed_len() {
+    let mut msg = packet();
+    msg.trailer.push(0);
+    let mut buf = ByteBuf::new();
+    assert!(buf.append(&msg, codec::encode).is_err());
+}
+
+#[test]
+fn derive_oversized_len() {
+    // A length prefix past the data fails before anything is allocated.
+    let mut buf = ByteBuf::from(vec![0xFF, 0xFF, 0xFF, 0xFF, 1, 2, 3]);
+    match buf.read::<Blob, _, _>(codec::decode) {
+        Err(Error::Underflow) => (),
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
@@ -59,6 +59,8 @@ impl<'a> Appender<'a> {
         }
     }
 
+    /// Adds a block of at least `min_capacity` bytes regardless of the limit
+    /// of the buffer.
     pub fn append(&mut self, min_capacity: usize) {
         self.inner.append_block(min_capacity)
     }
@@ -66,4 +68,19 @@ impl<'a> Appender<'a> {
     pub fn append_bytes(&mut self, bytes: Vec<u8>) {
         self.inner.append_bytes(bytes)
     }
+
+    /// Adds a block of at least `min_capacity` bytes, or fails if the limit
+    /// of the buffer does not allow it.
+    pub fn try_append(&mut self, min_capacity: usize) -> Result<(), ()> {
+        self.inner.try_append_block(min_capacity)
+    }
+
+    pub fn try_append_bytes(&mut self, bytes: Vec<u8>) -> Result<(), ()> {
+        self.inner.try_append_bytes(bytes)
+    }
+
+    #[inline]
+    pub(super) fn buf(&self) -> &ByteBuf {
+        self.inner
+    }
 }
@@ -0,0 +1,241 @@
+use super::ByteBuf;
+use super::Error;
+use super::codec::u8;
+
+/// The order in which the bits of each byte are read or written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOrder {
+    /// The most significant bit first, as in H.264 and most network formats.
+    Msb,
+    /// The least significant bit first, as in deflate.
+    Lsb,
+}
+
+/// Reads bits from the front of a `ByteBuf`.
+///
+/// A byte is consumed from the buffer as soon as its first bit is read, so
+/// the bits left in it are lost if the reader is dropped before `align`.
+pub struct BitReader<'a> {
+    inner: &'a mut ByteBuf,
+    order: BitOrder,
+    cur: u8,
+    // bits not yet read in `cur`
+    left: u32,
+}
+
+/// Appends bits to a `ByteBuf`.
+///
+/// A partially written byte is padded with zero bits and appended by
+/// `align`, or when the writer is dropped.
+pub struct BitWriter<'a> {
+    inner: &'a mut ByteBuf,
+    order: BitOrder,
+    cur: u8,
+    // bits already written in `cur`
+    filled: u32,
+}
+
+#[inline]
+pub(super) fn reader<'a>(inner: &'a mut ByteBuf, order: BitOrder) -> BitReader<'a> {
+    BitReader {
+        inner,
+        order,
+        cur: 0,
+        left: 0,
+    }
+}
+
+#[inline]
+pub(super) fn writer<'a>(inner: &'a mut ByteBuf, order: BitOrder) -> BitWriter<'a> {
+    BitWriter {
+        inner,
+        order,
+        cur: 0,
+        filled: 0,
+    }
+}
+
+// `n` is at most 8 as bits are taken a byte at a time.
+#[inline]
+fn mask(n: u32) -> u64 {
+    (1 << n) - 1
+}
+
+impl<'a> BitReader<'a> {
+    #[inline]
+    pub fn order(&self) -> BitOrder {
+        self.order
+    }
+
+    /// Returns the number of bits that can still be read.
+    #[inline]
+    pub fn bits_left(&self) -> usize {
+        self.left as usize + self.inner.len() * 8
+    }
+
+    #[inline]
+    pub fn is_aligned(&self) -> bool {
+        self.left == 0
+    }
+
+    /// Discards the bits left in the current byte.
+    #[inline]
+    pub fn align(&mut self) {
+        self.left = 0;
+    }
+
+    #[inline]
+    pub fn read_bit(&mut self) -> Result<bool, Error> {
+        self.read_bits(1).map(|b| b != 0)
+    }
+
+    /// Reads `n` bits, at most 64. Nothing is consumed on underflow.
+    ///
+    /// In `Msb` order the first bit read is the most significant of the
+    /// result; in `Lsb` order it is the least significant.
+    pub fn read_bits(&mut self, n: u32) -> Result<u64, Error> {
+        assert!(n <= 64, "Cannot read more than 64 bits at a time");
+        if (n as usize) > self.bits_left() {
+            return Err(Error::Underflow);
+        }
+        let mut v = 0u64;
+        let mut done = 0;
+        while done < n {
+            if self.left == 0 {
+                self.cur = self.inner.read(u8::read)?;
+                self.left = 8;
+            }
+            let k = if self.left < n - done {
+                self.left
+            } else {
+                n - done
+            };
+            let cur = self.cur as u64;
+            match self.order {
+                BitOrder::Msb => {
+                    let chunk = (cur >> (self.left - k)) & mask(k);
+                    v = v << k | chunk;
+                }
+                BitOrder::Lsb => {
+                    let chunk = (cur >> (8 - self.left)) & mask(k);
+                    v |= chunk << done;
+                }
+            }
+            self.left -= k;
+            done += k;
+        }
+        Ok(v)
+    }
+
+    /// Reads an unsigned Exp-Golomb code, `ue(v)` in H.264.
+    pub fn read_exp_golomb(&mut self) -> Result<u64, Error> {
+        let mut zeros = 0;
+        while !self.read_bit()? {
+            zeros += 1;
+            if zeros > 63 {
+                return Err(Error::InvalidEncoding);
+            }
+        }
+        let mut v = 1u64;
+        for _ in 0..zeros {
+            v = v << 1 | self.read_bit()? as u64;
+        }
+        Ok(v - 1)
+    }
+
+    /// Reads a signed Exp-Golomb code, `se(v)` in H.264.
+    pub fn read_signed_exp_golomb(&mut self) -> Result<i64, Error> {
+        let k = self.read_exp_golomb()?;
+        match k & 1 {
+            1 => Ok((k / 2 + 1) as i64),
+            _ => Ok(-((k / 2) as i64)),
+        }
+    }
+}
+
+impl<'a> BitWriter<'a> {
+    #[inline]
+    pub fn order(&self) -> BitOrder {
+        self.order
+    }
+
+    #[inline]
+    pub fn is_aligned(&self) -> bool {
+        self.filled == 0
+    }
+
+    /// Pads the current byte with zero bits and appends it, if any.
+    pub fn align(&mut self) -> Result<(), ()> {
+        if self.filled > 0 {
+            let cur = self.cur;
+            self.cur = 0;
+            self.filled = 0;
+            self.inner.append(cur, u8::append)?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn write_bit(&mut self, bit: bool) -> Result<(), ()> {
+        self.write_bits(bit as u64, 1)
+    }
+
+    /// Writes the low `n` bits of `v`, at most 64, in the writer's order.
+    pub fn write_bits(&mut self, v: u64, n: u32) -> Result<(), ()> {
+        assert!(n <= 64, "Cannot write more than 64 bits at a time");
+        let mut done = 0;
+        while done < n {
+            let space = 8 - self.filled;
+            let k = if space < n - done { space } else { n - done };
+            let chunk = match self.order {
+                BitOrder::Msb => (v >> (n - done - k)) & mask(k),
+                BitOrder::Lsb => (v >> done) & mask(k),
+            } as u8;
+            self.cur |= match self.order {
+                BitOrder::Msb => chunk << (space - k),
+                BitOrder::Lsb => chunk << self.filled,
+            };
+            self.filled += k;
+            done += k;
+            if self.filled == 8 {
+                self.align()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes an unsigned Exp-Golomb code, `ue(v)` in H.264.
+    pub fn write_exp_golomb(&mut self, v: u64) -> Result<(), ()> {
+        if v == u64::max_value() {
+            return Err(());
+        }
+        let v = v + 1;
+        let len = 64 - v.leading_zeros();
+        for _ in 1..len {
+            self.write_bit(false)?;
+        }
+        for i in (0..len).rev() {
+            self.write_bit(v >> i & 1 != 0)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a signed Exp-Golomb code, `se(v)` in H.264.
+    pub fn write_signed_exp_golomb(&mut self, v: i64) -> Result<(), ()> {
+        if v == i64::min_value() {
+            return Err(());
+        }
+        let k = if v > 0 {
+            (v as u64) * 2 - 1
+        } else {
+            (-v) as u64 * 2
+        };
+        self.write_exp_golomb(k)
+    }
+}
+
+impl<'a> Drop for BitWriter<'a> {
+    fn drop(&mut self) {
+        let _ = self.align();
+    }
+}
@@ -0,0 +1,46 @@
+use buf::checksum::Checksum;
+
+const MOD: u32 = 65_521;
+
+// The most bytes that can be summed before `b` may overflow a u32.
+const NMAX: usize = 5_552;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Adler32 {
+    a: u32,
+    b: u32,
+}
+
+impl Adler32 {
+    #[inline]
+    pub fn new() -> Self {
+        Adler32 { a: 1, b: 0 }
+    }
+}
+
+impl Default for Adler32 {
+    #[inline]
+    fn default() -> Self {
+        Adler32::new()
+    }
+}
+
+impl Checksum for Adler32 {
+    type Sum = u32;
+
+    fn update(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(NMAX) {
+            for &x in chunk {
+                self.a += x as u32;
+                self.b += self.a;
+            }
+            self.a %= MOD;
+            self.b %= MOD;
+        }
+    }
+
+    #[inline]
+    fn sum(&self) -> u32 {
+        self.b << 16 | self.a
+    }
+}
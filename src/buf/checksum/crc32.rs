@@ -0,0 +1,75 @@
+use buf::checksum::Checksum;
+
+const fn make_table(poly: u32) -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            crc = if crc & 1 != 0 { crc >> 1 ^ poly } else { crc >> 1 };
+            k += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+// Reversed polynomials of CRC-32 (IEEE 802.3) and CRC-32C (Castagnoli).
+static CRC32_TABLE: [u32; 256] = make_table(0xEDB8_8320);
+static CRC32C_TABLE: [u32; 256] = make_table(0x82F6_3B78);
+
+#[inline]
+fn update(table: &[u32; 256], crc: u32, bytes: &[u8]) -> u32 {
+    bytes.iter().fold(crc, |crc, &b| {
+        table[((crc ^ b as u32) & 0xFF) as usize] ^ crc >> 8
+    })
+}
+
+/// CRC-32 as used by Ethernet, zlib and gzip.
+#[derive(Debug, Clone, Copy)]
+pub struct Crc32 {
+    crc: u32,
+}
+
+/// CRC-32C (Castagnoli) as used by iSCSI, SCTP and ext4.
+#[derive(Debug, Clone, Copy)]
+pub struct Crc32c {
+    crc: u32,
+}
+
+macro_rules! impl_crc {
+    ($t:ident, $table:ident) => {
+        impl $t {
+            #[inline]
+            pub fn new() -> Self {
+                $t { crc: !0 }
+            }
+        }
+
+        impl Default for $t {
+            #[inline]
+            fn default() -> Self {
+                $t::new()
+            }
+        }
+
+        impl Checksum for $t {
+            type Sum = u32;
+
+            #[inline]
+            fn update(&mut self, bytes: &[u8]) {
+                self.crc = update(&$table, self.crc, bytes);
+            }
+
+            #[inline]
+            fn sum(&self) -> u32 {
+                !self.crc
+            }
+        }
+    };
+}
+
+impl_crc!(Crc32, CRC32_TABLE);
+impl_crc!(Crc32c, CRC32C_TABLE);
@@ -0,0 +1,117 @@
+//! Checksums and non-cryptographic hashes computed block by block, without
+//! flattening the buffer.
+//!
+//! Each algorithm is available as an incremental type implementing
+//! `Checksum`, and as `get`-style functions over the rest of a buffer or the
+//! next `len` bytes of it:
+//!
+//! ```ignore
+//! let crc = buf.get(0, checksum::crc32)?;
+//! let hash = buf.get_exact(4, len, checksum::xxh64_exact)?;
+//! ```
+
+mod adler32;
+pub use self::adler32::Adler32;
+
+mod crc32;
+pub use self::crc32::{Crc32, Crc32c};
+
+mod xxhash;
+pub use self::xxhash::{Xxh32, Xxh64};
+
+use std::cmp;
+use std::slice;
+
+use buf::{Appender, Error, GetIter};
+use buf::codec::u32;
+
+pub trait Checksum {
+    type Sum;
+
+    fn update(&mut self, bytes: &[u8]);
+
+    fn sum(&self) -> Self::Sum;
+}
+
+// Feeds the next `len` bytes of `chain`, or all of them if `None`, to `c`.
+fn digest<C>(mut c: C, chain: &mut GetIter, len: Option<usize>) -> Result<C::Sum, Error>
+where
+    C: Checksum,
+{
+    let mut n = len.unwrap_or(usize::max_value());
+    for block in chain {
+        if n == 0 {
+            break;
+        }
+        let off = block.read_pos() as isize;
+        let m = cmp::min(block.len(), n);
+        c.update(unsafe { slice::from_raw_parts(block.as_ptr().offset(off), m) });
+        n -= m;
+    }
+    match len {
+        Some(..) if n > 0 => Err(Error::IndexOutOfBounds),
+        _ => Ok(c.sum()),
+    }
+}
+
+macro_rules! get_fns {
+    ($name:ident, $name_exact:ident, $t:ident, $sum:ty) => {
+        #[inline]
+        pub fn $name(chain: &mut GetIter) -> Result<$sum, Error> {
+            digest($t::new(), chain, None)
+        }
+
+        #[inline]
+        pub fn $name_exact(chain: &mut GetIter, len: usize) -> Result<$sum, Error> {
+            digest($t::new(), chain, Some(len))
+        }
+    };
+}
+
+get_fns!(crc32, crc32_exact, Crc32, u32);
+get_fns!(crc32c, crc32c_exact, Crc32c, u32);
+get_fns!(adler32, adler32_exact, Adler32, u32);
+get_fns!(xxh32, xxh32_exact, Xxh32, u32);
+get_fns!(xxh64, xxh64_exact, Xxh64, u64);
+
+/// Appends the big-endian CRC32 of the bytes from index `start` to the end.
+///
+/// `buf.append(0, checksum::append_crc32)` seals a whole frame.
+pub fn append_crc32(start: usize, chain: &mut Appender) -> Result<usize, ()> {
+    let crc = {
+        let buf = chain.buf();
+        let len = buf.len();
+        if start > len {
+            return Err(());
+        }
+        if start == len {
+            Crc32::new().sum()
+        } else {
+            buf.get(start, crc32).map_err(|_| ())?
+        }
+    };
+    u32::big_endian::append(crc, chain)
+}
+
+/// Checks that the last 4 bytes hold the big-endian CRC32 of the bytes
+/// before them, as written by `append_crc32`. Nothing is consumed.
+pub fn verify_crc32(chain: &mut GetIter) -> Result<bool, Error> {
+    let len = chain.len();
+    if len < 4 {
+        return Err(Error::IndexOutOfBounds);
+    }
+    let mut c = Crc32::new();
+    let mut n = len - 4;
+    let mut trailer = 0u32;
+    for block in chain {
+        let off = block.read_pos() as isize;
+        let s = unsafe { slice::from_raw_parts(block.as_ptr().offset(off), block.len()) };
+        let m = cmp::min(s.len(), n);
+        c.update(&s[..m]);
+        n -= m;
+        for &b in &s[m..] {
+            trailer = trailer << 8 | b as u32;
+        }
+    }
+    Ok(c.sum() == trailer)
+}
@@ -0,0 +1,260 @@
+use std::cmp;
+
+use buf::checksum::Checksum;
+
+const P32_1: u32 = 2_654_435_761;
+const P32_2: u32 = 2_246_822_519;
+const P32_3: u32 = 3_266_489_917;
+const P32_4: u32 = 668_265_263;
+const P32_5: u32 = 374_761_393;
+
+const P64_1: u64 = 11_400_714_785_074_694_791;
+const P64_2: u64 = 14_029_467_366_897_019_727;
+const P64_3: u64 = 1_609_587_929_392_839_161;
+const P64_4: u64 = 9_650_029_242_287_828_579;
+const P64_5: u64 = 2_870_177_450_012_600_261;
+
+#[inline]
+fn read32(s: &[u8]) -> u32 {
+    u32::from(s[0]) | u32::from(s[1]) << 8 | u32::from(s[2]) << 16 | u32::from(s[3]) << 24
+}
+
+#[inline]
+fn read64(s: &[u8]) -> u64 {
+    u64::from(read32(s)) | u64::from(read32(&s[4..])) << 32
+}
+
+#[inline]
+fn round32(acc: u32, input: u32) -> u32 {
+    acc.wrapping_add(input.wrapping_mul(P32_2))
+        .rotate_left(13)
+        .wrapping_mul(P32_1)
+}
+
+#[inline]
+fn round64(acc: u64, input: u64) -> u64 {
+    acc.wrapping_add(input.wrapping_mul(P64_2))
+        .rotate_left(31)
+        .wrapping_mul(P64_1)
+}
+
+#[inline]
+fn merge64(acc: u64, v: u64) -> u64 {
+    (acc ^ round64(0, v)).wrapping_mul(P64_1).wrapping_add(P64_4)
+}
+
+/// 32-bit xxHash.
+#[derive(Debug, Clone, Copy)]
+pub struct Xxh32 {
+    seed: u32,
+    v: [u32; 4],
+    buf: [u8; 16],
+    buf_len: usize,
+    total_len: u64,
+}
+
+impl Xxh32 {
+    #[inline]
+    pub fn new() -> Self {
+        Xxh32::with_seed(0)
+    }
+
+    pub fn with_seed(seed: u32) -> Self {
+        Xxh32 {
+            seed,
+            v: [
+                seed.wrapping_add(P32_1).wrapping_add(P32_2),
+                seed.wrapping_add(P32_2),
+                seed,
+                seed.wrapping_sub(P32_1),
+            ],
+            buf: [0; 16],
+            buf_len: 0,
+            total_len: 0,
+        }
+    }
+
+    #[inline]
+    fn stripe(&mut self, s: &[u8]) {
+        for i in 0..4 {
+            self.v[i] = round32(self.v[i], read32(&s[i * 4..]));
+        }
+    }
+}
+
+impl Default for Xxh32 {
+    #[inline]
+    fn default() -> Self {
+        Xxh32::new()
+    }
+}
+
+impl Checksum for Xxh32 {
+    type Sum = u32;
+
+    fn update(&mut self, mut bytes: &[u8]) {
+        self.total_len += bytes.len() as u64;
+        if self.buf_len > 0 {
+            let n = cmp::min(16 - self.buf_len, bytes.len());
+            self.buf[self.buf_len..self.buf_len + n].copy_from_slice(&bytes[..n]);
+            self.buf_len += n;
+            bytes = &bytes[n..];
+            if self.buf_len < 16 {
+                return;
+            }
+            let buf = self.buf;
+            self.stripe(&buf);
+            self.buf_len = 0;
+        }
+        while bytes.len() >= 16 {
+            self.stripe(&bytes[..16]);
+            bytes = &bytes[16..];
+        }
+        self.buf[..bytes.len()].copy_from_slice(bytes);
+        self.buf_len = bytes.len();
+    }
+
+    fn sum(&self) -> u32 {
+        let v = &self.v;
+        let mut h = if self.total_len >= 16 {
+            v[0].rotate_left(1)
+                .wrapping_add(v[1].rotate_left(7))
+                .wrapping_add(v[2].rotate_left(12))
+                .wrapping_add(v[3].rotate_left(18))
+        } else {
+            self.seed.wrapping_add(P32_5)
+        };
+        h = h.wrapping_add(self.total_len as u32);
+        let mut rest = &self.buf[..self.buf_len];
+        while rest.len() >= 4 {
+            h = h.wrapping_add(read32(rest).wrapping_mul(P32_3))
+                .rotate_left(17)
+                .wrapping_mul(P32_4);
+            rest = &rest[4..];
+        }
+        for &b in rest {
+            h = h.wrapping_add(u32::from(b).wrapping_mul(P32_5))
+                .rotate_left(11)
+                .wrapping_mul(P32_1);
+        }
+        h ^= h >> 15;
+        h = h.wrapping_mul(P32_2);
+        h ^= h >> 13;
+        h = h.wrapping_mul(P32_3);
+        h ^ h >> 16
+    }
+}
+
+/// 64-bit xxHash.
+#[derive(Debug, Clone, Copy)]
+pub struct Xxh64 {
+    seed: u64,
+    v: [u64; 4],
+    buf: [u8; 32],
+    buf_len: usize,
+    total_len: u64,
+}
+
+impl Xxh64 {
+    #[inline]
+    pub fn new() -> Self {
+        Xxh64::with_seed(0)
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Xxh64 {
+            seed,
+            v: [
+                seed.wrapping_add(P64_1).wrapping_add(P64_2),
+                seed.wrapping_add(P64_2),
+                seed,
+                seed.wrapping_sub(P64_1),
+            ],
+            buf: [0; 32],
+            buf_len: 0,
+            total_len: 0,
+        }
+    }
+
+    #[inline]
+    fn stripe(&mut self, s: &[u8]) {
+        for i in 0..4 {
+            self.v[i] = round64(self.v[i], read64(&s[i * 8..]));
+        }
+    }
+}
+
+impl Default for Xxh64 {
+    #[inline]
+    fn default() -> Self {
+        Xxh64::new()
+    }
+}
+
+impl Checksum for Xxh64 {
+    type Sum = u64;
+
+    fn update(&mut self, mut bytes: &[u8]) {
+        self.total_len += bytes.len() as u64;
+        if self.buf_len > 0 {
+            let n = cmp::min(32 - self.buf_len, bytes.len());
+            self.buf[self.buf_len..self.buf_len + n].copy_from_slice(&bytes[..n]);
+            self.buf_len += n;
+            bytes = &bytes[n..];
+            if self.buf_len < 32 {
+                return;
+            }
+            let buf = self.buf;
+            self.stripe(&buf);
+            self.buf_len = 0;
+        }
+        while bytes.len() >= 32 {
+            self.stripe(&bytes[..32]);
+            bytes = &bytes[32..];
+        }
+        self.buf[..bytes.len()].copy_from_slice(bytes);
+        self.buf_len = bytes.len();
+    }
+
+    fn sum(&self) -> u64 {
+        let v = &self.v;
+        let mut h = if self.total_len >= 32 {
+            let mut h = v[0].rotate_left(1)
+                .wrapping_add(v[1].rotate_left(7))
+                .wrapping_add(v[2].rotate_left(12))
+                .wrapping_add(v[3].rotate_left(18));
+            for &v in v {
+                h = merge64(h, v);
+            }
+            h
+        } else {
+            self.seed.wrapping_add(P64_5)
+        };
+        h = h.wrapping_add(self.total_len);
+        let mut rest = &self.buf[..self.buf_len];
+        while rest.len() >= 8 {
+            h = (h ^ round64(0, read64(rest)))
+                .rotate_left(27)
+                .wrapping_mul(P64_1)
+                .wrapping_add(P64_4);
+            rest = &rest[8..];
+        }
+        if rest.len() >= 4 {
+            h = (h ^ u64::from(read32(rest)).wrapping_mul(P64_1))
+                .rotate_left(23)
+                .wrapping_mul(P64_2)
+                .wrapping_add(P64_3);
+            rest = &rest[4..];
+        }
+        for &b in rest {
+            h = (h ^ u64::from(b).wrapping_mul(P64_5))
+                .rotate_left(11)
+                .wrapping_mul(P64_1);
+        }
+        h ^= h >> 33;
+        h = h.wrapping_mul(P64_2);
+        h ^= h >> 29;
+        h = h.wrapping_mul(P64_3);
+        h ^ h >> 32
+    }
+}
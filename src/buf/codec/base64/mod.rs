@@ -0,0 +1,166 @@
+//! Base64 text, in the `standard` and `url_safe` alphabets of RFC 4648.
+//!
+//! `append` and `prepend` encode a byte slice; the standard alphabet is
+//! padded with `=` and the URL-safe one is not. `read` and `get` decode
+//! text in either form, and fail with `Error::InvalidEncoding` if it is
+//! malformed. The `_exact` variants decode the next `len` characters only.
+
+use buf::{Appender, Error, GetIter, Prepender, ReadIter};
+use buf::codec::{get_each, read_each, u8s};
+
+pub mod standard;
+pub mod url_safe;
+
+struct Alphabet {
+    chars: &'static [u8; 64],
+    pad: bool,
+}
+
+const STANDARD: Alphabet = Alphabet {
+    chars: b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/",
+    pad: true,
+};
+
+const URL_SAFE: Alphabet = Alphabet {
+    chars: b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_",
+    pad: false,
+};
+
+// Input bytes encoded per call to `u8s::append`; a multiple of 3.
+const CHUNK: usize = 768;
+
+struct Decoder<'a> {
+    alphabet: &'a Alphabet,
+    acc: u32,
+    k: usize,
+    pad: usize,
+    out: Vec<u8>,
+}
+
+impl<'a> Decoder<'a> {
+    #[inline]
+    fn with_capacity(alphabet: &'a Alphabet, len: usize) -> Self {
+        Decoder {
+            alphabet,
+            acc: 0,
+            k: 0,
+            pad: 0,
+            out: Vec::with_capacity(len / 4 * 3 + 2),
+        }
+    }
+
+    #[inline]
+    fn value(&self, c: u8) -> Result<u32, Error> {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            _ if c == self.alphabet.chars[62] => 62,
+            _ if c == self.alphabet.chars[63] => 63,
+            _ => return Err(Error::InvalidEncoding),
+        };
+        Ok(v as u32)
+    }
+
+    fn push(&mut self, c: u8) -> Result<(), Error> {
+        if c == b'=' {
+            self.pad += 1;
+            if self.k < 2 || self.k + self.pad > 4 {
+                return Err(Error::InvalidEncoding);
+            }
+            return Ok(());
+        }
+        if self.pad > 0 {
+            return Err(Error::InvalidEncoding);
+        }
+        self.acc = self.acc << 6 | self.value(c)?;
+        self.k += 1;
+        if self.k == 4 {
+            let acc = self.acc;
+            self.out
+                .extend_from_slice(&[(acc >> 16) as u8, (acc >> 8) as u8, acc as u8]);
+            self.acc = 0;
+            self.k = 0;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>, Error> {
+        if self.pad > 0 && self.k + self.pad != 4 {
+            return Err(Error::InvalidEncoding);
+        }
+        let acc = self.acc;
+        match self.k {
+            0 => (),
+            2 => self.out.push((acc >> 4) as u8),
+            3 => self
+                .out
+                .extend_from_slice(&[(acc >> 10) as u8, (acc >> 2) as u8]),
+            _ => return Err(Error::InvalidEncoding),
+        }
+        Ok(self.out)
+    }
+}
+
+fn encode(alphabet: &Alphabet, src: &[u8], dst: &mut [u8]) -> usize {
+    let chars = alphabet.chars;
+    let mut n = 0;
+    for group in src.chunks(3) {
+        let b0 = group[0] as usize;
+        let b1 = *group.get(1).unwrap_or(&0) as usize;
+        let b2 = *group.get(2).unwrap_or(&0) as usize;
+        dst[n] = chars[b0 >> 2];
+        dst[n + 1] = chars[(b0 & 0x03) << 4 | b1 >> 4];
+        n += 2;
+        if group.len() > 1 {
+            dst[n] = chars[(b1 & 0x0F) << 2 | b2 >> 6];
+            n += 1;
+        }
+        if group.len() > 2 {
+            dst[n] = chars[b2 & 0x3F];
+            n += 1;
+        }
+        if alphabet.pad {
+            while n % 4 != 0 {
+                dst[n] = b'=';
+                n += 1;
+            }
+        }
+    }
+    n
+}
+
+fn read_exact(alphabet: &Alphabet, chain: &mut ReadIter, len: usize) -> Result<Vec<u8>, Error> {
+    if chain.len() < len {
+        return Err(Error::Underflow);
+    }
+    let mut decoder = Decoder::with_capacity(alphabet, len);
+    read_each(chain, len, |c| decoder.push(c))?;
+    decoder.finish()
+}
+
+fn get_exact(alphabet: &Alphabet, chain: &mut GetIter, len: usize) -> Result<Vec<u8>, Error> {
+    let mut decoder = Decoder::with_capacity(alphabet, len);
+    get_each(chain, len, |c| decoder.push(c))?;
+    decoder.finish()
+}
+
+fn append(alphabet: &Alphabet, v: &[u8], chain: &mut Appender) -> Result<usize, ()> {
+    let mut buf = [0u8; CHUNK / 3 * 4];
+    let mut n = 0;
+    for src in v.chunks(CHUNK) {
+        let len = encode(alphabet, src, &mut buf);
+        n += u8s::append(&buf[..len], chain)?;
+    }
+    Ok(n)
+}
+
+fn prepend(alphabet: &Alphabet, v: &[u8], chain: &mut Prepender) -> Result<usize, ()> {
+    let mut buf = [0u8; CHUNK / 3 * 4];
+    let mut n = 0;
+    for src in v.chunks(CHUNK).rev() {
+        let len = encode(alphabet, src, &mut buf);
+        n += u8s::prepend(&buf[..len], chain)?;
+    }
+    Ok(n)
+}
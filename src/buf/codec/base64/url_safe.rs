@@ -0,0 +1,34 @@
+use buf::{Appender, Error, GetIter, Prepender, ReadIter};
+use buf::codec::base64::URL_SAFE;
+
+#[inline]
+pub fn read(chain: &mut ReadIter) -> Result<Vec<u8>, Error> {
+    let len = chain.len();
+    super::read_exact(&URL_SAFE, chain, len)
+}
+
+#[inline]
+pub fn read_exact(chain: &mut ReadIter, len: usize) -> Result<Vec<u8>, Error> {
+    super::read_exact(&URL_SAFE, chain, len)
+}
+
+#[inline]
+pub fn get(chain: &mut GetIter) -> Result<Vec<u8>, Error> {
+    let len = chain.len();
+    super::get_exact(&URL_SAFE, chain, len)
+}
+
+#[inline]
+pub fn get_exact(chain: &mut GetIter, len: usize) -> Result<Vec<u8>, Error> {
+    super::get_exact(&URL_SAFE, chain, len)
+}
+
+#[inline]
+pub fn append(v: &[u8], chain: &mut Appender) -> Result<usize, ()> {
+    super::append(&URL_SAFE, v, chain)
+}
+
+#[inline]
+pub fn prepend(v: &[u8], chain: &mut Prepender) -> Result<usize, ()> {
+    super::prepend(&URL_SAFE, v, chain)
+}
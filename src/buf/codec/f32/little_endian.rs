@@ -103,7 +103,7 @@ pub fn append(mut v: f32, chain: &mut Appender) -> Result<usize, ()> {
             let cap = block.capacity();
             block.set_write_pos(cap);
         }
-        chain.append(0);
+        chain.try_append(0)?;
     }
 }
 
@@ -135,6 +135,6 @@ pub fn prepend(mut v: f32, chain: &mut Prepender) -> Result<usize, ()> {
             }
             block.set_read_pos(0);
         }
-        chain.prepend(0);
+        chain.try_prepend(0)?;
     }
 }
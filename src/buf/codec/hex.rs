@@ -0,0 +1,110 @@
+//! Hexadecimal text.
+//!
+//! `append` and `prepend` write the lowercase hex digits of a byte slice.
+//! `read` and `get` decode hex text, in either case, back into bytes, and
+//! fail with `Error::InvalidEncoding` on a non-hex digit or an odd number
+//! of digits. The `_exact` variants decode the next `len` digits only.
+
+use buf::{Appender, Error, GetIter, Prepender, ReadIter};
+use buf::codec::{get_each, read_each, u8s};
+
+const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+// Input bytes encoded per call to `u8s::append`.
+const CHUNK: usize = 512;
+
+#[inline]
+fn value(c: u8) -> Result<u8, Error> {
+    match c {
+        b'0'..=b'9' => Ok(c - b'0'),
+        b'a'..=b'f' => Ok(c - b'a' + 10),
+        b'A'..=b'F' => Ok(c - b'A' + 10),
+        _ => Err(Error::InvalidEncoding),
+    }
+}
+
+struct Decoder {
+    hi: Option<u8>,
+    out: Vec<u8>,
+}
+
+impl Decoder {
+    #[inline]
+    fn with_capacity(len: usize) -> Self {
+        Decoder {
+            hi: None,
+            out: Vec::with_capacity(len / 2),
+        }
+    }
+
+    #[inline]
+    fn push(&mut self, c: u8) -> Result<(), Error> {
+        let v = value(c)?;
+        match self.hi.take() {
+            Some(hi) => self.out.push(hi << 4 | v),
+            None => self.hi = Some(v),
+        }
+        Ok(())
+    }
+}
+
+fn encode(src: &[u8], dst: &mut [u8]) -> usize {
+    for (i, &b) in src.iter().enumerate() {
+        dst[2 * i] = DIGITS[(b >> 4) as usize];
+        dst[2 * i + 1] = DIGITS[(b & 0xF) as usize];
+    }
+    src.len() * 2
+}
+
+#[inline]
+pub fn read(chain: &mut ReadIter) -> Result<Vec<u8>, Error> {
+    let len = chain.len();
+    read_exact(chain, len)
+}
+
+pub fn read_exact(chain: &mut ReadIter, len: usize) -> Result<Vec<u8>, Error> {
+    if len & 1 != 0 {
+        return Err(Error::InvalidEncoding);
+    }
+    if chain.len() < len {
+        return Err(Error::Underflow);
+    }
+    let mut decoder = Decoder::with_capacity(len);
+    read_each(chain, len, |c| decoder.push(c))?;
+    Ok(decoder.out)
+}
+
+#[inline]
+pub fn get(chain: &mut GetIter) -> Result<Vec<u8>, Error> {
+    let len = chain.len();
+    get_exact(chain, len)
+}
+
+pub fn get_exact(chain: &mut GetIter, len: usize) -> Result<Vec<u8>, Error> {
+    if len & 1 != 0 {
+        return Err(Error::InvalidEncoding);
+    }
+    let mut decoder = Decoder::with_capacity(len);
+    get_each(chain, len, |c| decoder.push(c))?;
+    Ok(decoder.out)
+}
+
+pub fn append(v: &[u8], chain: &mut Appender) -> Result<usize, ()> {
+    let mut buf = [0u8; CHUNK * 2];
+    let mut n = 0;
+    for src in v.chunks(CHUNK) {
+        let len = encode(src, &mut buf);
+        n += u8s::append(&buf[..len], chain)?;
+    }
+    Ok(n)
+}
+
+pub fn prepend(v: &[u8], chain: &mut Prepender) -> Result<usize, ()> {
+    let mut buf = [0u8; CHUNK * 2];
+    let mut n = 0;
+    for src in v.chunks(CHUNK).rev() {
+        let len = encode(src, &mut buf);
+        n += u8s::prepend(&buf[..len], chain)?;
+    }
+    Ok(n)
+}
@@ -0,0 +1,27 @@
+use buf::{Appender, Error, GetIter, Prepender, ReadIter, SetIter};
+use buf::codec::u128;
+
+#[inline]
+pub fn read(chain: &mut ReadIter) -> Result<i128, Error> {
+    u128::big_endian::read(chain).map(|v| v as i128)
+}
+
+#[inline]
+pub fn get(chain: &mut GetIter) -> Result<i128, Error> {
+    u128::big_endian::get(chain).map(|v| v as i128)
+}
+
+#[inline]
+pub fn set(v: i128, chain: &mut SetIter) -> Result<usize, Error> {
+    u128::big_endian::set(v as u128, chain)
+}
+
+#[inline]
+pub fn append(v: i128, chain: &mut Appender) -> Result<usize, ()> {
+    u128::big_endian::append(v as u128, chain)
+}
+
+#[inline]
+pub fn prepend(v: i128, chain: &mut Prepender) -> Result<usize, ()> {
+    u128::big_endian::prepend(v as u128, chain)
+}
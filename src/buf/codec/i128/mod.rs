@@ -0,0 +1,4 @@
+pub mod big_endian;
+pub mod little_endian;
+pub mod varint;
+pub mod zigzag;
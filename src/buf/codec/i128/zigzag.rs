@@ -0,0 +1,37 @@
+use buf::{Appender, Error, GetIter, Prepender, ReadIter, SetIter};
+use buf::codec::u128;
+
+#[inline]
+fn encode(v: i128) -> u128 {
+    ((v << 1) ^ (v >> 127)) as u128
+}
+
+#[inline]
+fn decode(v: u128) -> i128 {
+    ((v >> 1) as i128) ^ -((v & 1) as i128)
+}
+
+#[inline]
+pub fn read(chain: &mut ReadIter) -> Result<i128, Error> {
+    u128::varint::read(chain).map(decode)
+}
+
+#[inline]
+pub fn get(chain: &mut GetIter) -> Result<i128, Error> {
+    u128::varint::get(chain).map(decode)
+}
+
+#[inline]
+pub fn set(v: i128, chain: &mut SetIter) -> Result<usize, Error> {
+    u128::varint::set(encode(v), chain)
+}
+
+#[inline]
+pub fn append(v: i128, chain: &mut Appender) -> Result<usize, ()> {
+    u128::varint::append(encode(v), chain)
+}
+
+#[inline]
+pub fn prepend(v: i128, chain: &mut Prepender) -> Result<usize, ()> {
+    u128::varint::prepend(encode(v), chain)
+}
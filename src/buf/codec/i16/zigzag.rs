@@ -0,0 +1,37 @@
+use buf::{Appender, Error, GetIter, Prepender, ReadIter, SetIter};
+use buf::codec::u16;
+
+#[inline]
+fn encode(v: i16) -> u16 {
+    ((v << 1) ^ (v >> 15)) as u16
+}
+
+#[inline]
+fn decode(v: u16) -> i16 {
+    ((v >> 1) as i16) ^ -((v & 1) as i16)
+}
+
+#[inline]
+pub fn read(chain: &mut ReadIter) -> Result<i16, Error> {
+    u16::varint::read(chain).map(decode)
+}
+
+#[inline]
+pub fn get(chain: &mut GetIter) -> Result<i16, Error> {
+    u16::varint::get(chain).map(decode)
+}
+
+#[inline]
+pub fn set(v: i16, chain: &mut SetIter) -> Result<usize, Error> {
+    u16::varint::set(encode(v), chain)
+}
+
+#[inline]
+pub fn append(v: i16, chain: &mut Appender) -> Result<usize, ()> {
+    u16::varint::append(encode(v), chain)
+}
+
+#[inline]
+pub fn prepend(v: i16, chain: &mut Prepender) -> Result<usize, ()> {
+    u16::varint::prepend(encode(v), chain)
+}
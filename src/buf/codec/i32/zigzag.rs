@@ -0,0 +1,37 @@
+use buf::{Appender, Error, GetIter, Prepender, ReadIter, SetIter};
+use buf::codec::u32;
+
+#[inline]
+fn encode(v: i32) -> u32 {
+    ((v << 1) ^ (v >> 31)) as u32
+}
+
+#[inline]
+fn decode(v: u32) -> i32 {
+    ((v >> 1) as i32) ^ -((v & 1) as i32)
+}
+
+#[inline]
+pub fn read(chain: &mut ReadIter) -> Result<i32, Error> {
+    u32::varint::read(chain).map(decode)
+}
+
+#[inline]
+pub fn get(chain: &mut GetIter) -> Result<i32, Error> {
+    u32::varint::get(chain).map(decode)
+}
+
+#[inline]
+pub fn set(v: i32, chain: &mut SetIter) -> Result<usize, Error> {
+    u32::varint::set(encode(v), chain)
+}
+
+#[inline]
+pub fn append(v: i32, chain: &mut Appender) -> Result<usize, ()> {
+    u32::varint::append(encode(v), chain)
+}
+
+#[inline]
+pub fn prepend(v: i32, chain: &mut Prepender) -> Result<usize, ()> {
+    u32::varint::prepend(encode(v), chain)
+}
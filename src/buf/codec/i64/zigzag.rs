@@ -0,0 +1,37 @@
+use buf::{Appender, Error, GetIter, Prepender, ReadIter, SetIter};
+use buf::codec::u64;
+
+#[inline]
+fn encode(v: i64) -> u64 {
+    ((v << 1) ^ (v >> 63)) as u64
+}
+
+#[inline]
+fn decode(v: u64) -> i64 {
+    ((v >> 1) as i64) ^ -((v & 1) as i64)
+}
+
+#[inline]
+pub fn read(chain: &mut ReadIter) -> Result<i64, Error> {
+    u64::varint::read(chain).map(decode)
+}
+
+#[inline]
+pub fn get(chain: &mut GetIter) -> Result<i64, Error> {
+    u64::varint::get(chain).map(decode)
+}
+
+#[inline]
+pub fn set(v: i64, chain: &mut SetIter) -> Result<usize, Error> {
+    u64::varint::set(encode(v), chain)
+}
+
+#[inline]
+pub fn append(v: i64, chain: &mut Appender) -> Result<usize, ()> {
+    u64::varint::append(encode(v), chain)
+}
+
+#[inline]
+pub fn prepend(v: i64, chain: &mut Prepender) -> Result<usize, ()> {
+    u64::varint::prepend(encode(v), chain)
+}
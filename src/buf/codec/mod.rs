@@ -1,4 +1,6 @@
-use std::ptr;
+use std::{cmp, ptr, slice};
+
+use buf::{Error, GetIter, ReadIter};
 
 fn reverse(p: *mut u8, len: usize) {
     let j = len - 1;
@@ -12,6 +14,53 @@ fn reverse(p: *mut u8, len: usize) {
     }
 }
 
+// Feeds the next `n` bytes of `chain` to `f`, consuming them.
+fn read_each<F>(chain: &mut ReadIter, mut n: usize, mut f: F) -> Result<(), Error>
+where
+    F: FnMut(u8) -> Result<(), Error>,
+{
+    if n == 0 {
+        return Ok(());
+    }
+    for mut block in chain {
+        let off = block.read_pos();
+        let len = cmp::min(block.len(), n);
+        let s = unsafe { slice::from_raw_parts(block.as_ptr().offset(off as isize), len) };
+        for &b in s {
+            f(b)?;
+        }
+        block.set_read_pos(off + len);
+        n -= len;
+        if n == 0 {
+            return Ok(());
+        }
+    }
+    Err(Error::Underflow)
+}
+
+// Feeds the next `n` bytes of `chain` to `f`.
+fn get_each<F>(chain: &mut GetIter, mut n: usize, mut f: F) -> Result<(), Error>
+where
+    F: FnMut(u8) -> Result<(), Error>,
+{
+    if n == 0 {
+        return Ok(());
+    }
+    for block in chain {
+        let off = block.read_pos() as isize;
+        let len = cmp::min(block.len(), n);
+        let s = unsafe { slice::from_raw_parts(block.as_ptr().offset(off), len) };
+        for &b in s {
+            f(b)?;
+        }
+        n -= len;
+        if n == 0 {
+            return Ok(());
+        }
+    }
+    Err(Error::IndexOutOfBounds)
+}
+
 pub mod i8;
 pub mod u8;
 pub mod u8s;
@@ -25,6 +74,21 @@ pub mod u32;
 pub mod i64;
 pub mod u64;
 
+pub mod i128;
+pub mod u128;
+
+pub mod uuid;
+
+pub mod base64;
+pub mod hex;
+
 pub mod f32;
 
 pub mod f64;
+
+pub mod str;
+
+pub mod protobuf;
+
+mod traits;
+pub use self::traits::{decode, encode, Be, Decode, Encode, Le, Varint, Zigzag};
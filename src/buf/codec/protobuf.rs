@@ -0,0 +1,313 @@
+//! Protocol Buffers wire format.
+//!
+//! Scalar values are read and appended with the codec of their type, e.g.
+//! `uint32`, `sint64` or `fixed32`, after the `Tag` of their field. Messages
+//! implement `Message` by appending their fields in `encode` and decoding
+//! one field at a time in `merge_field`:
+//!
+//! ```
+//! use ruyi::buf::{ByteBuf, Error};
+//! use ruyi::buf::codec::protobuf::{self, tag, uint32, Message, Tag, WireType};
+//!
+//! #[derive(Default)]
+//! struct Test1 {
+//!     a: u32,
+//! }
+//!
+//! impl Message for Test1 {
+//!     fn encode(&self, buf: &mut ByteBuf) -> Result<(), ()> {
+//!         buf.append(Tag::new(1, WireType::Varint), tag::append)?;
+//!         buf.append(self.a, uint32::append)?;
+//!         Ok(())
+//!     }
+//!
+//!     fn merge_field(&mut self, tag: Tag, buf: &mut ByteBuf) -> Result<(), Error> {
+//!         match tag.field {
+//!             1 => self.a = buf.read(uint32::read)?,
+//!             _ => protobuf::skip(tag, buf)?,
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let mut buf = ByteBuf::new();
+//! Test1 { a: 150 }.encode(&mut buf).unwrap();
+//! assert_eq!(buf.as_bytes(), &[0x08, 0x96, 0x01][..]);
+//! assert_eq!(Test1::decode(&mut buf).unwrap().a, 150);
+//! ```
+
+use buf::{Appender, ByteBuf, Error, ReadIter};
+use buf::codec::u64;
+
+pub use buf::codec::u32::varint as uint32;
+pub use buf::codec::u64::varint as uint64;
+pub use buf::codec::i32::zigzag as sint32;
+pub use buf::codec::i64::zigzag as sint64;
+pub use buf::codec::u32::little_endian as fixed32;
+pub use buf::codec::u64::little_endian as fixed64;
+pub use buf::codec::i32::little_endian as sfixed32;
+pub use buf::codec::i64::little_endian as sfixed64;
+pub use buf::codec::f32::little_endian as float;
+pub use buf::codec::f64::little_endian as double;
+pub use buf::codec::str::varint_prefixed as string;
+
+/// The largest field number.
+pub const MAX_FIELD: u32 = (1 << 29) - 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireType {
+    Varint = 0,
+    Fixed64 = 1,
+    LengthDelimited = 2,
+    StartGroup = 3,
+    EndGroup = 4,
+    Fixed32 = 5,
+}
+
+/// The key of a field, i.e. its number and wire type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tag {
+    pub field: u32,
+    pub wire_type: WireType,
+}
+
+impl Tag {
+    #[inline]
+    pub fn new(field: u32, wire_type: WireType) -> Self {
+        Tag { field, wire_type }
+    }
+}
+
+pub mod tag {
+    use buf::{Appender, Error, ReadIter};
+    use buf::codec::u32;
+
+    use super::{Tag, WireType, MAX_FIELD};
+
+    pub fn read(chain: &mut ReadIter) -> Result<Tag, Error> {
+        let key = u32::varint::read(chain)?;
+        let wire_type = match key & 0x07 {
+            0 => WireType::Varint,
+            1 => WireType::Fixed64,
+            2 => WireType::LengthDelimited,
+            3 => WireType::StartGroup,
+            4 => WireType::EndGroup,
+            5 => WireType::Fixed32,
+            _ => return Err(Error::InvalidEncoding),
+        };
+        match key >> 3 {
+            0 => Err(Error::InvalidEncoding),
+            field => Ok(Tag { field, wire_type }),
+        }
+    }
+
+    #[inline]
+    pub fn append(tag: Tag, chain: &mut Appender) -> Result<usize, ()> {
+        if tag.field == 0 || tag.field > MAX_FIELD {
+            return Err(());
+        }
+        u32::varint::append((tag.field << 3) | tag.wire_type as u32, chain)
+    }
+}
+
+/// `int32` and `enum` values, where negative values take ten bytes.
+pub mod int32 {
+    use buf::{Appender, Error, ReadIter};
+    use buf::codec::u64;
+
+    #[inline]
+    pub fn read(chain: &mut ReadIter) -> Result<i32, Error> {
+        u64::varint::read(chain).map(|v| v as i32)
+    }
+
+    #[inline]
+    pub fn append(v: i32, chain: &mut Appender) -> Result<usize, ()> {
+        u64::varint::append(i64::from(v) as u64, chain)
+    }
+}
+
+pub mod int64 {
+    use buf::{Appender, Error, ReadIter};
+    use buf::codec::u64;
+
+    #[inline]
+    pub fn read(chain: &mut ReadIter) -> Result<i64, Error> {
+        u64::varint::read(chain).map(|v| v as i64)
+    }
+
+    #[inline]
+    pub fn append(v: i64, chain: &mut Appender) -> Result<usize, ()> {
+        u64::varint::append(v as u64, chain)
+    }
+}
+
+pub mod bool {
+    use buf::{Appender, Error, ReadIter};
+    use buf::codec::u64;
+
+    #[inline]
+    pub fn read(chain: &mut ReadIter) -> Result<bool, Error> {
+        u64::varint::read(chain).map(|v| v != 0)
+    }
+
+    #[inline]
+    pub fn append(v: bool, chain: &mut Appender) -> Result<usize, ()> {
+        u64::varint::append(v as u64, chain)
+    }
+}
+
+/// `bytes` values, prefixed by their length in varint.
+pub mod bytes {
+    use buf::{Appender, Error, ReadIter};
+    use buf::codec::{u64, u8s};
+
+    pub fn read(chain: &mut ReadIter) -> Result<Vec<u8>, Error> {
+        let n = u64::varint::read(chain)? as usize;
+        if chain.len() < n {
+            return Err(Error::Underflow);
+        }
+        u8s::read_exact(chain, n)
+    }
+
+    #[inline]
+    pub fn append(v: &[u8], chain: &mut Appender) -> Result<usize, ()> {
+        Ok(u64::varint::append(v.len() as u64, chain)? + u8s::append(v, chain)?)
+    }
+}
+
+/// A message with fields in the wire format.
+pub trait Message: Default {
+    /// Appends the fields of the message to `buf`.
+    fn encode(&self, buf: &mut ByteBuf) -> Result<(), ()>;
+
+    /// Reads the value of the field of `tag` from the front of `buf`.
+    /// Unknown fields should be passed to `skip`.
+    fn merge_field(&mut self, tag: Tag, buf: &mut ByteBuf) -> Result<(), Error>;
+
+    /// Reads all the fields in `buf` into the message.
+    fn merge(&mut self, buf: &mut ByteBuf) -> Result<(), Error> {
+        while !buf.is_empty() {
+            let tag = buf.read(tag::read)?;
+            if tag.wire_type == WireType::EndGroup {
+                return Err(Error::InvalidEncoding);
+            }
+            self.merge_field(tag, buf)?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn decode(buf: &mut ByteBuf) -> Result<Self, Error> {
+        let mut msg = Self::default();
+        msg.merge(buf)?;
+        Ok(msg)
+    }
+}
+
+fn skip_exact(buf: &mut ByteBuf, n: usize) -> Result<(), Error> {
+    if buf.len() < n {
+        return Err(Error::Underflow);
+    }
+    buf.skip(n);
+    Ok(())
+}
+
+/// Skips the value of the field of `tag`, including all the fields of a
+/// group.
+pub fn skip(mut tag: Tag, buf: &mut ByteBuf) -> Result<(), Error> {
+    // The fields of the groups not ended yet, innermost last, so that nested
+    // groups take no stack.
+    let mut groups = Vec::new();
+    loop {
+        match tag.wire_type {
+            WireType::Varint => {
+                buf.read(u64::varint::read)?;
+            }
+            WireType::Fixed64 => skip_exact(buf, 8)?,
+            WireType::LengthDelimited => {
+                let n = buf.read(u64::varint::read)? as usize;
+                skip_exact(buf, n)?;
+            }
+            WireType::StartGroup => groups.push(tag.field),
+            WireType::EndGroup => match groups.pop() {
+                Some(field) if field == tag.field => (),
+                _ => return Err(Error::InvalidEncoding),
+            },
+            WireType::Fixed32 => skip_exact(buf, 4)?,
+        }
+        if groups.is_empty() {
+            return Ok(());
+        }
+        tag = buf.read(tag::read)?;
+    }
+}
+
+/// Splits the value of a length-delimited field off the front of `buf`.
+pub fn read_delimited(buf: &mut ByteBuf) -> Result<ByteBuf, Error> {
+    let n = buf.read(u64::varint::read)? as usize;
+    if buf.len() < n {
+        return Err(Error::Underflow);
+    }
+    match n {
+        0 => Ok(ByteBuf::new()),
+        _ => buf.drain_to(n),
+    }
+}
+
+// Appends the field `field` of the length-delimited `value`.
+fn append_delimited(field: u32, value: ByteBuf, buf: &mut ByteBuf) -> Result<(), ()> {
+    buf.append(Tag::new(field, WireType::LengthDelimited), tag::append)?;
+    buf.append(value.len() as u64, u64::varint::append)?;
+    if !value.is_empty() {
+        buf.extend(value);
+    }
+    Ok(())
+}
+
+/// Reads the value of an embedded message field into `msg`, merging it
+/// with any previous value.
+#[inline]
+pub fn merge_message<M: Message>(msg: &mut M, buf: &mut ByteBuf) -> Result<(), Error> {
+    msg.merge(&mut read_delimited(buf)?)
+}
+
+#[inline]
+pub fn read_message<M: Message>(buf: &mut ByteBuf) -> Result<M, Error> {
+    M::decode(&mut read_delimited(buf)?)
+}
+
+/// Appends `msg` as the embedded message field `field`.
+pub fn append_message<M: Message>(field: u32, msg: &M, buf: &mut ByteBuf) -> Result<(), ()> {
+    let mut value = ByteBuf::new();
+    msg.encode(&mut value)?;
+    append_delimited(field, value, buf)
+}
+
+/// Reads the values of a packed repeated field with `read` into `values`.
+pub fn read_packed<T, R>(buf: &mut ByteBuf, read: R, values: &mut Vec<T>) -> Result<(), Error>
+where
+    R: Fn(&mut ReadIter) -> Result<T, Error>,
+{
+    let mut packed = read_delimited(buf)?;
+    while !packed.is_empty() {
+        values.push(packed.read(&read)?);
+    }
+    Ok(())
+}
+
+/// Appends `values` with `append` as the packed repeated field `field`, or
+/// nothing if `values` is empty.
+pub fn append_packed<T, A>(field: u32, values: &[T], append: A, buf: &mut ByteBuf) -> Result<(), ()>
+where
+    T: Copy,
+    A: Fn(T, &mut Appender) -> Result<usize, ()>,
+{
+    if values.is_empty() {
+        return Ok(());
+    }
+    let mut packed = ByteBuf::new();
+    for &v in values {
+        packed.append(v, &append)?;
+    }
+    append_delimited(field, packed, buf)
+}
@@ -0,0 +1,75 @@
+use std::slice;
+
+use buf::{Appender, Error, GetIter, Prepender, ReadIter};
+use buf::codec::u8s;
+
+const CRLF: &[u8] = b"\r\n";
+
+// Returns the position of the first LF preceded by CR in `s`, where `cr`
+// tells whether the byte right before `s` is a CR.
+fn find_crlf(s: &[u8], cr: bool) -> Option<usize> {
+    let mut start = 0;
+    while let Some(i) = s[start..].iter().position(|&b| b == b'\n') {
+        let i = start + i;
+        if (i == 0 && cr) || (i > 0 && s[i - 1] == b'\r') {
+            return Some(i);
+        }
+        start = i + 1;
+    }
+    None
+}
+
+/// Reads up to the next CRLF and consumes it. The CRLF is not included.
+pub fn read(chain: &mut ReadIter) -> Result<String, Error> {
+    let mut v = Vec::new();
+    for mut block in chain {
+        let off = block.read_pos();
+        let s = unsafe { slice::from_raw_parts(block.as_ptr().offset(off as isize), block.len()) };
+        match find_crlf(s, v.last() == Some(&b'\r')) {
+            Some(i) => {
+                v.extend_from_slice(&s[..i]);
+                v.pop();
+                block.set_read_pos(off + i + 1);
+                return super::from_utf8(v);
+            }
+            None => {
+                v.extend_from_slice(s);
+                let pos = block.write_pos();
+                block.set_read_pos(pos);
+            }
+        }
+    }
+    Err(Error::Underflow)
+}
+
+pub fn get(chain: &mut GetIter) -> Result<String, Error> {
+    let mut v = Vec::new();
+    for block in chain {
+        let off = block.read_pos() as isize;
+        let s = unsafe { slice::from_raw_parts(block.as_ptr().offset(off), block.len()) };
+        match find_crlf(s, v.last() == Some(&b'\r')) {
+            Some(i) => {
+                v.extend_from_slice(&s[..i]);
+                v.pop();
+                return super::from_utf8(v);
+            }
+            None => v.extend_from_slice(s),
+        }
+    }
+    Err(Error::IndexOutOfBounds)
+}
+
+pub fn append(v: &str, chain: &mut Appender) -> Result<usize, ()> {
+    if v.contains("\r\n") {
+        return Err(());
+    }
+    Ok(u8s::append(v.as_bytes(), chain)? + u8s::append(CRLF, chain)?)
+}
+
+pub fn prepend(v: &str, chain: &mut Prepender) -> Result<usize, ()> {
+    if v.contains("\r\n") {
+        return Err(());
+    }
+    let n = u8s::prepend(CRLF, chain)?;
+    Ok(u8s::prepend(v.as_bytes(), chain)? + n)
+}
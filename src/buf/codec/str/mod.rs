@@ -0,0 +1,109 @@
+//! UTF-8 strings, either prefixed by their length in bytes or terminated by
+//! a delimiter.
+//!
+//! `read` and `get` fail with `Error::InvalidUtf8` if the bytes are not valid
+//! UTF-8. `append` and `prepend` fail if the length does not fit the prefix,
+//! or if the string contains its own terminator.
+
+use std::slice;
+
+use buf::{Error, GetBlock};
+
+#[inline]
+fn from_utf8(v: Vec<u8>) -> Result<String, Error> {
+    String::from_utf8(v).map_err(|_| Error::InvalidUtf8)
+}
+
+#[inline]
+fn as_slice<'a>(block: GetBlock<'a>) -> &'a [u8] {
+    let off = block.read_pos() as isize;
+    unsafe { slice::from_raw_parts(block.as_ptr().offset(off), block.len()) }
+}
+
+// Returns the number of bytes of `n` in varint.
+#[inline]
+fn varint_size(mut n: usize) -> usize {
+    let mut size = 1;
+    while n & !0x7F != 0 {
+        n >>= 7;
+        size += 1;
+    }
+    size
+}
+
+macro_rules! prefixed {
+    ($(#[$attr:meta])* $name:ident, $t:ident, $($codec:ident)::+, $size:expr) => {
+        $(#[$attr])*
+        pub mod $name {
+            use buf::{Appender, Error, GetIter, Prepender, ReadIter};
+            use buf::codec::u8s;
+            use buf::codec::$($codec)::+ as prefix;
+
+            #[inline]
+            pub fn read(chain: &mut ReadIter) -> Result<String, Error> {
+                let n = prefix::read(chain)? as usize;
+                if chain.len() < n {
+                    return Err(Error::Underflow);
+                }
+                super::from_utf8(u8s::read_exact(chain, n)?)
+            }
+
+            pub fn get(chain: &mut GetIter) -> Result<String, Error> {
+                // The prefix takes the size `append` encodes `n` in.
+                let n = prefix::get(&mut chain.clone())? as usize;
+                let v: Vec<u8> = chain
+                    .flat_map(|b| super::as_slice(b).iter().cloned())
+                    .skip(($size)(n))
+                    .take(n)
+                    .collect();
+                if v.len() < n {
+                    return Err(Error::IndexOutOfBounds);
+                }
+                super::from_utf8(v)
+            }
+
+            pub fn append(v: &str, chain: &mut Appender) -> Result<usize, ()> {
+                if v.len() > $t::max_value() as usize {
+                    return Err(());
+                }
+                Ok(prefix::append(v.len() as $t, chain)? + u8s::append(v.as_bytes(), chain)?)
+            }
+
+            pub fn prepend(v: &str, chain: &mut Prepender) -> Result<usize, ()> {
+                if v.len() > $t::max_value() as usize {
+                    return Err(());
+                }
+                let n = u8s::prepend(v.as_bytes(), chain)?;
+                Ok(prefix::prepend(v.len() as $t, chain)? + n)
+            }
+        }
+    };
+}
+
+prefixed!(
+    /// Strings of up to 255 bytes, prefixed by a `u8` length.
+    u8_prefixed, u8, u8, |_| 1
+);
+prefixed!(
+    /// Strings prefixed by a big-endian `u16` length.
+    u16be_prefixed, u16, u16::big_endian, |_| 2
+);
+prefixed!(
+    /// Strings prefixed by a little-endian `u16` length.
+    u16le_prefixed, u16, u16::little_endian, |_| 2
+);
+prefixed!(
+    /// Strings prefixed by a big-endian `u32` length.
+    u32be_prefixed, u32, u32::big_endian, |_| 4
+);
+prefixed!(
+    /// Strings prefixed by a little-endian `u32` length.
+    u32le_prefixed, u32, u32::little_endian, |_| 4
+);
+prefixed!(
+    /// Strings prefixed by a `u32` length in varint.
+    varint_prefixed, u32, u32::varint, super::varint_size
+);
+
+pub mod crlf_terminated;
+pub mod nul_terminated;
@@ -0,0 +1,57 @@
+use std::slice;
+
+use buf::{Appender, Error, GetIter, Prepender, ReadIter};
+use buf::codec::{u8, u8s};
+
+/// Reads up to the next NUL and consumes it. The NUL is not included.
+pub fn read(chain: &mut ReadIter) -> Result<String, Error> {
+    let mut v = Vec::new();
+    for mut block in chain {
+        let off = block.read_pos();
+        let s = unsafe { slice::from_raw_parts(block.as_ptr().offset(off as isize), block.len()) };
+        match s.iter().position(|&b| b == 0) {
+            Some(i) => {
+                v.extend_from_slice(&s[..i]);
+                block.set_read_pos(off + i + 1);
+                return super::from_utf8(v);
+            }
+            None => {
+                v.extend_from_slice(s);
+                let pos = block.write_pos();
+                block.set_read_pos(pos);
+            }
+        }
+    }
+    Err(Error::Underflow)
+}
+
+pub fn get(chain: &mut GetIter) -> Result<String, Error> {
+    let mut v = Vec::new();
+    for block in chain {
+        let off = block.read_pos() as isize;
+        let s = unsafe { slice::from_raw_parts(block.as_ptr().offset(off), block.len()) };
+        match s.iter().position(|&b| b == 0) {
+            Some(i) => {
+                v.extend_from_slice(&s[..i]);
+                return super::from_utf8(v);
+            }
+            None => v.extend_from_slice(s),
+        }
+    }
+    Err(Error::IndexOutOfBounds)
+}
+
+pub fn append(v: &str, chain: &mut Appender) -> Result<usize, ()> {
+    if v.as_bytes().contains(&0) {
+        return Err(());
+    }
+    Ok(u8s::append(v.as_bytes(), chain)? + u8::append(0, chain)?)
+}
+
+pub fn prepend(v: &str, chain: &mut Prepender) -> Result<usize, ()> {
+    if v.as_bytes().contains(&0) {
+        return Err(());
+    }
+    let n = u8::prepend(0, chain)?;
+    Ok(u8s::prepend(v.as_bytes(), chain)? + n)
+}
@@ -0,0 +1,353 @@
+use std::cmp;
+use std::convert::TryFrom;
+
+use buf::{Appender, Error, ReadIter};
+
+/// A value that can be appended to a `ByteBuf`.
+///
+/// Use `ByteBuf::append` with `codec::encode` to write any `Encode` value:
+/// `buf.append(&msg, codec::encode)`.
+pub trait Encode {
+    fn encode(&self, chain: &mut Appender) -> Result<usize, ()>;
+}
+
+/// A value that can be read from the front of a `ByteBuf`.
+///
+/// Use `ByteBuf::read` with `codec::decode` to read any `Decode` value:
+/// `let msg: Msg = buf.read(codec::decode)?`.
+pub trait Decode: Sized {
+    fn decode(chain: &mut ReadIter) -> Result<Self, Error>;
+}
+
+#[inline]
+pub fn encode<T>(v: &T, chain: &mut Appender) -> Result<usize, ()>
+where
+    T: Encode + ?Sized,
+{
+    v.encode(chain)
+}
+
+#[inline]
+pub fn decode<T>(chain: &mut ReadIter) -> Result<T, Error>
+where
+    T: Decode,
+{
+    T::decode(chain)
+}
+
+/// Big-endian encoding of the wrapped number.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default, Hash, Eq, Ord)]
+pub struct Be<T>(pub T);
+
+/// Little-endian encoding of the wrapped number.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default, Hash, Eq, Ord)]
+pub struct Le<T>(pub T);
+
+/// LEB128 varint encoding of the wrapped integer.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default, Hash, Eq, Ord)]
+pub struct Varint<T>(pub T);
+
+/// Zigzag varint encoding of the wrapped signed integer.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default, Hash, Eq, Ord)]
+pub struct Zigzag<T>(pub T);
+
+macro_rules! impl_wrapper {
+    ($wrapper:ident, $t:ty, $m:ident, $codec:ident) => {
+        impl Encode for $wrapper<$t> {
+            #[inline]
+            fn encode(&self, chain: &mut Appender) -> Result<usize, ()> {
+                super::$m::$codec::append(self.0, chain)
+            }
+        }
+
+        impl Decode for $wrapper<$t> {
+            #[inline]
+            fn decode(chain: &mut ReadIter) -> Result<Self, Error> {
+                super::$m::$codec::read(chain).map($wrapper)
+            }
+        }
+
+        impl From<$t> for $wrapper<$t> {
+            #[inline]
+            fn from(v: $t) -> Self {
+                $wrapper(v)
+            }
+        }
+    };
+}
+
+impl_wrapper!(Be, u16, u16, big_endian);
+impl_wrapper!(Be, i16, i16, big_endian);
+impl_wrapper!(Be, u32, u32, big_endian);
+impl_wrapper!(Be, i32, i32, big_endian);
+impl_wrapper!(Be, u64, u64, big_endian);
+impl_wrapper!(Be, i64, i64, big_endian);
+impl_wrapper!(Be, u128, u128, big_endian);
+impl_wrapper!(Be, i128, i128, big_endian);
+impl_wrapper!(Be, f32, f32, big_endian);
+impl_wrapper!(Be, f64, f64, big_endian);
+
+impl_wrapper!(Le, u16, u16, little_endian);
+impl_wrapper!(Le, i16, i16, little_endian);
+impl_wrapper!(Le, u32, u32, little_endian);
+impl_wrapper!(Le, i32, i32, little_endian);
+impl_wrapper!(Le, u64, u64, little_endian);
+impl_wrapper!(Le, i64, i64, little_endian);
+impl_wrapper!(Le, u128, u128, little_endian);
+impl_wrapper!(Le, i128, i128, little_endian);
+impl_wrapper!(Le, f32, f32, little_endian);
+impl_wrapper!(Le, f64, f64, little_endian);
+
+impl_wrapper!(Varint, u16, u16, varint);
+impl_wrapper!(Varint, i16, i16, varint);
+impl_wrapper!(Varint, u32, u32, varint);
+impl_wrapper!(Varint, i32, i32, varint);
+impl_wrapper!(Varint, u64, u64, varint);
+impl_wrapper!(Varint, i64, i64, varint);
+impl_wrapper!(Varint, u128, u128, varint);
+impl_wrapper!(Varint, i128, i128, varint);
+
+impl_wrapper!(Zigzag, i16, i16, zigzag);
+impl_wrapper!(Zigzag, i32, i32, zigzag);
+impl_wrapper!(Zigzag, i64, i64, zigzag);
+impl_wrapper!(Zigzag, i128, i128, zigzag);
+
+impl Encode for u8 {
+    #[inline]
+    fn encode(&self, chain: &mut Appender) -> Result<usize, ()> {
+        super::u8::append(*self, chain)
+    }
+}
+
+impl Decode for u8 {
+    #[inline]
+    fn decode(chain: &mut ReadIter) -> Result<Self, Error> {
+        super::u8::read(chain)
+    }
+}
+
+impl Encode for i8 {
+    #[inline]
+    fn encode(&self, chain: &mut Appender) -> Result<usize, ()> {
+        super::i8::append(*self, chain)
+    }
+}
+
+impl Decode for i8 {
+    #[inline]
+    fn decode(chain: &mut ReadIter) -> Result<Self, Error> {
+        super::i8::read(chain)
+    }
+}
+
+// One byte: 0 for false, anything else for true.
+impl Encode for bool {
+    #[inline]
+    fn encode(&self, chain: &mut Appender) -> Result<usize, ()> {
+        super::u8::append(*self as u8, chain)
+    }
+}
+
+impl Decode for bool {
+    #[inline]
+    fn decode(chain: &mut ReadIter) -> Result<Self, Error> {
+        super::u8::read(chain).map(|b| b != 0)
+    }
+}
+
+impl Encode for () {
+    #[inline]
+    fn encode(&self, _: &mut Appender) -> Result<usize, ()> {
+        Ok(0)
+    }
+}
+
+impl Decode for () {
+    #[inline]
+    fn decode(_: &mut ReadIter) -> Result<Self, Error> {
+        Ok(())
+    }
+}
+
+impl<'a, T> Encode for &'a T
+where
+    T: Encode + ?Sized,
+{
+    #[inline]
+    fn encode(&self, chain: &mut Appender) -> Result<usize, ()> {
+        (**self).encode(chain)
+    }
+}
+
+impl<T> Encode for Box<T>
+where
+    T: Encode + ?Sized,
+{
+    #[inline]
+    fn encode(&self, chain: &mut Appender) -> Result<usize, ()> {
+        (**self).encode(chain)
+    }
+}
+
+impl<T> Decode for Box<T>
+where
+    T: Decode,
+{
+    #[inline]
+    fn decode(chain: &mut ReadIter) -> Result<Self, Error> {
+        T::decode(chain).map(Box::new)
+    }
+}
+
+// A one-byte tag, 0 for `None` and 1 for `Some`, followed by the value if any.
+impl<T> Encode for Option<T>
+where
+    T: Encode,
+{
+    fn encode(&self, chain: &mut Appender) -> Result<usize, ()> {
+        match *self {
+            Some(ref v) => Ok(super::u8::append(1, chain)? + v.encode(chain)?),
+            None => super::u8::append(0, chain),
+        }
+    }
+}
+
+impl<T> Decode for Option<T>
+where
+    T: Decode,
+{
+    fn decode(chain: &mut ReadIter) -> Result<Self, Error> {
+        match super::u8::read(chain)? {
+            0 => Ok(None),
+            _ => T::decode(chain).map(Some),
+        }
+    }
+}
+
+// Encoded as the number of elements in varint, followed by the elements.
+impl<T> Encode for [T]
+where
+    T: Encode,
+{
+    fn encode(&self, chain: &mut Appender) -> Result<usize, ()> {
+        if self.len() > ::std::u32::MAX as usize {
+            return Err(());
+        }
+        let mut n = super::u32::varint::append(self.len() as u32, chain)?;
+        for v in self {
+            n += v.encode(chain)?;
+        }
+        Ok(n)
+    }
+}
+
+impl<T> Encode for Vec<T>
+where
+    T: Encode,
+{
+    #[inline]
+    fn encode(&self, chain: &mut Appender) -> Result<usize, ()> {
+        self.as_slice().encode(chain)
+    }
+}
+
+impl<T> Decode for Vec<T>
+where
+    T: Decode,
+{
+    fn decode(chain: &mut ReadIter) -> Result<Self, Error> {
+        let n = super::u32::varint::read(chain)? as usize;
+        // Never trust the announced count for the allocation.
+        let mut v = Vec::with_capacity(cmp::min(n, chain.len()));
+        for _ in 0..n {
+            v.push(T::decode(chain)?);
+        }
+        Ok(v)
+    }
+}
+
+// Encoded as the length in bytes in varint, followed by the UTF-8 bytes.
+impl Encode for str {
+    #[inline]
+    fn encode(&self, chain: &mut Appender) -> Result<usize, ()> {
+        super::str::varint_prefixed::append(self, chain)
+    }
+}
+
+impl Encode for String {
+    #[inline]
+    fn encode(&self, chain: &mut Appender) -> Result<usize, ()> {
+        super::str::varint_prefixed::append(self, chain)
+    }
+}
+
+impl Decode for String {
+    #[inline]
+    fn decode(chain: &mut ReadIter) -> Result<Self, Error> {
+        super::str::varint_prefixed::read(chain)
+    }
+}
+
+// Fixed-size arrays carry no length prefix.
+impl<T, const N: usize> Encode for [T; N]
+where
+    T: Encode,
+{
+    fn encode(&self, chain: &mut Appender) -> Result<usize, ()> {
+        let mut n = 0;
+        for v in self {
+            n += v.encode(chain)?;
+        }
+        Ok(n)
+    }
+}
+
+impl<T, const N: usize> Decode for [T; N]
+where
+    T: Decode,
+{
+    fn decode(chain: &mut ReadIter) -> Result<Self, Error> {
+        let mut v = Vec::with_capacity(N);
+        for _ in 0..N {
+            v.push(T::decode(chain)?);
+        }
+        match <[T; N]>::try_from(v) {
+            Ok(a) => Ok(a),
+            Err(..) => ::unreachable(),
+        }
+    }
+}
+
+macro_rules! impl_tuple {
+    ($($name:ident)+) => {
+        #[allow(non_snake_case)]
+        impl<$($name: Encode),+> Encode for ($($name,)+) {
+            fn encode(&self, chain: &mut Appender) -> Result<usize, ()> {
+                let ($(ref $name,)+) = *self;
+                let mut n = 0;
+                $(n += $name.encode(chain)?;)+
+                Ok(n)
+            }
+        }
+
+        impl<$($name: Decode),+> Decode for ($($name,)+) {
+            #[inline]
+            fn decode(chain: &mut ReadIter) -> Result<Self, Error> {
+                Ok(($($name::decode(chain)?,)+))
+            }
+        }
+    };
+}
+
+impl_tuple!(A);
+impl_tuple!(A B);
+impl_tuple!(A B C);
+impl_tuple!(A B C D);
+impl_tuple!(A B C D E);
+impl_tuple!(A B C D E F);
+impl_tuple!(A B C D E F G);
+impl_tuple!(A B C D E F G H);
+impl_tuple!(A B C D E F G H I);
+impl_tuple!(A B C D E F G H I J);
+impl_tuple!(A B C D E F G H I J K);
+impl_tuple!(A B C D E F G H I J K L);
@@ -0,0 +1,126 @@
+use std::ptr;
+
+use buf::{Appender, Error, GetIter, Prepender, ReadIter, SetIter};
+use buf::codec::u128::U128_SIZE;
+
+pub fn read(chain: &mut ReadIter) -> Result<u128, Error> {
+    let mut bytes = [0u8; U128_SIZE];
+    let mut ptr_dst = bytes.as_mut_ptr();
+    let mut n = U128_SIZE;
+    for mut block in chain {
+        let off = block.read_pos();
+        let ptr_src = unsafe { block.as_ptr().offset(off as isize) };
+        let len = block.len();
+        if len >= n {
+            unsafe { ptr::copy_nonoverlapping(ptr_src, ptr_dst, n) };
+            block.set_read_pos(off + n);
+            return Ok(u128::from_le_bytes(bytes));
+        }
+        n -= len;
+        unsafe {
+            ptr::copy_nonoverlapping(ptr_src, ptr_dst, len);
+            ptr_dst = ptr_dst.offset(len as isize);
+        }
+        let pos = block.write_pos();
+        block.set_read_pos(pos);
+    }
+    Err(Error::Underflow)
+}
+
+pub fn get(chain: &mut GetIter) -> Result<u128, Error> {
+    let mut bytes = [0u8; U128_SIZE];
+    let mut ptr_dst = bytes.as_mut_ptr();
+    let mut n = U128_SIZE;
+    for block in chain {
+        let off = block.read_pos() as isize;
+        let ptr_src = unsafe { block.as_ptr().offset(off) };
+        let len = block.len();
+        if len >= n {
+            unsafe { ptr::copy_nonoverlapping(ptr_src, ptr_dst, n) };
+            return Ok(u128::from_le_bytes(bytes));
+        }
+        n -= len;
+        unsafe {
+            ptr::copy_nonoverlapping(ptr_src, ptr_dst, len);
+            ptr_dst = ptr_dst.offset(len as isize);
+        }
+    }
+    Err(Error::Underflow)
+}
+
+pub fn set(v: u128, chain: &mut SetIter) -> Result<usize, Error> {
+    let bytes = v.to_le_bytes();
+    let mut ptr_src = bytes.as_ptr();
+    let mut n = U128_SIZE;
+    for mut block in chain {
+        let off = block.read_pos() as isize;
+        let ptr_dst = unsafe { block.as_mut_ptr().offset(off) };
+        let len = block.len();
+        if len >= n {
+            unsafe { ptr::copy_nonoverlapping(ptr_src, ptr_dst, n) };
+            return Ok(U128_SIZE);
+        }
+        n -= len;
+        unsafe {
+            ptr::copy_nonoverlapping(ptr_src, ptr_dst, len);
+            ptr_src = ptr_src.offset(len as isize);
+        }
+    }
+    Err(Error::IndexOutOfBounds)
+}
+
+pub fn append(v: u128, chain: &mut Appender) -> Result<usize, ()> {
+    let bytes = v.to_le_bytes();
+    let mut ptr_src = bytes.as_ptr();
+    let mut n = U128_SIZE;
+    loop {
+        if let Some(mut block) = chain.last_mut() {
+            let off = block.write_pos();
+            let ptr_dst = unsafe { block.as_mut_ptr().offset(off as isize) };
+            let appendable = block.appendable();
+            if appendable >= n {
+                unsafe { ptr::copy_nonoverlapping(ptr_src, ptr_dst, n) };
+                block.set_write_pos(off + n);
+                return Ok(U128_SIZE);
+            }
+            n -= appendable;
+            unsafe {
+                ptr::copy_nonoverlapping(ptr_src, ptr_dst, appendable);
+                ptr_src = ptr_src.offset(appendable as isize);
+            }
+            let cap = block.capacity();
+            block.set_write_pos(cap);
+        }
+        chain.try_append(0)?;
+    }
+}
+
+pub fn prepend(v: u128, chain: &mut Prepender) -> Result<usize, ()> {
+    let bytes = v.to_le_bytes();
+    let mut ptr_src = bytes.as_ptr();
+    let mut n = U128_SIZE;
+    unsafe { ptr_src = ptr_src.offset(n as isize) };
+    loop {
+        if let Some(mut block) = chain.first_mut() {
+            let prependable = block.prependable();
+            let mut ptr_dst = block.as_mut_ptr();
+            if prependable >= n {
+                let off = prependable - n;
+                unsafe {
+                    ptr_dst = ptr_dst.offset(off as isize);
+                    ptr_src = ptr_src.offset(-(n as isize));
+                    ptr::copy_nonoverlapping(ptr_src, ptr_dst, n);
+                }
+                block.set_read_pos(off);
+                return Ok(U128_SIZE);
+            }
+            n -= prependable;
+            unsafe {
+                ptr_src = ptr_src.offset(-(prependable as isize));
+                ptr::copy_nonoverlapping(ptr_src, ptr_dst, prependable);
+            }
+            block.set_read_pos(0);
+        }
+        chain.try_prepend(0)?;
+    }
+}
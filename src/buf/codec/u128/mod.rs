@@ -0,0 +1,5 @@
+const U128_SIZE: usize = 16;
+
+pub mod big_endian;
+pub mod little_endian;
+pub mod varint;
@@ -74,7 +74,7 @@ pub fn append(mut v: u64, chain: &mut Appender) -> Result<usize, ()> {
             }
             block.set_write_pos(cap);
         }
-        chain.append(0);
+        chain.try_append(0)?;
     }
 }
 
@@ -100,6 +100,6 @@ pub fn prepend(v: u64, chain: &mut Prepender) -> Result<usize, ()> {
             }
             block.set_read_pos(0);
         }
-        chain.prepend(0);
+        chain.try_prepend(0)?;
     }
 }
@@ -38,7 +38,7 @@ pub fn append(v: u8, chain: &mut Appender) -> Result<usize, ()> {
                 return Ok(U8_SIZE);
             }
         }
-        chain.append(0);
+        chain.try_append(0)?;
     }
 }
 
@@ -52,6 +52,6 @@ pub fn prepend(v: u8, chain: &mut Prepender) -> Result<usize, ()> {
                 return Ok(U8_SIZE);
             }
         }
-        chain.prepend(0);
+        chain.try_prepend(0)?;
     }
 }
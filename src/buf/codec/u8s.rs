@@ -134,7 +134,7 @@ pub fn append(v: &[u8], chain: &mut Appender) -> Result<usize, ()> {
             let cap = block.capacity();
             block.set_write_pos(cap);
         }
-        chain.append(0);
+        chain.try_append(0)?;
     }
 }
 
@@ -143,7 +143,7 @@ pub fn append_bytes(v: Vec<u8>, chain: &mut Appender) -> Result<usize, ()> {
     if n < 6 * 1024 {
         append(&v, chain)
     } else {
-        chain.append_bytes(v);
+        chain.try_append_bytes(v)?;
         Ok(n)
     }
 }
@@ -173,7 +173,7 @@ pub fn prepend(v: &[u8], chain: &mut Prepender) -> Result<usize, ()> {
             }
             block.set_read_pos(0);
         }
-        chain.prepend(0);
+        chain.try_prepend(0)?;
     }
 }
 
@@ -182,7 +182,7 @@ pub fn prepend_bytes(v: Vec<u8>, chain: &mut Prepender) -> Result<usize, ()> {
     if n < 6 * 1024 {
         prepend(&v, chain)
     } else {
-        chain.prepend_bytes(v);
+        chain.try_prepend_bytes(v)?;
         Ok(n)
     }
 }
@@ -220,7 +220,7 @@ pub fn append_fill(filling: Filling, chain: &mut Appender) -> Result<usize, ()>
             let cap = block.capacity();
             block.set_write_pos(cap);
         }
-        chain.append(0);
+        chain.try_append(0)?;
     }
 }
 
@@ -243,6 +243,6 @@ pub fn prepend_fill(filling: Filling, chain: &mut Prepender) -> Result<usize, ()
             unsafe { ptr::write_bytes(ptr_dst, filling.val(), prependable) };
             block.set_read_pos(0);
         }
-        chain.prepend(0);
+        chain.try_prepend(0)?;
     }
 }
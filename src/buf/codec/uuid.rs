@@ -0,0 +1,59 @@
+//! UUIDs as `u128`s, stored in the 16-byte big-endian layout of RFC 4122.
+
+use buf::{Appender, Error, GetIter, Prepender, ReadIter, SetIter};
+use buf::codec::u128;
+
+#[inline]
+pub fn read(chain: &mut ReadIter) -> Result<u128, Error> {
+    u128::big_endian::read(chain)
+}
+
+#[inline]
+pub fn get(chain: &mut GetIter) -> Result<u128, Error> {
+    u128::big_endian::get(chain)
+}
+
+#[inline]
+pub fn set(v: u128, chain: &mut SetIter) -> Result<usize, Error> {
+    u128::big_endian::set(v, chain)
+}
+
+#[inline]
+pub fn append(v: u128, chain: &mut Appender) -> Result<usize, ()> {
+    u128::big_endian::append(v, chain)
+}
+
+#[inline]
+pub fn prepend(v: u128, chain: &mut Prepender) -> Result<usize, ()> {
+    u128::big_endian::prepend(v, chain)
+}
+
+/// Parses the hyphenated form, e.g. `67e55044-10b1-426f-9247-bb680e5fe0c8`.
+pub fn parse(s: &str) -> Option<u128> {
+    let s = s.as_bytes();
+    if s.len() != 36 {
+        return None;
+    }
+    let mut v = 0u128;
+    for (i, &c) in s.iter().enumerate() {
+        match i {
+            8 | 13 | 18 | 23 => if c != b'-' {
+                return None;
+            },
+            _ => v = (v << 4) | (c as char).to_digit(16)? as u128,
+        }
+    }
+    Some(v)
+}
+
+/// Formats `v` in the lowercase hyphenated form.
+pub fn to_hyphenated(v: u128) -> String {
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        v >> 96,
+        (v >> 80) & 0xffff,
+        (v >> 64) & 0xffff,
+        (v >> 48) & 0xffff,
+        v & 0xffff_ffff_ffff
+    )
+}
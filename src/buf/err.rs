@@ -2,4 +2,6 @@
 pub enum Error {
     #[fail(display = "Index out of bounds")] IndexOutOfBounds,
     #[fail(display = "Buffer underflow")] Underflow,
+    #[fail(display = "Invalid UTF-8")] InvalidUtf8,
+    #[fail(display = "Invalid encoding")] InvalidEncoding,
 }
@@ -5,6 +5,7 @@ pub struct GetBlock<'a> {
     get_pos: usize,
 }
 
+#[derive(Clone)]
 pub struct GetIter<'a> {
     blocks: &'a [Block],
     idx: usize,
@@ -1,55 +1,179 @@
 use std::fmt;
-use std::mem;
-use std::str;
 
-use super::ByteBuf;
+use super::{Bytes, ByteBuf, Error};
+
+const BYTES_PER_ROW: usize = 16;
 
 pub struct HexDump<'a> {
     inner: &'a ByteBuf,
 }
 
+/// Rows that differ between two buffers, as returned by `HexDump::diff`.
+pub struct HexDiff<'a> {
+    left: &'a ByteBuf,
+    right: &'a ByteBuf,
+}
+
+/// Builds a `ByteBuf` from `HexDump` output, one string literal per row or
+/// all rows in one literal.
+///
+/// ```ignore
+/// let buf = hex_dump!(
+///     "00000000h: 48 65 6C 6C 6F 2C 20 77 6F 72 6C 64 21 0D 0A 00   Hello, world!...",
+///     "00000010h: FF                                                ."
+/// );
+/// ```
+#[macro_export]
+macro_rules! hex_dump {
+    ($($row:expr),+ $(,)*) => {
+        $crate::buf::HexDump::parse(concat!($($row, "\n"),+)).expect("Invalid hex dump")
+    };
+}
+
 #[inline]
 pub(super) fn new<'a>(inner: &'a ByteBuf) -> HexDump<'a> {
     HexDump { inner }
 }
 
+impl<'a> HexDump<'a> {
+    /// Parses the `Display` output of `HexDump` back into a `ByteBuf`.
+    ///
+    /// Offsets such as `00000010h:` and the ASCII column are optional and
+    /// ignored. The ASCII column must be separated from the bytes by at least
+    /// two spaces, as `HexDump` does.
+    pub fn parse(s: &str) -> Result<ByteBuf, Error> {
+        let mut v = Vec::new();
+        for line in s.lines() {
+            parse_row(line, &mut v)?;
+        }
+        Ok(ByteBuf::from(v))
+    }
+
+    /// Returns the rows where this buffer and `other` differ, each printed
+    /// from both sides and followed by a line marking the differing bytes.
+    #[inline]
+    pub fn diff(&self, other: &'a ByteBuf) -> HexDiff<'a> {
+        HexDiff {
+            left: self.inner,
+            right: other,
+        }
+    }
+}
+
+fn parse_row(line: &str, v: &mut Vec<u8>) -> Result<(), Error> {
+    let mut row = line.trim_start();
+    if let Some(i) = row.find(':') {
+        let addr = row[..i].trim_end_matches('h');
+        if !addr.is_empty() && addr.bytes().all(|b| b.is_ascii_hexdigit()) {
+            row = &row[i + 1..];
+        }
+    }
+    let row = row.trim_end_matches('\r').as_bytes();
+    let mut i = 0;
+    let mut first = true;
+    loop {
+        let start = i;
+        while i < row.len() && row[i] == b' ' {
+            i += 1;
+        }
+        if i == row.len() || (!first && i - start > 1) {
+            return Ok(());
+        }
+        if i + 2 > row.len() || (i + 2 < row.len() && row[i + 2] != b' ') {
+            return Err(Error::InvalidEncoding);
+        }
+        v.push(value(row[i])? << 4 | value(row[i + 1])?);
+        i += 2;
+        first = false;
+    }
+}
+
+#[inline]
+fn value(c: u8) -> Result<u8, Error> {
+    match c {
+        b'0'..=b'9' => Ok(c - b'0'),
+        b'a'..=b'f' => Ok(c - b'a' + 10),
+        b'A'..=b'F' => Ok(c - b'A' + 10),
+        _ => Err(Error::InvalidEncoding),
+    }
+}
+
+// Fills `row` from `bytes` and returns the number of bytes filled.
+fn next_row(bytes: &mut Bytes, row: &mut [u8; BYTES_PER_ROW]) -> usize {
+    let mut n = 0;
+    while n < BYTES_PER_ROW {
+        match bytes.next() {
+            Some(b) => row[n] = b,
+            None => break,
+        }
+        n += 1;
+    }
+    n
+}
+
+fn write_row(f: &mut fmt::Formatter, addr: usize, row: &[u8]) -> fmt::Result {
+    let mut asc = String::with_capacity(BYTES_PER_ROW);
+    write!(f, "{:08X}h:", addr)?;
+    for &b in row {
+        write!(f, " {:02X}", b)?;
+        let c = b as char;
+        asc.push(if c.is_control() { '.' } else { c });
+    }
+    for _ in row.len()..BYTES_PER_ROW {
+        write!(f, "   ")?;
+    }
+    writeln!(f, "   {}", asc)
+}
+
 impl<'a> fmt::Display for HexDump<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut bytes = self.inner.bytes();
-        let mut next = bytes.next();
-        if next.is_none() {
-            return Ok(());
+        let mut row = [0u8; BYTES_PER_ROW];
+        let mut addr = 0;
+        loop {
+            let n = next_row(&mut bytes, &mut row);
+            // Whole rows are followed by an empty one, unless there are none.
+            if n == 0 && addr == 0 {
+                return Ok(());
+            }
+            write_row(f, addr, &row[..n])?;
+            if n < BYTES_PER_ROW {
+                return Ok(());
+            }
+            addr += BYTES_PER_ROW;
         }
+    }
+}
 
-        const BYTES_PER_ROW: usize = 16;
-
+impl<'a> fmt::Display for HexDiff<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut left = self.left.bytes();
+        let mut right = self.right.bytes();
+        let mut row_l = [0u8; BYTES_PER_ROW];
+        let mut row_r = [0u8; BYTES_PER_ROW];
         let mut addr = 0;
-        let mut asc: [u8; BYTES_PER_ROW] = unsafe { mem::uninitialized() };
         loop {
-            write!(f, "{:08X}h:", addr)?;
-            // dump one row
-            let mut i = 0;
-            while i < BYTES_PER_ROW {
-                match next {
-                    Some(b) => {
-                        write!(f, " {:02X}", b)?;
-                        unsafe {
-                            let c = asc.get_unchecked_mut(i);
-                            *c = if (b as char).is_control() { b'.' } else { b };
-                        }
-                        i += 1;
-                        next = bytes.next();
-                    }
-                    None => {
-                        for _ in i..BYTES_PER_ROW {
-                            write!(f, "   ")?;
-                        }
-                        writeln!(f, "   {}", unsafe { str::from_utf8_unchecked(&asc[0..i]) })?;
-                        return Ok(());
-                    }
+            let n_l = next_row(&mut left, &mut row_l);
+            let n_r = next_row(&mut right, &mut row_r);
+            if n_l == 0 && n_r == 0 {
+                return Ok(());
+            }
+            if row_l[..n_l] != row_r[..n_r] {
+                write!(f, "- ")?;
+                write_row(f, addr, &row_l[..n_l])?;
+                write!(f, "+ ")?;
+                write_row(f, addr, &row_r[..n_r])?;
+                let mut marks = String::new();
+                for i in 0..n_l.max(n_r) {
+                    let same = i < n_l && i < n_r && row_l[i] == row_r[i];
+                    marks.push_str(if same { "   " } else { " ^^" });
                 }
+                // Aligned under the bytes, after "- 00000000h:".
+                writeln!(f, "{:12}{}", "", marks.trim_end())?;
+            }
+            if n_l < BYTES_PER_ROW && n_r < BYTES_PER_ROW {
+                return Ok(());
             }
-            writeln!(f, "   {}", unsafe { str::from_utf8_unchecked(&asc[0..i]) })?;
             addr += BYTES_PER_ROW;
         }
     }
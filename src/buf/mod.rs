@@ -28,18 +28,29 @@ mod window;
 pub use self::window::{Window, Windows};
 
 mod hex_dump;
-pub use self::hex_dump::HexDump;
+pub use self::hex_dump::{HexDiff, HexDump};
+
+pub mod checksum;
 
 mod reader;
 pub use self::reader::Reader;
 
+mod bits;
+pub use self::bits::{BitOrder, BitReader, BitWriter};
+
 mod writer;
 pub use self::writer::Writer;
 
+#[cfg(feature = "serde")]
+pub mod serde;
+
 use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::mem;
 use std::ptr;
+use std::slice;
+
+use memchr::{self, memmem};
 
 const EMPTY: &[u8] = &[];
 
@@ -48,6 +59,7 @@ pub struct ByteBuf {
     blocks: Vec<Block>,
     idx: usize,
     growth: usize,
+    limit: usize,
 }
 
 impl From<Vec<u8>> for ByteBuf {
@@ -56,6 +68,7 @@ impl From<Vec<u8>> for ByteBuf {
             blocks: vec![Block::from(bytes)],
             idx: 0,
             growth: 8 * 1024,
+            limit: usize::max_value(),
         }
     }
 }
@@ -75,6 +88,7 @@ impl ByteBuf {
             blocks: vec![inner],
             idx: 0,
             growth,
+            limit: usize::max_value(),
         }
     }
 
@@ -87,14 +101,38 @@ impl ByteBuf {
             blocks: Vec::new(),
             idx: 0,
             growth,
+            limit: usize::max_value(),
         }
     }
 
+    /// Creates an empty `ByteBuf` whose blocks may hold at most `limit` bytes
+    /// in total. Appends and prepends that need more capacity fail, and
+    /// nothing of the failed value is kept.
+    #[inline]
+    pub fn with_limit(limit: usize) -> Self {
+        let mut buf = Self::new();
+        buf.limit = limit;
+        buf
+    }
+
     #[inline]
     pub fn set_growth(&mut self, growth: usize) {
         self.growth = growth;
     }
 
+    #[inline]
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Sets the capacity ceiling of the blocks not yet consumed. Blocks
+    /// added by `extend` are not checked against it, but those added by
+    /// `try_extend` are.
+    #[inline]
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+    }
+
     pub fn len(&self) -> usize {
         (&self.blocks[self.idx..])
             .iter()
@@ -149,7 +187,15 @@ impl ByteBuf {
     where
         A: Fn(T, &mut Appender) -> Result<usize, E>,
     {
-        append(t, &mut self.appender())
+        if !self.is_limited() {
+            return append(t, &mut self.appender());
+        }
+        let len = self.len();
+        let r = append(t, &mut self.appender());
+        if r.is_err() {
+            self.truncate(len);
+        }
+        r
     }
 
     #[inline]
@@ -157,7 +203,16 @@ impl ByteBuf {
     where
         P: Fn(T, &mut Prepender) -> Result<usize, E>,
     {
-        prepend(t, &mut self.prepender())
+        if !self.is_limited() {
+            return prepend(t, &mut self.prepender());
+        }
+        let len = self.len();
+        let r = prepend(t, &mut self.prepender());
+        if r.is_err() {
+            let n = self.len() - len;
+            self.skip(n);
+        }
+        r
     }
 
     pub fn try_reserve_in_head(&mut self, len: usize) -> usize {
@@ -210,6 +265,16 @@ impl ByteBuf {
         }
     }
 
+    /// Moves the bytes of `other` to the end, or gives `other` back if
+    /// they do not fit the limit.
+    pub fn try_extend(&mut self, other: Self) -> Result<(), Self> {
+        if self.is_limited() && self.len() + other.len() > self.limit {
+            return Err(other);
+        }
+        self.extend(other);
+        Ok(())
+    }
+
     pub fn split_off(&mut self, mut at: usize) -> Result<Self, Error> {
         let idx = self.locate_idx(&mut at)
             .map_err(|_| Error::IndexOutOfBounds)?;
@@ -243,7 +308,9 @@ impl ByteBuf {
                     self.idx = idx;
                 }
             } else {
-                return Ok(Self::with_growth(self.growth));
+                let mut other = Self::with_growth(self.growth);
+                other.limit = self.limit;
+                return Ok(other);
             }
         }
 
@@ -257,6 +324,7 @@ impl ByteBuf {
             blocks: other_blocks,
             idx: other_idx,
             growth: self.growth,
+            limit: self.limit,
         })
     }
 
@@ -364,10 +432,15 @@ impl ByteBuf {
         if needle.is_empty() {
             return Some(pos);
         }
+        let finder = memmem::Finder::new(needle);
         let mut bytes = unsafe { self.blocks.get_unchecked(idx) }.as_bytes_from(off);
         loop {
             if bytes.len() >= needle.len() {
-                bytes = match bytes.windows(needle.len()).position(|w| w == needle) {
+                let found = match needle.len() {
+                    1 => memchr::memchr(needle[0], bytes),
+                    _ => finder.find(bytes),
+                };
+                bytes = match found {
                     Some(n) => return Some(pos + n),
                     None => {
                         let n = bytes.len() - needle.len() + 1;
@@ -380,6 +453,7 @@ impl ByteBuf {
             if idx >= self.blocks.len() {
                 break;
             }
+            // matches straddling the block boundary
             for m in 0..bytes.len() {
                 let (left, right) = needle.split_at(bytes.len() - m);
                 if &bytes[m..] == left {
@@ -399,8 +473,84 @@ impl ByteBuf {
         self.find_from(needle, 0)
     }
 
+    /// Finds the first match of any of `needles` from `pos`, returning its
+    /// index and the index of the needle. Earlier needles win ties.
+    ///
+    /// The buffer is scanned once for the first bytes of the needles, which
+    /// are compared only where one of those bytes occurs.
+    #[inline]
+    pub fn find_any_from(&self, needles: &[&[u8]], pos: usize) -> Option<(usize, usize)> {
+        self.find_any_of(needles, pos)
+    }
+
+    // Searches like `find_any_from` for needles of any byte container.
+    pub(crate) fn find_any_of<N>(&self, needles: &[N], mut pos: usize) -> Option<(usize, usize)>
+    where
+        N: AsRef<[u8]>,
+    {
+        let mut off = pos;
+        let mut idx = match self.locate_idx(&mut off) {
+            Ok(i) => i,
+            Err(..) => return None,
+        };
+        if let Some(i) = needles.iter().position(|n| n.as_ref().is_empty()) {
+            return Some((pos, i));
+        }
+        let mut firsts: Vec<u8> = needles.iter().map(|n| n.as_ref()[0]).collect();
+        firsts.sort();
+        firsts.dedup();
+        let mut table = [false; 256];
+        for &b in &firsts {
+            table[b as usize] = true;
+        }
+        let mut bytes = unsafe { self.blocks.get_unchecked(idx) }.as_bytes_from(off);
+        loop {
+            let mut from = 0;
+            while from < bytes.len() {
+                let rest = &bytes[from..];
+                let found = match firsts.len() {
+                    1 => memchr::memchr(firsts[0], rest),
+                    2 => memchr::memchr2(firsts[0], firsts[1], rest),
+                    3 => memchr::memchr3(firsts[0], firsts[1], firsts[2], rest),
+                    _ => rest.iter().position(|&b| table[b as usize]),
+                };
+                let n = match found {
+                    Some(n) => from + n,
+                    None => break,
+                };
+                let rest = &bytes[n..];
+                for (i, needle) in needles.iter().enumerate() {
+                    let needle = needle.as_ref();
+                    let matched = match rest.len() >= needle.len() {
+                        true => rest.starts_with(needle),
+                        // matches straddling the block boundary
+                        false => {
+                            needle.starts_with(rest)
+                                && self.starts_with_internal(&needle[rest.len()..], idx + 1)
+                        }
+                    };
+                    if matched {
+                        return Some((pos + n, i));
+                    }
+                }
+                from = n + 1;
+            }
+            idx += 1;
+            if idx >= self.blocks.len() {
+                return None;
+            }
+            pos += bytes.len();
+            bytes = unsafe { self.blocks.get_unchecked(idx) }.as_bytes();
+        }
+    }
+
+    #[inline]
+    pub fn find_any(&self, needles: &[&[u8]]) -> Option<(usize, usize)> {
+        self.find_any_from(needles, 0)
+    }
+
     pub fn rfind_from(&self, needle: &[u8], mut rpos: usize) -> Option<usize> {
-        rpos += needle.len();
+        rpos = rpos.saturating_add(needle.len());
         let mut off = rpos;
         let (mut idx, mut bytes) = match self.locate_idx(&mut off) {
             Ok(i) => (i, unsafe { self.blocks.get_unchecked(i) }.as_bytes_to(off)),
@@ -419,9 +569,14 @@ impl ByteBuf {
             return Some(rpos);
         }
         rpos -= off;
+        let finder = memmem::FinderRev::new(needle);
         loop {
             if bytes.len() >= needle.len() {
-                bytes = match bytes.windows(needle.len()).rposition(|w| w == needle) {
+                let found = match needle.len() {
+                    1 => memchr::memrchr(needle[0], bytes),
+                    _ => finder.rfind(bytes),
+                };
+                bytes = match found {
                     Some(n) => return Some(rpos + n),
                     None => &bytes[..needle.len() - 1],
                 };
@@ -429,6 +584,7 @@ impl ByteBuf {
             if idx == self.idx {
                 break;
             }
+            // matches straddling the block boundary
             for m in (1..bytes.len() + 1).rev() {
                 let (left, right) = needle.split_at(needle.len() - m);
                 if &bytes[..m] == right {
@@ -512,6 +668,16 @@ impl ByteBuf {
         writer::new(self)
     }
 
+    #[inline]
+    pub fn as_bit_reader(&mut self, order: BitOrder) -> BitReader {
+        bits::reader(self, order)
+    }
+
+    #[inline]
+    pub fn as_bit_writer(&mut self, order: BitOrder) -> BitWriter {
+        bits::writer(self, order)
+    }
+
     #[inline]
     pub fn as_hex_dump(&self) -> HexDump {
         hex_dump::new(self)
@@ -522,6 +688,21 @@ impl ByteBuf {
         bytes::new(self)
     }
 
+    // Readable bytes block by block, possibly including empty slices.
+    #[inline]
+    pub(crate) fn as_slices<'a>(&'a self) -> impl Iterator<Item = &'a [u8]> + 'a {
+        self.blocks[self.idx..].iter().map(Block::as_bytes)
+    }
+
+    // Readable bytes block by block, for rewriting them in place.
+    #[inline]
+    pub(crate) fn as_mut_slices<'a>(&'a mut self) -> impl Iterator<Item = &'a mut [u8]> + 'a {
+        self.blocks[self.idx..].iter_mut().map(|block| unsafe {
+            let off = block.read_pos() as isize;
+            slice::from_raw_parts_mut(block.as_mut_ptr().offset(off), block.len())
+        })
+    }
+
     #[inline]
     pub(crate) fn add_block(&mut self, block: Block) {
         self.blocks.push(block);
@@ -601,13 +782,66 @@ impl ByteBuf {
     }
 
     #[inline]
-    fn append_block(&mut self, min_capacity: usize) {
-        let cap = if min_capacity <= self.growth {
+    fn is_limited(&self) -> bool {
+        self.limit != usize::max_value()
+    }
+
+    #[inline]
+    fn new_block_capacity(&self, min_capacity: usize) -> usize {
+        if min_capacity <= self.growth {
             self.growth
         } else {
             min_capacity
-        };
+        }
+    }
+
+    // Returns the capacity of a new block of at least `min_capacity` bytes,
+    // or `Err` if it would exceed the limit.
+    fn try_new_block_capacity(&self, min_capacity: usize) -> Result<usize, ()> {
+        let cap = self.new_block_capacity(min_capacity);
+        if !self.is_limited() {
+            return Ok(cap);
+        }
+        let used = self.blocks[self.idx..]
+            .iter()
+            .fold(0, |n, b| n + b.capacity());
+        let left = self.limit.saturating_sub(used);
+        if left == 0 || left < min_capacity {
+            Err(())
+        } else if left < cap {
+            Ok(left)
+        } else {
+            Ok(cap)
+        }
+    }
+
+    // Drops the bytes after the first `len` ones, and the blocks holding them.
+    fn truncate(&mut self, mut len: usize) {
+        let mut i = self.idx;
+        while i < self.blocks.len() {
+            let block = &mut self.blocks[i];
+            i += 1;
+            if len <= block.len() {
+                let read_pos = block.read_pos();
+                block.set_write_pos(read_pos + len);
+                break;
+            }
+            len -= block.len();
+        }
+        self.blocks.truncate(i);
+    }
+
+    #[inline]
+    fn append_block(&mut self, min_capacity: usize) {
+        let cap = self.new_block_capacity(min_capacity);
+        self.blocks.push(Block::with_capacity(cap));
+    }
+
+    #[inline]
+    fn try_append_block(&mut self, min_capacity: usize) -> Result<(), ()> {
+        let cap = self.try_new_block_capacity(min_capacity)?;
         self.blocks.push(Block::with_capacity(cap));
+        Ok(())
     }
 
     #[inline]
@@ -632,16 +866,26 @@ impl ByteBuf {
         }
     }
 
+    #[inline]
+    fn try_append_bytes(&mut self, bytes: Vec<u8>) -> Result<(), ()> {
+        self.try_new_block_capacity(bytes.capacity())?;
+        self.append_bytes(bytes);
+        Ok(())
+    }
+
     #[inline]
     fn prepend_block(&mut self, min_capacity: usize) {
-        let cap = if min_capacity <= self.growth {
-            self.growth
-        } else {
-            min_capacity
-        };
+        let cap = self.new_block_capacity(min_capacity);
         self.blocks.insert(0, Block::for_prependable(cap));
     }
 
+    #[inline]
+    fn try_prepend_block(&mut self, min_capacity: usize) -> Result<(), ()> {
+        let cap = self.try_new_block_capacity(min_capacity)?;
+        self.blocks.insert(0, Block::for_prependable(cap));
+        Ok(())
+    }
+
     #[inline]
     fn prepend_bytes(&mut self, bytes: Vec<u8>) {
         let temp = match self.first_mut() {
@@ -659,6 +903,13 @@ impl ByteBuf {
             self.blocks.insert(0, block);
         }
     }
+
+    #[inline]
+    fn try_prepend_bytes(&mut self, bytes: Vec<u8>) -> Result<(), ()> {
+        self.try_new_block_capacity(bytes.capacity())?;
+        self.prepend_bytes(bytes);
+        Ok(())
+    }
 }
 
 impl Ord for ByteBuf {
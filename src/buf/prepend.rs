@@ -54,6 +54,8 @@ impl<'a> Prepender<'a> {
         }
     }
 
+    /// Adds a block of at least `min_capacity` bytes regardless of the limit
+    /// of the buffer.
     #[inline]
     pub fn prepend(&mut self, min_capacity: usize) {
         self.inner.prepend_block(min_capacity)
@@ -63,4 +65,16 @@ impl<'a> Prepender<'a> {
     pub fn prepend_bytes(&mut self, bytes: Vec<u8>) {
         self.inner.prepend_bytes(bytes)
     }
+
+    /// Adds a block of at least `min_capacity` bytes, or fails if the limit
+    /// of the buffer does not allow it.
+    #[inline]
+    pub fn try_prepend(&mut self, min_capacity: usize) -> Result<(), ()> {
+        self.inner.try_prepend_block(min_capacity)
+    }
+
+    #[inline]
+    pub fn try_prepend_bytes(&mut self, bytes: Vec<u8>) -> Result<(), ()> {
+        self.inner.try_prepend_bytes(bytes)
+    }
 }
@@ -0,0 +1,114 @@
+//! A compact, bincode-like binary format for serde over `ByteBuf`.
+//!
+//! Integers are varints (signed ones zigzag encoded), floats are
+//! little-endian, and strings, byte arrays, sequences and maps are prefixed
+//! by their length in varint. Enum variants are written as their index.
+//! The format is not self-describing, so `deserialize_any` is unsupported.
+//!
+//! ```ignore
+//! buf.append(&msg, serde::append)?;
+//! let msg: Msg = buf.read(serde::read)?;
+//! ```
+
+mod ser;
+pub use self::ser::Serializer;
+
+mod de;
+pub use self::de::Deserializer;
+
+use std::error;
+use std::fmt::{self, Display};
+
+use serde::{de as serde_de, ser as serde_ser, Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+
+use buf::{self, Appender, ByteBuf, ReadIter};
+
+#[derive(Debug)]
+pub enum Error {
+    Buf(buf::Error),
+    Overflow,
+    InvalidUtf8,
+    InvalidChar(u32),
+    UnknownLength,
+    Unsupported(&'static str),
+    Custom(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Buf(ref e) => Display::fmt(e, f),
+            Error::Overflow => write!(f, "Length overflow"),
+            Error::InvalidUtf8 => write!(f, "Invalid UTF-8"),
+            Error::InvalidChar(c) => write!(f, "Invalid char: {:#x}", c),
+            Error::UnknownLength => write!(f, "Sequence length must be known"),
+            Error::Unsupported(what) => write!(f, "Unsupported: {}", what),
+            Error::Custom(ref msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl error::Error for Error {
+    #[inline]
+    fn description(&self) -> &str {
+        "serde error"
+    }
+}
+
+impl From<buf::Error> for Error {
+    #[inline]
+    fn from(e: buf::Error) -> Self {
+        Error::Buf(e)
+    }
+}
+
+impl From<()> for Error {
+    #[inline]
+    fn from(_: ()) -> Self {
+        Error::Overflow
+    }
+}
+
+impl serde_ser::Error for Error {
+    #[inline]
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
+    }
+}
+
+impl serde_de::Error for Error {
+    #[inline]
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
+    }
+}
+
+pub fn append<T>(v: &T, chain: &mut Appender) -> Result<usize, Error>
+where
+    T: Serialize + ?Sized,
+{
+    let mut ser = Serializer::new(chain);
+    v.serialize(&mut ser)?;
+    Ok(ser.written())
+}
+
+pub fn read<T>(chain: &mut ReadIter) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(&mut Deserializer::new(chain))
+}
+
+/// Deserializes a value that may borrow `str`s and `[u8]`s from `buf`.
+///
+/// Bytes that lie within a single block are borrowed. Those spanning blocks
+/// are copied, so borrowing types such as `&str` fail on them while `Cow`
+/// falls back to an owned value.
+pub fn from_buf<'de, T>(buf: &'de mut ByteBuf) -> Result<T, Error>
+where
+    T: Deserialize<'de>,
+{
+    let mut chain = buf.read_iter();
+    T::deserialize(&mut Deserializer::new(&mut chain))
+}
@@ -44,7 +44,15 @@ impl<'a> Write for Writer<'a> {
                     block.set_write_pos(cap);
                 }
             }
-            self.inner.append_block(0);
+            if self.inner.try_append_block(0).is_err() {
+                return match buf.len() - n {
+                    0 => Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "Buffer limit exceeded",
+                    )),
+                    written => Ok(written),
+                };
+            }
         }
     }
 
@@ -17,8 +17,18 @@ extern crate bitflags;
 #[macro_use]
 extern crate failure;
 
+extern crate getrandom;
+
+extern crate memchr;
+
+#[cfg(feature = "flate")]
+extern crate miniz_oxide;
+
 extern crate net2;
 
+#[cfg(feature = "serde")]
+extern crate serde;
+
 #[macro_use]
 extern crate futures;
 
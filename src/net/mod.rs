@@ -1,6 +1,11 @@
 pub mod tcp;
 pub use self::tcp::{TcpListener, TcpListenerBuilder, TcpStream};
 
+#[cfg(unix)]
+pub mod udp;
+#[cfg(unix)]
+pub use self::udp::UdpSocket;
+
 #[inline]
 pub fn init() {
     ::sys::net::init()
@@ -9,6 +9,7 @@ use futures::stream::StreamFuture;
 use futures::sink::{Send, SendAll};
 
 use buf::ByteBuf;
+use proto::{Duplex, Framed};
 use sys::net::tcp;
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -408,13 +409,19 @@ where
     T: AsRef<TcpStream> + AsMut<TcpStream>,
 {
     #[inline]
-    fn try_from(io: T) -> io::Result<Self> {
+    pub(crate) fn try_from(io: T) -> io::Result<Self> {
         Ok(Sender {
             inner: tcp::Sender::try_from(io)?,
             buf: ByteBuf::new(),
         })
     }
 
+    /// Returns `io`, dropping any data not yet sent.
+    #[inline]
+    pub(crate) fn into_inner(self) -> T {
+        self.inner.into_inner()
+    }
+
     #[inline]
     pub fn into_twoway(self) -> (RecvHalf<T>, SendHalf<T>) {
         let (r, s) = self.inner.into_twoway();
@@ -616,3 +623,14 @@ where
         },
     ))
 }
+
+/// Splits `io` and frames both halves with `codec`, into one `Stream` and
+/// `Sink` of messages.
+#[inline]
+pub fn framed<T, C>(io: T, codec: C) -> io::Result<Framed<Duplex<RecvHalf<T>, SendHalf<T>>, C>>
+where
+    T: AsRef<TcpStream> + AsMut<TcpStream>,
+{
+    let (r, s) = split(io)?;
+    Ok(Framed::new(Duplex::new(r, s), codec))
+}
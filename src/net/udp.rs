@@ -0,0 +1,126 @@
+use std::fmt;
+use std::io;
+use std::net::{self, SocketAddr};
+
+use futures::{Async, Future, Poll};
+
+use sys::net::udp;
+
+/// A UDP socket, registered with the event loop of the current thread.
+///
+/// Only on Unix, as overlapped UDP I/O on Windows is not implemented.
+pub struct UdpSocket {
+    inner: udp::UdpSocket,
+}
+
+impl UdpSocket {
+    #[inline]
+    pub fn bind(addr: &SocketAddr) -> io::Result<Self> {
+        Ok(UdpSocket {
+            inner: udp::UdpSocket::try_from(net::UdpSocket::bind(addr)?)?,
+        })
+    }
+
+    #[inline]
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.as_inner().local_addr()
+    }
+
+    #[inline]
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        self.inner.as_inner().set_ttl(ttl)
+    }
+
+    #[inline]
+    pub fn ttl(&self) -> io::Result<u32> {
+        self.inner.as_inner().ttl()
+    }
+
+    #[inline]
+    pub fn set_broadcast(&self, broadcast: bool) -> io::Result<()> {
+        self.inner.as_inner().set_broadcast(broadcast)
+    }
+
+    #[inline]
+    pub fn broadcast(&self) -> io::Result<bool> {
+        self.inner.as_inner().broadcast()
+    }
+
+    /// Sends `buf` to `addr` as one datagram.
+    #[inline]
+    pub fn poll_send_to(&mut self, buf: &[u8], addr: &SocketAddr) -> Poll<usize, io::Error> {
+        self.inner.poll_send_to(buf, addr)
+    }
+
+    /// Receives a datagram into `buf`, truncating it if `buf` is too small.
+    #[inline]
+    pub fn poll_recv_from(&mut self, buf: &mut [u8]) -> Poll<(usize, SocketAddr), io::Error> {
+        self.inner.poll_recv_from(buf)
+    }
+
+    #[inline]
+    pub fn send_to(self, data: Vec<u8>, addr: SocketAddr) -> SendTo {
+        SendTo {
+            state: Some((self, data, addr)),
+        }
+    }
+
+    #[inline]
+    pub fn recv_from(self, buf: Vec<u8>) -> RecvFrom {
+        RecvFrom {
+            state: Some((self, buf)),
+        }
+    }
+}
+
+impl fmt::Debug for UdpSocket {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.as_inner().fmt(f)
+    }
+}
+
+/// Yields the socket and the data sent.
+pub struct SendTo {
+    state: Option<(UdpSocket, Vec<u8>, SocketAddr)>,
+}
+
+impl Future for SendTo {
+    type Item = (UdpSocket, Vec<u8>);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match self.state {
+            Some((ref mut socket, ref data, ref addr)) => {
+                try_ready!(socket.poll_send_to(data, addr));
+            }
+            None => panic!("Attempted to poll SendTo after completion"),
+        }
+        match self.state.take() {
+            Some((socket, data, _)) => Ok(Async::Ready((socket, data))),
+            None => ::unreachable(),
+        }
+    }
+}
+
+/// Yields the socket, the buffer, the length of the datagram received into
+/// it and the address of the sender.
+pub struct RecvFrom {
+    state: Option<(UdpSocket, Vec<u8>)>,
+}
+
+impl Future for RecvFrom {
+    type Item = (UdpSocket, Vec<u8>, usize, SocketAddr);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let (n, addr) = match self.state {
+            Some((ref mut socket, ref mut buf)) => try_ready!(socket.poll_recv_from(buf)),
+            None => panic!("Attempted to poll RecvFrom after completion"),
+        };
+        match self.state.take() {
+            Some((socket, buf)) => Ok(Async::Ready((socket, buf, n, addr))),
+            None => ::unreachable(),
+        }
+    }
+}
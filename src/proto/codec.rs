@@ -0,0 +1,37 @@
+use std::io;
+
+use buf::ByteBuf;
+
+/// Decodes frames of type `Item` from the front of the data received so far.
+pub trait Decoder {
+    type Item;
+    type Error: From<io::Error>;
+
+    /// Splits the next item off the front of `data`, or returns `None` if
+    /// `data` does not hold a whole one yet.
+    fn decode(&mut self, data: &mut ByteBuf) -> Result<Option<Self::Item>, Self::Error>;
+
+    /// Decodes the rest of `data` once the stream has ended. By default a
+    /// partial frame left in `data` is an `UnexpectedEof` error.
+    fn decode_eof(&mut self, data: &mut ByteBuf) -> Result<Option<Self::Item>, Self::Error> {
+        match self.decode(data)? {
+            Some(item) => Ok(Some(item)),
+            None => match data.is_empty() {
+                true => Ok(None),
+                false => Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Stream ended within a frame",
+                ).into()),
+            },
+        }
+    }
+}
+
+/// Encodes items of type `Item` as frames.
+pub trait Encoder {
+    type Item;
+    type Error: From<io::Error>;
+
+    /// Appends the frame of `item` to `dst`.
+    fn encode(&mut self, item: Self::Item, dst: &mut ByteBuf) -> Result<(), Self::Error>;
+}
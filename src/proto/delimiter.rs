@@ -0,0 +1,155 @@
+use std::io;
+
+use buf::ByteBuf;
+use buf::codec::u8s;
+
+use super::{frame_too_long, max_frame_len, put, Decoder, Encoder, Framed};
+
+/// Frames a byte stream by one or more delimiters.
+///
+/// A search that finds no delimiter is resumed where it stopped once more
+/// data arrives, so a long frame is scanned only once.
+#[derive(Debug, Clone)]
+pub struct DelimiterCodec {
+    delimiters: Vec<Vec<u8>>,
+    keep: bool,
+    max_len: usize,
+    // Where to resume searching in the data not yet decoded.
+    searched: usize,
+}
+
+impl DelimiterCodec {
+    #[inline]
+    pub fn new(delimiter: &[u8]) -> Self {
+        Self::any(&[delimiter])
+    }
+
+    /// Splits on whichever of `delimiters` comes first. Frames are encoded
+    /// with the first one.
+    pub fn any(delimiters: &[&[u8]]) -> Self {
+        assert!(!delimiters.is_empty(), "No delimiter");
+        assert!(
+            delimiters.iter().all(|d| !d.is_empty()),
+            "Empty delimiter"
+        );
+        DelimiterCodec {
+            delimiters: delimiters.iter().map(|d| d.to_vec()).collect(),
+            keep: false,
+            max_len: max_frame_len(),
+            searched: 0,
+        }
+    }
+
+    #[inline]
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    /// Sets the maximum length of a frame, excluding its delimiter. Longer
+    /// frames fail with `FrameTooLong`.
+    #[inline]
+    pub fn set_max_len(&mut self, max_len: usize) -> &mut Self {
+        self.max_len = max_len;
+        self
+    }
+
+    #[inline]
+    pub fn keep_delimiter(&self) -> bool {
+        self.keep
+    }
+
+    /// Sets whether decoded frames end with their delimiter. Defaults to
+    /// `false`.
+    #[inline]
+    pub fn set_keep_delimiter(&mut self, keep: bool) -> &mut Self {
+        self.keep = keep;
+        self
+    }
+
+    #[inline]
+    pub fn frames<S>(self, stream: S) -> Framed<S, Self> {
+        Framed::new(stream, self)
+    }
+
+    fn find(&self, data: &ByteBuf) -> Option<(usize, usize)> {
+        if self.delimiters.len() == 1 {
+            let delimiter = &self.delimiters[0];
+            return data.find_from(delimiter, self.searched)
+                .map(|i| (i, delimiter.len()));
+        }
+        data.find_any_of(&self.delimiters, self.searched)
+            .map(|(i, n)| (i, self.delimiters[n].len()))
+    }
+}
+
+impl Decoder for DelimiterCodec {
+    type Item = ByteBuf;
+    type Error = io::Error;
+
+    fn decode(&mut self, data: &mut ByteBuf) -> io::Result<Option<ByteBuf>> {
+        match self.find(data) {
+            Some((i, n)) => {
+                self.searched = 0;
+                if i > self.max_len {
+                    return Err(frame_too_long(i, self.max_len));
+                }
+                let len = if self.keep { i + n } else { i };
+                let frame = match data.drain_to(len) {
+                    Ok(frame) => frame,
+                    _ => ::unreachable(),
+                };
+                if !self.keep {
+                    data.skip(n);
+                }
+                Ok(Some(frame))
+            }
+            None => {
+                let longest = self.delimiters.iter().map(|d| d.len()).max().unwrap_or(1);
+                self.searched = (data.len() + 1).saturating_sub(longest);
+                if self.searched > self.max_len {
+                    return Err(frame_too_long(self.searched, self.max_len));
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    /// Takes any bytes after the last delimiter as the last frame.
+    fn decode_eof(&mut self, data: &mut ByteBuf) -> io::Result<Option<ByteBuf>> {
+        if let Some(frame) = self.decode(data)? {
+            return Ok(Some(frame));
+        }
+        self.searched = 0;
+        let len = data.len();
+        if len == 0 {
+            return Ok(None);
+        }
+        if len > self.max_len {
+            return Err(frame_too_long(len, self.max_len));
+        }
+        Ok(data.drain_to(len).ok())
+    }
+}
+
+/// Appends the first delimiter to each frame, which must not contain any.
+impl Encoder for DelimiterCodec {
+    type Item = ByteBuf;
+    type Error = io::Error;
+
+    fn encode(&mut self, mut data: ByteBuf, dst: &mut ByteBuf) -> io::Result<()> {
+        let len = data.len();
+        if len > self.max_len {
+            return Err(frame_too_long(len, self.max_len));
+        }
+        if data.find_any_of(&self.delimiters, 0).is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Frame contains a delimiter",
+            ));
+        }
+        data.append(&self.delimiters[0][..], u8s::append)
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "Buffer limit exceeded"))?;
+        put(dst, data);
+        Ok(())
+    }
+}
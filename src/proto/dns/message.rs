@@ -0,0 +1,612 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::str;
+
+/// The maximum length of a message over UDP without EDNS.
+pub const MAX_UDP_LEN: usize = 512;
+
+/// Types of records and questions.
+pub mod rtype {
+    pub const A: u16 = 1;
+    pub const NS: u16 = 2;
+    pub const CNAME: u16 = 5;
+    pub const SOA: u16 = 6;
+    pub const PTR: u16 = 12;
+    pub const MX: u16 = 15;
+    pub const TXT: u16 = 16;
+    pub const AAAA: u16 = 28;
+    pub const SRV: u16 = 33;
+    pub const OPT: u16 = 41;
+    pub const ANY: u16 = 255;
+}
+
+pub mod class {
+    pub const IN: u16 = 1;
+    pub const CH: u16 = 3;
+    pub const ANY: u16 = 255;
+}
+
+/// Response codes, where those above 15 need EDNS.
+pub mod rcode {
+    pub const NO_ERROR: u16 = 0;
+    pub const FORM_ERR: u16 = 1;
+    pub const SERV_FAIL: u16 = 2;
+    pub const NX_DOMAIN: u16 = 3;
+    pub const NOT_IMP: u16 = 4;
+    pub const REFUSED: u16 = 5;
+    pub const BADVERS: u16 = 16;
+}
+
+#[inline]
+pub(super) fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[inline]
+fn invalid_input(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Question {
+    /// The domain name, without the trailing dot.
+    pub name: String,
+    pub qtype: u16,
+    pub qclass: u16,
+}
+
+impl Question {
+    #[inline]
+    pub fn new(name: &str, qtype: u16) -> Self {
+        Question {
+            name: name.trim_end_matches('.').to_string(),
+            qtype,
+            qclass: class::IN,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RData {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    Ns(String),
+    Cname(String),
+    Ptr(String),
+    Mx {
+        preference: u16,
+        exchange: String,
+    },
+    Txt(Vec<Vec<u8>>),
+    Srv {
+        priority: u16,
+        weight: u16,
+        port: u16,
+        target: String,
+    },
+    Soa {
+        mname: String,
+        rname: String,
+        serial: u32,
+        refresh: u32,
+        retry: u32,
+        expire: u32,
+        minimum: u32,
+    },
+    /// The data of any other type, as is.
+    Other(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub name: String,
+    pub rtype: u16,
+    pub class: u16,
+    pub ttl: u32,
+    pub data: RData,
+}
+
+impl Record {
+    /// Returns a record of `IN` class, of the type of `data` unless it is
+    /// `RData::Other`.
+    pub fn new(name: &str, ttl: u32, data: RData) -> Self {
+        let rtype = match data {
+            RData::A(..) => rtype::A,
+            RData::Aaaa(..) => rtype::AAAA,
+            RData::Ns(..) => rtype::NS,
+            RData::Cname(..) => rtype::CNAME,
+            RData::Ptr(..) => rtype::PTR,
+            RData::Mx { .. } => rtype::MX,
+            RData::Txt(..) => rtype::TXT,
+            RData::Srv { .. } => rtype::SRV,
+            RData::Soa { .. } => rtype::SOA,
+            RData::Other(..) => 0,
+        };
+        Record {
+            name: name.trim_end_matches('.').to_string(),
+            rtype,
+            class: class::IN,
+            ttl,
+            data,
+        }
+    }
+}
+
+/// The EDNS (RFC 6891) pseudo-record `OPT` of a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edns {
+    /// The largest UDP payload the sender can receive.
+    pub udp_size: u16,
+    pub version: u8,
+    /// The DO bit, i.e. whether DNSSEC records are wanted.
+    pub dnssec_ok: bool,
+    pub options: Vec<(u16, Vec<u8>)>,
+}
+
+impl Edns {
+    #[inline]
+    pub fn new(udp_size: u16) -> Self {
+        Edns {
+            udp_size,
+            version: 0,
+            dnssec_ok: false,
+            options: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Message {
+    pub id: u16,
+    /// Whether the message is a response rather than a query.
+    pub response: bool,
+    pub opcode: u8,
+    pub authoritative: bool,
+    pub truncated: bool,
+    pub recursion_desired: bool,
+    pub recursion_available: bool,
+    /// The response code, including the bits in `edns`.
+    pub rcode: u16,
+    pub questions: Vec<Question>,
+    pub answers: Vec<Record>,
+    pub authorities: Vec<Record>,
+    /// The additional records, without the `OPT` record of `edns`.
+    pub additionals: Vec<Record>,
+    pub edns: Option<Edns>,
+}
+
+impl Message {
+    /// Returns a query of `question` asking for recursion.
+    #[inline]
+    pub fn query(id: u16, question: Question) -> Self {
+        Message {
+            id,
+            recursion_desired: true,
+            questions: vec![question],
+            ..Default::default()
+        }
+    }
+
+    /// Returns an empty response to `query`.
+    #[inline]
+    pub fn response(query: &Message) -> Self {
+        Message {
+            id: query.id,
+            response: true,
+            opcode: query.opcode,
+            recursion_desired: query.recursion_desired,
+            questions: query.questions.clone(),
+            ..Default::default()
+        }
+    }
+
+    pub fn encode(&self) -> io::Result<Vec<u8>> {
+        let mut e = Encoder::default();
+        e.u16(self.id);
+        let rcode = match (self.rcode, &self.edns) {
+            (rcode, &None) if rcode > 0x0F => {
+                return Err(invalid_input("Extended DNS response code without EDNS"))
+            }
+            (rcode, _) if rcode > 0x0FFF => return Err(invalid_input("Invalid DNS response code")),
+            (rcode, _) => rcode,
+        };
+        let flags = (self.response as u16) << 15 | (u16::from(self.opcode) & 0x0F) << 11
+            | (self.authoritative as u16) << 10 | (self.truncated as u16) << 9
+            | (self.recursion_desired as u16) << 8
+            | (self.recursion_available as u16) << 7 | rcode & 0x0F;
+        e.u16(flags);
+        let additionals = self.additionals.len() + self.edns.is_some() as usize;
+        for &n in &[
+            self.questions.len(),
+            self.answers.len(),
+            self.authorities.len(),
+            additionals,
+        ] {
+            if n > 0xFFFF {
+                return Err(invalid_input("Too many DNS records"));
+            }
+            e.u16(n as u16);
+        }
+        for q in &self.questions {
+            e.name(&q.name, true)?;
+            e.u16(q.qtype);
+            e.u16(q.qclass);
+        }
+        for r in self.answers
+            .iter()
+            .chain(&self.authorities)
+            .chain(&self.additionals)
+        {
+            e.record(r)?;
+        }
+        if let Some(ref edns) = self.edns {
+            e.buf.push(0);
+            e.u16(rtype::OPT);
+            e.u16(edns.udp_size);
+            e.buf.push((rcode >> 4) as u8);
+            e.buf.push(edns.version);
+            e.u16((edns.dnssec_ok as u16) << 15);
+            let start = e.start_rdata();
+            for &(code, ref data) in &edns.options {
+                if data.len() > 0xFFFF {
+                    return Err(invalid_input("EDNS option too long"));
+                }
+                e.u16(code);
+                e.u16(data.len() as u16);
+                e.buf.extend_from_slice(data);
+            }
+            e.end_rdata(start)?;
+        }
+        Ok(e.buf)
+    }
+
+    pub fn decode(data: &[u8]) -> io::Result<Self> {
+        let mut d = Decoder { data, pos: 0 };
+        let id = d.u16()?;
+        let flags = d.u16()?;
+        let mut counts = [0; 4];
+        for n in &mut counts {
+            *n = d.u16()? as usize;
+        }
+        let mut msg = Message {
+            id,
+            response: flags & 0x8000 != 0,
+            opcode: ((flags >> 11) & 0x0F) as u8,
+            authoritative: flags & 0x0400 != 0,
+            truncated: flags & 0x0200 != 0,
+            recursion_desired: flags & 0x0100 != 0,
+            recursion_available: flags & 0x0080 != 0,
+            rcode: flags & 0x0F,
+            ..Default::default()
+        };
+        for _ in 0..counts[0] {
+            msg.questions.push(Question {
+                name: d.name()?,
+                qtype: d.u16()?,
+                qclass: d.u16()?,
+            });
+        }
+        for _ in 0..counts[1] {
+            msg.answers.push(d.record()?);
+        }
+        for _ in 0..counts[2] {
+            msg.authorities.push(d.record()?);
+        }
+        for _ in 0..counts[3] {
+            let r = d.record()?;
+            if r.rtype != rtype::OPT {
+                msg.additionals.push(r);
+                continue;
+            }
+            if msg.edns.is_some() || !r.name.is_empty() {
+                return Err(invalid_data("Invalid DNS OPT record"));
+            }
+            let data = match r.data {
+                RData::Other(data) => data,
+                _ => ::unreachable(),
+            };
+            let mut options = Vec::new();
+            let mut od = Decoder {
+                data: &data,
+                pos: 0,
+            };
+            while od.pos < data.len() {
+                let code = od.u16()?;
+                let n = od.u16()? as usize;
+                options.push((code, od.bytes(n)?.to_vec()));
+            }
+            msg.rcode |= ((r.ttl >> 24) as u16) << 4;
+            msg.edns = Some(Edns {
+                udp_size: r.class,
+                version: (r.ttl >> 16) as u8,
+                dnssec_ok: r.ttl & 0x8000 != 0,
+                options,
+            });
+        }
+        Ok(msg)
+    }
+}
+
+#[derive(Default)]
+struct Encoder {
+    buf: Vec<u8>,
+    // The offsets of the names written, by their lowercase.
+    names: HashMap<String, u16>,
+}
+
+impl Encoder {
+    #[inline]
+    fn u16(&mut self, v: u16) {
+        self.buf.push((v >> 8) as u8);
+        self.buf.push(v as u8);
+    }
+
+    #[inline]
+    fn u32(&mut self, v: u32) {
+        self.u16((v >> 16) as u16);
+        self.u16(v as u16);
+    }
+
+    // Writes `name`, ending with a pointer to the longest suffix written
+    // before if `compress`.
+    fn name(&mut self, name: &str, compress: bool) -> io::Result<()> {
+        let name = name.trim_end_matches('.');
+        if name.len() > 253 {
+            return Err(invalid_input("DNS name too long"));
+        }
+        let mut suffix = name;
+        while !suffix.is_empty() {
+            let key = suffix.to_lowercase();
+            if compress {
+                if let Some(&off) = self.names.get(&key) {
+                    self.u16(0xC000 | off);
+                    return Ok(());
+                }
+            }
+            if self.buf.len() < 0x4000 {
+                self.names.entry(key).or_insert(self.buf.len() as u16);
+            }
+            let (label, rest) = match suffix.find('.') {
+                Some(i) => (&suffix[..i], &suffix[i + 1..]),
+                None => (suffix, ""),
+            };
+            if label.is_empty() || label.len() > 63 {
+                return Err(invalid_input("Invalid DNS label"));
+            }
+            self.buf.push(label.len() as u8);
+            self.buf.extend_from_slice(label.as_bytes());
+            suffix = rest;
+        }
+        self.buf.push(0);
+        Ok(())
+    }
+
+    #[inline]
+    fn start_rdata(&mut self) -> usize {
+        self.u16(0);
+        self.buf.len()
+    }
+
+    fn end_rdata(&mut self, start: usize) -> io::Result<()> {
+        let n = self.buf.len() - start;
+        if n > 0xFFFF {
+            return Err(invalid_input("DNS record data too long"));
+        }
+        self.buf[start - 2] = (n >> 8) as u8;
+        self.buf[start - 1] = n as u8;
+        Ok(())
+    }
+
+    fn record(&mut self, r: &Record) -> io::Result<()> {
+        self.name(&r.name, true)?;
+        self.u16(r.rtype);
+        self.u16(r.class);
+        self.u32(r.ttl);
+        let start = self.start_rdata();
+        match r.data {
+            RData::A(ref ip) => self.buf.extend_from_slice(&ip.octets()),
+            RData::Aaaa(ref ip) => self.buf.extend_from_slice(&ip.octets()),
+            RData::Ns(ref name) | RData::Cname(ref name) | RData::Ptr(ref name) => {
+                self.name(name, true)?
+            }
+            RData::Mx {
+                preference,
+                ref exchange,
+            } => {
+                self.u16(preference);
+                self.name(exchange, true)?;
+            }
+            RData::Txt(ref strings) => for s in strings {
+                if s.len() > 255 {
+                    return Err(invalid_input("DNS character string too long"));
+                }
+                self.buf.push(s.len() as u8);
+                self.buf.extend_from_slice(s);
+            },
+            // The target of SRV must not be compressed (RFC 2782).
+            RData::Srv {
+                priority,
+                weight,
+                port,
+                ref target,
+            } => {
+                self.u16(priority);
+                self.u16(weight);
+                self.u16(port);
+                self.name(target, false)?;
+            }
+            RData::Soa {
+                ref mname,
+                ref rname,
+                serial,
+                refresh,
+                retry,
+                expire,
+                minimum,
+            } => {
+                self.name(mname, true)?;
+                self.name(rname, true)?;
+                for &v in &[serial, refresh, retry, expire, minimum] {
+                    self.u32(v);
+                }
+            }
+            RData::Other(ref data) => self.buf.extend_from_slice(data),
+        }
+        self.end_rdata(start)
+    }
+}
+
+struct Decoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    #[inline]
+    fn bytes(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.data.len() - self.pos < n {
+            return Err(invalid_data("Truncated DNS message"));
+        }
+        self.pos += n;
+        Ok(&self.data[self.pos - n..self.pos])
+    }
+
+    #[inline]
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    #[inline]
+    fn u16(&mut self) -> io::Result<u16> {
+        let b = self.bytes(2)?;
+        Ok((b[0] as u16) << 8 | b[1] as u16)
+    }
+
+    #[inline]
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok((self.u16()? as u32) << 16 | self.u16()? as u32)
+    }
+
+    // Reads a name, following pointers only to before the start of the
+    // labels read so far, so that they cannot loop.
+    fn name(&mut self) -> io::Result<String> {
+        let mut name = String::new();
+        let mut pos = self.pos;
+        let mut start = pos;
+        let mut end = None;
+        loop {
+            let n = match self.data.get(pos) {
+                Some(&n) => n as usize,
+                None => return Err(invalid_data("Truncated DNS message")),
+            };
+            match n & 0xC0 {
+                0x00 if n == 0 => break,
+                0x00 => {
+                    let label = match self.data.get(pos + 1..pos + 1 + n) {
+                        Some(label) => label,
+                        None => return Err(invalid_data("Truncated DNS message")),
+                    };
+                    let label =
+                        str::from_utf8(label).map_err(|_| invalid_data("Invalid DNS label"))?;
+                    if !name.is_empty() {
+                        name.push('.');
+                    }
+                    name.push_str(label);
+                    if name.len() > 253 {
+                        return Err(invalid_data("DNS name too long"));
+                    }
+                    pos += 1 + n;
+                }
+                0xC0 => {
+                    let ptr = match self.data.get(pos + 1) {
+                        Some(&lo) => (n & 0x3F) << 8 | lo as usize,
+                        None => return Err(invalid_data("Truncated DNS message")),
+                    };
+                    if ptr >= start {
+                        return Err(invalid_data("Invalid DNS name pointer"));
+                    }
+                    end = end.or(Some(pos + 2));
+                    pos = ptr;
+                    start = ptr;
+                }
+                _ => return Err(invalid_data("Invalid DNS label")),
+            }
+        }
+        self.pos = end.unwrap_or(pos + 1);
+        Ok(name)
+    }
+
+    fn record(&mut self) -> io::Result<Record> {
+        let name = self.name()?;
+        let rtype = self.u16()?;
+        let class = self.u16()?;
+        let ttl = self.u32()?;
+        let n = self.u16()? as usize;
+        let end = self.pos + n;
+        if end > self.data.len() {
+            return Err(invalid_data("Truncated DNS message"));
+        }
+        // Names in the data may point anywhere before, so the data is read
+        // in place.
+        let data = match rtype {
+            rtype::A => {
+                let b = self.bytes(n)?;
+                match n {
+                    4 => RData::A(Ipv4Addr::new(b[0], b[1], b[2], b[3])),
+                    _ => return Err(invalid_data("Invalid DNS A record")),
+                }
+            }
+            rtype::AAAA => {
+                let b = self.bytes(n)?;
+                if n != 16 {
+                    return Err(invalid_data("Invalid DNS AAAA record"));
+                }
+                let mut ip = [0; 16];
+                ip.copy_from_slice(b);
+                RData::Aaaa(Ipv6Addr::from(ip))
+            }
+            rtype::NS => RData::Ns(self.name()?),
+            rtype::CNAME => RData::Cname(self.name()?),
+            rtype::PTR => RData::Ptr(self.name()?),
+            rtype::MX => RData::Mx {
+                preference: self.u16()?,
+                exchange: self.name()?,
+            },
+            rtype::TXT => {
+                let mut strings = Vec::new();
+                while self.pos < end {
+                    let len = self.u8()? as usize;
+                    strings.push(self.bytes(len)?.to_vec());
+                }
+                RData::Txt(strings)
+            }
+            rtype::SRV => RData::Srv {
+                priority: self.u16()?,
+                weight: self.u16()?,
+                port: self.u16()?,
+                target: self.name()?,
+            },
+            rtype::SOA => RData::Soa {
+                mname: self.name()?,
+                rname: self.name()?,
+                serial: self.u32()?,
+                refresh: self.u32()?,
+                retry: self.u32()?,
+                expire: self.u32()?,
+                minimum: self.u32()?,
+            },
+            _ => RData::Other(self.bytes(n)?.to_vec()),
+        };
+        if self.pos != end {
+            return Err(invalid_data("Invalid DNS record length"));
+        }
+        Ok(Record {
+            name,
+            rtype,
+            class,
+            ttl,
+            data,
+        })
+    }
+}
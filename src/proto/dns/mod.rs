@@ -0,0 +1,11 @@
+//! DNS messages (RFC 1035) with EDNS (RFC 6891), and a stub resolver which
+//! asks recursive servers over UDP, and over TCP for truncated responses.
+//! The resolver is only on Unix, as it needs `net::UdpSocket`.
+
+mod message;
+pub use self::message::*;
+
+#[cfg(unix)]
+mod resolver;
+#[cfg(unix)]
+pub use self::resolver::*;
@@ -0,0 +1,592 @@
+use std::cell::RefCell;
+use std::cmp;
+use std::collections::HashMap;
+use std::fs;
+use std::mem;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use futures::{Async, Future, Poll, Sink, Stream};
+use futures::sink::Send;
+
+use buf::ByteBuf;
+use net::{TcpStream, UdpSocket};
+use net::tcp::{self, Recv, Sender};
+use proto::random;
+use reactor::Timer;
+
+use super::{invalid_data, rcode, rtype, Edns, Message, Question, RData, Record};
+
+/// The UDP payload size advertised with EDNS, which avoids fragmentation
+/// on common paths.
+pub const EDNS_UDP_SIZE: u16 = 1232;
+
+// The number of CNAME records followed for a query before giving up.
+const MAX_CNAMES: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    pub nameservers: Vec<SocketAddr>,
+    /// The domains to append to names of fewer than `ndots` dots.
+    pub search: Vec<String>,
+    pub ndots: usize,
+    /// The time to wait for a response from a server.
+    pub timeout: Duration,
+    /// The number of times to try each server.
+    pub attempts: usize,
+}
+
+impl Default for Config {
+    #[inline]
+    fn default() -> Self {
+        Config {
+            nameservers: vec![SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 53)],
+            search: Vec::new(),
+            ndots: 1,
+            timeout: Duration::from_secs(5),
+            attempts: 2,
+        }
+    }
+}
+
+impl Config {
+    /// Parses the content of `resolv.conf`. Unknown and invalid lines are
+    /// ignored, like the C library does.
+    pub fn parse(conf: &str) -> Self {
+        let mut config = Config {
+            nameservers: Vec::new(),
+            ..Default::default()
+        };
+        for line in conf.lines() {
+            let line = match line.find(&['#', ';'][..]) {
+                Some(i) => &line[..i],
+                None => line,
+            };
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("nameserver") => {
+                    // Zone indices of IPv6 addresses are not supported.
+                    let ip = fields.next().and_then(|f| f.split('%').next());
+                    if let Some(ip) = ip.and_then(|ip| ip.parse().ok()) {
+                        config.nameservers.push(SocketAddr::new(ip, 53));
+                    }
+                }
+                Some("domain") => {
+                    if let Some(domain) = fields.next() {
+                        config.search = vec![domain.trim_end_matches('.').to_string()];
+                    }
+                }
+                Some("search") => {
+                    config.search = fields
+                        .map(|domain| domain.trim_end_matches('.').to_string())
+                        .collect()
+                }
+                Some("options") => for option in fields {
+                    let mut kv = option.splitn(2, ':');
+                    let (key, value) = (kv.next(), kv.next().and_then(|v| v.parse().ok()));
+                    match (key, value) {
+                        (Some("ndots"), Some(n)) => config.ndots = cmp::min(n, 15),
+                        (Some("timeout"), Some(n)) => {
+                            config.timeout = Duration::from_secs(cmp::max(n, 1) as u64)
+                        }
+                        (Some("attempts"), Some(n)) => config.attempts = cmp::max(n, 1),
+                        _ => (),
+                    }
+                },
+                _ => (),
+            }
+        }
+        if config.nameservers.is_empty() {
+            config.nameservers = Config::default().nameservers;
+        }
+        config
+    }
+
+    /// Reads `/etc/resolv.conf`.
+    #[inline]
+    pub fn system() -> io::Result<Self> {
+        Ok(Config::parse(&fs::read_to_string("/etc/resolv.conf")?))
+    }
+
+    // Returns the names to try for `name` in order.
+    fn names(&self, name: &str) -> Vec<String> {
+        if name.ends_with('.') {
+            return vec![name.trim_end_matches('.').to_string()];
+        }
+        let searched = self.search
+            .iter()
+            .map(|domain| format!("{}.{}", name, domain));
+        match name.matches('.').count() >= self.ndots {
+            true => Some(name.to_string()).into_iter().chain(searched).collect(),
+            false => searched.chain(Some(name.to_string())).collect(),
+        }
+    }
+}
+
+// Records of a name and type, or `None` if the name does not exist.
+struct Entry {
+    expires: Instant,
+    records: Option<Vec<Record>>,
+}
+
+struct Inner {
+    config: Config,
+    cache: RefCell<HashMap<(String, u16), Entry>>,
+}
+
+impl Inner {
+    fn cached(&self, name: &str, qtype: u16) -> Option<Option<Vec<Record>>> {
+        let mut cache = self.cache.borrow_mut();
+        let key = (name.to_lowercase(), qtype);
+        match cache.get(&key) {
+            Some(entry) if entry.expires > Instant::now() => return Some(entry.records.clone()),
+            Some(..) => (),
+            None => return None,
+        }
+        cache.remove(&key);
+        None
+    }
+
+    fn cache(&self, name: &str, qtype: u16, ttl: u32, records: Option<Vec<Record>>) {
+        if ttl == 0 {
+            return;
+        }
+        let entry = Entry {
+            expires: Instant::now() + Duration::from_secs(u64::from(ttl)),
+            records,
+        };
+        self.cache
+            .borrow_mut()
+            .insert((name.to_lowercase(), qtype), entry);
+    }
+}
+
+/// A stub resolver, which asks the recursive servers of its `Config` and
+/// caches their answers.
+///
+/// Clones share the cache. It has to be used in the thread of the event
+/// loop it was first used in.
+#[derive(Clone)]
+pub struct Resolver {
+    inner: Rc<Inner>,
+}
+
+impl Resolver {
+    #[inline]
+    pub fn new(config: Config) -> Self {
+        Resolver {
+            inner: Rc::new(Inner {
+                config,
+                cache: RefCell::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Returns a resolver of the system configuration.
+    #[inline]
+    pub fn system() -> io::Result<Self> {
+        Ok(Self::new(Config::system()?))
+    }
+
+    #[inline]
+    pub fn config(&self) -> &Config {
+        &self.inner.config
+    }
+
+    /// Looks up the records of `qtype` of `name`, following CNAME records.
+    /// Fails with `NotFound` if the name does not exist.
+    #[inline]
+    pub fn query(&self, name: &str, qtype: u16) -> Query {
+        Query {
+            inner: self.inner.clone(),
+            names: self.inner.config.names(name),
+            qtype,
+            attempt: 0,
+            cnames: 0,
+            error: None,
+            state: State::Start,
+        }
+    }
+
+    /// Looks up the IPv4 and IPv6 addresses of `host`, IPv4 first.
+    pub fn lookup_ip(&self, host: &str) -> LookupIp {
+        if let Ok(ip) = host.parse() {
+            return LookupIp {
+                queries: [None, None],
+                addrs: [vec![ip], Vec::new()],
+                error: None,
+            };
+        }
+        LookupIp {
+            queries: [
+                Some(self.query(host, rtype::A)),
+                Some(self.query(host, rtype::AAAA)),
+            ],
+            addrs: [Vec::new(), Vec::new()],
+            error: None,
+        }
+    }
+}
+
+#[inline]
+fn query_id() -> io::Result<u16> {
+    let mut id = [0; 2];
+    random::fill(&mut id)?;
+    Ok(u16::from_be_bytes(id))
+}
+
+#[inline]
+fn timed_out() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "DNS query timed out")
+}
+
+// Whether `response` answers `query`.
+fn is_response(query: &Message, response: &Message) -> bool {
+    response.response && response.id == query.id
+        && response.questions.len() == query.questions.len()
+        && response
+            .questions
+            .iter()
+            .zip(&query.questions)
+            .all(|(r, q)| {
+                r.qtype == q.qtype && r.qclass == q.qclass
+                    && r.name.eq_ignore_ascii_case(&q.name)
+            })
+}
+
+struct Udp {
+    socket: UdpSocket,
+    server: SocketAddr,
+    sent: bool,
+    buf: Vec<u8>,
+}
+
+impl Udp {
+    fn poll(&mut self, query: &Message, request: &[u8]) -> Poll<Message, io::Error> {
+        if !self.sent {
+            try_ready!(self.socket.poll_send_to(request, &self.server));
+            self.sent = true;
+        }
+        // Anything but a response from the server is ignored.
+        loop {
+            let (n, from) = try_ready!(self.socket.poll_recv_from(&mut self.buf));
+            if from != self.server {
+                continue;
+            }
+            match Message::decode(&self.buf[..n]) {
+                Ok(ref response) if !is_response(query, response) => continue,
+                Ok(response) => return Ok(Async::Ready(response)),
+                Err(..) => continue,
+            }
+        }
+    }
+}
+
+enum Tcp {
+    Connecting(tcp::Connect<TcpStream>),
+    Sending(Send<Sender<TcpStream>>),
+    Receiving(Recv<TcpStream>, Vec<u8>),
+}
+
+impl Tcp {
+    fn poll(&mut self, query: &Message, request: &[u8]) -> Poll<Message, io::Error> {
+        loop {
+            let next = match *self {
+                Tcp::Connecting(ref mut connect) => {
+                    let sender = try_ready!(connect.poll());
+                    let mut framed = Vec::with_capacity(2 + request.len());
+                    framed.push((request.len() >> 8) as u8);
+                    framed.push(request.len() as u8);
+                    framed.extend_from_slice(request);
+                    Tcp::Sending(sender.send(ByteBuf::from(framed)))
+                }
+                Tcp::Sending(ref mut send) => {
+                    let sender = try_ready!(send.poll());
+                    Tcp::Receiving(sender.into_recv(), Vec::new())
+                }
+                Tcp::Receiving(ref mut recv, ref mut data) => {
+                    match try_ready!(recv.poll()) {
+                        Some(buf) => data.extend_from_slice(&buf.as_bytes()),
+                        None => {
+                            return Err(io::Error::new(
+                                io::ErrorKind::UnexpectedEof,
+                                "DNS server closed the connection",
+                            ))
+                        }
+                    }
+                    if data.len() < 2 {
+                        continue;
+                    }
+                    let n = 2 + ((data[0] as usize) << 8 | data[1] as usize);
+                    if data.len() < n {
+                        continue;
+                    }
+                    let response = Message::decode(&data[2..n])?;
+                    if !is_response(query, &response) {
+                        return Err(invalid_data("Unexpected DNS response"));
+                    }
+                    return Ok(Async::Ready(response));
+                }
+            };
+            *self = next;
+        }
+    }
+}
+
+enum Exchange {
+    Udp(Udp),
+    Tcp(Tcp),
+}
+
+enum State {
+    Start,
+    Exchanging {
+        query: Message,
+        request: Vec<u8>,
+        server: SocketAddr,
+        exchange: Exchange,
+        timer: Timer,
+    },
+}
+
+/// Yields the records of a name and type.
+pub struct Query {
+    inner: Rc<Inner>,
+    // The names left to try.
+    names: Vec<String>,
+    qtype: u16,
+    // The number of attempts made for the first name.
+    attempt: usize,
+    // The number of CNAME records followed.
+    cnames: usize,
+    error: Option<io::Error>,
+    state: State,
+}
+
+impl Query {
+    fn start(&mut self) -> io::Result<State> {
+        let servers = &self.inner.config.nameservers;
+        let server = servers[self.attempt % servers.len()];
+        let mut query = Message::query(query_id()?, Question::new(&self.names[0], self.qtype));
+        query.edns = Some(Edns::new(EDNS_UDP_SIZE));
+        let request = query.encode()?;
+        let local = match server {
+            SocketAddr::V4(..) => IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
+            SocketAddr::V6(..) => IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0)),
+        };
+        let udp = Udp {
+            socket: UdpSocket::bind(&SocketAddr::new(local, 0))?,
+            server,
+            sent: false,
+            buf: vec![0; EDNS_UDP_SIZE as usize],
+        };
+        Ok(State::Exchanging {
+            query,
+            request,
+            server,
+            exchange: Exchange::Udp(udp),
+            timer: Timer::new(self.inner.config.timeout),
+        })
+    }
+
+    // Returns the records answering `response`, or `None` to query the
+    // names left.
+    fn answer(&mut self, response: Message) -> io::Result<Option<Vec<Record>>> {
+        let name = self.names[0].clone();
+        match response.rcode {
+            rcode::NO_ERROR => {
+                let ttl = response.answers.iter().map(|r| r.ttl).min();
+                let target = self.follow(&name, &response.answers)?;
+                let records: Vec<Record> = response
+                    .answers
+                    .into_iter()
+                    .filter(|r| r.rtype == self.qtype || self.qtype == rtype::ANY)
+                    .collect();
+                // The server did not resolve the alias, so it is asked for
+                // the target instead.
+                if records.is_empty() && !target.eq_ignore_ascii_case(&name) {
+                    self.names = vec![target];
+                    return Ok(None);
+                }
+                let ttl = match ttl {
+                    Some(ttl) if !records.is_empty() => ttl,
+                    _ => negative_ttl(&response.authorities),
+                };
+                self.inner.cache(&name, self.qtype, ttl, Some(records.clone()));
+                Ok(Some(records))
+            }
+            rcode::NX_DOMAIN => {
+                let ttl = negative_ttl(&response.authorities);
+                self.inner.cache(&name, self.qtype, ttl, None);
+                self.names.remove(0);
+                Ok(None)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("DNS server failed with response code {}", response.rcode),
+            )),
+        }
+    }
+
+    // Returns the name the CNAME records in `answers` lead `name` to.
+    fn follow(&mut self, name: &str, answers: &[Record]) -> io::Result<String> {
+        let mut name = name.to_string();
+        if self.qtype == rtype::CNAME || self.qtype == rtype::ANY {
+            return Ok(name);
+        }
+        loop {
+            let mut target = answers.iter().filter_map(|r| match r.data {
+                RData::Cname(ref target) if r.name.eq_ignore_ascii_case(&name) => Some(target),
+                _ => None,
+            });
+            match target.next() {
+                Some(target) => name = target.clone(),
+                None => return Ok(name),
+            }
+            self.cnames += 1;
+            if self.cnames > MAX_CNAMES {
+                return Err(invalid_data("Too many DNS CNAME records"));
+            }
+        }
+    }
+}
+
+// Returns the time to cache a negative answer for, from the SOA record of
+// the zone (RFC 2308).
+fn negative_ttl(authorities: &[Record]) -> u32 {
+    authorities
+        .iter()
+        .filter_map(|r| match r.data {
+            RData::Soa { minimum, .. } => Some(cmp::min(r.ttl, minimum)),
+            _ => None,
+        })
+        .next()
+        .unwrap_or(0)
+}
+
+impl Future for Query {
+    type Item = Vec<Record>;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            let result = match self.state {
+                State::Start => {
+                    if self.names.is_empty() {
+                        return Err(io::Error::new(
+                            io::ErrorKind::NotFound,
+                            "DNS name does not exist",
+                        ));
+                    }
+                    match self.inner.cached(&self.names[0], self.qtype) {
+                        Some(Some(records)) => return Ok(Async::Ready(records)),
+                        Some(None) => {
+                            self.names.remove(0);
+                            continue;
+                        }
+                        None => (),
+                    }
+                    let config = &self.inner.config;
+                    if self.attempt >= config.attempts * config.nameservers.len() {
+                        return Err(self.error.take().unwrap_or_else(timed_out));
+                    }
+                    self.state = self.start()?;
+                    continue;
+                }
+                State::Exchanging {
+                    ref query,
+                    ref request,
+                    server,
+                    ref mut exchange,
+                    ref mut timer,
+                } => {
+                    let result = match *exchange {
+                        Exchange::Udp(ref mut udp) => udp.poll(query, request),
+                        Exchange::Tcp(ref mut tcp) => tcp.poll(query, request),
+                    };
+                    match result {
+                        // Truncated responses are asked again over TCP.
+                        Ok(Async::Ready(ref response)) if response.truncated => {
+                            if let Exchange::Tcp(..) = *exchange {
+                                Err(invalid_data("Truncated DNS response over TCP"))
+                            } else {
+                                *exchange = Exchange::Tcp(Tcp::Connecting(tcp::connect(&server)));
+                                continue;
+                            }
+                        }
+                        Ok(Async::Ready(response)) => Ok(response),
+                        Ok(Async::NotReady) => match timer.poll() {
+                            Ok(Async::NotReady) => return Ok(Async::NotReady),
+                            _ => Err(timed_out()),
+                        },
+                        Err(e) => Err(e),
+                    }
+                }
+            };
+            self.state = State::Start;
+            match result.and_then(|response| self.answer(response)) {
+                Ok(Some(records)) => return Ok(Async::Ready(records)),
+                Ok(None) => self.attempt = 0,
+                Err(e) => {
+                    // Asking again would not end the chain of CNAME records.
+                    if self.cnames > MAX_CNAMES {
+                        return Err(e);
+                    }
+                    self.error = Some(e);
+                    self.attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Yields the addresses of a host.
+pub struct LookupIp {
+    // The queries of A and AAAA records.
+    queries: [Option<Query>; 2],
+    addrs: [Vec<IpAddr>; 2],
+    error: Option<io::Error>,
+}
+
+impl Future for LookupIp {
+    type Item = Vec<IpAddr>;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let mut done = true;
+        for (query, addrs) in self.queries.iter_mut().zip(self.addrs.iter_mut()) {
+            let result = match *query {
+                Some(ref mut query) => query.poll(),
+                None => continue,
+            };
+            match result {
+                Ok(Async::Ready(records)) => {
+                    addrs.extend(records.into_iter().filter_map(|r| match r.data {
+                        RData::A(ip) => Some(IpAddr::V4(ip)),
+                        RData::Aaaa(ip) => Some(IpAddr::V6(ip)),
+                        _ => None,
+                    }))
+                }
+                Ok(Async::NotReady) => {
+                    done = false;
+                    continue;
+                }
+                Err(e) => {
+                    self.error.get_or_insert(e);
+                }
+            }
+            *query = None;
+        }
+        if !done {
+            return Ok(Async::NotReady);
+        }
+        let mut addrs = mem::take(&mut self.addrs[0]);
+        addrs.append(&mut self.addrs[1]);
+        match (addrs.is_empty(), self.error.take()) {
+            (true, Some(e)) => Err(e),
+            _ => Ok(Async::Ready(addrs)),
+        }
+    }
+}
@@ -0,0 +1,461 @@
+//! Streaming deflate, zlib and gzip compression over `ByteBuf` streams.
+//!
+//! `Deflate` compresses each `ByteBuf` of the underlying stream and flushes
+//! it, so that every item can be decompressed as soon as it is received.
+//! `Inflate` yields the decompressed bytes as they become available, in items
+//! of at most `max_output_len` bytes, and ends with the compressed stream.
+//!
+//! ```ignore
+//! let frames = flate::inflate(tcp::recv(conn), Format::Gzip);
+//! tcp::send_all(conn, flate::deflate(frames, Format::Gzip))
+//! ```
+
+use std::cmp;
+use std::io;
+
+use futures::{Async, Poll, Stream};
+use memchr::memchr;
+use miniz_oxide::{DataFormat, MZError, MZFlush, MZStatus};
+use miniz_oxide::deflate::core::{create_comp_flags_from_zip_params, CompressorOxide};
+use miniz_oxide::deflate::stream::deflate as deflate_raw;
+use miniz_oxide::inflate::stream::{inflate as inflate_raw, InflateState};
+
+use buf::ByteBuf;
+use buf::checksum::{Checksum, Crc32};
+use super::max_frame_len;
+
+// Output space added per call to the compressor or the decompressor.
+const CHUNK: usize = 8 * 1024;
+
+const DEFAULT_LEVEL: u32 = 6;
+const WINDOW_BITS: i32 = 15;
+
+const GZIP_HEADER: [u8; 10] = [0x1F, 0x8B, 8, 0, 0, 0, 0, 0, 0, 0xFF];
+const GZIP_TRAILER_SIZE: usize = 8;
+
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Raw deflate data, RFC 1951.
+    Raw,
+    /// Deflate data with a zlib header and Adler-32 trailer, RFC 1950.
+    Zlib,
+    /// Deflate data in a single gzip member, RFC 1952.
+    Gzip,
+}
+
+pub struct Deflate<S> {
+    stream: S,
+    format: Format,
+    compressor: Box<CompressorOxide>,
+    crc: Crc32,
+    len: u32,
+    started: bool,
+    done: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Header,
+    Body,
+    Trailer,
+    Done,
+}
+
+pub struct Inflate<S> {
+    stream: S,
+    format: Format,
+    state: Box<InflateState>,
+    phase: Phase,
+    // Gzip header or trailer bytes received so far.
+    pending: Vec<u8>,
+    crc: Crc32,
+    len: u32,
+    // Input left over when an item reached `max_output_len`.
+    input: Option<ByteBuf>,
+    // Whether the decompressor may have more output without more input.
+    capped: bool,
+    max_output_len: usize,
+}
+
+#[inline]
+pub fn deflate<S>(stream: S, format: Format) -> Deflate<S>
+where
+    S: Stream<Item = ByteBuf, Error = io::Error>,
+{
+    Deflate::new(stream, format)
+}
+
+#[inline]
+pub fn inflate<S>(stream: S, format: Format) -> Inflate<S>
+where
+    S: Stream<Item = ByteBuf, Error = io::Error>,
+{
+    Inflate::new(stream, format)
+}
+
+#[inline]
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[inline]
+fn le_u32(b: &[u8]) -> u32 {
+    u32::from(b[0]) | u32::from(b[1]) << 8 | u32::from(b[2]) << 16 | u32::from(b[3]) << 24
+}
+
+#[inline]
+fn put_le_u32(v: u32, out: &mut Vec<u8>) {
+    out.extend_from_slice(&[v as u8, (v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8]);
+}
+
+// Returns the length of the gzip header at the front of `h`, or `None` if
+// more bytes are needed. The optional header CRC is not checked.
+fn gzip_header_len(h: &[u8]) -> io::Result<Option<usize>> {
+    if h.len() < GZIP_HEADER.len() {
+        return Ok(None);
+    }
+    if h[0] != 0x1F || h[1] != 0x8B || h[2] != 8 {
+        return Err(invalid_data("Invalid gzip header"));
+    }
+    let flags = h[3];
+    let mut n = GZIP_HEADER.len();
+    if flags & FEXTRA != 0 {
+        if h.len() < n + 2 {
+            return Ok(None);
+        }
+        n += 2 + (h[n] as usize | (h[n + 1] as usize) << 8);
+    }
+    for &flag in &[FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            match h.get(n..).and_then(|s| memchr(0, s)) {
+                Some(i) => n += i + 1,
+                None => return Ok(None),
+            }
+        }
+    }
+    if flags & FHCRC != 0 {
+        n += 2;
+    }
+    if h.len() < n {
+        Ok(None)
+    } else {
+        Ok(Some(n))
+    }
+}
+
+impl<S> Deflate<S> {
+    #[inline]
+    pub fn new(stream: S, format: Format) -> Self {
+        Self::with_level(stream, format, DEFAULT_LEVEL)
+    }
+
+    /// Compresses with `level` from 0, no compression, to 10, the best.
+    pub fn with_level(stream: S, format: Format, level: u32) -> Self {
+        let window_bits = match format {
+            Format::Zlib => WINDOW_BITS,
+            _ => -WINDOW_BITS,
+        };
+        let level = cmp::min(level, 10) as i32;
+        let flags = create_comp_flags_from_zip_params(level, window_bits, 0);
+        Deflate {
+            stream,
+            format,
+            compressor: Box::new(CompressorOxide::new(flags)),
+            crc: Crc32::new(),
+            len: 0,
+            started: false,
+            done: false,
+        }
+    }
+
+    #[inline]
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    #[inline]
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    fn start(&mut self, out: &mut Vec<u8>) {
+        if !self.started {
+            self.started = true;
+            if self.format == Format::Gzip {
+                out.extend_from_slice(&GZIP_HEADER);
+            }
+        }
+    }
+
+    fn compress(&mut self, mut input: &[u8], out: &mut Vec<u8>, flush: MZFlush) -> io::Result<()> {
+        if self.format == Format::Gzip {
+            self.crc.update(input);
+            self.len = self.len.wrapping_add(input.len() as u32);
+        }
+        loop {
+            let off = out.len();
+            out.resize(off + CHUNK, 0);
+            let res = deflate_raw(&mut self.compressor, input, &mut out[off..], flush);
+            out.truncate(off + res.bytes_written);
+            input = &input[res.bytes_consumed..];
+            match res.status {
+                Ok(MZStatus::StreamEnd) => return Ok(()),
+                Ok(_) => if flush != MZFlush::Finish && input.is_empty() && res.bytes_written < CHUNK {
+                    return Ok(());
+                },
+                // No progress without more input.
+                Err(MZError::Buf) if input.is_empty() && flush != MZFlush::Finish => return Ok(()),
+                Err(_) => return Err(io::Error::new(io::ErrorKind::Other, "Failed to deflate")),
+            }
+        }
+    }
+
+    fn compress_buf(&mut self, data: &ByteBuf) -> io::Result<ByteBuf> {
+        let mut out = Vec::new();
+        self.start(&mut out);
+        let mut slices = data.as_slices().filter(|s| !s.is_empty()).peekable();
+        while let Some(s) = slices.next() {
+            let flush = if slices.peek().is_some() {
+                MZFlush::None
+            } else {
+                MZFlush::Sync
+            };
+            self.compress(s, &mut out, flush)?;
+        }
+        Ok(ByteBuf::from(out))
+    }
+
+    fn finish(&mut self) -> io::Result<ByteBuf> {
+        let mut out = Vec::new();
+        self.start(&mut out);
+        self.compress(&[], &mut out, MZFlush::Finish)?;
+        if self.format == Format::Gzip {
+            put_le_u32(self.crc.sum(), &mut out);
+            put_le_u32(self.len, &mut out);
+        }
+        Ok(ByteBuf::from(out))
+    }
+}
+
+impl<S> Stream for Deflate<S>
+where
+    S: Stream<Item = ByteBuf, Error = io::Error>,
+{
+    type Item = ByteBuf;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if self.done {
+            return Ok(Async::Ready(None));
+        }
+        loop {
+            match try_ready!(self.stream.poll()) {
+                Some(data) => if !data.is_empty() {
+                    return self.compress_buf(&data).map(|out| Async::Ready(Some(out)));
+                },
+                None => {
+                    self.done = true;
+                    return self.finish().map(|out| Async::Ready(Some(out)));
+                }
+            }
+        }
+    }
+}
+
+impl<S> Inflate<S> {
+    pub fn new(stream: S, format: Format) -> Self {
+        let (data_format, phase) = match format {
+            Format::Raw => (DataFormat::Raw, Phase::Body),
+            Format::Zlib => (DataFormat::Zlib, Phase::Body),
+            Format::Gzip => (DataFormat::Raw, Phase::Header),
+        };
+        Inflate {
+            stream,
+            format,
+            state: InflateState::new_boxed(data_format),
+            phase,
+            pending: Vec::new(),
+            crc: Crc32::new(),
+            len: 0,
+            input: None,
+            capped: false,
+            max_output_len: max_frame_len(),
+        }
+    }
+
+    #[inline]
+    pub fn max_output_len(&self) -> usize {
+        self.max_output_len
+    }
+
+    /// Sets the maximum length of each decompressed item, `max_frame_len()`
+    /// by default. The rest is yielded by the following items.
+    #[inline]
+    pub fn set_max_output_len(&mut self, max_output_len: usize) -> &mut Self {
+        self.max_output_len = cmp::max(max_output_len, 1);
+        self
+    }
+
+    #[inline]
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    #[inline]
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    fn decompress(&mut self, input: &[u8], out: &mut Vec<u8>) -> io::Result<usize> {
+        let mut consumed = 0;
+        loop {
+            let off = out.len();
+            let space = cmp::min(CHUNK, self.max_output_len - off);
+            self.capped = space == 0;
+            if self.capped {
+                return Ok(consumed);
+            }
+            out.resize(off + space, 0);
+            let res = inflate_raw(&mut self.state, &input[consumed..], &mut out[off..], MZFlush::None);
+            out.truncate(off + res.bytes_written);
+            consumed += res.bytes_consumed;
+            if self.format == Format::Gzip {
+                self.crc.update(&out[off..]);
+                self.len = self.len.wrapping_add(res.bytes_written as u32);
+            }
+            match res.status {
+                Ok(MZStatus::StreamEnd) => {
+                    self.phase = match self.format {
+                        Format::Gzip => Phase::Trailer,
+                        _ => Phase::Done,
+                    };
+                    return Ok(consumed);
+                }
+                Ok(_) => if consumed == input.len() && res.bytes_written < space {
+                    return Ok(consumed);
+                },
+                // Waiting for more input.
+                Err(MZError::Buf) => return Ok(consumed),
+                Err(_) => return Err(invalid_data("Invalid deflate data")),
+            }
+        }
+    }
+
+    // Feeds `input` until `out` reaches `max_output_len`, returning the
+    // number of bytes consumed.
+    fn feed(&mut self, mut input: &[u8], out: &mut Vec<u8>) -> io::Result<usize> {
+        let len = input.len();
+        while !input.is_empty() && !self.capped {
+            match self.phase {
+                Phase::Header => {
+                    let n = self.pending.len();
+                    self.pending.extend_from_slice(input);
+                    match gzip_header_len(&self.pending)? {
+                        Some(header_len) => {
+                            input = &input[header_len - n..];
+                            self.pending.clear();
+                            self.phase = Phase::Body;
+                        }
+                        None => input = &[],
+                    }
+                }
+                Phase::Body => {
+                    let n = self.decompress(input, out)?;
+                    input = &input[n..];
+                }
+                Phase::Trailer => {
+                    let n = cmp::min(GZIP_TRAILER_SIZE - self.pending.len(), input.len());
+                    self.pending.extend_from_slice(&input[..n]);
+                    input = &input[n..];
+                    if self.pending.len() == GZIP_TRAILER_SIZE {
+                        if le_u32(&self.pending) != self.crc.sum() {
+                            return Err(invalid_data("Gzip CRC-32 mismatch"));
+                        }
+                        if le_u32(&self.pending[4..]) != self.len {
+                            return Err(invalid_data("Gzip length mismatch"));
+                        }
+                        self.phase = Phase::Done;
+                    }
+                }
+                Phase::Done => return Err(invalid_data("Trailing data after compressed stream")),
+            }
+        }
+        Ok(len - input.len())
+    }
+
+    fn feed_buf(&mut self, mut data: ByteBuf, out: &mut Vec<u8>) -> io::Result<()> {
+        let mut consumed = 0;
+        for s in data.as_slices() {
+            let n = self.feed(s, out)?;
+            consumed += n;
+            if n < s.len() {
+                break;
+            }
+        }
+        data.skip(consumed);
+        if !data.is_empty() {
+            self.input = Some(data);
+        }
+        Ok(())
+    }
+}
+
+impl<S> Stream for Inflate<S>
+where
+    S: Stream<Item = ByteBuf, Error = io::Error>,
+{
+    type Item = ByteBuf;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            let mut out = Vec::new();
+            if self.capped {
+                self.decompress(&[], &mut out)?;
+            }
+            if let Some(data) = self.input.take() {
+                self.feed_buf(data, &mut out)?;
+            }
+            if !out.is_empty() {
+                return Ok(Async::Ready(Some(ByteBuf::from(out))));
+            }
+            if self.phase == Phase::Done {
+                return Ok(Async::Ready(None));
+            }
+            match try_ready!(self.stream.poll()) {
+                Some(data) => self.input = Some(data),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Compressed stream ended unexpectedly",
+                    ))
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use buf::{ByteBuf, Prepender};
+
+/// The maximum frame length of decoders created before any call to
+/// `set_max_frame_len`.
+pub const DEFAULT_MAX_FRAME_LEN: usize = 8 * 1024 * 1024;
+
+static MAX_FRAME_LEN: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_FRAME_LEN);
+
+/// A peer announced a frame longer than the decoder accepts.
+///
+/// Decoders fail with an `io::Error` of kind `InvalidData` wrapping it:
+///
+/// ```ignore
+/// let too_long = e.get_ref().and_then(|e| e.downcast_ref::<FrameTooLong>());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameTooLong {
+    pub len: usize,
+    pub max: usize,
+}
+
+impl fmt::Display for FrameTooLong {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Frame of {} bytes exceeds the maximum of {} bytes",
+            self.len, self.max
+        )
+    }
+}
+
+impl Error for FrameTooLong {
+    fn description(&self) -> &str {
+        "Frame too long"
+    }
+}
+
+/// Returns the maximum frame length of newly created decoders.
+#[inline]
+pub fn max_frame_len() -> usize {
+    MAX_FRAME_LEN.load(Ordering::Relaxed)
+}
+
+/// Sets the maximum frame length of decoders created from now on. Each
+/// decoder can override it with its own `set_max_frame_len`.
+#[inline]
+pub fn set_max_frame_len(len: usize) {
+    MAX_FRAME_LEN.store(len, Ordering::Relaxed)
+}
+
+#[inline]
+pub(crate) fn frame_too_long(len: usize, max: usize) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, FrameTooLong { len, max })
+}
+
+// Prepends the length of `data` with `prepend`, into the head room reserved
+// by `try_reserve_in_head` if there is enough, or else into a new block of
+// `size` bytes.
+pub(crate) fn prefix<T, P>(mut data: ByteBuf, len: T, size: usize, prepend: P) -> ByteBuf
+where
+    T: Copy,
+    P: Fn(T, &mut Prepender) -> Result<usize, ()>,
+{
+    if data.try_reserve_in_head(size) >= size && data.prepend(len, &prepend).is_ok() {
+        return data;
+    }
+    let mut head = ByteBuf::with_capacity(size);
+    head.try_reserve_in_head(size);
+    let _ = head.prepend(len, &prepend);
+    head.extend(data);
+    head
+}
+
+// Appends an encoded `frame` to `dst`, taking it over if `dst` is empty.
+#[inline]
+pub(crate) fn put(dst: &mut ByteBuf, frame: ByteBuf) {
+    match dst.is_empty() {
+        true => *dst = frame,
+        false => dst.extend(frame),
+    }
+}
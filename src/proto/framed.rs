@@ -0,0 +1,261 @@
+use std::io;
+
+use futures::{Async, AsyncSink, Poll, Sink, StartSend, Stream};
+
+use buf::ByteBuf;
+
+use super::{frame_too_long, Decoder, Encoder};
+
+/// A `Stream` of the items decoded from the `ByteBuf`s of `T`, and a `Sink`
+/// of the items encoded into `T`.
+///
+/// `T` is either end of a connection, e.g. `tcp::Recv` or `tcp::Sender`,
+/// or both of them joined by `Duplex`.
+pub struct Framed<T, C> {
+    io: T,
+    codec: C,
+    data: ByteBuf,
+    eof: bool,
+    done: bool,
+    // An encoded frame not yet accepted by `io`.
+    pending: Option<ByteBuf>,
+}
+
+/// Joins a `Stream` and a `Sink`, e.g. the halves of a split connection.
+pub struct Duplex<R, S> {
+    recv: R,
+    send: S,
+}
+
+impl<T, C> Framed<T, C> {
+    #[inline]
+    pub fn new(io: T, codec: C) -> Self {
+        Framed {
+            io,
+            codec,
+            data: ByteBuf::new(),
+            eof: false,
+            done: false,
+            pending: None,
+        }
+    }
+
+    /// Resumes framing with `data` received but not decoded by a previous
+    /// codec, e.g. after a protocol upgrade.
+    #[inline]
+    pub fn from_parts(io: T, codec: C, data: ByteBuf) -> Self {
+        Framed {
+            io,
+            codec,
+            data,
+            eof: false,
+            done: false,
+            pending: None,
+        }
+    }
+
+    /// Sets the most bytes received but not decoded yet. Receiving more fails
+    /// the stream with `FrameTooLong`.
+    #[inline]
+    pub fn set_limit(&mut self, limit: usize) -> &mut Self {
+        self.data.set_limit(limit);
+        self
+    }
+
+    #[inline]
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    #[inline]
+    pub fn codec_mut(&mut self) -> &mut C {
+        &mut self.codec
+    }
+
+    #[inline]
+    pub fn get_ref(&self) -> &T {
+        &self.io
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.io
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+
+    /// Returns `T`, the codec and the data received but not decoded yet.
+    #[inline]
+    pub fn into_parts(self) -> (T, C, ByteBuf) {
+        (self.io, self.codec, self.data)
+    }
+}
+
+impl<T, C> From<T> for Framed<T, C>
+where
+    C: Default,
+{
+    #[inline]
+    fn from(io: T) -> Self {
+        Self::new(io, C::default())
+    }
+}
+
+impl<T, C> Framed<T, C>
+where
+    T: Stream<Item = ByteBuf, Error = io::Error>,
+    C: Decoder,
+{
+    fn poll_decode(&mut self) -> Poll<Option<C::Item>, C::Error> {
+        loop {
+            if self.eof {
+                return Ok(Async::Ready(self.codec.decode_eof(&mut self.data)?));
+            }
+            if let Some(item) = self.codec.decode(&mut self.data)? {
+                return Ok(Async::Ready(Some(item)));
+            }
+            match try_ready!(self.io.poll()) {
+                Some(data) => {
+                    let limit = self.data.limit();
+                    if self.data.is_empty() && data.len() <= limit {
+                        self.data = data;
+                        self.data.set_limit(limit);
+                    } else if let Err(data) = self.data.try_extend(data) {
+                        let len = self.data.len() + data.len();
+                        return Err(frame_too_long(len, limit).into());
+                    }
+                }
+                None => self.eof = true,
+            }
+        }
+    }
+}
+
+/// Ends after the first error, as the rest of the data cannot be framed.
+impl<T, C> Stream for Framed<T, C>
+where
+    T: Stream<Item = ByteBuf, Error = io::Error>,
+    C: Decoder,
+{
+    type Item = C::Item;
+    type Error = C::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if self.done {
+            return Ok(Async::Ready(None));
+        }
+        let polled = self.poll_decode();
+        match polled {
+            Ok(Async::Ready(None)) | Err(..) => self.done = true,
+            _ => (),
+        }
+        polled
+    }
+}
+
+impl<T, C> Framed<T, C>
+where
+    T: Sink<SinkItem = ByteBuf, SinkError = io::Error>,
+{
+    fn poll_pending_send(&mut self) -> Poll<(), io::Error> {
+        while let Some(data) = self.pending.take() {
+            if let AsyncSink::NotReady(data) = self.io.start_send(data)? {
+                self.pending = Some(data);
+                try_ready!(self.io.poll_complete());
+            }
+        }
+        Ok(Async::Ready(()))
+    }
+}
+
+impl<T, C> Sink for Framed<T, C>
+where
+    T: Sink<SinkItem = ByteBuf, SinkError = io::Error>,
+    C: Encoder,
+{
+    type SinkItem = C::Item;
+    type SinkError = C::Error;
+
+    fn start_send(&mut self, item: Self::SinkItem) -> StartSend<Self::SinkItem, Self::SinkError> {
+        if self.poll_pending_send()?.is_not_ready() {
+            return Ok(AsyncSink::NotReady(item));
+        }
+        let mut data = ByteBuf::new();
+        self.codec.encode(item, &mut data)?;
+        if let AsyncSink::NotReady(data) = self.io.start_send(data)? {
+            self.pending = Some(data);
+        }
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        try_ready!(self.poll_pending_send());
+        Ok(self.io.poll_complete()?)
+    }
+
+    fn close(&mut self) -> Poll<(), Self::SinkError> {
+        try_ready!(self.poll_complete());
+        Ok(self.io.close()?)
+    }
+}
+
+impl<R, S> Duplex<R, S> {
+    #[inline]
+    pub fn new(recv: R, send: S) -> Self {
+        Duplex { recv, send }
+    }
+
+    #[inline]
+    pub fn get_ref(&self) -> (&R, &S) {
+        (&self.recv, &self.send)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self) -> (&mut R, &mut S) {
+        (&mut self.recv, &mut self.send)
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> (R, S) {
+        (self.recv, self.send)
+    }
+}
+
+impl<R, S> Stream for Duplex<R, S>
+where
+    R: Stream,
+{
+    type Item = R::Item;
+    type Error = R::Error;
+
+    #[inline]
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        self.recv.poll()
+    }
+}
+
+impl<R, S> Sink for Duplex<R, S>
+where
+    S: Sink,
+{
+    type SinkItem = S::SinkItem;
+    type SinkError = S::SinkError;
+
+    #[inline]
+    fn start_send(&mut self, item: Self::SinkItem) -> StartSend<Self::SinkItem, Self::SinkError> {
+        self.send.start_send(item)
+    }
+
+    #[inline]
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        self.send.poll_complete()
+    }
+
+    #[inline]
+    fn close(&mut self) -> Poll<(), Self::SinkError> {
+        self.send.close()
+    }
+}
@@ -0,0 +1,372 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::io;
+
+use buf::ByteBuf;
+use buf::codec::u8s;
+use proto::{max_frame_len, put, Decoder, Encoder};
+
+use super::{Headers, Request, Response, DEFAULT_MAX_HEADERS, DEFAULT_MAX_HEAD_LEN};
+use super::parse::{body_framing, invalid_data, is_token, parse_head, parse_version, Framing,
+                   Parser};
+
+/// Decodes requests and encodes responses, for servers.
+///
+/// Requests are decoded one after another from the same data, so pipelined
+/// requests need no special handling as long as the responses are encoded
+/// in the same order.
+#[derive(Debug)]
+pub struct ServerCodec {
+    parser: Parser,
+    request: Option<Request>,
+    // Whether each request not yet responded to is a HEAD request.
+    heads: VecDeque<bool>,
+}
+
+/// Encodes requests and decodes responses, for clients.
+#[derive(Debug)]
+pub struct ClientCodec {
+    parser: Parser,
+    response: Option<Response>,
+    // Whether each request not yet responded to is a HEAD request.
+    heads: VecDeque<bool>,
+}
+
+#[inline]
+fn invalid_input(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+#[inline]
+fn unexpected_eof() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "Connection closed within a message")
+}
+
+#[inline]
+fn append(dst: &mut ByteBuf, bytes: &[u8]) -> io::Result<()> {
+    match dst.append(bytes, u8s::append) {
+        Ok(..) => Ok(()),
+        Err(()) => Err(io::Error::new(io::ErrorKind::Other, "Buffer limit exceeded")),
+    }
+}
+
+// Writes a message, adding `Content-Length` if `len` and the headers do not
+// announce any other framing, and leaving out the body unless `send_body`.
+fn write_message(
+    dst: &mut ByteBuf,
+    start: &str,
+    headers: &Headers,
+    body: ByteBuf,
+    len: bool,
+    send_body: bool,
+) -> io::Result<()> {
+    let chunked = headers.has_token("Transfer-Encoding", "chunked");
+    let mut head = String::with_capacity(256);
+    head.push_str(start);
+    head.push_str("\r\n");
+    for (name, value) in headers {
+        if !is_token(name) || value.contains(|c| c == '\r' || c == '\n') {
+            return Err(invalid_input("Invalid header field"));
+        }
+        let _ = write!(head, "{}: {}\r\n", name, value);
+    }
+    if len && !chunked && !headers.contains("Content-Length") {
+        let _ = write!(head, "Content-Length: {}\r\n", body.len());
+    }
+    head.push_str("\r\n");
+    append(dst, head.as_bytes())?;
+    if !send_body {
+        return Ok(());
+    }
+    if !chunked {
+        put(dst, body);
+        return Ok(());
+    }
+    if !body.is_empty() {
+        append(dst, format!("{:x}\r\n", body.len()).as_bytes())?;
+        put(dst, body);
+        append(dst, b"\r\n")?;
+    }
+    append(dst, b"0\r\n\r\n")
+}
+
+macro_rules! limits {
+    ($codec:ident) => {
+        impl Default for $codec {
+            #[inline]
+            fn default() -> Self {
+                $codec::new()
+            }
+        }
+
+        impl $codec {
+            #[inline]
+            pub fn max_head_len(&self) -> usize {
+                self.parser.max_head_len
+            }
+
+            /// Sets the maximum length of a message head, i.e. the start line
+            /// and header fields. Defaults to `DEFAULT_MAX_HEAD_LEN`.
+            #[inline]
+            pub fn set_max_head_len(&mut self, max_head_len: usize) -> &mut Self {
+                self.parser.max_head_len = max_head_len;
+                self
+            }
+
+            #[inline]
+            pub fn max_headers(&self) -> usize {
+                self.parser.max_headers
+            }
+
+            /// Sets the maximum number of header fields. Defaults to
+            /// `DEFAULT_MAX_HEADERS`.
+            #[inline]
+            pub fn set_max_headers(&mut self, max_headers: usize) -> &mut Self {
+                self.parser.max_headers = max_headers;
+                self
+            }
+
+            #[inline]
+            pub fn max_body_len(&self) -> usize {
+                self.parser.max_body_len
+            }
+
+            /// Sets the maximum length of a body, after removing any chunked
+            /// framing. Longer bodies fail with `FrameTooLong`, even when
+            /// announced by `Content-Length` only.
+            #[inline]
+            pub fn set_max_body_len(&mut self, max_body_len: usize) -> &mut Self {
+                self.parser.max_body_len = max_body_len;
+                self
+            }
+        }
+    };
+}
+
+limits!(ServerCodec);
+limits!(ClientCodec);
+
+impl ServerCodec {
+    #[inline]
+    pub fn new() -> Self {
+        ServerCodec {
+            parser: Parser::new(DEFAULT_MAX_HEAD_LEN, DEFAULT_MAX_HEADERS, max_frame_len()),
+            request: None,
+            heads: VecDeque::new(),
+        }
+    }
+
+    /// Returns whether the head of a request has been decoded but not yet
+    /// its body.
+    #[inline]
+    pub fn in_body(&self) -> bool {
+        self.parser.in_body()
+    }
+
+    fn decode_head(&mut self, head: &[u8]) -> io::Result<Request> {
+        let (start, headers) = parse_head(head, self.parser.max_headers)?;
+        let mut parts = start.split(' ');
+        let (method, target, version) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(method), Some(target), Some(version), None)
+                if is_token(method) && !target.is_empty() =>
+            {
+                (method, target, parse_version(version)?)
+            }
+            _ => return Err(invalid_data("Invalid request line")),
+        };
+        let framing = match body_framing(&headers)? {
+            Some(Framing::Eof) => return Err(invalid_data("Unsupported Transfer-Encoding")),
+            Some(framing) => framing,
+            None => Framing::Length(0),
+        };
+        self.parser.start_body(framing)?;
+        Ok(Request {
+            method: method.to_owned(),
+            target: target.to_owned(),
+            version,
+            headers,
+            body: ByteBuf::new(),
+        })
+    }
+}
+
+impl Decoder for ServerCodec {
+    type Item = Request;
+    type Error = io::Error;
+
+    fn decode(&mut self, data: &mut ByteBuf) -> io::Result<Option<Request>> {
+        if self.request.is_none() {
+            let head = match self.parser.head(data)? {
+                Some(head) => head,
+                None => return Ok(None),
+            };
+            let request = self.decode_head(&head.as_bytes())?;
+            self.request = Some(request);
+        }
+        let (body, trailers) = match self.parser.body(data, false)? {
+            Some(t) => t,
+            None => return Ok(None),
+        };
+        let mut request = match self.request.take() {
+            Some(request) => request,
+            None => ::unreachable(),
+        };
+        request.body = body;
+        for (name, value) in &trailers {
+            request.headers.append(name, value);
+        }
+        self.heads.push_back(request.method == "HEAD");
+        Ok(Some(request))
+    }
+
+    fn decode_eof(&mut self, data: &mut ByteBuf) -> io::Result<Option<Request>> {
+        match self.decode(data)? {
+            Some(request) => Ok(Some(request)),
+            None if self.request.is_none() && data.is_empty() => Ok(None),
+            None => Err(unexpected_eof()),
+        }
+    }
+}
+
+/// Adds `Content-Length` unless the headers announce chunks, and leaves out
+/// the body of responses to HEAD requests.
+impl Encoder for ServerCodec {
+    type Item = Response;
+    type Error = io::Error;
+
+    fn encode(&mut self, response: Response, dst: &mut ByteBuf) -> io::Result<()> {
+        // Interim responses precede the final one to the same request.
+        let head = match response.status >= 200 {
+            true => self.heads.pop_front().unwrap_or(false),
+            false => false,
+        };
+        if response.reason.contains(|c| c == '\r' || c == '\n') {
+            return Err(invalid_input("Invalid reason phrase"));
+        }
+        let bodiless = Response::is_bodiless(response.status);
+        let start = format!(
+            "{} {} {}",
+            response.version, response.status, response.reason
+        );
+        let len = !(bodiless || head && response.body.is_empty());
+        write_message(
+            dst,
+            &start,
+            &response.headers,
+            response.body,
+            len,
+            !bodiless && !head,
+        )
+    }
+}
+
+impl ClientCodec {
+    #[inline]
+    pub fn new() -> Self {
+        ClientCodec {
+            parser: Parser::new(DEFAULT_MAX_HEAD_LEN, DEFAULT_MAX_HEADERS, max_frame_len()),
+            response: None,
+            heads: VecDeque::new(),
+        }
+    }
+
+    fn decode_head(&mut self, head: &[u8]) -> io::Result<Response> {
+        let (start, headers) = parse_head(head, self.parser.max_headers)?;
+        let mut parts = start.splitn(3, ' ');
+        let version = parse_version(parts.next().unwrap_or(""))?;
+        let status = match parts.next() {
+            Some(s) if s.len() == 3 && s.bytes().all(|b| b.is_ascii_digit()) => match s.parse() {
+                Ok(status) => status,
+                _ => ::unreachable(),
+            },
+            _ => return Err(invalid_data("Invalid status line")),
+        };
+        let reason = parts.next().unwrap_or("");
+
+        // Responses to HEAD have no body either.
+        let head = self.heads.front().cloned().unwrap_or(false);
+        let framing = if Response::is_bodiless(status) || head {
+            Framing::Length(0)
+        } else {
+            body_framing(&headers)?.unwrap_or(Framing::Eof)
+        };
+        if status >= 200 {
+            self.heads.pop_front();
+        }
+        self.parser.start_body(framing)?;
+        Ok(Response {
+            version,
+            status,
+            reason: reason.to_owned(),
+            headers,
+            body: ByteBuf::new(),
+        })
+    }
+
+    fn decode_response(&mut self, data: &mut ByteBuf, eof: bool) -> io::Result<Option<Response>> {
+        if self.response.is_none() {
+            let head = match self.parser.head(data)? {
+                Some(head) => head,
+                None => return Ok(None),
+            };
+            let response = self.decode_head(&head.as_bytes())?;
+            self.response = Some(response);
+        }
+        let (body, trailers) = match self.parser.body(data, eof)? {
+            Some(t) => t,
+            None => return Ok(None),
+        };
+        let mut response = match self.response.take() {
+            Some(response) => response,
+            None => ::unreachable(),
+        };
+        response.body = body;
+        for (name, value) in &trailers {
+            response.headers.append(name, value);
+        }
+        Ok(Some(response))
+    }
+}
+
+impl Decoder for ClientCodec {
+    type Item = Response;
+    type Error = io::Error;
+
+    #[inline]
+    fn decode(&mut self, data: &mut ByteBuf) -> io::Result<Option<Response>> {
+        self.decode_response(data, false)
+    }
+
+    /// Ends a body framed by the connection closing.
+    fn decode_eof(&mut self, data: &mut ByteBuf) -> io::Result<Option<Response>> {
+        match self.decode_response(data, true)? {
+            Some(response) => Ok(Some(response)),
+            None if self.response.is_none() && data.is_empty() => Ok(None),
+            None => Err(unexpected_eof()),
+        }
+    }
+}
+
+/// Adds `Content-Length` to requests with a body, or of a method that
+/// expects one, unless the headers announce chunks.
+impl Encoder for ClientCodec {
+    type Item = Request;
+    type Error = io::Error;
+
+    fn encode(&mut self, request: Request, dst: &mut ByteBuf) -> io::Result<()> {
+        if !is_token(&request.method) || request.target.is_empty()
+            || request.target.contains(|c: char| c.is_whitespace())
+        {
+            return Err(invalid_input("Invalid request line"));
+        }
+        let start = format!("{} {} {}", request.method, request.target, request.version);
+        let len = match request.method.as_str() {
+            "POST" | "PUT" | "PATCH" => true,
+            _ => !request.body.is_empty(),
+        };
+        let head = request.method == "HEAD";
+        write_message(dst, &start, &request.headers, request.body, len, true)?;
+        self.heads.push_back(head);
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+use std::fmt;
+use std::slice;
+
+/// The HTTP version of a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    Http10,
+    Http11,
+}
+
+/// Header fields in the order received, with case-insensitive lookup.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Headers {
+    fields: Vec<(String, String)>,
+}
+
+pub struct Iter<'a> {
+    inner: slice::Iter<'a, (String, String)>,
+}
+
+impl Version {
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Version::Http10 => "HTTP/1.0",
+            Version::Http11 => "HTTP/1.1",
+        }
+    }
+
+    pub(crate) fn parse(s: &str) -> Option<Self> {
+        match s {
+            "HTTP/1.1" => Some(Version::Http11),
+            "HTTP/1.0" => Some(Version::Http10),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Headers {
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Returns the value of the first field named `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|&&(ref n, _)| n.eq_ignore_ascii_case(name))
+            .map(|&(_, ref v)| v.as_str())
+    }
+
+    /// Returns the values of all the fields named `name`.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.fields
+            .iter()
+            .filter(move |&&(ref n, _)| n.eq_ignore_ascii_case(name))
+            .map(|&(_, ref v)| v.as_str())
+    }
+
+    #[inline]
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Replaces all the fields named `name` with one of `value`.
+    pub fn insert<N, V>(&mut self, name: N, value: V) -> &mut Self
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        let name = name.into();
+        self.remove(&name);
+        self.fields.push((name, value.into()));
+        self
+    }
+
+    /// Adds a field, keeping any others of the same name.
+    pub fn append<N, V>(&mut self, name: N, value: V) -> &mut Self
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        self.fields.push((name.into(), value.into()));
+        self
+    }
+
+    /// Removes all the fields named `name`, returning whether there were any.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.fields.len();
+        self.fields.retain(|&(ref n, _)| !n.eq_ignore_ascii_case(name));
+        self.fields.len() != len
+    }
+
+    #[inline]
+    pub fn iter<'a>(&'a self) -> Iter<'a> {
+        Iter {
+            inner: self.fields.iter(),
+        }
+    }
+
+    /// Returns whether any of the comma-separated values of the fields named
+    /// `name` is `token`, e.g. `close` in `Connection`.
+    pub fn has_token(&self, name: &str, token: &str) -> bool {
+        self.get_all(name)
+            .flat_map(|v| v.split(','))
+            .any(|t| t.trim().eq_ignore_ascii_case(token))
+    }
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a str, &'a str);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|&(ref n, ref v)| (n.as_str(), v.as_str()))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a> IntoIterator for &'a Headers {
+    type Item = (&'a str, &'a str);
+    type IntoIter = Iter<'a>;
+
+    #[inline]
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
@@ -0,0 +1,122 @@
+use buf::ByteBuf;
+
+use super::{Headers, Version};
+
+/// An HTTP request with its whole body.
+#[derive(Debug)]
+pub struct Request {
+    pub method: String,
+    pub target: String,
+    pub version: Version,
+    pub headers: Headers,
+    pub body: ByteBuf,
+}
+
+/// An HTTP response with its whole body.
+#[derive(Debug)]
+pub struct Response {
+    pub version: Version,
+    pub status: u16,
+    pub reason: String,
+    pub headers: Headers,
+    pub body: ByteBuf,
+}
+
+#[inline]
+fn keep_alive(version: Version, headers: &Headers) -> bool {
+    if headers.has_token("Connection", "close") {
+        return false;
+    }
+    version == Version::Http11 || headers.has_token("Connection", "keep-alive")
+}
+
+impl Request {
+    /// Creates an HTTP/1.1 request without headers or body.
+    #[inline]
+    pub fn new<M, T>(method: M, target: T) -> Self
+    where
+        M: Into<String>,
+        T: Into<String>,
+    {
+        Request {
+            method: method.into(),
+            target: target.into(),
+            version: Version::Http11,
+            headers: Headers::new(),
+            body: ByteBuf::new(),
+        }
+    }
+
+    /// Returns whether the connection stays open after the response.
+    #[inline]
+    pub fn keep_alive(&self) -> bool {
+        keep_alive(self.version, &self.headers)
+    }
+}
+
+impl Response {
+    /// Creates an HTTP/1.1 response without headers or body, with the
+    /// standard reason phrase of `status`.
+    #[inline]
+    pub fn new(status: u16) -> Self {
+        Response {
+            version: Version::Http11,
+            status,
+            reason: reason(status).to_owned(),
+            headers: Headers::new(),
+            body: ByteBuf::new(),
+        }
+    }
+
+    /// Returns whether the connection stays open after this response.
+    #[inline]
+    pub fn keep_alive(&self) -> bool {
+        keep_alive(self.version, &self.headers)
+    }
+
+    /// Returns whether a response of `status` never has a body.
+    #[inline]
+    pub fn is_bodiless(status: u16) -> bool {
+        status < 200 || status == 204 || status == 304
+    }
+}
+
+/// Returns the standard reason phrase of `status`, or an empty string.
+pub fn reason(status: u16) -> &'static str {
+    match status {
+        100 => "Continue",
+        101 => "Switching Protocols",
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        204 => "No Content",
+        206 => "Partial Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        303 => "See Other",
+        304 => "Not Modified",
+        307 => "Temporary Redirect",
+        308 => "Permanent Redirect",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        414 => "URI Too Long",
+        415 => "Unsupported Media Type",
+        426 => "Upgrade Required",
+        429 => "Too Many Requests",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        505 => "HTTP Version Not Supported",
+        _ => "",
+    }
+}
@@ -0,0 +1,21 @@
+//! HTTP/1.x messages and codecs.
+//!
+//! Messages are decoded with their whole body, so the codecs suit APIs with
+//! bodies of bounded size rather than streaming.
+
+mod headers;
+pub use self::headers::*;
+
+mod message;
+pub use self::message::*;
+
+mod parse;
+
+mod codec;
+pub use self::codec::*;
+
+/// The maximum length of a message head of codecs not configured otherwise.
+pub const DEFAULT_MAX_HEAD_LEN: usize = 64 * 1024;
+
+/// The maximum number of header fields of codecs not configured otherwise.
+pub const DEFAULT_MAX_HEADERS: usize = 100;
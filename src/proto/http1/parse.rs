@@ -0,0 +1,320 @@
+use std::io;
+use std::mem;
+use std::str;
+
+use buf::ByteBuf;
+use proto::{frame_too_long, put};
+
+use super::{Headers, Version};
+
+// The longest chunk size line accepted, extensions included.
+const MAX_CHUNK_LINE_LEN: usize = 1024;
+
+/// How the end of a message body is found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Framing {
+    Length(usize),
+    Chunked,
+    // Until the connection closes, for responses only.
+    Eof,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Chunk {
+    Size,
+    Data(usize),
+    DataEnd,
+    Trailers,
+}
+
+/// Splits messages into heads and bodies, resuming where it stopped once
+/// more data arrives.
+#[derive(Debug)]
+pub(crate) struct Parser {
+    pub max_head_len: usize,
+    pub max_headers: usize,
+    pub max_body_len: usize,
+    // Where to resume searching for the end of the head.
+    searched: usize,
+    // The framing and state of the body being read, if any.
+    framing: Option<Framing>,
+    chunk: Chunk,
+    body: ByteBuf,
+    trailers: Headers,
+}
+
+#[inline]
+pub(crate) fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[inline]
+fn trim_cr(line: &str) -> &str {
+    match line.ends_with('\r') {
+        true => &line[..line.len() - 1],
+        false => line,
+    }
+}
+
+#[inline]
+pub(crate) fn is_token(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| {
+        b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+    })
+}
+
+// Splits the first `n` bytes off `data`.
+#[inline]
+fn take(data: &mut ByteBuf, n: usize) -> ByteBuf {
+    if n == 0 {
+        return ByteBuf::new();
+    }
+    match data.drain_to(n) {
+        Ok(taken) => taken,
+        _ => ::unreachable(),
+    }
+}
+
+fn parse_field(line: &str, headers: &mut Headers, max_headers: usize) -> io::Result<()> {
+    if headers.len() >= max_headers {
+        return Err(invalid_data("Too many header fields"));
+    }
+    let i = match line.find(':') {
+        Some(i) => i,
+        None => return Err(invalid_data("Invalid header field")),
+    };
+    let name = &line[..i];
+    // Also rejects obsolete line folding, which starts with whitespace.
+    if !is_token(name) {
+        return Err(invalid_data("Invalid header field name"));
+    }
+    let value = line[i + 1..].trim_matches(|c| c == ' ' || c == '\t');
+    headers.append(name, value);
+    Ok(())
+}
+
+/// Parses a message head into its start line and header fields.
+pub(crate) fn parse_head(head: &[u8], max_headers: usize) -> io::Result<(&str, Headers)> {
+    let head = str::from_utf8(head).map_err(|_| invalid_data("Invalid message head"))?;
+    let mut lines = head.split('\n').map(trim_cr);
+    let start = match lines.next() {
+        Some(start) => start,
+        None => ::unreachable(),
+    };
+    let mut headers = Headers::new();
+    for line in lines.take_while(|line| !line.is_empty()) {
+        parse_field(line, &mut headers, max_headers)?;
+    }
+    Ok((start, headers))
+}
+
+pub(crate) fn parse_version(s: &str) -> io::Result<Version> {
+    Version::parse(s).ok_or_else(|| invalid_data("Unsupported HTTP version"))
+}
+
+/// Returns the framing of a body announced by `headers`, or `None` if they
+/// announce neither a length nor chunks.
+pub(crate) fn body_framing(headers: &Headers) -> io::Result<Option<Framing>> {
+    if headers.contains("Transfer-Encoding") {
+        // A length as well may be an attempt at request smuggling.
+        if headers.contains("Content-Length") {
+            return Err(invalid_data("Both Transfer-Encoding and Content-Length"));
+        }
+        let last = headers
+            .get_all("Transfer-Encoding")
+            .flat_map(|v| v.split(','))
+            .last()
+            .map(|t| t.trim());
+        return match last {
+            Some(t) if t.eq_ignore_ascii_case("chunked") => Ok(Some(Framing::Chunked)),
+            _ => Ok(Some(Framing::Eof)),
+        };
+    }
+    let mut len = None;
+    for v in headers.get_all("Content-Length").flat_map(|v| v.split(',')) {
+        let v = v.trim();
+        if v.is_empty() || !v.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid_data("Invalid Content-Length"));
+        }
+        let n = v.parse::<usize>()
+            .map_err(|_| invalid_data("Invalid Content-Length"))?;
+        if len.map_or(false, |len| len != n) {
+            return Err(invalid_data("Conflicting Content-Length"));
+        }
+        len = Some(n);
+    }
+    Ok(len.map(Framing::Length))
+}
+
+impl Parser {
+    pub fn new(max_head_len: usize, max_headers: usize, max_body_len: usize) -> Self {
+        Parser {
+            max_head_len,
+            max_headers,
+            max_body_len,
+            searched: 0,
+            framing: None,
+            chunk: Chunk::Size,
+            body: ByteBuf::new(),
+            trailers: Headers::new(),
+        }
+    }
+
+    /// Returns whether a body is being read.
+    #[inline]
+    pub fn in_body(&self) -> bool {
+        self.framing.is_some()
+    }
+
+    /// Splits the next head, up to and including its blank line, off the
+    /// front of `data`. Blank lines before it are skipped.
+    pub fn head(&mut self, data: &mut ByteBuf) -> io::Result<Option<ByteBuf>> {
+        if self.searched == 0 {
+            while data.bytes().next().map_or(false, |b| b == b'\r' || b == b'\n') {
+                data.skip(1);
+            }
+        }
+        let found = data.find_any_from(&[b"\r\n\r\n", b"\n\n"], self.searched);
+        let end = match found {
+            Some((i, 0)) => i + 4,
+            Some((i, _)) => i + 2,
+            None => {
+                self.searched = (data.len() + 1).saturating_sub(4);
+                if self.searched > self.max_head_len {
+                    return Err(frame_too_long(self.searched, self.max_head_len));
+                }
+                return Ok(None);
+            }
+        };
+        self.searched = 0;
+        if end > self.max_head_len {
+            return Err(frame_too_long(end, self.max_head_len));
+        }
+        Ok(Some(take(data, end)))
+    }
+
+    /// Starts reading a body.
+    pub fn start_body(&mut self, framing: Framing) -> io::Result<()> {
+        self.framing = Some(framing);
+        self.chunk = Chunk::Size;
+        match framing {
+            Framing::Length(n) if n > self.max_body_len => Err(frame_too_long(n, self.max_body_len)),
+            _ => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn add_to_body(&mut self, data: ByteBuf) -> io::Result<()> {
+        put(&mut self.body, data);
+        let len = self.body.len();
+        if len > self.max_body_len {
+            return Err(frame_too_long(len, self.max_body_len));
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn done(&mut self) -> Option<(ByteBuf, Headers)> {
+        self.framing = None;
+        let body = mem::replace(&mut self.body, ByteBuf::new());
+        Some((body, mem::replace(&mut self.trailers, Headers::new())))
+    }
+
+    // Splits the next line of chunked framing off `data`.
+    fn line(&mut self, data: &mut ByteBuf, max: usize) -> io::Result<Option<String>> {
+        let i = match data.find(b"\n") {
+            Some(i) => i,
+            None => {
+                if data.len() > max {
+                    return Err(invalid_data("Chunk line too long"));
+                }
+                return Ok(None);
+            }
+        };
+        let line = take(data, i + 1);
+        let line = line.as_bytes();
+        let line = str::from_utf8(&line[..i]).map_err(|_| invalid_data("Invalid chunk line"))?;
+        Ok(Some(trim_cr(line).to_owned()))
+    }
+
+    /// Reads the body started by `start_body` from `data`, and returns it and
+    /// any trailer fields once it is whole. `eof` tells that no more data
+    /// will arrive.
+    pub fn body(&mut self, data: &mut ByteBuf, eof: bool) -> io::Result<Option<(ByteBuf, Headers)>> {
+        loop {
+            let framing = match self.framing {
+                Some(framing) => framing,
+                None => ::unreachable(),
+            };
+            match (framing, self.chunk) {
+                (Framing::Length(n), _) => {
+                    if data.len() < n {
+                        return Ok(None);
+                    }
+                    self.body = take(data, n);
+                    return Ok(self.done());
+                }
+                (Framing::Eof, _) => {
+                    let rest = mem::replace(data, ByteBuf::new());
+                    self.add_to_body(rest)?;
+                    return Ok(if eof { self.done() } else { None });
+                }
+                (Framing::Chunked, Chunk::Size) => {
+                    let line = match self.line(data, MAX_CHUNK_LINE_LEN)? {
+                        Some(line) => line,
+                        None => return Ok(None),
+                    };
+                    let size = line.split(';').next().unwrap_or("").trim();
+                    if size.is_empty() || !size.bytes().all(|b| b.is_ascii_hexdigit()) {
+                        return Err(invalid_data("Invalid chunk size"));
+                    }
+                    let size = usize::from_str_radix(size, 16)
+                        .map_err(|_| invalid_data("Invalid chunk size"))?;
+                    if size > self.max_body_len - self.body.len() {
+                        let len = self.body.len().saturating_add(size);
+                        return Err(frame_too_long(len, self.max_body_len));
+                    }
+                    self.chunk = match size {
+                        0 => Chunk::Trailers,
+                        n => Chunk::Data(n),
+                    };
+                }
+                (Framing::Chunked, Chunk::Data(n)) => {
+                    let len = data.len();
+                    if len == 0 {
+                        return Ok(None);
+                    }
+                    // Moves partial chunks along, so that they need no buffering.
+                    let m = if len < n { len } else { n };
+                    let chunk = take(data, m);
+                    self.add_to_body(chunk)?;
+                    self.chunk = match n - m {
+                        0 => Chunk::DataEnd,
+                        rest => Chunk::Data(rest),
+                    };
+                }
+                (Framing::Chunked, Chunk::DataEnd) => {
+                    let line = match self.line(data, 2)? {
+                        Some(line) => line,
+                        None => return Ok(None),
+                    };
+                    if !line.is_empty() {
+                        return Err(invalid_data("Missing line end after chunk"));
+                    }
+                    self.chunk = Chunk::Size;
+                }
+                (Framing::Chunked, Chunk::Trailers) => {
+                    let max = self.max_head_len;
+                    let line = match self.line(data, max)? {
+                        Some(line) => line,
+                        None => return Ok(None),
+                    };
+                    if line.is_empty() {
+                        return Ok(self.done());
+                    }
+                    parse_field(&line, &mut self.trailers, self.max_headers)?;
+                }
+            }
+        }
+    }
+}
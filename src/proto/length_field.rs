@@ -0,0 +1,310 @@
+use std::cmp;
+use std::io;
+
+use buf::ByteBuf;
+use buf::codec::u8s;
+
+use super::{frame_too_long, max_frame_len, prefix, put, Decoder, Encoder, Framed};
+
+const MAX_VARINT_SIZE: usize = 10;
+
+/// The encoding of a length field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Width {
+    One,
+    Two,
+    Three,
+    Four,
+    Eight,
+    /// A LEB128 varint of up to 10 bytes, as in `codec::u64::varint`.
+    Varint,
+}
+
+/// The byte order of a fixed width length field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    Big,
+    Little,
+}
+
+/// Frames a byte stream by a length field in the header of each frame.
+///
+/// A frame is `offset` header bytes, the length field, and the rest of the
+/// frame, `length + adjustment` bytes. The decoder yields each frame with its
+/// first `strip` bytes removed. The encoder is given a frame without the
+/// length field and inserts one at `offset`; `strip` does not apply to it.
+///
+/// ```ignore
+/// // A 2-byte type, a 4-byte big-endian length of the whole frame, the body.
+/// let codec = LengthFieldCodec::builder()
+///     .offset(2)
+///     .width(Width::Four)
+///     .adjustment(-6)
+///     .build();
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct LengthFieldCodec {
+    offset: usize,
+    width: Width,
+    endian: Endian,
+    adjustment: isize,
+    strip: usize,
+    max_frame_len: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LengthFieldCodecBuilder {
+    inner: LengthFieldCodec,
+}
+
+#[inline]
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[inline]
+fn invalid_input(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+impl Width {
+    #[inline]
+    fn fixed(self) -> Option<usize> {
+        match self {
+            Width::One => Some(1),
+            Width::Two => Some(2),
+            Width::Three => Some(3),
+            Width::Four => Some(4),
+            Width::Eight => Some(8),
+            Width::Varint => None,
+        }
+    }
+}
+
+impl Default for LengthFieldCodecBuilder {
+    #[inline]
+    fn default() -> Self {
+        LengthFieldCodecBuilder {
+            inner: LengthFieldCodec {
+                offset: 0,
+                width: Width::Four,
+                endian: Endian::Big,
+                adjustment: 0,
+                strip: 0,
+                max_frame_len: max_frame_len(),
+            },
+        }
+    }
+}
+
+impl LengthFieldCodecBuilder {
+    /// Sets the number of header bytes before the length field. Defaults to 0.
+    #[inline]
+    pub fn offset(&mut self, offset: usize) -> &mut Self {
+        self.inner.offset = offset;
+        self
+    }
+
+    /// Defaults to `Width::Four`.
+    #[inline]
+    pub fn width(&mut self, width: Width) -> &mut Self {
+        self.inner.width = width;
+        self
+    }
+
+    /// Defaults to `Endian::Big`.
+    #[inline]
+    pub fn endian(&mut self, endian: Endian) -> &mut Self {
+        self.inner.endian = endian;
+        self
+    }
+
+    /// Sets the number of bytes after the length field minus its value, e.g.
+    /// the negated size of the header if the length includes it.
+    #[inline]
+    pub fn adjustment(&mut self, adjustment: isize) -> &mut Self {
+        self.inner.adjustment = adjustment;
+        self
+    }
+
+    /// Sets the number of bytes removed from the front of decoded frames,
+    /// e.g. `offset` plus the field width to yield the body only.
+    #[inline]
+    pub fn strip(&mut self, strip: usize) -> &mut Self {
+        self.inner.strip = strip;
+        self
+    }
+
+    /// Sets the maximum length of a whole frame, header included.
+    #[inline]
+    pub fn max_frame_len(&mut self, max_frame_len: usize) -> &mut Self {
+        self.inner.max_frame_len = max_frame_len;
+        self
+    }
+
+    #[inline]
+    pub fn build(&self) -> LengthFieldCodec {
+        self.inner
+    }
+}
+
+impl LengthFieldCodec {
+    #[inline]
+    pub fn builder() -> LengthFieldCodecBuilder {
+        Default::default()
+    }
+
+    #[inline]
+    pub fn max_frame_len(&self) -> usize {
+        self.max_frame_len
+    }
+
+    #[inline]
+    pub fn frames<S>(self, stream: S) -> Framed<S, Self> {
+        Framed::new(stream, self)
+    }
+
+    // Returns the value and the size of the length field, or `None` if `data`
+    // does not hold all of it yet.
+    fn get_len(&self, data: &ByteBuf) -> io::Result<Option<(u64, usize)>> {
+        let mut bytes = data.bytes().skip(self.offset);
+        match self.width.fixed() {
+            Some(n) => {
+                let mut v = 0u64;
+                for i in 0..n {
+                    let b = match bytes.next() {
+                        Some(b) => b as u64,
+                        None => return Ok(None),
+                    };
+                    v |= match self.endian {
+                        Endian::Big => b << (8 * (n - 1 - i)),
+                        Endian::Little => b << (8 * i),
+                    };
+                }
+                Ok(Some((v, n)))
+            }
+            None => {
+                let mut v = 0u64;
+                for i in 0..MAX_VARINT_SIZE {
+                    let b = match bytes.next() {
+                        Some(b) => b as u64,
+                        None => return Ok(None),
+                    };
+                    let shift = 7 * i as u32;
+                    // Bits shifted out of 64 cannot be represented.
+                    if (b & 0x7F).leading_zeros() < shift {
+                        return Err(invalid_data("Varint length field overflows u64"));
+                    }
+                    v |= (b & 0x7F) << shift;
+                    if b & 0x80 == 0 {
+                        return Ok(Some((v, i + 1)));
+                    }
+                }
+                Err(invalid_data("Invalid varint length field"))
+            }
+        }
+    }
+}
+
+impl Decoder for LengthFieldCodec {
+    type Item = ByteBuf;
+    type Error = io::Error;
+
+    fn decode(&mut self, data: &mut ByteBuf) -> io::Result<Option<ByteBuf>> {
+        let (len, width) = match self.get_len(data)? {
+            Some(t) => t,
+            None => return Ok(None),
+        };
+        let head = self.offset + width;
+        let frame_len = (head as i128) + (len as i128) + (self.adjustment as i128);
+        if frame_len < head as i128 {
+            return Err(invalid_data("Invalid length field"));
+        }
+        if frame_len > self.max_frame_len as i128 {
+            let len = cmp::min(frame_len, usize::max_value() as i128) as usize;
+            return Err(frame_too_long(len, self.max_frame_len));
+        }
+        let frame_len = frame_len as usize;
+        if frame_len < self.strip {
+            return Err(invalid_data("Frame shorter than the bytes to strip"));
+        }
+        if data.len() < frame_len {
+            return Ok(None);
+        }
+        let mut frame = match data.drain_to(frame_len) {
+            Ok(frame) => frame,
+            _ => ::unreachable(),
+        };
+        frame.skip(self.strip);
+        Ok(Some(frame))
+    }
+}
+
+/// Inserts the length field at `offset` of each frame.
+impl Encoder for LengthFieldCodec {
+    type Item = ByteBuf;
+    type Error = io::Error;
+
+    fn encode(&mut self, mut data: ByteBuf, dst: &mut ByteBuf) -> io::Result<()> {
+        let data_len = data.len();
+        if data_len < self.offset {
+            return Err(invalid_input("Frame shorter than the length field offset"));
+        }
+        let len = (data_len - self.offset) as i128 - self.adjustment as i128;
+        if len < 0 {
+            return Err(invalid_input("Negative length field"));
+        }
+        let len = len as u128;
+
+        let mut field = [0u8; MAX_VARINT_SIZE];
+        let width = match self.width.fixed() {
+            Some(n) => {
+                if (n < 8 && len >> (8 * n) != 0) || len > u64::max_value() as u128 {
+                    return Err(invalid_input("Length does not fit in the length field"));
+                }
+                for (i, b) in field[..n].iter_mut().enumerate() {
+                    let shift = match self.endian {
+                        Endian::Big => 8 * (n - 1 - i),
+                        Endian::Little => 8 * i,
+                    };
+                    *b = (len >> shift) as u8;
+                }
+                n
+            }
+            None => {
+                if len > u64::max_value() as u128 {
+                    return Err(invalid_input("Length does not fit in the length field"));
+                }
+                let mut v = len as u64;
+                let mut n = 0;
+                while v & !0x7F != 0 {
+                    field[n] = (v | 0x80) as u8;
+                    v >>= 7;
+                    n += 1;
+                }
+                field[n] = v as u8;
+                n + 1
+            }
+        };
+        let field = &field[..width];
+
+        let frame_len = data_len + width;
+        if frame_len > self.max_frame_len {
+            return Err(frame_too_long(frame_len, self.max_frame_len));
+        }
+        if self.offset == 0 {
+            put(dst, prefix(data, field, width, u8s::prepend));
+            return Ok(());
+        }
+        let mut frame = match data.drain_to(self.offset) {
+            Ok(frame) => frame,
+            _ => ::unreachable(),
+        };
+        frame
+            .append(field, u8s::append)
+            .map_err(|_| invalid_input("Buffer limit exceeded"))?;
+        frame.extend(data);
+        put(dst, frame);
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use std::io;
+
+use buf::ByteBuf;
+use buf::codec::{u8, u8s};
+
+use super::{frame_too_long, max_frame_len, put, Decoder, Encoder, Framed};
+
+/// Frames a byte stream into lines ending with `\n` or `\r\n`.
+///
+/// A search that finds no line end is resumed where it stopped once more
+/// data arrives, so a long line is scanned only once.
+#[derive(Debug, Clone)]
+pub struct LineCodec {
+    keep: bool,
+    crlf: bool,
+    max_len: usize,
+    // Where to resume searching in the data not yet decoded.
+    searched: usize,
+}
+
+impl Default for LineCodec {
+    #[inline]
+    fn default() -> Self {
+        LineCodec::new()
+    }
+}
+
+impl LineCodec {
+    #[inline]
+    pub fn new() -> Self {
+        LineCodec {
+            keep: false,
+            crlf: true,
+            max_len: max_frame_len(),
+            searched: 0,
+        }
+    }
+
+    #[inline]
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    /// Sets the maximum length of a line, excluding its line end. Longer
+    /// lines fail with `FrameTooLong`.
+    #[inline]
+    pub fn set_max_len(&mut self, max_len: usize) -> &mut Self {
+        self.max_len = max_len;
+        self
+    }
+
+    #[inline]
+    pub fn keep_line_end(&self) -> bool {
+        self.keep
+    }
+
+    /// Sets whether decoded lines end with their `\n` or `\r\n`. Defaults to
+    /// `false`.
+    #[inline]
+    pub fn set_keep_line_end(&mut self, keep: bool) -> &mut Self {
+        self.keep = keep;
+        self
+    }
+
+    #[inline]
+    pub fn crlf(&self) -> bool {
+        self.crlf
+    }
+
+    /// Sets whether lines are encoded with `\r\n` rather than `\n`. Defaults
+    /// to `true`.
+    #[inline]
+    pub fn set_crlf(&mut self, crlf: bool) -> &mut Self {
+        self.crlf = crlf;
+        self
+    }
+
+    #[inline]
+    pub fn frames<S>(self, stream: S) -> Framed<S, Self> {
+        Framed::new(stream, self)
+    }
+}
+
+impl Decoder for LineCodec {
+    type Item = ByteBuf;
+    type Error = io::Error;
+
+    fn decode(&mut self, data: &mut ByteBuf) -> io::Result<Option<ByteBuf>> {
+        let i = match data.find_from(b"\n", self.searched) {
+            Some(i) => i,
+            None => {
+                self.searched = data.len();
+                // The last byte may be the `\r` of a line end.
+                if self.searched.saturating_sub(1) > self.max_len {
+                    return Err(frame_too_long(self.searched, self.max_len));
+                }
+                return Ok(None);
+            }
+        };
+        self.searched = 0;
+        let cr = match i {
+            0 => false,
+            _ => data.get(i - 1, u8::get).ok() == Some(b'\r'),
+        };
+        let len = if cr { i - 1 } else { i };
+        if len > self.max_len {
+            return Err(frame_too_long(len, self.max_len));
+        }
+        let end = if self.keep { i + 1 } else { len };
+        let line = match data.drain_to(end) {
+            Ok(line) => line,
+            _ => ::unreachable(),
+        };
+        if !self.keep {
+            data.skip(i + 1 - len);
+        }
+        Ok(Some(line))
+    }
+
+    /// Takes any bytes after the last line end as the last line.
+    fn decode_eof(&mut self, data: &mut ByteBuf) -> io::Result<Option<ByteBuf>> {
+        if let Some(line) = self.decode(data)? {
+            return Ok(Some(line));
+        }
+        self.searched = 0;
+        let len = data.len();
+        if len == 0 {
+            return Ok(None);
+        }
+        if len > self.max_len {
+            return Err(frame_too_long(len, self.max_len));
+        }
+        Ok(data.drain_to(len).ok())
+    }
+}
+
+/// Appends a line end to each line, which must not contain `\n`.
+impl Encoder for LineCodec {
+    type Item = ByteBuf;
+    type Error = io::Error;
+
+    fn encode(&mut self, mut data: ByteBuf, dst: &mut ByteBuf) -> io::Result<()> {
+        let len = data.len();
+        if len > self.max_len {
+            return Err(frame_too_long(len, self.max_len));
+        }
+        if data.find(b"\n").is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Line contains a line end",
+            ));
+        }
+        let end: &[u8] = if self.crlf { b"\r\n" } else { b"\n" };
+        data.append(end, u8s::append)
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "Buffer limit exceeded"))?;
+        put(dst, data);
+        Ok(())
+    }
+}
@@ -0,0 +1,245 @@
+//! The binary protocol.
+
+use std::io;
+
+use buf::ByteBuf;
+use buf::codec::{u16, u32, u64, u8};
+use proto::{frame_too_long, max_frame_len, put, Decoder, Encoder, Framed};
+
+/// The length of a packet header.
+pub const HEADER_LEN: usize = 24;
+
+/// Command opcodes.
+pub mod opcode {
+    pub const GET: u8 = 0x00;
+    pub const SET: u8 = 0x01;
+    pub const ADD: u8 = 0x02;
+    pub const REPLACE: u8 = 0x03;
+    pub const DELETE: u8 = 0x04;
+    pub const INCREMENT: u8 = 0x05;
+    pub const DECREMENT: u8 = 0x06;
+    pub const QUIT: u8 = 0x07;
+    pub const FLUSH: u8 = 0x08;
+    pub const GETQ: u8 = 0x09;
+    pub const NOOP: u8 = 0x0A;
+    pub const VERSION: u8 = 0x0B;
+    pub const GETK: u8 = 0x0C;
+    pub const GETKQ: u8 = 0x0D;
+    pub const APPEND: u8 = 0x0E;
+    pub const PREPEND: u8 = 0x0F;
+}
+
+/// Response statuses.
+pub mod status {
+    pub const NO_ERROR: u16 = 0x0000;
+    pub const KEY_NOT_FOUND: u16 = 0x0001;
+    pub const KEY_EXISTS: u16 = 0x0002;
+    pub const VALUE_TOO_LARGE: u16 = 0x0003;
+    pub const INVALID_ARGUMENTS: u16 = 0x0004;
+    pub const ITEM_NOT_STORED: u16 = 0x0005;
+    pub const NON_NUMERIC_VALUE: u16 = 0x0006;
+    pub const UNKNOWN_COMMAND: u16 = 0x0081;
+    pub const OUT_OF_MEMORY: u16 = 0x0082;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Magic {
+    Request = 0x80,
+    Response = 0x81,
+}
+
+/// A packet of the binary protocol.
+#[derive(Debug, PartialEq)]
+pub struct Packet {
+    pub magic: Magic,
+    pub opcode: u8,
+    pub data_type: u8,
+    /// The vbucket id of a request, or the status of a response.
+    pub status: u16,
+    pub opaque: u32,
+    pub cas: u64,
+    pub extras: ByteBuf,
+    pub key: ByteBuf,
+    pub value: ByteBuf,
+}
+
+/// Decodes and encodes packets of the binary protocol, both requests and
+/// responses.
+#[derive(Debug, Clone, Copy)]
+pub struct BinaryCodec {
+    max_body_len: usize,
+}
+
+#[inline]
+fn invalid_input(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+// Splits `len` bytes off the front of `data`, which holds at least as many.
+fn take(data: &mut ByteBuf, len: usize) -> ByteBuf {
+    match len {
+        0 => ByteBuf::new(),
+        _ => match data.drain_to(len) {
+            Ok(taken) => taken,
+            _ => ::unreachable(),
+        },
+    }
+}
+
+impl Packet {
+    #[inline]
+    pub fn request(opcode: u8) -> Self {
+        Packet {
+            magic: Magic::Request,
+            opcode,
+            data_type: 0,
+            status: 0,
+            opaque: 0,
+            cas: 0,
+            extras: ByteBuf::new(),
+            key: ByteBuf::new(),
+            value: ByteBuf::new(),
+        }
+    }
+
+    #[inline]
+    pub fn response(opcode: u8, status: u16) -> Self {
+        Packet {
+            magic: Magic::Response,
+            status,
+            ..Packet::request(opcode)
+        }
+    }
+
+    fn head(&self, key_len: u16, extras_len: u8, body_len: u32) -> Result<ByteBuf, ()> {
+        let mut head = ByteBuf::with_capacity(HEADER_LEN);
+        head.append(self.magic as u8, u8::append)?;
+        head.append(self.opcode, u8::append)?;
+        head.append(key_len, u16::big_endian::append)?;
+        head.append(extras_len, u8::append)?;
+        head.append(self.data_type, u8::append)?;
+        head.append(self.status, u16::big_endian::append)?;
+        head.append(body_len, u32::big_endian::append)?;
+        head.append(self.opaque, u32::big_endian::append)?;
+        head.append(self.cas, u64::big_endian::append)?;
+        Ok(head)
+    }
+}
+
+impl Default for BinaryCodec {
+    #[inline]
+    fn default() -> Self {
+        BinaryCodec::new()
+    }
+}
+
+impl BinaryCodec {
+    #[inline]
+    pub fn new() -> Self {
+        BinaryCodec {
+            max_body_len: max_frame_len(),
+        }
+    }
+
+    #[inline]
+    pub fn max_body_len(&self) -> usize {
+        self.max_body_len
+    }
+
+    /// Sets the maximum length of the extras, key and value of a packet.
+    /// Defaults to `max_frame_len()`.
+    #[inline]
+    pub fn set_max_body_len(&mut self, max_body_len: usize) -> &mut Self {
+        self.max_body_len = max_body_len;
+        self
+    }
+
+    #[inline]
+    pub fn frames<S>(self, stream: S) -> Framed<S, Self> {
+        Framed::new(stream, self)
+    }
+}
+
+impl Decoder for BinaryCodec {
+    type Item = Packet;
+    type Error = io::Error;
+
+    fn decode(&mut self, data: &mut ByteBuf) -> io::Result<Option<Packet>> {
+        if data.len() < HEADER_LEN {
+            return Ok(None);
+        }
+        macro_rules! get {
+            ($index:expr, $get:path) => {
+                match data.get($index, $get) {
+                    Ok(v) => v,
+                    _ => ::unreachable(),
+                }
+            };
+        }
+        let magic = match get!(0, u8::get) {
+            0x80 => Magic::Request,
+            0x81 => Magic::Response,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid magic")),
+        };
+        let key_len = get!(2, u16::big_endian::get) as usize;
+        let extras_len = get!(4, u8::get) as usize;
+        let body_len = get!(8, u32::big_endian::get) as usize;
+        if body_len > self.max_body_len {
+            return Err(frame_too_long(body_len, self.max_body_len));
+        }
+        if key_len + extras_len > body_len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Key and extras longer than the body",
+            ));
+        }
+        if data.len() < HEADER_LEN + body_len {
+            return Ok(None);
+        }
+        let packet = Packet {
+            magic,
+            opcode: get!(1, u8::get),
+            data_type: get!(5, u8::get),
+            status: get!(6, u16::big_endian::get),
+            opaque: get!(12, u32::big_endian::get),
+            cas: get!(16, u64::big_endian::get),
+            extras: {
+                data.skip(HEADER_LEN);
+                take(data, extras_len)
+            },
+            key: take(data, key_len),
+            value: take(data, body_len - key_len - extras_len),
+        };
+        Ok(Some(packet))
+    }
+}
+
+impl Encoder for BinaryCodec {
+    type Item = Packet;
+    type Error = io::Error;
+
+    fn encode(&mut self, packet: Packet, dst: &mut ByteBuf) -> io::Result<()> {
+        let key_len = packet.key.len();
+        let extras_len = packet.extras.len();
+        if key_len > ::std::u16::MAX as usize {
+            return Err(invalid_input("Key too long"));
+        }
+        if extras_len > ::std::u8::MAX as usize {
+            return Err(invalid_input("Extras too long"));
+        }
+        let body_len = key_len + extras_len + packet.value.len();
+        if body_len > ::std::u32::MAX as usize {
+            return Err(invalid_input("Body too long"));
+        }
+        let mut frame = packet
+            .head(key_len as u16, extras_len as u8, body_len as u32)
+            .map_err(|()| io::Error::new(io::ErrorKind::Other, "Buffer limit exceeded"))?;
+        for part in [packet.extras, packet.key, packet.value] {
+            if !part.is_empty() {
+                frame.extend(part);
+            }
+        }
+        put(dst, frame);
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+use std::io;
+
+use futures::{Async, Future, Poll, Sink, Stream};
+
+use buf::ByteBuf;
+use proto::{self, Framed, PipelineCodec};
+
+use super::text::{ClientCodec, Request, Response};
+
+/// A client of a memcached server at the other end of `T`, e.g. the halves
+/// of `net::tcp::split` joined by `Duplex`, over the text protocol.
+pub struct Client<T> {
+    framed: Framed<T, ClientCodec>,
+}
+
+/// Sends requests without waiting for responses, and yields the client and
+/// the responses in the order of the requests. `noreply` requests have no
+/// response.
+pub struct Pipeline<T> {
+    inner: proto::Pipeline<T, ClientCodec>,
+}
+
+/// Sends a request and yields the client and the response, or `None` for a
+/// `noreply` request.
+pub struct Call<T> {
+    inner: Pipeline<T>,
+}
+
+impl<T> Client<T> {
+    #[inline]
+    pub fn new(io: T) -> Self {
+        Self::with_codec(io, ClientCodec::new())
+    }
+
+    #[inline]
+    pub fn with_codec(io: T, codec: ClientCodec) -> Self {
+        Client {
+            framed: Framed::new(io, codec),
+        }
+    }
+
+    #[inline]
+    pub fn codec(&self) -> &ClientCodec {
+        self.framed.codec()
+    }
+
+    #[inline]
+    pub fn codec_mut(&mut self) -> &mut ClientCodec {
+        self.framed.codec_mut()
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.framed.into_inner()
+    }
+
+    #[inline]
+    pub fn call(self, req: Request) -> Call<T> {
+        Call {
+            inner: self.pipeline(Some(req)),
+        }
+    }
+
+    /// Sends `reqs` without waiting for the response to each.
+    pub fn pipeline<I>(self, reqs: I) -> Pipeline<T>
+    where
+        I: IntoIterator<Item = Request>,
+    {
+        Pipeline {
+            inner: proto::Pipeline::new(self.framed, reqs),
+        }
+    }
+}
+
+impl PipelineCodec for ClientCodec {
+    #[inline]
+    fn has_response(&self, req: &Request) -> bool {
+        !req.noreply()
+    }
+}
+
+impl<T> Future for Pipeline<T>
+where
+    T: Stream<Item = ByteBuf, Error = io::Error> + Sink<SinkItem = ByteBuf, SinkError = io::Error>,
+{
+    type Item = (Client<T>, Vec<Response>);
+    type Error = io::Error;
+
+    #[inline]
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let (framed, responses) = try_ready!(self.inner.poll());
+        Ok(Async::Ready((Client { framed }, responses)))
+    }
+}
+
+impl<T> Future for Call<T>
+where
+    T: Stream<Item = ByteBuf, Error = io::Error> + Sink<SinkItem = ByteBuf, SinkError = io::Error>,
+{
+    type Item = (Client<T>, Option<Response>);
+    type Error = io::Error;
+
+    #[inline]
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let (client, mut responses) = try_ready!(self.inner.poll());
+        Ok(Async::Ready((client, responses.pop())))
+    }
+}
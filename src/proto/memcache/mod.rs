@@ -0,0 +1,18 @@
+//! The memcached text and binary protocols.
+//!
+//! `text::ClientCodec` frames a connection into responses to the requests
+//! it encodes, over which `Client` sends requests and pipelines.
+//! `binary::BinaryCodec` frames packets of the binary protocol.
+
+pub mod binary;
+pub mod text;
+
+mod client;
+pub use self::client::*;
+
+/// The maximum length of a key.
+pub const MAX_KEY_LEN: usize = 250;
+
+/// The maximum length of a command or status line of text codecs not
+/// configured otherwise.
+pub const DEFAULT_MAX_LINE_LEN: usize = 2048;
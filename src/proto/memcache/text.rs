@@ -0,0 +1,626 @@
+//! The text protocol.
+
+use std::fmt::Write;
+use std::io;
+use std::str;
+
+use buf::ByteBuf;
+use buf::codec::u8s;
+use proto::{frame_too_long, max_frame_len, put, Decoder, Encoder, Framed};
+
+use super::{DEFAULT_MAX_LINE_LEN, MAX_KEY_LEN};
+
+/// How a storage command stores its data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Store {
+    Set,
+    Add,
+    Replace,
+    Append,
+    Prepend,
+    /// Stores only if the item is unchanged since it was fetched with this
+    /// CAS unique by `gets`.
+    Cas(u64),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Request {
+    Get(Vec<String>),
+    /// Gets items with their CAS uniques.
+    Gets(Vec<String>),
+    Store {
+        mode: Store,
+        key: String,
+        flags: u32,
+        exptime: i64,
+        data: ByteBuf,
+        noreply: bool,
+    },
+    Delete {
+        key: String,
+        noreply: bool,
+    },
+    Incr {
+        key: String,
+        delta: u64,
+        noreply: bool,
+    },
+    Decr {
+        key: String,
+        delta: u64,
+        noreply: bool,
+    },
+}
+
+/// An item in reply to `get` or `gets`.
+#[derive(Debug, PartialEq)]
+pub struct Item {
+    pub key: String,
+    pub flags: u32,
+    pub data: ByteBuf,
+    /// The CAS unique, in reply to `gets` only.
+    pub cas: Option<u64>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Response {
+    /// The items found, in reply to `get` or `gets`.
+    Values(Vec<Item>),
+    Stored,
+    NotStored,
+    Exists,
+    NotFound,
+    Deleted,
+    /// The new value, in reply to `incr` or `decr`.
+    Number(u64),
+    Error,
+    ClientError(String),
+    ServerError(String),
+}
+
+/// Encodes requests and decodes responses, for clients.
+#[derive(Debug)]
+pub struct ClientCodec {
+    lines: Lines,
+    // The items of a reply to `get` so far.
+    values: Option<Vec<Item>>,
+    // An item whose header has been decoded.
+    item: Option<(Item, usize)>,
+}
+
+/// Decodes requests and encodes responses, for servers.
+#[derive(Debug)]
+pub struct ServerCodec {
+    lines: Lines,
+    // A storage command whose header has been decoded.
+    store: Option<(Request, usize)>,
+}
+
+#[derive(Debug)]
+struct Lines {
+    max_line_len: usize,
+    max_data_len: usize,
+    // Where to resume searching for the end of a line.
+    searched: usize,
+}
+
+#[inline]
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[inline]
+fn invalid_input(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+fn append(dst: &mut ByteBuf, bytes: &[u8]) -> io::Result<()> {
+    match dst.append(bytes, u8s::append) {
+        Ok(..) => Ok(()),
+        Err(()) => Err(io::Error::new(io::ErrorKind::Other, "Buffer limit exceeded")),
+    }
+}
+
+fn parse<T: str::FromStr>(word: Option<&str>) -> io::Result<T> {
+    word.and_then(|w| w.parse().ok())
+        .ok_or_else(|| invalid_data("Invalid number"))
+}
+
+fn parse_key(word: Option<&str>) -> io::Result<String> {
+    match word {
+        Some(key) if key.len() <= MAX_KEY_LEN => Ok(key.to_owned()),
+        _ => Err(invalid_data("Invalid key")),
+    }
+}
+
+fn check_key(key: &str) -> io::Result<()> {
+    match !key.is_empty() && key.len() <= MAX_KEY_LEN && key.bytes().all(|b| b > b' ' && b != 0x7F) {
+        true => Ok(()),
+        false => Err(invalid_input("Invalid key")),
+    }
+}
+
+fn retrieval(line: &mut String, cmd: &str, keys: &[String]) -> io::Result<()> {
+    if keys.is_empty() {
+        return Err(invalid_input("No key"));
+    }
+    line.push_str(cmd);
+    for key in keys {
+        check_key(key)?;
+        line.push(' ');
+        line.push_str(key);
+    }
+    Ok(())
+}
+
+fn check_text(s: &str) -> io::Result<()> {
+    match s.contains(|c| c == '\r' || c == '\n') {
+        true => Err(invalid_input("Text contains a line end")),
+        false => Ok(()),
+    }
+}
+
+impl Lines {
+    #[inline]
+    fn new() -> Self {
+        Lines {
+            max_line_len: DEFAULT_MAX_LINE_LEN,
+            max_data_len: max_frame_len(),
+            searched: 0,
+        }
+    }
+
+    fn line(&mut self, data: &mut ByteBuf) -> io::Result<Option<String>> {
+        let i = match data.find_from(b"\r\n", self.searched) {
+            Some(i) => i,
+            None => {
+                // The last byte may be the `\r` of a line end.
+                self.searched = data.len().saturating_sub(1);
+                if self.searched > self.max_line_len {
+                    return Err(frame_too_long(self.searched, self.max_line_len));
+                }
+                return Ok(None);
+            }
+        };
+        self.searched = 0;
+        if i > self.max_line_len {
+            return Err(frame_too_long(i, self.max_line_len));
+        }
+        let line = match i {
+            0 => Vec::new(),
+            _ => match data.drain_to(i) {
+                Ok(line) => line.as_bytes().into_owned(),
+                _ => ::unreachable(),
+            },
+        };
+        data.skip(2);
+        String::from_utf8(line)
+            .map(Some)
+            .map_err(|_| invalid_data("Invalid UTF-8 line"))
+    }
+
+    fn check_len(&self, len: usize) -> io::Result<usize> {
+        match len > self.max_data_len {
+            true => Err(frame_too_long(len, self.max_data_len)),
+            false => Ok(len),
+        }
+    }
+
+    // Decodes a data block of `len` bytes and its line end.
+    fn data(&self, data: &mut ByteBuf, len: usize) -> io::Result<Option<ByteBuf>> {
+        if data.len() < len + 2 {
+            return Ok(None);
+        }
+        let block = match len {
+            0 => ByteBuf::new(),
+            _ => match data.drain_to(len) {
+                Ok(block) => block,
+                _ => ::unreachable(),
+            },
+        };
+        let mut bytes = data.bytes();
+        if bytes.next() != Some(b'\r') || bytes.next() != Some(b'\n') {
+            return Err(invalid_data("Missing line end after data block"));
+        }
+        data.skip(2);
+        Ok(Some(block))
+    }
+}
+
+macro_rules! limits {
+    ($codec:ident) => {
+        impl Default for $codec {
+            #[inline]
+            fn default() -> Self {
+                $codec::new()
+            }
+        }
+
+        impl $codec {
+            #[inline]
+            pub fn max_line_len(&self) -> usize {
+                self.lines.max_line_len
+            }
+
+            /// Sets the maximum length of a command or status line. Defaults
+            /// to `DEFAULT_MAX_LINE_LEN`.
+            #[inline]
+            pub fn set_max_line_len(&mut self, max_line_len: usize) -> &mut Self {
+                self.lines.max_line_len = max_line_len;
+                self
+            }
+
+            #[inline]
+            pub fn max_data_len(&self) -> usize {
+                self.lines.max_data_len
+            }
+
+            /// Sets the maximum length of a data block. Defaults to
+            /// `max_frame_len()`.
+            #[inline]
+            pub fn set_max_data_len(&mut self, max_data_len: usize) -> &mut Self {
+                self.lines.max_data_len = max_data_len;
+                self
+            }
+
+            #[inline]
+            pub fn frames<S>(self, stream: S) -> Framed<S, Self> {
+                Framed::new(stream, self)
+            }
+        }
+    };
+}
+
+limits!(ClientCodec);
+limits!(ServerCodec);
+
+impl Request {
+    /// Returns whether the server sends no reply to the request.
+    #[inline]
+    pub fn noreply(&self) -> bool {
+        match *self {
+            Request::Get(..) | Request::Gets(..) => false,
+            Request::Store { noreply, .. }
+            | Request::Delete { noreply, .. }
+            | Request::Incr { noreply, .. }
+            | Request::Decr { noreply, .. } => noreply,
+        }
+    }
+}
+
+impl ClientCodec {
+    #[inline]
+    pub fn new() -> Self {
+        ClientCodec {
+            lines: Lines::new(),
+            values: None,
+            item: None,
+        }
+    }
+
+    fn value(&mut self, line: &str) -> io::Result<()> {
+        let mut words = line.split(' ').skip(1);
+        let key = parse_key(words.next())?;
+        let flags = parse(words.next())?;
+        let len = self.lines.check_len(parse(words.next())?)?;
+        let cas = match words.next() {
+            Some(cas) => Some(parse(Some(cas))?),
+            None => None,
+        };
+        if words.next().is_some() {
+            return Err(invalid_data("Invalid VALUE line"));
+        }
+        let item = Item {
+            key,
+            flags,
+            data: ByteBuf::new(),
+            cas,
+        };
+        self.item = Some((item, len));
+        Ok(())
+    }
+}
+
+impl Encoder for ClientCodec {
+    type Item = Request;
+    type Error = io::Error;
+
+    fn encode(&mut self, req: Request, dst: &mut ByteBuf) -> io::Result<()> {
+        let mut line = String::with_capacity(64);
+        let (data, noreply) = match req {
+            Request::Get(keys) => {
+                retrieval(&mut line, "get", &keys)?;
+                (None, false)
+            }
+            Request::Gets(keys) => {
+                retrieval(&mut line, "gets", &keys)?;
+                (None, false)
+            }
+            Request::Store {
+                mode,
+                key,
+                flags,
+                exptime,
+                data,
+                noreply,
+            } => {
+                check_key(&key)?;
+                let cmd = match mode {
+                    Store::Set => "set",
+                    Store::Add => "add",
+                    Store::Replace => "replace",
+                    Store::Append => "append",
+                    Store::Prepend => "prepend",
+                    Store::Cas(..) => "cas",
+                };
+                let _ = write!(line, "{} {} {} {} {}", cmd, key, flags, exptime, data.len());
+                if let Store::Cas(cas) = mode {
+                    let _ = write!(line, " {}", cas);
+                }
+                (Some(data), noreply)
+            }
+            Request::Delete { key, noreply } => {
+                check_key(&key)?;
+                let _ = write!(line, "delete {}", key);
+                (None, noreply)
+            }
+            Request::Incr { key, delta, noreply } => {
+                check_key(&key)?;
+                let _ = write!(line, "incr {} {}", key, delta);
+                (None, noreply)
+            }
+            Request::Decr { key, delta, noreply } => {
+                check_key(&key)?;
+                let _ = write!(line, "decr {} {}", key, delta);
+                (None, noreply)
+            }
+        };
+        if noreply {
+            line.push_str(" noreply");
+        }
+        line.push_str("\r\n");
+        append(dst, line.as_bytes())?;
+        if let Some(data) = data {
+            if !data.is_empty() {
+                put(dst, data);
+            }
+            append(dst, b"\r\n")?;
+        }
+        Ok(())
+    }
+}
+
+impl Decoder for ClientCodec {
+    type Item = Response;
+    type Error = io::Error;
+
+    fn decode(&mut self, data: &mut ByteBuf) -> io::Result<Option<Response>> {
+        loop {
+            if let Some((mut item, len)) = self.item.take() {
+                item.data = match self.lines.data(data, len)? {
+                    Some(block) => block,
+                    None => {
+                        self.item = Some((item, len));
+                        return Ok(None);
+                    }
+                };
+                match self.values {
+                    Some(ref mut values) => values.push(item),
+                    None => ::unreachable(),
+                }
+            }
+            let line = match self.lines.line(data)? {
+                Some(line) => line,
+                None => return Ok(None),
+            };
+            if line.starts_with("VALUE ") {
+                if self.values.is_none() {
+                    self.values = Some(Vec::new());
+                }
+                self.value(&line)?;
+                continue;
+            }
+            if line == "END" {
+                let values = self.values.take().unwrap_or_default();
+                return Ok(Some(Response::Values(values)));
+            }
+            if self.values.is_some() {
+                return Err(invalid_data("Invalid line within values"));
+            }
+            let res = match &line[..] {
+                "STORED" => Response::Stored,
+                "NOT_STORED" => Response::NotStored,
+                "EXISTS" => Response::Exists,
+                "NOT_FOUND" => Response::NotFound,
+                "DELETED" => Response::Deleted,
+                "ERROR" => Response::Error,
+                _ if line.starts_with("CLIENT_ERROR ") => {
+                    Response::ClientError(line["CLIENT_ERROR ".len()..].to_owned())
+                }
+                _ if line.starts_with("SERVER_ERROR ") => {
+                    Response::ServerError(line["SERVER_ERROR ".len()..].to_owned())
+                }
+                _ => Response::Number(parse(Some(&line[..]))?),
+            };
+            return Ok(Some(res));
+        }
+    }
+
+    fn decode_eof(&mut self, data: &mut ByteBuf) -> io::Result<Option<Response>> {
+        if let Some(res) = self.decode(data)? {
+            return Ok(Some(res));
+        }
+        match data.is_empty() && self.values.is_none() {
+            true => Ok(None),
+            false => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Stream ended within a response",
+            )),
+        }
+    }
+}
+
+impl ServerCodec {
+    #[inline]
+    pub fn new() -> Self {
+        ServerCodec {
+            lines: Lines::new(),
+            store: None,
+        }
+    }
+
+    fn request(&mut self, line: &str) -> io::Result<Option<Request>> {
+        let mut words = line.split(' ').filter(|w| !w.is_empty());
+        let cmd = words.next().unwrap_or("");
+        let mode = match cmd {
+            "get" | "gets" => {
+                let keys = words
+                    .map(|key| parse_key(Some(key)))
+                    .collect::<io::Result<Vec<_>>>()?;
+                if keys.is_empty() {
+                    return Err(invalid_data("No key"));
+                }
+                return Ok(Some(match cmd {
+                    "get" => Request::Get(keys),
+                    _ => Request::Gets(keys),
+                }));
+            }
+            "delete" => {
+                let key = parse_key(words.next())?;
+                let noreply = noreply(words)?;
+                return Ok(Some(Request::Delete { key, noreply }));
+            }
+            "incr" | "decr" => {
+                let key = parse_key(words.next())?;
+                let delta = parse(words.next())?;
+                let noreply = noreply(words)?;
+                return Ok(Some(match cmd {
+                    "incr" => Request::Incr { key, delta, noreply },
+                    _ => Request::Decr { key, delta, noreply },
+                }));
+            }
+            "set" => Store::Set,
+            "add" => Store::Add,
+            "replace" => Store::Replace,
+            "append" => Store::Append,
+            "prepend" => Store::Prepend,
+            "cas" => Store::Cas(0),
+            _ => return Err(invalid_data("Unknown command")),
+        };
+        let key = parse_key(words.next())?;
+        let flags = parse(words.next())?;
+        let exptime = parse(words.next())?;
+        let len = self.lines.check_len(parse(words.next())?)?;
+        let mode = match mode {
+            Store::Cas(..) => Store::Cas(parse(words.next())?),
+            mode => mode,
+        };
+        let noreply = noreply(words)?;
+        let req = Request::Store {
+            mode,
+            key,
+            flags,
+            exptime,
+            data: ByteBuf::new(),
+            noreply,
+        };
+        self.store = Some((req, len));
+        Ok(None)
+    }
+}
+
+fn noreply<'a, I: Iterator<Item = &'a str>>(mut words: I) -> io::Result<bool> {
+    match (words.next(), words.next()) {
+        (None, _) => Ok(false),
+        (Some("noreply"), None) => Ok(true),
+        _ => Err(invalid_data("Too many arguments")),
+    }
+}
+
+impl Decoder for ServerCodec {
+    type Item = Request;
+    type Error = io::Error;
+
+    fn decode(&mut self, data: &mut ByteBuf) -> io::Result<Option<Request>> {
+        if self.store.is_none() {
+            let line = match self.lines.line(data)? {
+                Some(line) => line,
+                None => return Ok(None),
+            };
+            if let Some(req) = self.request(&line)? {
+                return Ok(Some(req));
+            }
+        }
+        let (mut req, len) = match self.store.take() {
+            Some(store) => store,
+            None => ::unreachable(),
+        };
+        let block = match self.lines.data(data, len)? {
+            Some(block) => block,
+            None => {
+                self.store = Some((req, len));
+                return Ok(None);
+            }
+        };
+        if let Request::Store { ref mut data, .. } = req {
+            *data = block;
+        }
+        Ok(Some(req))
+    }
+
+    fn decode_eof(&mut self, data: &mut ByteBuf) -> io::Result<Option<Request>> {
+        if let Some(req) = self.decode(data)? {
+            return Ok(Some(req));
+        }
+        match data.is_empty() && self.store.is_none() {
+            true => Ok(None),
+            false => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Stream ended within a request",
+            )),
+        }
+    }
+}
+
+impl Encoder for ServerCodec {
+    type Item = Response;
+    type Error = io::Error;
+
+    fn encode(&mut self, res: Response, dst: &mut ByteBuf) -> io::Result<()> {
+        let line = match res {
+            Response::Values(items) => {
+                for item in items {
+                    check_key(&item.key)?;
+                    let mut line = format!("VALUE {} {} {}", item.key, item.flags, item.data.len());
+                    if let Some(cas) = item.cas {
+                        let _ = write!(line, " {}", cas);
+                    }
+                    line.push_str("\r\n");
+                    append(dst, line.as_bytes())?;
+                    if !item.data.is_empty() {
+                        put(dst, item.data);
+                    }
+                    append(dst, b"\r\n")?;
+                }
+                "END".to_owned()
+            }
+            Response::Stored => "STORED".to_owned(),
+            Response::NotStored => "NOT_STORED".to_owned(),
+            Response::Exists => "EXISTS".to_owned(),
+            Response::NotFound => "NOT_FOUND".to_owned(),
+            Response::Deleted => "DELETED".to_owned(),
+            Response::Number(n) => n.to_string(),
+            Response::Error => "ERROR".to_owned(),
+            Response::ClientError(msg) => {
+                check_text(&msg)?;
+                format!("CLIENT_ERROR {}", msg)
+            }
+            Response::ServerError(msg) => {
+                check_text(&msg)?;
+                format!("SERVER_ERROR {}", msg)
+            }
+        };
+        append(dst, line.as_bytes())?;
+        append(dst, b"\r\n")
+    }
+}
@@ -1,14 +1,44 @@
-mod u8_prefix;
-pub use self::u8_prefix::*;
+mod codec;
+pub use self::codec::*;
 
-mod u16be_prefix;
-pub use self::u16be_prefix::*;
+mod frame;
+pub use self::frame::*;
 
-mod u16le_prefix;
-pub use self::u16le_prefix::*;
+mod framed;
+pub use self::framed::*;
 
-mod u32be_prefix;
-pub use self::u32be_prefix::*;
+mod delimiter;
+pub use self::delimiter::*;
 
-mod u32le_prefix;
-pub use self::u32le_prefix::*;
+mod length_field;
+pub use self::length_field::*;
+
+mod line;
+pub use self::line::*;
+
+mod pipeline;
+pub use self::pipeline::*;
+
+mod prefix;
+pub use self::prefix::*;
+
+mod random;
+
+#[cfg(feature = "flate")]
+pub mod flate;
+
+pub mod dns;
+
+pub mod http1;
+
+pub mod memcache;
+
+pub mod mqtt;
+
+pub mod proxy;
+
+pub mod resp;
+
+pub mod socks5;
+
+pub mod websocket;
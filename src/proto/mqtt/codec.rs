@@ -0,0 +1,788 @@
+use std::io;
+use std::str;
+
+use buf::ByteBuf;
+use proto::{frame_too_long, max_frame_len, put, Decoder, Encoder, Framed};
+
+use super::{packet_type, reason, Ack, ConnAck, Connect, Packet, Property, Protocol, Publish, QoS,
+            Reason, SubAck, Subscribe, Subscription, Unsubscribe, Will};
+
+/// The largest remaining length of a packet, the most a variable byte
+/// integer of 4 bytes holds.
+pub const MAX_REMAINING_LEN: usize = 268_435_455;
+
+/// Decodes and encodes MQTT control packets.
+///
+/// Packets are decoded and encoded per `protocol`, which is set to the
+/// version of each CONNECT packet decoded or encoded, so that both ends of
+/// a connection follow the version of the client.
+#[derive(Debug, Clone, Copy)]
+pub struct MqttCodec {
+    protocol: Protocol,
+    max_packet_len: usize,
+}
+
+#[inline]
+fn malformed() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Malformed MQTT packet")
+}
+
+#[inline]
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[inline]
+fn invalid_input(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+// Returns the remaining length of the packet at the front of `data` and the
+// size of its field, or `None` if `data` does not hold all of it yet.
+//
+// Unlike LEB128 the field is at most 4 bytes long, so a longer one is an
+// error rather than an overflow.
+fn get_remaining_len(data: &ByteBuf) -> io::Result<Option<(usize, usize)>> {
+    let mut len = 0;
+    for (i, b) in data.bytes().skip(1).take(4).enumerate() {
+        len |= (b as usize & 0x7F) << (7 * i);
+        if b & 0x80 == 0 {
+            return Ok(Some((len, i + 1)));
+        }
+        if i == 3 {
+            return Err(invalid_data("Malformed MQTT remaining length"));
+        }
+    }
+    Ok(None)
+}
+
+fn put_varint(mut n: usize, buf: &mut Vec<u8>) {
+    loop {
+        let b = (n & 0x7F) as u8;
+        n >>= 7;
+        if n == 0 {
+            buf.push(b);
+            return;
+        }
+        buf.push(b | 0x80);
+    }
+}
+
+// Reads the fields of a packet of known length.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    #[inline]
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.pos == self.data.len()
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        match self.is_empty() {
+            true => Ok(()),
+            false => Err(malformed()),
+        }
+    }
+
+    fn bytes(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.data.len() - self.pos < n {
+            return Err(malformed());
+        }
+        let bytes = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    #[inline]
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    #[inline]
+    fn u16(&mut self) -> io::Result<u16> {
+        let b = self.bytes(2)?;
+        Ok(u16::from(b[0]) << 8 | u16::from(b[1]))
+    }
+
+    #[inline]
+    fn u32(&mut self) -> io::Result<u32> {
+        let b = self.bytes(4)?;
+        Ok(u32::from(b[0]) << 24 | u32::from(b[1]) << 16 | u32::from(b[2]) << 8 | u32::from(b[3]))
+    }
+
+    fn varint(&mut self) -> io::Result<u32> {
+        let mut n = 0;
+        for i in 0..4 {
+            let b = self.u8()?;
+            n |= u32::from(b & 0x7F) << (7 * i);
+            if b & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(malformed())
+    }
+
+    fn binary(&mut self) -> io::Result<Vec<u8>> {
+        let len = self.u16()? as usize;
+        Ok(self.bytes(len)?.to_vec())
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.u16()? as usize;
+        match str::from_utf8(self.bytes(len)?) {
+            Ok(s) if !s.contains('\0') => Ok(s.to_string()),
+            _ => Err(invalid_data("Invalid MQTT UTF-8 string")),
+        }
+    }
+
+    fn qos(&mut self, bits: u8) -> io::Result<QoS> {
+        QoS::from_u8(bits).ok_or_else(|| invalid_data("Invalid MQTT QoS"))
+    }
+
+    fn properties(&mut self) -> io::Result<Vec<Property>> {
+        let len = self.varint()? as usize;
+        let mut r = Reader::new(self.bytes(len)?);
+        let mut properties = Vec::new();
+        while !r.is_empty() {
+            let property = match r.varint()? {
+                0x01 => Property::PayloadFormatIndicator(r.u8()?),
+                0x02 => Property::MessageExpiryInterval(r.u32()?),
+                0x03 => Property::ContentType(r.string()?),
+                0x08 => Property::ResponseTopic(r.string()?),
+                0x09 => Property::CorrelationData(r.binary()?),
+                0x0B => Property::SubscriptionIdentifier(r.varint()?),
+                0x11 => Property::SessionExpiryInterval(r.u32()?),
+                0x12 => Property::AssignedClientIdentifier(r.string()?),
+                0x13 => Property::ServerKeepAlive(r.u16()?),
+                0x15 => Property::AuthenticationMethod(r.string()?),
+                0x16 => Property::AuthenticationData(r.binary()?),
+                0x17 => Property::RequestProblemInformation(r.u8()?),
+                0x18 => Property::WillDelayInterval(r.u32()?),
+                0x19 => Property::RequestResponseInformation(r.u8()?),
+                0x1A => Property::ResponseInformation(r.string()?),
+                0x1C => Property::ServerReference(r.string()?),
+                0x1F => Property::ReasonString(r.string()?),
+                0x21 => Property::ReceiveMaximum(r.u16()?),
+                0x22 => Property::TopicAliasMaximum(r.u16()?),
+                0x23 => Property::TopicAlias(r.u16()?),
+                0x24 => Property::MaximumQoS(r.u8()?),
+                0x25 => Property::RetainAvailable(r.u8()?),
+                0x26 => Property::UserProperty(r.string()?, r.string()?),
+                0x27 => Property::MaximumPacketSize(r.u32()?),
+                0x28 => Property::WildcardSubscriptionAvailable(r.u8()?),
+                0x29 => Property::SubscriptionIdentifierAvailable(r.u8()?),
+                0x2A => Property::SharedSubscriptionAvailable(r.u8()?),
+                _ => return Err(invalid_data("Invalid MQTT property")),
+            };
+            properties.push(property);
+        }
+        Ok(properties)
+    }
+}
+
+#[inline]
+fn put_u16(n: u16, buf: &mut Vec<u8>) {
+    buf.extend_from_slice(&[(n >> 8) as u8, n as u8]);
+}
+
+#[inline]
+fn put_u32(n: u32, buf: &mut Vec<u8>) {
+    buf.extend_from_slice(&[(n >> 24) as u8, (n >> 16) as u8, (n >> 8) as u8, n as u8]);
+}
+
+fn put_binary(data: &[u8], buf: &mut Vec<u8>) -> io::Result<()> {
+    if data.len() > u16::MAX as usize {
+        return Err(invalid_input("MQTT string or binary data too long"));
+    }
+    put_u16(data.len() as u16, buf);
+    buf.extend_from_slice(data);
+    Ok(())
+}
+
+fn put_string(s: &str, buf: &mut Vec<u8>) -> io::Result<()> {
+    if s.contains('\0') {
+        return Err(invalid_input("MQTT string contains a null character"));
+    }
+    put_binary(s.as_bytes(), buf)
+}
+
+fn put_properties(properties: &[Property], buf: &mut Vec<u8>) -> io::Result<()> {
+    let mut p = Vec::new();
+    for property in properties {
+        p.push(property.id());
+        match *property {
+            Property::PayloadFormatIndicator(n)
+            | Property::RequestProblemInformation(n)
+            | Property::RequestResponseInformation(n)
+            | Property::MaximumQoS(n)
+            | Property::RetainAvailable(n)
+            | Property::WildcardSubscriptionAvailable(n)
+            | Property::SubscriptionIdentifierAvailable(n)
+            | Property::SharedSubscriptionAvailable(n) => p.push(n),
+            Property::ServerKeepAlive(n)
+            | Property::ReceiveMaximum(n)
+            | Property::TopicAliasMaximum(n)
+            | Property::TopicAlias(n) => put_u16(n, &mut p),
+            Property::MessageExpiryInterval(n)
+            | Property::SessionExpiryInterval(n)
+            | Property::WillDelayInterval(n)
+            | Property::MaximumPacketSize(n) => put_u32(n, &mut p),
+            Property::SubscriptionIdentifier(n) => {
+                if n as usize > MAX_REMAINING_LEN {
+                    return Err(invalid_input("MQTT subscription identifier too large"));
+                }
+                put_varint(n as usize, &mut p)
+            }
+            Property::ContentType(ref s)
+            | Property::ResponseTopic(ref s)
+            | Property::AssignedClientIdentifier(ref s)
+            | Property::AuthenticationMethod(ref s)
+            | Property::ResponseInformation(ref s)
+            | Property::ServerReference(ref s)
+            | Property::ReasonString(ref s) => put_string(s, &mut p)?,
+            Property::CorrelationData(ref data) | Property::AuthenticationData(ref data) => {
+                put_binary(data, &mut p)?
+            }
+            Property::UserProperty(ref k, ref v) => {
+                put_string(k, &mut p)?;
+                put_string(v, &mut p)?
+            }
+        }
+    }
+    put_varint(p.len(), buf);
+    buf.extend_from_slice(&p);
+    Ok(())
+}
+
+impl Default for MqttCodec {
+    #[inline]
+    fn default() -> Self {
+        MqttCodec::new()
+    }
+}
+
+impl MqttCodec {
+    /// Returns a codec of MQTT 3.1.1 until a CONNECT packet says otherwise.
+    #[inline]
+    pub fn new() -> Self {
+        MqttCodec {
+            protocol: Protocol::V311,
+            max_packet_len: max_frame_len(),
+        }
+    }
+
+    #[inline]
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    #[inline]
+    pub fn set_protocol(&mut self, protocol: Protocol) -> &mut Self {
+        self.protocol = protocol;
+        self
+    }
+
+    #[inline]
+    pub fn max_packet_len(&self) -> usize {
+        self.max_packet_len
+    }
+
+    /// Sets the maximum remaining length of a packet. Defaults to
+    /// `max_frame_len()`.
+    #[inline]
+    pub fn set_max_packet_len(&mut self, max_packet_len: usize) -> &mut Self {
+        self.max_packet_len = max_packet_len;
+        self
+    }
+
+    #[inline]
+    pub fn frames<S>(self, stream: S) -> Framed<S, Self> {
+        Framed::new(stream, self)
+    }
+
+    #[inline]
+    fn v5(&self) -> bool {
+        self.protocol == Protocol::V5
+    }
+
+    fn properties(&self, r: &mut Reader) -> io::Result<Vec<Property>> {
+        match self.v5() {
+            true => r.properties(),
+            false => Ok(Vec::new()),
+        }
+    }
+
+    fn decode_connect(&mut self, r: &mut Reader) -> io::Result<Connect> {
+        if r.string()? != "MQTT" {
+            return Err(invalid_data("Invalid MQTT protocol name"));
+        }
+        self.protocol = match r.u8()? {
+            4 => Protocol::V311,
+            5 => Protocol::V5,
+            _ => return Err(invalid_data("Unsupported MQTT protocol level")),
+        };
+        let flags = r.u8()?;
+        if flags & 0x01 != 0 {
+            return Err(malformed());
+        }
+        let keep_alive = r.u16()?;
+        let properties = self.properties(r)?;
+        let client_id = r.string()?;
+        let will = match flags & 0x04 {
+            0 if flags & 0x38 != 0 => return Err(malformed()),
+            0 => None,
+            _ => Some(Will {
+                properties: self.properties(r)?,
+                topic: r.string()?,
+                payload: r.binary()?,
+                qos: r.qos(flags >> 3 & 0x03)?,
+                retain: flags & 0x20 != 0,
+            }),
+        };
+        let username = match flags & 0x80 {
+            0 => None,
+            _ => Some(r.string()?),
+        };
+        let password = match flags & 0x40 {
+            0 => None,
+            _ => Some(r.binary()?),
+        };
+        Ok(Connect {
+            protocol: self.protocol,
+            clean_start: flags & 0x02 != 0,
+            keep_alive,
+            client_id,
+            will,
+            username,
+            password,
+            properties,
+        })
+    }
+
+    fn decode_ack(&self, r: &mut Reader) -> io::Result<Ack> {
+        let mut ack = Ack::new(r.u16()?);
+        if self.v5() && !r.is_empty() {
+            ack.code = r.u8()?;
+            if !r.is_empty() {
+                ack.properties = r.properties()?;
+            }
+        }
+        Ok(ack)
+    }
+
+    fn decode_reason(&self, r: &mut Reader) -> io::Result<Reason> {
+        let mut body = Reason::default();
+        if self.v5() && !r.is_empty() {
+            body.code = r.u8()?;
+            if !r.is_empty() {
+                body.properties = r.properties()?;
+            }
+        }
+        Ok(body)
+    }
+
+    fn decode_subscribe(&self, r: &mut Reader) -> io::Result<Subscribe> {
+        let packet_id = r.u16()?;
+        let properties = self.properties(r)?;
+        let mut subscriptions = Vec::new();
+        while !r.is_empty() {
+            let filter = r.string()?;
+            let options = r.u8()?;
+            let reserved = match self.v5() {
+                true => 0xC0,
+                false => 0xFC,
+            };
+            if options & reserved != 0 || options >> 4 & 0x03 == 3 {
+                return Err(malformed());
+            }
+            subscriptions.push(Subscription {
+                filter,
+                qos: r.qos(options & 0x03)?,
+                no_local: options & 0x04 != 0,
+                retain_as_published: options & 0x08 != 0,
+                retain_handling: options >> 4 & 0x03,
+            });
+        }
+        if subscriptions.is_empty() {
+            return Err(invalid_data("MQTT SUBSCRIBE without topic filters"));
+        }
+        Ok(Subscribe {
+            packet_id,
+            subscriptions,
+            properties,
+        })
+    }
+
+    fn decode_unsubscribe(&self, r: &mut Reader) -> io::Result<Unsubscribe> {
+        let packet_id = r.u16()?;
+        let properties = self.properties(r)?;
+        let mut filters = Vec::new();
+        while !r.is_empty() {
+            filters.push(r.string()?);
+        }
+        if filters.is_empty() {
+            return Err(invalid_data("MQTT UNSUBSCRIBE without topic filters"));
+        }
+        Ok(Unsubscribe {
+            packet_id,
+            filters,
+            properties,
+        })
+    }
+
+    fn decode_sub_ack(&self, r: &mut Reader, unsub: bool) -> io::Result<SubAck> {
+        let packet_id = r.u16()?;
+        let properties = self.properties(r)?;
+        let codes = match unsub && !self.v5() {
+            true => Vec::new(),
+            false => r.bytes(r.data.len() - r.pos)?.to_vec(),
+        };
+        Ok(SubAck {
+            packet_id,
+            codes,
+            properties,
+        })
+    }
+
+    // Decodes the variable header of a PUBLISH, leaving the payload.
+    fn decode_publish(&self, flags: u8, body: &mut ByteBuf) -> io::Result<Publish> {
+        let qos = QoS::from_u8(flags >> 1 & 0x03).ok_or_else(|| invalid_data("Invalid MQTT QoS"))?;
+        if qos == QoS::AtMostOnce && flags & 0x08 != 0 {
+            return Err(malformed());
+        }
+        // Finds the end of the variable header from its bounded prefix, the
+        // topic, packet identifier and property length, so that only the
+        // header is flattened and the payload blocks are left as they are.
+        let len = {
+            let topic_len = body.bytes().take(2).fold(0, |n, b| n << 8 | b as usize);
+            let prefix: Vec<u8> = body.bytes().take(2 + topic_len + 2 + 4).collect();
+            let mut r = Reader::new(&prefix);
+            r.bytes(2 + topic_len)?;
+            if qos != QoS::AtMostOnce {
+                r.u16()?;
+            }
+            match self.v5() {
+                true => r.varint()? as usize + r.pos,
+                false => r.pos,
+            }
+        };
+        let head = match len < body.len() {
+            true => body.drain_to(len).map_err(|_| malformed())?,
+            false => ::std::mem::replace(body, ByteBuf::new()),
+        };
+        let data = head.as_bytes();
+        let mut r = Reader::new(&data);
+        let topic = r.string()?;
+        let packet_id = match qos {
+            QoS::AtMostOnce => 0,
+            _ => match r.u16()? {
+                0 => return Err(invalid_data("Invalid MQTT packet identifier")),
+                id => id,
+            },
+        };
+        let properties = self.properties(&mut r)?;
+        r.finish()?;
+        Ok(Publish {
+            dup: flags & 0x08 != 0,
+            qos,
+            retain: flags & 0x01 != 0,
+            topic,
+            packet_id,
+            properties,
+            payload: match body.is_empty() {
+                true => ByteBuf::new(),
+                false => ::std::mem::replace(body, ByteBuf::new()),
+            },
+        })
+    }
+
+    fn decode_packet(&mut self, first: u8, mut body: ByteBuf) -> io::Result<Packet> {
+        let (ty, flags) = (first >> 4, first & 0x0F);
+        let expected = match ty {
+            packet_type::PUBLISH => flags,
+            packet_type::PUBREL | packet_type::SUBSCRIBE | packet_type::UNSUBSCRIBE => 0x02,
+            _ => 0,
+        };
+        if flags != expected {
+            return Err(invalid_data("Invalid MQTT packet flags"));
+        }
+        if ty == packet_type::PUBLISH {
+            return Ok(Packet::Publish(self.decode_publish(flags, &mut body)?));
+        }
+        let data = body.as_bytes();
+        let mut r = Reader::new(&data);
+        let packet = match ty {
+            packet_type::CONNECT => Packet::Connect(self.decode_connect(&mut r)?),
+            packet_type::CONNACK => {
+                let flags = r.u8()?;
+                if flags & 0xFE != 0 {
+                    return Err(malformed());
+                }
+                Packet::ConnAck(ConnAck {
+                    session_present: flags != 0,
+                    code: r.u8()?,
+                    properties: self.properties(&mut r)?,
+                })
+            }
+            packet_type::PUBACK => Packet::PubAck(self.decode_ack(&mut r)?),
+            packet_type::PUBREC => Packet::PubRec(self.decode_ack(&mut r)?),
+            packet_type::PUBREL => Packet::PubRel(self.decode_ack(&mut r)?),
+            packet_type::PUBCOMP => Packet::PubComp(self.decode_ack(&mut r)?),
+            packet_type::SUBSCRIBE => Packet::Subscribe(self.decode_subscribe(&mut r)?),
+            packet_type::SUBACK => Packet::SubAck(self.decode_sub_ack(&mut r, false)?),
+            packet_type::UNSUBSCRIBE => Packet::Unsubscribe(self.decode_unsubscribe(&mut r)?),
+            packet_type::UNSUBACK => Packet::UnsubAck(self.decode_sub_ack(&mut r, true)?),
+            packet_type::PINGREQ => Packet::PingReq,
+            packet_type::PINGRESP => Packet::PingResp,
+            packet_type::DISCONNECT => Packet::Disconnect(self.decode_reason(&mut r)?),
+            packet_type::AUTH if self.v5() => Packet::Auth(self.decode_reason(&mut r)?),
+            _ => return Err(invalid_data("Invalid MQTT packet type")),
+        };
+        r.finish()?;
+        Ok(packet)
+    }
+}
+
+// Splits `len` bytes off the front of `data`, which holds at least as many.
+fn take(data: &mut ByteBuf, len: usize) -> ByteBuf {
+    match len {
+        0 => ByteBuf::new(),
+        _ => match data.drain_to(len) {
+            Ok(taken) => taken,
+            _ => ::unreachable(),
+        },
+    }
+}
+
+impl Decoder for MqttCodec {
+    type Item = Packet;
+    type Error = io::Error;
+
+    fn decode(&mut self, data: &mut ByteBuf) -> io::Result<Option<Packet>> {
+        let first = match data.bytes().next() {
+            Some(first) => first,
+            None => return Ok(None),
+        };
+        let (len, n) = match get_remaining_len(data)? {
+            Some(len) => len,
+            None => return Ok(None),
+        };
+        if len > self.max_packet_len {
+            return Err(frame_too_long(len, self.max_packet_len));
+        }
+        if data.len() < 1 + n + len {
+            return Ok(None);
+        }
+        data.skip(1 + n);
+        let body = take(data, len);
+        self.decode_packet(first, body).map(Some)
+    }
+}
+
+impl MqttCodec {
+    fn encode_ack(&self, ack: &Ack, buf: &mut Vec<u8>) -> io::Result<()> {
+        put_u16(ack.packet_id, buf);
+        if self.v5() && (ack.code != reason::SUCCESS || !ack.properties.is_empty()) {
+            buf.push(ack.code);
+            if !ack.properties.is_empty() {
+                put_properties(&ack.properties, buf)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn encode_reason(&self, body: &Reason, buf: &mut Vec<u8>) -> io::Result<()> {
+        if self.v5() && (body.code != reason::SUCCESS || !body.properties.is_empty()) {
+            buf.push(body.code);
+            if !body.properties.is_empty() {
+                put_properties(&body.properties, buf)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn encode_properties(&self, properties: &[Property], buf: &mut Vec<u8>) -> io::Result<()> {
+        match self.v5() {
+            true => put_properties(properties, buf),
+            false => Ok(()),
+        }
+    }
+
+    fn encode_connect(&mut self, connect: &Connect, buf: &mut Vec<u8>) -> io::Result<()> {
+        self.protocol = connect.protocol;
+        put_string("MQTT", buf)?;
+        buf.push(connect.protocol as u8);
+        let mut flags = 0;
+        if connect.clean_start {
+            flags |= 0x02;
+        }
+        if let Some(ref will) = connect.will {
+            flags |= 0x04 | (will.qos as u8) << 3;
+            if will.retain {
+                flags |= 0x20;
+            }
+        }
+        if connect.password.is_some() {
+            flags |= 0x40;
+        }
+        if connect.username.is_some() {
+            flags |= 0x80;
+        }
+        buf.push(flags);
+        put_u16(connect.keep_alive, buf);
+        self.encode_properties(&connect.properties, buf)?;
+        put_string(&connect.client_id, buf)?;
+        if let Some(ref will) = connect.will {
+            self.encode_properties(&will.properties, buf)?;
+            put_string(&will.topic, buf)?;
+            put_binary(&will.payload, buf)?;
+        }
+        if let Some(ref username) = connect.username {
+            put_string(username, buf)?;
+        }
+        if let Some(ref password) = connect.password {
+            put_binary(password, buf)?;
+        }
+        Ok(())
+    }
+
+    fn encode_subscribe(&self, subscribe: &Subscribe, buf: &mut Vec<u8>) -> io::Result<()> {
+        if subscribe.subscriptions.is_empty() {
+            return Err(invalid_input("MQTT SUBSCRIBE without topic filters"));
+        }
+        put_u16(subscribe.packet_id, buf);
+        self.encode_properties(&subscribe.properties, buf)?;
+        for s in &subscribe.subscriptions {
+            put_string(&s.filter, buf)?;
+            let mut options = s.qos as u8;
+            if self.v5() {
+                if s.retain_handling > 2 {
+                    return Err(invalid_input("Invalid MQTT retain handling"));
+                }
+                options |= s.retain_handling << 4;
+                if s.no_local {
+                    options |= 0x04;
+                }
+                if s.retain_as_published {
+                    options |= 0x08;
+                }
+            }
+            buf.push(options);
+        }
+        Ok(())
+    }
+
+    // Returns the flags of the packet, and its payload if it is a PUBLISH.
+    fn encode_packet(
+        &mut self,
+        packet: Packet,
+        buf: &mut Vec<u8>,
+    ) -> io::Result<(u8, Option<ByteBuf>)> {
+        let flags = match packet {
+            Packet::Connect(ref connect) => {
+                self.encode_connect(connect, buf)?;
+                0
+            }
+            Packet::ConnAck(ref ack) => {
+                buf.push(ack.session_present as u8);
+                buf.push(ack.code);
+                self.encode_properties(&ack.properties, buf)?;
+                0
+            }
+            Packet::Publish(publish) => {
+                put_string(&publish.topic, buf)?;
+                if publish.qos != QoS::AtMostOnce {
+                    if publish.packet_id == 0 {
+                        return Err(invalid_input("MQTT PUBLISH of QoS > 0 without packet identifier"));
+                    }
+                    put_u16(publish.packet_id, buf);
+                }
+                self.encode_properties(&publish.properties, buf)?;
+                let mut flags = (publish.qos as u8) << 1;
+                if publish.dup {
+                    flags |= 0x08;
+                }
+                if publish.retain {
+                    flags |= 0x01;
+                }
+                return Ok((flags, Some(publish.payload)));
+            }
+            Packet::PubAck(ref ack) | Packet::PubRec(ref ack) | Packet::PubComp(ref ack) => {
+                self.encode_ack(ack, buf)?;
+                0
+            }
+            Packet::PubRel(ref ack) => {
+                self.encode_ack(ack, buf)?;
+                0x02
+            }
+            Packet::Subscribe(ref subscribe) => {
+                self.encode_subscribe(subscribe, buf)?;
+                0x02
+            }
+            Packet::SubAck(ref ack) | Packet::UnsubAck(ref ack) => {
+                put_u16(ack.packet_id, buf);
+                self.encode_properties(&ack.properties, buf)?;
+                if self.v5() || packet.packet_type() == packet_type::SUBACK {
+                    buf.extend_from_slice(&ack.codes);
+                }
+                0
+            }
+            Packet::Unsubscribe(ref unsubscribe) => {
+                if unsubscribe.filters.is_empty() {
+                    return Err(invalid_input("MQTT UNSUBSCRIBE without topic filters"));
+                }
+                put_u16(unsubscribe.packet_id, buf);
+                self.encode_properties(&unsubscribe.properties, buf)?;
+                for filter in &unsubscribe.filters {
+                    put_string(filter, buf)?;
+                }
+                0x02
+            }
+            Packet::PingReq | Packet::PingResp => 0,
+            Packet::Disconnect(ref body) => {
+                self.encode_reason(body, buf)?;
+                0
+            }
+            Packet::Auth(ref body) if self.v5() => {
+                self.encode_reason(body, buf)?;
+                0
+            }
+            Packet::Auth(..) => return Err(invalid_input("MQTT AUTH requires MQTT 5.0")),
+        };
+        Ok((flags, None))
+    }
+}
+
+impl Encoder for MqttCodec {
+    type Item = Packet;
+    type Error = io::Error;
+
+    fn encode(&mut self, packet: Packet, dst: &mut ByteBuf) -> io::Result<()> {
+        let ty = packet.packet_type();
+        let mut body = Vec::new();
+        let (flags, payload) = self.encode_packet(packet, &mut body)?;
+        let len = body.len() + payload.as_ref().map_or(0, |p| p.len());
+        if len > MAX_REMAINING_LEN {
+            return Err(invalid_input("MQTT packet too long"));
+        }
+        let mut head = Vec::with_capacity(5 + body.len());
+        head.push(ty << 4 | flags);
+        put_varint(len, &mut head);
+        head.extend_from_slice(&body);
+        put(dst, ByteBuf::from(head));
+        if let Some(payload) = payload {
+            if !payload.is_empty() {
+                put(dst, payload);
+            }
+        }
+        Ok(())
+    }
+}
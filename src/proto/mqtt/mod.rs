@@ -0,0 +1,16 @@
+//! MQTT 3.1.1 and 5.0.
+//!
+//! `MqttCodec` frames a connection into `Packet`s, and `Session` keeps the
+//! state of the QoS 1 and 2 flows and the keep alive of either end.
+
+mod packet;
+pub use self::packet::*;
+
+mod codec;
+pub use self::codec::*;
+
+mod session;
+pub use self::session::*;
+
+mod topic;
+pub use self::topic::*;
@@ -0,0 +1,408 @@
+use std::fmt;
+
+use buf::ByteBuf;
+
+/// Packet types, the high nibble of the first byte of a packet.
+pub mod packet_type {
+    pub const CONNECT: u8 = 1;
+    pub const CONNACK: u8 = 2;
+    pub const PUBLISH: u8 = 3;
+    pub const PUBACK: u8 = 4;
+    pub const PUBREC: u8 = 5;
+    pub const PUBREL: u8 = 6;
+    pub const PUBCOMP: u8 = 7;
+    pub const SUBSCRIBE: u8 = 8;
+    pub const SUBACK: u8 = 9;
+    pub const UNSUBSCRIBE: u8 = 10;
+    pub const UNSUBACK: u8 = 11;
+    pub const PINGREQ: u8 = 12;
+    pub const PINGRESP: u8 = 13;
+    pub const DISCONNECT: u8 = 14;
+    pub const AUTH: u8 = 15;
+}
+
+/// Reason codes of MQTT 5.0. The return codes of CONNACK and SUBACK of
+/// MQTT 3.1.1 are listed with the `V3_` prefix.
+pub mod reason {
+    pub const SUCCESS: u8 = 0x00;
+    pub const GRANTED_QOS_1: u8 = 0x01;
+    pub const GRANTED_QOS_2: u8 = 0x02;
+    pub const DISCONNECT_WITH_WILL: u8 = 0x04;
+    pub const NO_MATCHING_SUBSCRIBERS: u8 = 0x10;
+    pub const NO_SUBSCRIPTION_EXISTED: u8 = 0x11;
+    pub const CONTINUE_AUTHENTICATION: u8 = 0x18;
+    pub const RE_AUTHENTICATE: u8 = 0x19;
+    pub const UNSPECIFIED_ERROR: u8 = 0x80;
+    pub const MALFORMED_PACKET: u8 = 0x81;
+    pub const PROTOCOL_ERROR: u8 = 0x82;
+    pub const IMPLEMENTATION_SPECIFIC_ERROR: u8 = 0x83;
+    pub const UNSUPPORTED_PROTOCOL_VERSION: u8 = 0x84;
+    pub const CLIENT_IDENTIFIER_NOT_VALID: u8 = 0x85;
+    pub const BAD_USER_NAME_OR_PASSWORD: u8 = 0x86;
+    pub const NOT_AUTHORIZED: u8 = 0x87;
+    pub const SERVER_UNAVAILABLE: u8 = 0x88;
+    pub const SERVER_BUSY: u8 = 0x89;
+    pub const BANNED: u8 = 0x8A;
+    pub const SERVER_SHUTTING_DOWN: u8 = 0x8B;
+    pub const BAD_AUTHENTICATION_METHOD: u8 = 0x8C;
+    pub const KEEP_ALIVE_TIMEOUT: u8 = 0x8D;
+    pub const SESSION_TAKEN_OVER: u8 = 0x8E;
+    pub const TOPIC_FILTER_INVALID: u8 = 0x8F;
+    pub const TOPIC_NAME_INVALID: u8 = 0x90;
+    pub const PACKET_IDENTIFIER_IN_USE: u8 = 0x91;
+    pub const PACKET_IDENTIFIER_NOT_FOUND: u8 = 0x92;
+    pub const RECEIVE_MAXIMUM_EXCEEDED: u8 = 0x93;
+    pub const TOPIC_ALIAS_INVALID: u8 = 0x94;
+    pub const PACKET_TOO_LARGE: u8 = 0x95;
+    pub const MESSAGE_RATE_TOO_HIGH: u8 = 0x96;
+    pub const QUOTA_EXCEEDED: u8 = 0x97;
+    pub const ADMINISTRATIVE_ACTION: u8 = 0x98;
+    pub const PAYLOAD_FORMAT_INVALID: u8 = 0x99;
+    pub const RETAIN_NOT_SUPPORTED: u8 = 0x9A;
+    pub const QOS_NOT_SUPPORTED: u8 = 0x9B;
+    pub const USE_ANOTHER_SERVER: u8 = 0x9C;
+    pub const SERVER_MOVED: u8 = 0x9D;
+    pub const SHARED_SUBSCRIPTIONS_NOT_SUPPORTED: u8 = 0x9E;
+    pub const CONNECTION_RATE_EXCEEDED: u8 = 0x9F;
+    pub const MAXIMUM_CONNECT_TIME: u8 = 0xA0;
+    pub const SUBSCRIPTION_IDENTIFIERS_NOT_SUPPORTED: u8 = 0xA1;
+    pub const WILDCARD_SUBSCRIPTIONS_NOT_SUPPORTED: u8 = 0xA2;
+
+    pub const V3_UNACCEPTABLE_PROTOCOL_VERSION: u8 = 0x01;
+    pub const V3_IDENTIFIER_REJECTED: u8 = 0x02;
+    pub const V3_SERVER_UNAVAILABLE: u8 = 0x03;
+    pub const V3_BAD_USER_NAME_OR_PASSWORD: u8 = 0x04;
+    pub const V3_NOT_AUTHORIZED: u8 = 0x05;
+    pub const V3_SUBSCRIBE_FAILURE: u8 = 0x80;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Protocol {
+    /// MQTT 3.1.1, of protocol level 4.
+    V311 = 4,
+    /// MQTT 5.0, of protocol level 5.
+    V5 = 5,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum QoS {
+    AtMostOnce = 0,
+    AtLeastOnce = 1,
+    ExactlyOnce = 2,
+}
+
+impl QoS {
+    #[inline]
+    pub fn from_u8(n: u8) -> Option<Self> {
+        match n {
+            0 => Some(QoS::AtMostOnce),
+            1 => Some(QoS::AtLeastOnce),
+            2 => Some(QoS::ExactlyOnce),
+            _ => None,
+        }
+    }
+}
+
+/// A property of an MQTT 5.0 packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Property {
+    PayloadFormatIndicator(u8),
+    MessageExpiryInterval(u32),
+    ContentType(String),
+    ResponseTopic(String),
+    CorrelationData(Vec<u8>),
+    SubscriptionIdentifier(u32),
+    SessionExpiryInterval(u32),
+    AssignedClientIdentifier(String),
+    ServerKeepAlive(u16),
+    AuthenticationMethod(String),
+    AuthenticationData(Vec<u8>),
+    RequestProblemInformation(u8),
+    WillDelayInterval(u32),
+    RequestResponseInformation(u8),
+    ResponseInformation(String),
+    ServerReference(String),
+    ReasonString(String),
+    ReceiveMaximum(u16),
+    TopicAliasMaximum(u16),
+    TopicAlias(u16),
+    MaximumQoS(u8),
+    RetainAvailable(u8),
+    UserProperty(String, String),
+    MaximumPacketSize(u32),
+    WildcardSubscriptionAvailable(u8),
+    SubscriptionIdentifierAvailable(u8),
+    SharedSubscriptionAvailable(u8),
+}
+
+impl Property {
+    /// Returns the identifier of the property on the wire.
+    pub fn id(&self) -> u8 {
+        match *self {
+            Property::PayloadFormatIndicator(..) => 0x01,
+            Property::MessageExpiryInterval(..) => 0x02,
+            Property::ContentType(..) => 0x03,
+            Property::ResponseTopic(..) => 0x08,
+            Property::CorrelationData(..) => 0x09,
+            Property::SubscriptionIdentifier(..) => 0x0B,
+            Property::SessionExpiryInterval(..) => 0x11,
+            Property::AssignedClientIdentifier(..) => 0x12,
+            Property::ServerKeepAlive(..) => 0x13,
+            Property::AuthenticationMethod(..) => 0x15,
+            Property::AuthenticationData(..) => 0x16,
+            Property::RequestProblemInformation(..) => 0x17,
+            Property::WillDelayInterval(..) => 0x18,
+            Property::RequestResponseInformation(..) => 0x19,
+            Property::ResponseInformation(..) => 0x1A,
+            Property::ServerReference(..) => 0x1C,
+            Property::ReasonString(..) => 0x1F,
+            Property::ReceiveMaximum(..) => 0x21,
+            Property::TopicAliasMaximum(..) => 0x22,
+            Property::TopicAlias(..) => 0x23,
+            Property::MaximumQoS(..) => 0x24,
+            Property::RetainAvailable(..) => 0x25,
+            Property::UserProperty(..) => 0x26,
+            Property::MaximumPacketSize(..) => 0x27,
+            Property::WildcardSubscriptionAvailable(..) => 0x28,
+            Property::SubscriptionIdentifierAvailable(..) => 0x29,
+            Property::SharedSubscriptionAvailable(..) => 0x2A,
+        }
+    }
+}
+
+/// The will message of a client, published when it disconnects without a
+/// DISCONNECT packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Will {
+    pub topic: String,
+    pub payload: Vec<u8>,
+    pub qos: QoS,
+    pub retain: bool,
+    pub properties: Vec<Property>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Connect {
+    pub protocol: Protocol,
+    /// Clean Session of MQTT 3.1.1, or Clean Start of MQTT 5.0.
+    pub clean_start: bool,
+    /// The keep alive in seconds, or 0 to disable it.
+    pub keep_alive: u16,
+    pub client_id: String,
+    pub will: Option<Will>,
+    pub username: Option<String>,
+    pub password: Option<Vec<u8>>,
+    pub properties: Vec<Property>,
+}
+
+impl Connect {
+    /// Returns a CONNECT with a clean start and a keep alive of 60 seconds.
+    #[inline]
+    pub fn new(protocol: Protocol, client_id: &str) -> Self {
+        Connect {
+            protocol,
+            clean_start: true,
+            keep_alive: 60,
+            client_id: client_id.to_string(),
+            will: None,
+            username: None,
+            password: None,
+            properties: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnAck {
+    pub session_present: bool,
+    /// The return code of MQTT 3.1.1, or the reason code of MQTT 5.0.
+    pub code: u8,
+    pub properties: Vec<Property>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Publish {
+    pub dup: bool,
+    pub qos: QoS,
+    pub retain: bool,
+    pub topic: String,
+    /// The packet identifier, which is 0 for QoS 0.
+    pub packet_id: u16,
+    pub properties: Vec<Property>,
+    pub payload: ByteBuf,
+}
+
+impl Publish {
+    #[inline]
+    pub fn new(topic: &str, qos: QoS, payload: ByteBuf) -> Self {
+        Publish {
+            dup: false,
+            qos,
+            retain: false,
+            topic: topic.to_string(),
+            packet_id: 0,
+            properties: Vec::new(),
+            payload,
+        }
+    }
+}
+
+/// Copies the payload, which is not shared.
+impl Clone for Publish {
+    fn clone(&self) -> Self {
+        Publish {
+            dup: self.dup,
+            qos: self.qos,
+            retain: self.retain,
+            topic: self.topic.clone(),
+            packet_id: self.packet_id,
+            properties: self.properties.clone(),
+            payload: match self.payload.is_empty() {
+                true => ByteBuf::new(),
+                false => ByteBuf::from(self.payload.as_bytes().into_owned()),
+            },
+        }
+    }
+}
+
+/// The body of PUBACK, PUBREC, PUBREL and PUBCOMP.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ack {
+    pub packet_id: u16,
+    /// The reason code, always `SUCCESS` with MQTT 3.1.1.
+    pub code: u8,
+    pub properties: Vec<Property>,
+}
+
+impl Ack {
+    #[inline]
+    pub fn new(packet_id: u16) -> Self {
+        Ack {
+            packet_id,
+            code: reason::SUCCESS,
+            properties: Vec::new(),
+        }
+    }
+}
+
+/// A topic filter and the options to subscribe to it with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subscription {
+    pub filter: String,
+    /// The maximum QoS to receive messages with.
+    pub qos: QoS,
+    pub no_local: bool,
+    pub retain_as_published: bool,
+    /// When to send retained messages: 0 at subscribe, 1 at subscribe if
+    /// not subscribed yet, or 2 never.
+    pub retain_handling: u8,
+}
+
+impl Subscription {
+    #[inline]
+    pub fn new(filter: &str, qos: QoS) -> Self {
+        Subscription {
+            filter: filter.to_string(),
+            qos,
+            no_local: false,
+            retain_as_published: false,
+            retain_handling: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subscribe {
+    pub packet_id: u16,
+    pub subscriptions: Vec<Subscription>,
+    pub properties: Vec<Property>,
+}
+
+/// The body of SUBACK and UNSUBACK.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubAck {
+    pub packet_id: u16,
+    /// A code for each topic filter, which UNSUBACK of MQTT 3.1.1 lacks.
+    pub codes: Vec<u8>,
+    pub properties: Vec<Property>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unsubscribe {
+    pub packet_id: u16,
+    pub filters: Vec<String>,
+    pub properties: Vec<Property>,
+}
+
+/// The body of DISCONNECT and AUTH, always empty with MQTT 3.1.1.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Reason {
+    pub code: u8,
+    pub properties: Vec<Property>,
+}
+
+/// An MQTT control packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Packet {
+    Connect(Connect),
+    ConnAck(ConnAck),
+    Publish(Publish),
+    PubAck(Ack),
+    PubRec(Ack),
+    PubRel(Ack),
+    PubComp(Ack),
+    Subscribe(Subscribe),
+    SubAck(SubAck),
+    Unsubscribe(Unsubscribe),
+    UnsubAck(SubAck),
+    PingReq,
+    PingResp,
+    Disconnect(Reason),
+    /// Only of MQTT 5.0.
+    Auth(Reason),
+}
+
+impl Packet {
+    pub fn packet_type(&self) -> u8 {
+        match *self {
+            Packet::Connect(..) => packet_type::CONNECT,
+            Packet::ConnAck(..) => packet_type::CONNACK,
+            Packet::Publish(..) => packet_type::PUBLISH,
+            Packet::PubAck(..) => packet_type::PUBACK,
+            Packet::PubRec(..) => packet_type::PUBREC,
+            Packet::PubRel(..) => packet_type::PUBREL,
+            Packet::PubComp(..) => packet_type::PUBCOMP,
+            Packet::Subscribe(..) => packet_type::SUBSCRIBE,
+            Packet::SubAck(..) => packet_type::SUBACK,
+            Packet::Unsubscribe(..) => packet_type::UNSUBSCRIBE,
+            Packet::UnsubAck(..) => packet_type::UNSUBACK,
+            Packet::PingReq => packet_type::PINGREQ,
+            Packet::PingResp => packet_type::PINGRESP,
+            Packet::Disconnect(..) => packet_type::DISCONNECT,
+            Packet::Auth(..) => packet_type::AUTH,
+        }
+    }
+}
+
+impl fmt::Display for Packet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Packet::Connect(..) => "CONNECT",
+            Packet::ConnAck(..) => "CONNACK",
+            Packet::Publish(..) => "PUBLISH",
+            Packet::PubAck(..) => "PUBACK",
+            Packet::PubRec(..) => "PUBREC",
+            Packet::PubRel(..) => "PUBREL",
+            Packet::PubComp(..) => "PUBCOMP",
+            Packet::Subscribe(..) => "SUBSCRIBE",
+            Packet::SubAck(..) => "SUBACK",
+            Packet::Unsubscribe(..) => "UNSUBSCRIBE",
+            Packet::UnsubAck(..) => "UNSUBACK",
+            Packet::PingReq => "PINGREQ",
+            Packet::PingResp => "PINGRESP",
+            Packet::Disconnect(..) => "DISCONNECT",
+            Packet::Auth(..) => "AUTH",
+        };
+        f.write_str(name)
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::HashSet;
+use std::io;
+use std::time::{Duration, Instant};
+
+use super::{reason, Ack, Packet, Publish, QoS};
+
+/// The state of either end of an MQTT connection: the packet identifiers
+/// in use, the QoS 1 and 2 flows in progress and the keep alive.
+///
+/// Every packet received goes through `recv`, which answers the flows and
+/// pings, and every packet sent is noted with `sent`.
+#[derive(Debug)]
+pub struct Session {
+    last_id: u16,
+    // The publishes not acknowledged yet in the order of sending, or `None`
+    // for those released with PUBREL but not completed yet.
+    outgoing: Vec<(u16, Option<Publish>)>,
+    // The QoS 2 publishes received but not released yet.
+    incoming: HashSet<u16>,
+    // The identifiers of SUBSCRIBE and UNSUBSCRIBE not acknowledged yet.
+    reserved: HashSet<u16>,
+    keep_alive: Option<Duration>,
+    last_recv: Instant,
+    last_sent: Instant,
+}
+
+impl Default for Session {
+    #[inline]
+    fn default() -> Self {
+        Session::new()
+    }
+}
+
+impl Session {
+    #[inline]
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Session {
+            last_id: 0,
+            outgoing: Vec::new(),
+            incoming: HashSet::new(),
+            reserved: HashSet::new(),
+            keep_alive: None,
+            last_recv: now,
+            last_sent: now,
+        }
+    }
+
+    /// Sets the keep alive of the CONNECT packet in seconds, or 0 to
+    /// disable it.
+    #[inline]
+    pub fn set_keep_alive(&mut self, secs: u16) -> &mut Self {
+        self.keep_alive = match secs {
+            0 => None,
+            _ => Some(Duration::from_secs(u64::from(secs))),
+        };
+        self
+    }
+
+    #[inline]
+    pub fn keep_alive(&self) -> Option<Duration> {
+        self.keep_alive
+    }
+
+    /// Returns the number of QoS 1 and 2 publishes sent but not completed.
+    #[inline]
+    pub fn inflight(&self) -> usize {
+        self.outgoing.len()
+    }
+
+    fn in_use(&self, id: u16) -> bool {
+        self.reserved.contains(&id) || self.outgoing.iter().any(|&(i, _)| i == id)
+    }
+
+    /// Returns a packet identifier for a SUBSCRIBE or UNSUBSCRIBE, which is
+    /// in use until its acknowledgement is received.
+    pub fn packet_id(&mut self) -> io::Result<u16> {
+        let id = self.next_id()?;
+        self.reserved.insert(id);
+        Ok(id)
+    }
+
+    fn next_id(&mut self) -> io::Result<u16> {
+        for _ in 0..u16::MAX {
+            self.last_id = self.last_id.checked_add(1).unwrap_or(1);
+            if !self.in_use(self.last_id) {
+                return Ok(self.last_id);
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::WouldBlock,
+            "All MQTT packet identifiers in use",
+        ))
+    }
+
+    /// Returns the packet to send `publish` with, which is kept until it is
+    /// acknowledged if of QoS 1 or 2.
+    pub fn publish(&mut self, mut publish: Publish) -> io::Result<Packet> {
+        if publish.qos != QoS::AtMostOnce {
+            publish.packet_id = self.next_id()?;
+            self.outgoing.push((publish.packet_id, Some(publish.clone())));
+        }
+        Ok(Packet::Publish(publish))
+    }
+
+    /// Returns the packets to send again after reconnecting to a session
+    /// that is present: the publishes not acknowledged yet marked as
+    /// duplicates, and the PUBREL packets not completed yet.
+    pub fn resend(&self) -> Vec<Packet> {
+        self.outgoing
+            .iter()
+            .map(|&(id, ref publish)| match *publish {
+                Some(ref publish) => {
+                    let mut publish = publish.clone();
+                    publish.dup = true;
+                    Packet::Publish(publish)
+                }
+                None => Packet::PubRel(Ack::new(id)),
+            })
+            .collect()
+    }
+
+    /// Notes that a packet was sent.
+    #[inline]
+    pub fn sent(&mut self) {
+        self.last_sent = Instant::now();
+    }
+
+    /// Returns a PINGREQ to send if nothing has been sent for the keep
+    /// alive, as a client has to.
+    pub fn ping(&mut self) -> Option<Packet> {
+        match self.keep_alive {
+            Some(keep_alive) if self.last_sent.elapsed() >= keep_alive => {
+                self.sent();
+                Some(Packet::PingReq)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns whether nothing has been received for one and a half times
+    /// the keep alive, after which a server closes the connection.
+    #[inline]
+    pub fn is_expired(&self) -> bool {
+        match self.keep_alive {
+            Some(keep_alive) => self.last_recv.elapsed() > keep_alive + keep_alive / 2,
+            None => false,
+        }
+    }
+
+    /// Takes `packet` received, and returns the packet to pass on to the
+    /// application and the packet to reply with.
+    ///
+    /// Publishes are passed on once, i.e. duplicates of QoS 2 are not.
+    /// PUBACK and PUBCOMP are passed on to tell that a publish is complete,
+    /// while pings, PUBREC and PUBREL are answered and not passed on.
+    pub fn recv(&mut self, packet: Packet) -> (Option<Packet>, Option<Packet>) {
+        self.last_recv = Instant::now();
+        match packet {
+            Packet::Publish(publish) => match publish.qos {
+                QoS::AtMostOnce => (Some(Packet::Publish(publish)), None),
+                QoS::AtLeastOnce => {
+                    let ack = Packet::PubAck(Ack::new(publish.packet_id));
+                    (Some(Packet::Publish(publish)), Some(ack))
+                }
+                QoS::ExactlyOnce => {
+                    let rec = Packet::PubRec(Ack::new(publish.packet_id));
+                    match self.incoming.insert(publish.packet_id) {
+                        true => (Some(Packet::Publish(publish)), Some(rec)),
+                        false => (None, Some(rec)),
+                    }
+                }
+            },
+            Packet::PubRel(rel) => {
+                let mut comp = Ack::new(rel.packet_id);
+                if !self.incoming.remove(&rel.packet_id) {
+                    comp.code = reason::PACKET_IDENTIFIER_NOT_FOUND;
+                }
+                (None, Some(Packet::PubComp(comp)))
+            }
+            Packet::PubAck(ack) => {
+                self.complete(ack.packet_id);
+                (Some(Packet::PubAck(ack)), None)
+            }
+            Packet::PubRec(rec) => {
+                let i = self.outgoing.iter().position(|&(id, _)| id == rec.packet_id);
+                let mut rel = Ack::new(rec.packet_id);
+                match i {
+                    // A failure ends the flow.
+                    Some(..) if rec.code >= 0x80 => {
+                        self.complete(rec.packet_id);
+                        return (Some(Packet::PubRec(rec)), None);
+                    }
+                    Some(i) => self.outgoing[i].1 = None,
+                    None => rel.code = reason::PACKET_IDENTIFIER_NOT_FOUND,
+                }
+                (None, Some(Packet::PubRel(rel)))
+            }
+            Packet::PubComp(comp) => {
+                self.complete(comp.packet_id);
+                (Some(Packet::PubComp(comp)), None)
+            }
+            Packet::SubAck(ack) => {
+                self.reserved.remove(&ack.packet_id);
+                (Some(Packet::SubAck(ack)), None)
+            }
+            Packet::UnsubAck(ack) => {
+                self.reserved.remove(&ack.packet_id);
+                (Some(Packet::UnsubAck(ack)), None)
+            }
+            Packet::PingReq => (None, Some(Packet::PingResp)),
+            Packet::PingResp => (None, None),
+            packet => (Some(packet), None),
+        }
+    }
+
+    fn complete(&mut self, id: u16) {
+        self.outgoing.retain(|&(i, _)| i != id);
+    }
+}
@@ -0,0 +1,44 @@
+/// Returns whether `topic` matches `filter`, in which `+` matches a level
+/// and a trailing `#` matches any levels left.
+///
+/// Topics starting with `$` are not matched by a wildcard at the first
+/// level.
+pub fn topic_matches(filter: &str, topic: &str) -> bool {
+    if topic.starts_with('$') && (filter.starts_with('+') || filter.starts_with('#')) {
+        return false;
+    }
+    let mut levels = topic.split('/');
+    for f in filter.split('/') {
+        match (f, levels.next()) {
+            ("#", _) => return true,
+            ("+", Some(..)) => (),
+            (f, Some(level)) if f == level => (),
+            _ => return false,
+        }
+    }
+    levels.next().is_none()
+}
+
+/// Returns whether `filter` is a valid topic filter, where wildcards take
+/// whole levels and `#` is the last level.
+pub fn is_valid_filter(filter: &str) -> bool {
+    if filter.is_empty() {
+        return false;
+    }
+    let mut levels = filter.split('/').peekable();
+    while let Some(level) = levels.next() {
+        match level {
+            "#" if levels.peek().is_some() => return false,
+            "#" | "+" => (),
+            _ if level.contains(&['+', '#'][..]) => return false,
+            _ => (),
+        }
+    }
+    true
+}
+
+/// Returns whether `topic` is a valid topic name to publish to.
+#[inline]
+pub fn is_valid_topic(topic: &str) -> bool {
+    !topic.is_empty() && !topic.contains(&['+', '#'][..])
+}
@@ -0,0 +1,107 @@
+use std::collections::VecDeque;
+use std::io;
+use std::mem;
+
+use futures::{Async, AsyncSink, Future, Poll, Sink, Stream};
+
+use buf::ByteBuf;
+
+use super::{Decoder, Encoder, Framed};
+
+/// A codec of requests and the responses to them, e.g. of the client of a
+/// request-response protocol.
+pub trait PipelineCodec: Encoder<Error = io::Error> + Decoder<Error = io::Error> {
+    /// Returns whether the server answers `req`. Defaults to `true`.
+    #[inline]
+    fn has_response(&self, _req: &<Self as Encoder>::Item) -> bool {
+        true
+    }
+
+    /// Returns whether `item` is a response rather than data the server
+    /// sends on its own, which is discarded. Defaults to `true`.
+    #[inline]
+    fn is_response(&self, _item: &<Self as Decoder>::Item) -> bool {
+        true
+    }
+}
+
+/// Sends requests over `Framed<T, C>` without waiting for responses, and
+/// yields it and the responses in the order of the requests.
+pub struct Pipeline<T, C>
+where
+    C: PipelineCodec,
+{
+    framed: Option<Framed<T, C>>,
+    reqs: VecDeque<<C as Encoder>::Item>,
+    n: usize,
+    responses: Vec<<C as Decoder>::Item>,
+}
+
+impl<T, C> Pipeline<T, C>
+where
+    C: PipelineCodec,
+{
+    pub fn new<I>(framed: Framed<T, C>, reqs: I) -> Self
+    where
+        I: IntoIterator<Item = <C as Encoder>::Item>,
+    {
+        let reqs: VecDeque<_> = reqs.into_iter().collect();
+        let n = reqs.iter()
+            .filter(|req| framed.codec().has_response(req))
+            .count();
+        Pipeline {
+            framed: Some(framed),
+            reqs,
+            n,
+            responses: Vec::with_capacity(n),
+        }
+    }
+}
+
+/// Reads responses while sending the rest of the requests, so the server
+/// is not blocked on a full connection.
+impl<T, C> Future for Pipeline<T, C>
+where
+    T: Stream<Item = ByteBuf, Error = io::Error> + Sink<SinkItem = ByteBuf, SinkError = io::Error>,
+    C: PipelineCodec,
+{
+    type Item = (Framed<T, C>, Vec<<C as Decoder>::Item>);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        {
+            let framed = match self.framed {
+                Some(ref mut framed) => framed,
+                None => panic!("Attempted to poll Pipeline after completion"),
+            };
+            while let Some(req) = self.reqs.pop_front() {
+                if let AsyncSink::NotReady(req) = framed.start_send(req)? {
+                    self.reqs.push_front(req);
+                    break;
+                }
+            }
+            framed.poll_complete()?;
+            while self.responses.len() < self.n {
+                match try_ready!(framed.poll()) {
+                    Some(res) => if framed.codec().is_response(&res) {
+                        self.responses.push(res);
+                    },
+                    None => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "Connection closed with requests pending",
+                        ))
+                    }
+                }
+            }
+            // Requests with no response may still be pending.
+            if !self.reqs.is_empty() || framed.poll_complete()?.is_not_ready() {
+                return Ok(Async::NotReady);
+            }
+        }
+        match self.framed.take() {
+            Some(framed) => Ok(Async::Ready((framed, mem::take(&mut self.responses)))),
+            None => ::unreachable(),
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::cmp;
+use std::io;
+
+use buf::ByteBuf;
+
+use super::{frame_too_long, max_frame_len, prefix, put, Decoder, Encoder, Framed};
+
+// Defines a codec of frames prefixed with their length, `$size` bytes read
+// by `$get` and written by `$prepend`, and the `Framed` alias using it.
+macro_rules! prefix_codec {
+    ($codec:ident, $framed:ident, $t:ident, $size:expr, $get:path, $prepend:path) => {
+        #[derive(Debug, Clone, Copy)]
+        pub struct $codec {
+            max_frame_len: usize,
+        }
+
+        pub type $framed<S> = Framed<S, $codec>;
+
+        impl Default for $codec {
+            #[inline]
+            fn default() -> Self {
+                $codec::new()
+            }
+        }
+
+        impl $codec {
+            #[inline]
+            pub fn new() -> Self {
+                $codec {
+                    max_frame_len: max_frame_len(),
+                }
+            }
+
+            #[inline]
+            pub fn max_frame_len(&self) -> usize {
+                self.max_frame_len
+            }
+
+            /// Sets the maximum length of a frame, excluding its prefix. Longer
+            /// frames fail with `FrameTooLong`.
+            #[inline]
+            pub fn set_max_frame_len(&mut self, max_frame_len: usize) -> &mut Self {
+                self.max_frame_len = max_frame_len;
+                self
+            }
+        }
+
+        impl<S> Framed<S, $codec> {
+            #[inline]
+            pub fn max_frame_len(&self) -> usize {
+                self.codec().max_frame_len()
+            }
+
+            /// Sets the maximum length of a frame of the codec. A longer
+            /// frame fails the stream with `FrameTooLong`.
+            #[inline]
+            pub fn set_max_frame_len(&mut self, max_frame_len: usize) -> &mut Self {
+                self.codec_mut().set_max_frame_len(max_frame_len);
+                self
+            }
+        }
+
+        impl Decoder for $codec {
+            type Item = ByteBuf;
+            type Error = io::Error;
+
+            fn decode(&mut self, data: &mut ByteBuf) -> io::Result<Option<ByteBuf>> {
+                let len = data.len();
+                if len < $size {
+                    return Ok(None);
+                }
+                let n = match data.get(0, $get) {
+                    Ok(n) => n as usize,
+                    _ => ::unreachable(),
+                };
+                if n > self.max_frame_len {
+                    return Err(frame_too_long(n, self.max_frame_len));
+                }
+                if len < $size + n {
+                    return Ok(None);
+                }
+                data.skip($size);
+                Ok(data.drain_to(n).ok())
+            }
+        }
+
+        /// The length is prepended without reallocating if
+        /// `try_reserve_in_head` can reserve room for it.
+        impl Encoder for $codec {
+            type Item = ByteBuf;
+            type Error = io::Error;
+
+            fn encode(&mut self, item: ByteBuf, dst: &mut ByteBuf) -> io::Result<()> {
+                let len = item.len();
+                let max = cmp::min(self.max_frame_len, ::std::$t::MAX as usize);
+                if len > max {
+                    return Err(frame_too_long(len, max));
+                }
+                put(dst, prefix(item, len as $t, $size, $prepend));
+                Ok(())
+            }
+        }
+    };
+}
+
+prefix_codec!(
+    U8PrefixCodec,
+    U8Prefix,
+    u8,
+    1,
+    ::buf::codec::u8::get,
+    ::buf::codec::u8::prepend
+);
+prefix_codec!(
+    U16bePrefixCodec,
+    U16bePrefix,
+    u16,
+    2,
+    ::buf::codec::u16::big_endian::get,
+    ::buf::codec::u16::big_endian::prepend
+);
+prefix_codec!(
+    U16lePrefixCodec,
+    U16lePrefix,
+    u16,
+    2,
+    ::buf::codec::u16::little_endian::get,
+    ::buf::codec::u16::little_endian::prepend
+);
+prefix_codec!(
+    U32bePrefixCodec,
+    U32bePrefix,
+    u32,
+    4,
+    ::buf::codec::u32::big_endian::get,
+    ::buf::codec::u32::big_endian::prepend
+);
+prefix_codec!(
+    U32lePrefixCodec,
+    U32lePrefix,
+    u32,
+    4,
+    ::buf::codec::u32::little_endian::get,
+    ::buf::codec::u32::little_endian::prepend
+);
@@ -0,0 +1,315 @@
+//! The PROXY protocol of HAProxy, versions 1 and 2, which passes the
+//! addresses of a client through a proxy or load balancer in a header ahead
+//! of the data of the connection.
+
+use std::cmp;
+use std::io;
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str;
+
+use futures::{Async, Future, Poll};
+
+use net::TcpStream;
+use sys::net::tcp::Peek;
+
+/// The maximum length of a v1 header, including CRLF.
+pub const V1_MAX_LEN: usize = 107;
+
+/// The signature at the start of a v2 header.
+pub const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+/// Types of the TLVs of a v2 header.
+pub mod tlv {
+    pub const ALPN: u8 = 0x01;
+    pub const AUTHORITY: u8 = 0x02;
+    pub const CRC32C: u8 = 0x03;
+    pub const NOOP: u8 = 0x04;
+    pub const UNIQUE_ID: u8 = 0x05;
+    pub const SSL: u8 = 0x20;
+    pub const NETNS: u8 = 0x30;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    V1,
+    V2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// The connection was made by the proxy itself, e.g. for health checks.
+    Local,
+    Proxy,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tlv {
+    pub kind: u8,
+    pub value: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub version: Version,
+    pub command: Command,
+    /// The addresses of the client and of the server it connected to, or
+    /// `None` if unknown, e.g. for `PROXY UNKNOWN` or UNIX sockets.
+    pub addrs: Option<(SocketAddr, SocketAddr)>,
+    pub tlvs: Vec<Tlv>,
+}
+
+impl Header {
+    #[inline]
+    pub fn source(&self) -> Option<SocketAddr> {
+        self.addrs.map(|(src, _)| src)
+    }
+
+    #[inline]
+    pub fn destination(&self) -> Option<SocketAddr> {
+        self.addrs.map(|(_, dst)| dst)
+    }
+
+    /// Returns the value of the first TLV of `kind`.
+    #[inline]
+    pub fn tlv(&self, kind: u8) -> Option<&[u8]> {
+        self.tlvs
+            .iter()
+            .find(|tlv| tlv.kind == kind)
+            .map(|tlv| tlv.value.as_slice())
+    }
+}
+
+#[inline]
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Parses the header at the start of `data`, and returns it with its length,
+/// or `None` if `data` holds only part of it.
+pub fn parse(data: &[u8]) -> io::Result<Option<(Header, usize)>> {
+    match data.first() {
+        None => Ok(None),
+        Some(&b'P') => parse_v1(data),
+        Some(&b'\r') => parse_v2(data),
+        Some(..) => Err(invalid_data("Missing PROXY protocol header")),
+    }
+}
+
+#[inline]
+fn starts_with(data: &[u8], prefix: &[u8]) -> bool {
+    let n = cmp::min(data.len(), prefix.len());
+    data[..n] == prefix[..n]
+}
+
+fn parse_v1(data: &[u8]) -> io::Result<Option<(Header, usize)>> {
+    if !starts_with(data, b"PROXY ") {
+        return Err(invalid_data("Missing PROXY protocol header"));
+    }
+    let end = match data.windows(2)
+        .take(V1_MAX_LEN - 1)
+        .position(|crlf| crlf == b"\r\n")
+    {
+        Some(end) => end,
+        None if data.len() >= V1_MAX_LEN => {
+            return Err(invalid_data("PROXY v1 header too long"))
+        }
+        None => return Ok(None),
+    };
+    let line = str::from_utf8(&data[..end]).map_err(|_| invalid_data("Invalid PROXY v1 header"))?;
+    let mut fields = line.split(' ').skip(1);
+    let addrs = match fields.next() {
+        Some("UNKNOWN") => None,
+        Some("TCP4") => Some(v1_addrs(fields, true)?),
+        Some("TCP6") => Some(v1_addrs(fields, false)?),
+        _ => return Err(invalid_data("Invalid PROXY v1 protocol")),
+    };
+    let header = Header {
+        version: Version::V1,
+        command: Command::Proxy,
+        addrs,
+        tlvs: Vec::new(),
+    };
+    Ok(Some((header, end + 2)))
+}
+
+fn v1_ip(field: Option<&str>, v4: bool) -> Option<IpAddr> {
+    let field = field?;
+    match v4 {
+        true => field.parse::<Ipv4Addr>().ok().map(IpAddr::V4),
+        false => field.parse::<Ipv6Addr>().ok().map(IpAddr::V6),
+    }
+}
+
+fn v1_port(field: Option<&str>) -> Option<u16> {
+    let field = field?;
+    if field.is_empty() || !field.bytes().all(|b| b.is_ascii_digit())
+        || (field.len() > 1 && field.starts_with('0'))
+    {
+        return None;
+    }
+    field.parse().ok()
+}
+
+fn v1_addrs<'a, I>(mut fields: I, v4: bool) -> io::Result<(SocketAddr, SocketAddr)>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let src = v1_ip(fields.next(), v4);
+    let dst = v1_ip(fields.next(), v4);
+    let src_port = v1_port(fields.next());
+    let dst_port = v1_port(fields.next());
+    match (src, dst, src_port, dst_port, fields.next()) {
+        (Some(src), Some(dst), Some(src_port), Some(dst_port), None) => Ok((
+            SocketAddr::new(src, src_port),
+            SocketAddr::new(dst, dst_port),
+        )),
+        _ => Err(invalid_data("Invalid PROXY v1 address")),
+    }
+}
+
+#[inline]
+fn be16(b: &[u8]) -> usize {
+    (b[0] as usize) << 8 | b[1] as usize
+}
+
+fn parse_v2(data: &[u8]) -> io::Result<Option<(Header, usize)>> {
+    if !starts_with(data, &V2_SIGNATURE) {
+        return Err(invalid_data("Missing PROXY protocol header"));
+    }
+    if data.len() < 16 {
+        return Ok(None);
+    }
+    let command = match data[12] {
+        0x20 => Command::Local,
+        0x21 => Command::Proxy,
+        v if v >> 4 != 2 => return Err(invalid_data("Unsupported PROXY protocol version")),
+        _ => return Err(invalid_data("Invalid PROXY v2 command")),
+    };
+    let len = 16 + be16(&data[14..]);
+    if data.len() < len {
+        return Ok(None);
+    }
+    if data[13] & 0x0F > 2 {
+        return Err(invalid_data("Invalid PROXY v2 transport protocol"));
+    }
+    let body = &data[16..len];
+    let addr_len = match data[13] >> 4 {
+        0 => body.len(),
+        1 => 12,
+        2 => 36,
+        3 => 216,
+        _ => return Err(invalid_data("Invalid PROXY v2 address family")),
+    };
+    if body.len() < addr_len {
+        return Err(invalid_data("Invalid PROXY v2 address"));
+    }
+    let addrs = match data[13] >> 4 {
+        1 => Some((
+            SocketAddr::new(
+                IpAddr::V4(Ipv4Addr::new(body[0], body[1], body[2], body[3])),
+                be16(&body[8..]) as u16,
+            ),
+            SocketAddr::new(
+                IpAddr::V4(Ipv4Addr::new(body[4], body[5], body[6], body[7])),
+                be16(&body[10..]) as u16,
+            ),
+        )),
+        2 => {
+            let mut src = [0; 16];
+            let mut dst = [0; 16];
+            src.copy_from_slice(&body[..16]);
+            dst.copy_from_slice(&body[16..32]);
+            Some((
+                SocketAddr::new(IpAddr::V6(Ipv6Addr::from(src)), be16(&body[32..]) as u16),
+                SocketAddr::new(IpAddr::V6(Ipv6Addr::from(dst)), be16(&body[34..]) as u16),
+            ))
+        }
+        _ => None,
+    };
+    let mut tlvs = Vec::new();
+    let mut rest = &body[addr_len..];
+    while !rest.is_empty() {
+        if rest.len() < 3 || rest.len() < 3 + be16(&rest[1..]) {
+            return Err(invalid_data("Invalid PROXY v2 TLV"));
+        }
+        let n = 3 + be16(&rest[1..]);
+        tlvs.push(Tlv {
+            kind: rest[0],
+            value: rest[3..n].to_vec(),
+        });
+        rest = &rest[n..];
+    }
+    let header = Header {
+        version: Version::V2,
+        command,
+        // The addresses of a `LOCAL` connection are to be ignored.
+        addrs: match command {
+            Command::Local => None,
+            Command::Proxy => addrs,
+        },
+        tlvs,
+    };
+    Ok(Some((header, len)))
+}
+
+enum State<T>
+where
+    T: AsRef<TcpStream>,
+{
+    Peeking(Peek<T>),
+    Error(io::Error),
+    Done,
+}
+
+/// Reads the header at the start of a connection, leaving the data after it
+/// to be read, and yields the connection and the header.
+pub struct Accept<T>
+where
+    T: AsRef<TcpStream>,
+{
+    state: State<T>,
+}
+
+#[inline]
+pub fn accept<T>(io: T) -> Accept<T>
+where
+    T: AsRef<TcpStream>,
+{
+    let state = match Peek::try_from(io) {
+        Ok(peek) => State::Peeking(peek),
+        Err(e) => State::Error(e),
+    };
+    Accept { state }
+}
+
+impl<T> Future for Accept<T>
+where
+    T: AsRef<TcpStream>,
+{
+    type Item = (T, Header);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let header = match self.state {
+            State::Peeking(ref mut peek) => match try_ready!(peek.poll_read(parse)) {
+                Some(header) => header,
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Connection closed before the PROXY protocol header",
+                    ))
+                }
+            },
+            State::Error(..) => match mem::replace(&mut self.state, State::Done) {
+                State::Error(e) => return Err(e),
+                _ => ::unreachable(),
+            },
+            State::Done => panic!("Attempted to poll Accept after completion"),
+        };
+        match mem::replace(&mut self.state, State::Done) {
+            State::Peeking(peek) => Ok(Async::Ready((peek.into_inner(), header))),
+            _ => ::unreachable(),
+        }
+    }
+}
@@ -0,0 +1,9 @@
+use std::io;
+
+use getrandom::getrandom;
+
+// Fills `buf` from the random number generator of the OS, for values that
+// have to be unpredictable to others, e.g. masking keys and query IDs.
+pub(crate) fn fill(buf: &mut [u8]) -> io::Result<()> {
+    getrandom(buf).map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))
+}
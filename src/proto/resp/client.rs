@@ -0,0 +1,118 @@
+use std::io;
+
+use futures::{Async, Future, Poll, Sink, Stream};
+
+use buf::ByteBuf;
+use proto::{self, Framed, PipelineCodec};
+
+use super::{RespCodec, Value};
+
+/// A client of a Redis server at the other end of `T`, e.g. the halves of
+/// `net::tcp::split` joined by `Duplex`.
+///
+/// Error replies are values like any other, see `Value::is_error`. RESP3
+/// push data, e.g. Pub/Sub messages, is discarded.
+pub struct Client<T> {
+    framed: Framed<T, RespCodec>,
+}
+
+/// Sends commands without waiting for replies, and yields the client and
+/// the replies in the order of the commands.
+pub struct Pipeline<T> {
+    inner: proto::Pipeline<T, RespCodec>,
+}
+
+/// Sends a command and yields the client and the reply.
+pub struct Call<T> {
+    inner: Pipeline<T>,
+}
+
+impl<T> Client<T> {
+    #[inline]
+    pub fn new(io: T) -> Self {
+        Self::with_codec(io, RespCodec::new())
+    }
+
+    #[inline]
+    pub fn with_codec(io: T, codec: RespCodec) -> Self {
+        Client {
+            framed: Framed::new(io, codec),
+        }
+    }
+
+    #[inline]
+    pub fn codec(&self) -> &RespCodec {
+        self.framed.codec()
+    }
+
+    /// Returns the codec, e.g. to switch to RESP3 after `HELLO 3`.
+    #[inline]
+    pub fn codec_mut(&mut self) -> &mut RespCodec {
+        self.framed.codec_mut()
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.framed.into_inner()
+    }
+
+    /// Sends `cmd`, e.g. `Value::command(&["GET", "k"])`.
+    #[inline]
+    pub fn call(self, cmd: Value) -> Call<T> {
+        Call {
+            inner: self.pipeline(Some(cmd)),
+        }
+    }
+
+    /// Sends `cmds` without waiting for the reply to each.
+    pub fn pipeline<I>(self, cmds: I) -> Pipeline<T>
+    where
+        I: IntoIterator<Item = Value>,
+    {
+        Pipeline {
+            inner: proto::Pipeline::new(self.framed, cmds),
+        }
+    }
+}
+
+/// RESP3 push data is not a reply.
+impl PipelineCodec for RespCodec {
+    #[inline]
+    fn is_response(&self, value: &Value) -> bool {
+        match *value {
+            Value::Push(..) => false,
+            _ => true,
+        }
+    }
+}
+
+impl<T> Future for Pipeline<T>
+where
+    T: Stream<Item = ByteBuf, Error = io::Error> + Sink<SinkItem = ByteBuf, SinkError = io::Error>,
+{
+    type Item = (Client<T>, Vec<Value>);
+    type Error = io::Error;
+
+    #[inline]
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let (framed, replies) = try_ready!(self.inner.poll());
+        Ok(Async::Ready((Client { framed }, replies)))
+    }
+}
+
+impl<T> Future for Call<T>
+where
+    T: Stream<Item = ByteBuf, Error = io::Error> + Sink<SinkItem = ByteBuf, SinkError = io::Error>,
+{
+    type Item = (Client<T>, Value);
+    type Error = io::Error;
+
+    #[inline]
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let (client, mut replies) = try_ready!(self.inner.poll());
+        match replies.pop() {
+            Some(reply) => Ok(Async::Ready((client, reply))),
+            None => ::unreachable(),
+        }
+    }
+}
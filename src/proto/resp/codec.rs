@@ -0,0 +1,497 @@
+use std::cmp;
+use std::f64;
+use std::io;
+use std::str;
+
+use buf::ByteBuf;
+use buf::codec::u8s;
+use proto::{frame_too_long, max_frame_len, put, Decoder, Encoder, Framed};
+
+use super::Value;
+
+// The deepest nesting of aggregates accepted by the decoder.
+const MAX_DEPTH: usize = 512;
+
+/// Decodes and encodes RESP values.
+///
+/// The decoder accepts both RESP2 and RESP3 and keeps its progress across
+/// calls, so the front of a large or split value is parsed only once.
+/// Streamed strings and aggregates of RESP3 are not supported.
+#[derive(Debug)]
+pub struct RespCodec {
+    resp3: bool,
+    max_bulk_len: usize,
+    // Where to resume searching for the end of a line.
+    searched: usize,
+    // The type and length of a bulk string whose header has been decoded.
+    bulk: Option<(u8, usize)>,
+    // The aggregates being decoded, innermost last.
+    stack: Vec<Aggregate>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Array,
+    Set,
+    Push,
+    Map,
+    Attribute,
+}
+
+#[derive(Debug)]
+struct Aggregate {
+    kind: Kind,
+    len: usize,
+    values: Vec<Value>,
+}
+
+#[inline]
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[inline]
+fn invalid_input(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+fn parse_int(bytes: &[u8]) -> io::Result<i64> {
+    str::from_utf8(bytes)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| invalid_data("Invalid integer"))
+}
+
+fn parse_string(bytes: Vec<u8>) -> io::Result<String> {
+    String::from_utf8(bytes).map_err(|_| invalid_data("Invalid UTF-8 string"))
+}
+
+fn parse_double(bytes: &[u8]) -> io::Result<f64> {
+    match bytes {
+        b"inf" => Ok(f64::INFINITY),
+        b"-inf" => Ok(f64::NEG_INFINITY),
+        b"nan" => Ok(f64::NAN),
+        _ => str::from_utf8(bytes)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| invalid_data("Invalid double")),
+    }
+}
+
+fn is_big_number(bytes: &[u8]) -> bool {
+    let digits = match bytes.first() {
+        Some(&b'-') | Some(&b'+') => &bytes[1..],
+        _ => bytes,
+    };
+    !digits.is_empty() && digits.iter().all(u8::is_ascii_digit)
+}
+
+impl Aggregate {
+    fn new(kind: Kind, n: usize) -> Self {
+        let len = match kind {
+            Kind::Map => 2 * n,
+            Kind::Attribute => 2 * n + 1,
+            _ => n,
+        };
+        Aggregate {
+            kind,
+            len,
+            values: Vec::with_capacity(cmp::min(len, 64)),
+        }
+    }
+
+    #[inline]
+    fn is_complete(&self) -> bool {
+        self.values.len() == self.len
+    }
+
+    fn into_value(self) -> Value {
+        let mut values = self.values;
+        match self.kind {
+            Kind::Array => Value::Array(values),
+            Kind::Set => Value::Set(values),
+            Kind::Push => Value::Push(values),
+            Kind::Map => Value::Map(pairs(values)),
+            Kind::Attribute => {
+                let value = match values.pop() {
+                    Some(value) => value,
+                    None => ::unreachable(),
+                };
+                Value::Attribute(pairs(values), Box::new(value))
+            }
+        }
+    }
+}
+
+fn pairs(values: Vec<Value>) -> Vec<(Value, Value)> {
+    let mut pairs = Vec::with_capacity(values.len() / 2);
+    let mut values = values.into_iter();
+    while let (Some(k), Some(v)) = (values.next(), values.next()) {
+        pairs.push((k, v));
+    }
+    pairs
+}
+
+impl Default for RespCodec {
+    #[inline]
+    fn default() -> Self {
+        RespCodec::new()
+    }
+}
+
+impl RespCodec {
+    #[inline]
+    pub fn new() -> Self {
+        RespCodec {
+            resp3: false,
+            max_bulk_len: max_frame_len(),
+            searched: 0,
+            bulk: None,
+            stack: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn resp3(&self) -> bool {
+        self.resp3
+    }
+
+    /// Sets whether values are encoded with RESP3, e.g. after `HELLO 3`.
+    /// Defaults to `false`, with which values RESP2 cannot represent fail to
+    /// encode.
+    #[inline]
+    pub fn set_resp3(&mut self, resp3: bool) -> &mut Self {
+        self.resp3 = resp3;
+        self
+    }
+
+    #[inline]
+    pub fn max_bulk_len(&self) -> usize {
+        self.max_bulk_len
+    }
+
+    /// Sets the maximum length of a bulk string or a line, and the maximum
+    /// number of elements of an aggregate. Defaults to `max_frame_len()`.
+    #[inline]
+    pub fn set_max_bulk_len(&mut self, max_bulk_len: usize) -> &mut Self {
+        self.max_bulk_len = max_bulk_len;
+        self
+    }
+
+    #[inline]
+    pub fn frames<S>(self, stream: S) -> Framed<S, Self> {
+        Framed::new(stream, self)
+    }
+
+    fn line(&mut self, data: &mut ByteBuf) -> io::Result<Option<Vec<u8>>> {
+        let i = match data.find_from(b"\r\n", self.searched) {
+            Some(i) => i,
+            None => {
+                // The last byte may be the `\r` of a line end.
+                self.searched = data.len().saturating_sub(1);
+                if self.searched > self.max_bulk_len {
+                    return Err(frame_too_long(self.searched, self.max_bulk_len));
+                }
+                return Ok(None);
+            }
+        };
+        self.searched = 0;
+        if i > self.max_bulk_len {
+            return Err(frame_too_long(i, self.max_bulk_len));
+        }
+        let line = match i {
+            0 => Vec::new(),
+            _ => match data.drain_to(i) {
+                Ok(line) => line.as_bytes().into_owned(),
+                _ => ::unreachable(),
+            },
+        };
+        data.skip(2);
+        Ok(Some(line))
+    }
+
+    fn len(&self, bytes: &[u8]) -> io::Result<Option<usize>> {
+        match parse_int(bytes)? {
+            -1 => Ok(None),
+            n if n < 0 => Err(invalid_data("Invalid length")),
+            n if n as u64 > self.max_bulk_len as u64 => {
+                Err(frame_too_long(n as usize, self.max_bulk_len))
+            }
+            n => Ok(Some(n as usize)),
+        }
+    }
+
+    // Decodes a bulk string of `len` bytes and its line end.
+    fn bulk(&mut self, data: &mut ByteBuf, ty: u8, len: usize) -> io::Result<Option<Value>> {
+        if data.len() < len + 2 {
+            return Ok(None);
+        }
+        self.bulk = None;
+        let payload = match len {
+            0 => ByteBuf::new(),
+            _ => match data.drain_to(len) {
+                Ok(payload) => payload,
+                _ => ::unreachable(),
+            },
+        };
+        let mut bytes = data.bytes();
+        if bytes.next() != Some(b'\r') || bytes.next() != Some(b'\n') {
+            return Err(invalid_data("Missing line end after bulk string"));
+        }
+        data.skip(2);
+        let value = match ty {
+            b'$' => Value::Bulk(payload),
+            b'!' => Value::BulkError(payload),
+            _ => {
+                let mut text = payload;
+                if len < 4 || text.bytes().nth(3) != Some(b':') {
+                    return Err(invalid_data("Invalid verbatim string"));
+                }
+                let format = match text.drain_to(3) {
+                    Ok(format) => parse_string(format.as_bytes().into_owned())?,
+                    _ => ::unreachable(),
+                };
+                text.skip(1);
+                Value::Verbatim(format, text)
+            }
+        };
+        Ok(Some(value))
+    }
+
+    fn aggregate(&mut self, kind: Kind, bytes: &[u8]) -> io::Result<Option<Value>> {
+        let n = match self.len(bytes)? {
+            Some(n) => n,
+            None => match kind {
+                Kind::Array => return Ok(Some(Value::Null)),
+                _ => return Err(invalid_data("Invalid length")),
+            },
+        };
+        let aggregate = Aggregate::new(kind, n);
+        if aggregate.is_complete() {
+            return Ok(Some(aggregate.into_value()));
+        }
+        if self.stack.len() >= MAX_DEPTH {
+            return Err(invalid_data("Aggregates nested too deep"));
+        }
+        self.stack.push(aggregate);
+        Ok(None)
+    }
+
+    // Decodes the next value of the innermost aggregate, if it is not an
+    // aggregate of its own.
+    fn value(&mut self, data: &mut ByteBuf) -> io::Result<Option<Value>> {
+        if let Some((ty, len)) = self.bulk {
+            return self.bulk(data, ty, len);
+        }
+        let line = match self.line(data)? {
+            Some(line) => line,
+            None => return Ok(None),
+        };
+        let ty = match line.first() {
+            Some(&ty) => ty,
+            None => return Err(invalid_data("Empty line")),
+        };
+        let bytes = &line[1..];
+        let value = match ty {
+            b'+' => Value::Simple(parse_string(bytes.to_vec())?),
+            b'-' => Value::Error(parse_string(bytes.to_vec())?),
+            b':' => Value::Integer(parse_int(bytes)?),
+            b'$' | b'!' | b'=' => match self.len(bytes)? {
+                Some(len) => {
+                    self.bulk = Some((ty, len));
+                    return self.bulk(data, ty, len);
+                }
+                None if ty == b'$' => Value::Null,
+                None => return Err(invalid_data("Invalid length")),
+            },
+            b'*' => return self.aggregate(Kind::Array, bytes),
+            b'~' => return self.aggregate(Kind::Set, bytes),
+            b'>' => return self.aggregate(Kind::Push, bytes),
+            b'%' => return self.aggregate(Kind::Map, bytes),
+            b'|' => return self.aggregate(Kind::Attribute, bytes),
+            b'_' if bytes.is_empty() => Value::Null,
+            b',' => Value::Double(parse_double(bytes)?),
+            b'#' => match bytes {
+                b"t" => Value::Boolean(true),
+                b"f" => Value::Boolean(false),
+                _ => return Err(invalid_data("Invalid boolean")),
+            },
+            b'(' if is_big_number(bytes) => Value::BigNumber(parse_string(bytes.to_vec())?),
+            _ => return Err(invalid_data("Invalid value")),
+        };
+        Ok(Some(value))
+    }
+}
+
+impl Decoder for RespCodec {
+    type Item = Value;
+    type Error = io::Error;
+
+    fn decode(&mut self, data: &mut ByteBuf) -> io::Result<Option<Value>> {
+        loop {
+            let depth = self.stack.len();
+            let mut value = match self.value(data)? {
+                Some(value) => value,
+                None if self.stack.len() > depth => continue,
+                None => return Ok(None),
+            };
+            loop {
+                match self.stack.last_mut() {
+                    Some(aggregate) => {
+                        aggregate.values.push(value);
+                        if !aggregate.is_complete() {
+                            break;
+                        }
+                    }
+                    None => return Ok(Some(value)),
+                }
+                value = match self.stack.pop() {
+                    Some(aggregate) => aggregate.into_value(),
+                    None => ::unreachable(),
+                };
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, data: &mut ByteBuf) -> io::Result<Option<Value>> {
+        if let Some(value) = self.decode(data)? {
+            return Ok(Some(value));
+        }
+        match data.is_empty() && self.bulk.is_none() && self.stack.is_empty() {
+            true => Ok(None),
+            false => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Stream ended within a value",
+            )),
+        }
+    }
+}
+
+// Encodes into a head of small values, which is appended to `dst` before
+// each bulk string is put without copying.
+struct Writer<'a> {
+    dst: &'a mut ByteBuf,
+    head: Vec<u8>,
+    resp3: bool,
+}
+
+impl<'a> Writer<'a> {
+    fn flush(&mut self) -> io::Result<()> {
+        if self.head.is_empty() {
+            return Ok(());
+        }
+        let res = self.dst.append(&self.head[..], u8s::append);
+        self.head.clear();
+        match res {
+            Ok(..) => Ok(()),
+            Err(()) => Err(io::Error::new(io::ErrorKind::Other, "Buffer limit exceeded")),
+        }
+    }
+
+    fn line(&mut self, ty: u8, s: &str) -> io::Result<()> {
+        if s.contains(|c| c == '\r' || c == '\n') {
+            return Err(invalid_input("String contains a line end"));
+        }
+        self.head.push(ty);
+        self.head.extend_from_slice(s.as_bytes());
+        self.head.extend_from_slice(b"\r\n");
+        Ok(())
+    }
+
+    fn bulk(&mut self, ty: u8, data: ByteBuf) -> io::Result<()> {
+        self.head.push(ty);
+        self.head.extend_from_slice(data.len().to_string().as_bytes());
+        self.head.extend_from_slice(b"\r\n");
+        if !data.is_empty() {
+            self.flush()?;
+            put(self.dst, data);
+        }
+        self.head.extend_from_slice(b"\r\n");
+        Ok(())
+    }
+
+    fn values(&mut self, ty: u8, values: Vec<Value>) -> io::Result<()> {
+        self.line(ty, &values.len().to_string())?;
+        for value in values {
+            self.write(value)?;
+        }
+        Ok(())
+    }
+
+    fn pairs(&mut self, ty: u8, pairs: Vec<(Value, Value)>) -> io::Result<()> {
+        self.line(ty, &pairs.len().to_string())?;
+        for (k, v) in pairs {
+            self.write(k)?;
+            self.write(v)?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, value: Value) -> io::Result<()> {
+        match value {
+            Value::Null if self.resp3 => self.head.extend_from_slice(b"_\r\n"),
+            Value::Null => self.head.extend_from_slice(b"$-1\r\n"),
+            Value::Simple(s) => self.line(b'+', &s)?,
+            Value::Error(s) => self.line(b'-', &s)?,
+            Value::Integer(n) => self.line(b':', &n.to_string())?,
+            Value::Bulk(data) => self.bulk(b'$', data)?,
+            Value::Array(values) => self.values(b'*', values)?,
+            Value::Double(d) => {
+                let s = match d {
+                    d if d.is_nan() => "nan".to_owned(),
+                    d if d.is_infinite() && d > 0.0 => "inf".to_owned(),
+                    d if d.is_infinite() => "-inf".to_owned(),
+                    d => d.to_string(),
+                };
+                self.line(b',', &s)?
+            }
+            Value::Boolean(b) => self.line(b'#', if b { "t" } else { "f" })?,
+            Value::BigNumber(s) => match is_big_number(s.as_bytes()) {
+                true => self.line(b'(', &s)?,
+                false => return Err(invalid_input("Invalid big number")),
+            },
+            Value::BulkError(data) => self.bulk(b'!', data)?,
+            Value::Verbatim(format, text) => {
+                if format.len() != 3 || format.contains(|c| c == '\r' || c == '\n') {
+                    return Err(invalid_input("Invalid verbatim string format"));
+                }
+                self.head.push(b'=');
+                self.head.extend_from_slice((text.len() + 4).to_string().as_bytes());
+                self.head.extend_from_slice(b"\r\n");
+                self.head.extend_from_slice(format.as_bytes());
+                self.head.push(b':');
+                if !text.is_empty() {
+                    self.flush()?;
+                    put(self.dst, text);
+                }
+                self.head.extend_from_slice(b"\r\n");
+            }
+            Value::Map(pairs) => self.pairs(b'%', pairs)?,
+            Value::Set(values) => self.values(b'~', values)?,
+            Value::Attribute(pairs, value) => {
+                self.pairs(b'|', pairs)?;
+                self.write(*value)?
+            }
+            Value::Push(values) => self.values(b'>', values)?,
+        }
+        Ok(())
+    }
+}
+
+impl Encoder for RespCodec {
+    type Item = Value;
+    type Error = io::Error;
+
+    fn encode(&mut self, value: Value, dst: &mut ByteBuf) -> io::Result<()> {
+        if !self.resp3 && !value.is_resp2() {
+            return Err(invalid_input("Value not supported by RESP2"));
+        }
+        let mut writer = Writer {
+            dst,
+            head: Vec::with_capacity(64),
+            resp3: self.resp3,
+        };
+        writer.write(value)?;
+        writer.flush()
+    }
+}
@@ -0,0 +1,13 @@
+//! The Redis serialization protocol, RESP2 and RESP3.
+//!
+//! `RespCodec` frames a connection into `Value`s, over which `Client` sends
+//! commands and pipelines.
+
+mod value;
+pub use self::value::*;
+
+mod codec;
+pub use self::codec::*;
+
+mod client;
+pub use self::client::*;
@@ -0,0 +1,93 @@
+use buf::ByteBuf;
+
+/// A RESP2 or RESP3 value.
+///
+/// RESP2 has only the null, simple strings, errors, integers, bulk strings
+/// and arrays. Its null bulk string and null array both decode as `Null`.
+#[derive(Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Simple(String),
+    Error(String),
+    Integer(i64),
+    Bulk(ByteBuf),
+    Array(Vec<Value>),
+    Double(f64),
+    Boolean(bool),
+    /// The decimal digits of an integer of any size.
+    BigNumber(String),
+    BulkError(ByteBuf),
+    /// A three-letter format, e.g. `txt`, and the text.
+    Verbatim(String, ByteBuf),
+    Map(Vec<(Value, Value)>),
+    Set(Vec<Value>),
+    /// The attributes and the value they describe.
+    Attribute(Vec<(Value, Value)>, Box<Value>),
+    /// Out-of-band data, e.g. a Pub/Sub message.
+    Push(Vec<Value>),
+}
+
+impl Value {
+    /// Returns a command of bulk strings, e.g. `Value::command(&["GET", "k"])`.
+    pub fn command<I, A>(args: I) -> Self
+    where
+        I: IntoIterator<Item = A>,
+        A: AsRef<[u8]>,
+    {
+        let args = args.into_iter()
+            .map(|arg| Value::Bulk(ByteBuf::from(arg.as_ref().to_vec())))
+            .collect();
+        Value::Array(args)
+    }
+
+    #[inline]
+    pub fn is_null(&self) -> bool {
+        match *self {
+            Value::Null => true,
+            _ => false,
+        }
+    }
+
+    /// Returns whether the value is an error reply.
+    #[inline]
+    pub fn is_error(&self) -> bool {
+        match *self {
+            Value::Error(..) | Value::BulkError(..) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns whether RESP2 can represent the value.
+    pub fn is_resp2(&self) -> bool {
+        match *self {
+            Value::Null
+            | Value::Simple(..)
+            | Value::Error(..)
+            | Value::Integer(..)
+            | Value::Bulk(..) => true,
+            Value::Array(ref values) => values.iter().all(Value::is_resp2),
+            _ => false,
+        }
+    }
+}
+
+impl From<i64> for Value {
+    #[inline]
+    fn from(n: i64) -> Self {
+        Value::Integer(n)
+    }
+}
+
+impl From<ByteBuf> for Value {
+    #[inline]
+    fn from(data: ByteBuf) -> Self {
+        Value::Bulk(data)
+    }
+}
+
+impl<'a> From<&'a str> for Value {
+    #[inline]
+    fn from(s: &'a str) -> Self {
+        Value::Bulk(ByteBuf::from(s.as_bytes().to_vec()))
+    }
+}
@@ -0,0 +1,222 @@
+use std::io;
+use std::net::SocketAddr;
+
+use futures::{Async, Future, Poll};
+
+use net::TcpStream;
+use net::tcp::{self, Sender};
+use sys::net::tcp::Peek;
+
+use super::{method, Addr, AuthRequest, AuthStatus, Command, Greeting, MethodSelection, Reply,
+            Request};
+
+/// Connects through a SOCKS5 proxy.
+///
+/// Not supported on Windows, where `connect` and `udp_associate` always
+/// fail. The handshake reads the replies of the proxy without reading past
+/// them, so that none of the data from the destination is lost, which
+/// overlapped I/O cannot do.
+#[derive(Debug, Clone)]
+pub struct Connector {
+    proxy: SocketAddr,
+    auth: Option<AuthRequest>,
+}
+
+impl Connector {
+    #[inline]
+    pub fn new(proxy: SocketAddr) -> Self {
+        Connector { proxy, auth: None }
+    }
+
+    /// Authenticates with `username` and `password` if the proxy asks to.
+    #[inline]
+    pub fn auth(&mut self, username: &str, password: &str) -> &mut Self {
+        self.auth = Some(AuthRequest {
+            username: username.to_string(),
+            password: password.to_string(),
+        });
+        self
+    }
+
+    /// Connects to `addr` through the proxy, which resolves it if it is a
+    /// domain name.
+    #[inline]
+    pub fn connect<A: Into<Addr>>(&self, addr: A) -> Connect {
+        Connect {
+            inner: self.handshake(Command::Connect, addr.into()),
+        }
+    }
+
+    /// Asks the proxy to relay UDP datagrams from `addr`, or from any
+    /// address if it is unspecified. The association lasts as long as the
+    /// connection yielded with the address of the relay.
+    #[inline]
+    pub fn udp_associate<A: Into<Addr>>(&self, addr: A) -> UdpAssociate {
+        UdpAssociate {
+            inner: self.handshake(Command::UdpAssociate, addr.into()),
+        }
+    }
+
+    fn handshake(&self, command: Command, addr: Addr) -> Handshake {
+        let mut methods = vec![method::NO_AUTH];
+        if self.auth.is_some() {
+            methods.push(method::USERNAME_PASSWORD);
+        }
+        let mut out = Vec::new();
+        let mut request = Vec::new();
+        let error = Greeting { methods }
+            .encode(&mut out)
+            .and_then(|()| Request { command, addr }.encode(&mut request))
+            .err();
+        Handshake {
+            connect: Some(tcp::connect(&self.proxy)),
+            peek: None,
+            step: Step::Greeting,
+            out,
+            auth: self.auth.clone(),
+            request,
+            error,
+        }
+    }
+}
+
+/// Yields the connection to the destination.
+pub struct Connect {
+    inner: Handshake,
+}
+
+impl Future for Connect {
+    type Item = Sender<TcpStream>;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let (conn, _) = try_ready!(self.inner.poll());
+        Ok(Async::Ready(Sender::try_from(conn)?))
+    }
+}
+
+/// Yields the connection of the association and the address of the relay,
+/// which may be unspecified to mean the address of the proxy.
+pub struct UdpAssociate {
+    inner: Handshake,
+}
+
+impl Future for UdpAssociate {
+    type Item = (Sender<TcpStream>, Addr);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let (conn, relay) = try_ready!(self.inner.poll());
+        Ok(Async::Ready((Sender::try_from(conn)?, relay)))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    Greeting,
+    Method,
+    Auth,
+    AuthStatus,
+    Request,
+    Reply,
+}
+
+struct Handshake {
+    connect: Option<tcp::Connect<TcpStream>>,
+    peek: Option<Peek<TcpStream>>,
+    step: Step,
+    out: Vec<u8>,
+    auth: Option<AuthRequest>,
+    request: Vec<u8>,
+    error: Option<io::Error>,
+}
+
+#[inline]
+fn closed() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "SOCKS5 proxy closed the connection",
+    )
+}
+
+impl Future for Handshake {
+    type Item = (TcpStream, Addr);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        if self.peek.is_none() {
+            let conn = match self.connect {
+                Some(ref mut connect) => try_ready!(connect.poll()),
+                None => panic!("Attempted to poll a SOCKS5 handshake after completion"),
+            };
+            self.connect = None;
+            self.peek = Some(Peek::try_from(conn.into_inner())?);
+        }
+        let addr = loop {
+            let peek = match self.peek {
+                Some(ref mut peek) => peek,
+                None => ::unreachable(),
+            };
+            match self.step {
+                Step::Greeting => {
+                    try_ready!(peek.poll_write(&mut self.out));
+                    self.step = Step::Method;
+                }
+                Step::Method => {
+                    let selection = try_ready!(peek.poll_read(MethodSelection::parse));
+                    match selection.ok_or_else(closed)?.method {
+                        method::NO_AUTH => {
+                            self.out.extend_from_slice(&self.request);
+                            self.step = Step::Request;
+                        }
+                        method::USERNAME_PASSWORD if self.auth.is_some() => {
+                            if let Some(ref auth) = self.auth {
+                                auth.encode(&mut self.out)?;
+                            }
+                            self.step = Step::Auth;
+                        }
+                        _ => {
+                            return Err(io::Error::new(
+                                io::ErrorKind::PermissionDenied,
+                                "No acceptable SOCKS5 authentication method",
+                            ))
+                        }
+                    }
+                }
+                Step::Auth => {
+                    try_ready!(peek.poll_write(&mut self.out));
+                    self.step = Step::AuthStatus;
+                }
+                Step::AuthStatus => {
+                    let status = try_ready!(peek.poll_read(AuthStatus::parse));
+                    if status.ok_or_else(closed)?.status != 0 {
+                        return Err(io::Error::new(
+                            io::ErrorKind::PermissionDenied,
+                            "SOCKS5 authentication failed",
+                        ));
+                    }
+                    self.out.extend_from_slice(&self.request);
+                    self.step = Step::Request;
+                }
+                Step::Request => {
+                    try_ready!(peek.poll_write(&mut self.out));
+                    self.step = Step::Reply;
+                }
+                Step::Reply => {
+                    let reply = try_ready!(peek.poll_read(Reply::parse)).ok_or_else(closed)?;
+                    if let Some(e) = reply.to_error() {
+                        return Err(e);
+                    }
+                    break reply.addr;
+                }
+            }
+        };
+        match self.peek.take() {
+            Some(peek) => Ok(Async::Ready((peek.into_inner(), addr))),
+            None => ::unreachable(),
+        }
+    }
+}
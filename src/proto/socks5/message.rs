@@ -0,0 +1,398 @@
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str;
+
+pub const VERSION: u8 = 5;
+
+/// The version of the username/password authentication (RFC 1929).
+pub const AUTH_VERSION: u8 = 1;
+
+/// Authentication methods.
+pub mod method {
+    pub const NO_AUTH: u8 = 0x00;
+    pub const GSSAPI: u8 = 0x01;
+    pub const USERNAME_PASSWORD: u8 = 0x02;
+    pub const NO_ACCEPTABLE: u8 = 0xFF;
+}
+
+/// Reply codes of a request.
+pub mod reply {
+    pub const SUCCEEDED: u8 = 0x00;
+    pub const GENERAL_FAILURE: u8 = 0x01;
+    pub const NOT_ALLOWED: u8 = 0x02;
+    pub const NETWORK_UNREACHABLE: u8 = 0x03;
+    pub const HOST_UNREACHABLE: u8 = 0x04;
+    pub const CONNECTION_REFUSED: u8 = 0x05;
+    pub const TTL_EXPIRED: u8 = 0x06;
+    pub const COMMAND_NOT_SUPPORTED: u8 = 0x07;
+    pub const ADDRESS_TYPE_NOT_SUPPORTED: u8 = 0x08;
+}
+
+#[inline]
+pub(super) fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[inline]
+fn invalid_input(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+#[inline]
+fn be16(b: &[u8]) -> u16 {
+    (b[0] as u16) << 8 | b[1] as u16
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Connect = 1,
+    Bind = 2,
+    UdpAssociate = 3,
+}
+
+/// The address of a request or reply.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Addr {
+    Ip(SocketAddr),
+    /// A domain name and port, resolved by the proxy.
+    Domain(String, u16),
+}
+
+impl Addr {
+    #[inline]
+    pub fn port(&self) -> u16 {
+        match *self {
+            Addr::Ip(ref addr) => addr.port(),
+            Addr::Domain(_, port) => port,
+        }
+    }
+
+    /// Parses the address at the start of `data`, and returns it with its
+    /// length, or `None` if `data` holds only part of it.
+    pub fn parse(data: &[u8]) -> io::Result<Option<(Addr, usize)>> {
+        let len = match data.first() {
+            None => return Ok(None),
+            Some(&0x01) => 1 + 4 + 2,
+            Some(&0x03) => match data.get(1) {
+                Some(&n) => 2 + n as usize + 2,
+                None => return Ok(None),
+            },
+            Some(&0x04) => 1 + 16 + 2,
+            Some(..) => return Err(invalid_data("Unsupported SOCKS5 address type")),
+        };
+        if data.len() < len {
+            return Ok(None);
+        }
+        let port = be16(&data[len - 2..]);
+        let addr = match data[0] {
+            0x01 => Addr::Ip(SocketAddr::new(
+                IpAddr::V4(Ipv4Addr::new(data[1], data[2], data[3], data[4])),
+                port,
+            )),
+            0x03 => match str::from_utf8(&data[2..len - 2]) {
+                Ok(domain) => Addr::Domain(domain.to_string(), port),
+                Err(..) => return Err(invalid_data("Invalid SOCKS5 domain name")),
+            },
+            _ => {
+                let mut ip = [0; 16];
+                ip.copy_from_slice(&data[1..17]);
+                Addr::Ip(SocketAddr::new(IpAddr::V6(Ipv6Addr::from(ip)), port))
+            }
+        };
+        Ok(Some((addr, len)))
+    }
+
+    pub fn encode(&self, buf: &mut Vec<u8>) -> io::Result<()> {
+        match *self {
+            Addr::Ip(SocketAddr::V4(ref addr)) => {
+                buf.push(0x01);
+                buf.extend_from_slice(&addr.ip().octets());
+            }
+            Addr::Ip(SocketAddr::V6(ref addr)) => {
+                buf.push(0x04);
+                buf.extend_from_slice(&addr.ip().octets());
+            }
+            Addr::Domain(ref domain, _) => {
+                if domain.is_empty() || domain.len() > 255 {
+                    return Err(invalid_input("Invalid SOCKS5 domain name"));
+                }
+                buf.push(0x03);
+                buf.push(domain.len() as u8);
+                buf.extend_from_slice(domain.as_bytes());
+            }
+        }
+        let port = self.port();
+        buf.push((port >> 8) as u8);
+        buf.push(port as u8);
+        Ok(())
+    }
+}
+
+impl From<SocketAddr> for Addr {
+    #[inline]
+    fn from(addr: SocketAddr) -> Self {
+        Addr::Ip(addr)
+    }
+}
+
+impl fmt::Display for Addr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Addr::Ip(ref addr) => addr.fmt(f),
+            Addr::Domain(ref domain, port) => write!(f, "{}:{}", domain, port),
+        }
+    }
+}
+
+#[inline]
+fn check_version(data: &[u8], version: u8) -> io::Result<()> {
+    match data.first() {
+        Some(&v) if v != version => Err(invalid_data("Unsupported SOCKS version")),
+        _ => Ok(()),
+    }
+}
+
+/// The methods of authentication a client supports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Greeting {
+    pub methods: Vec<u8>,
+}
+
+impl Greeting {
+    pub fn parse(data: &[u8]) -> io::Result<Option<(Self, usize)>> {
+        check_version(data, VERSION)?;
+        let len = match data.get(1) {
+            Some(&0) => return Err(invalid_data("No SOCKS5 authentication methods")),
+            Some(&n) => 2 + n as usize,
+            None => return Ok(None),
+        };
+        if data.len() < len {
+            return Ok(None);
+        }
+        let greeting = Greeting {
+            methods: data[2..len].to_vec(),
+        };
+        Ok(Some((greeting, len)))
+    }
+
+    pub fn encode(&self, buf: &mut Vec<u8>) -> io::Result<()> {
+        if self.methods.is_empty() || self.methods.len() > 255 {
+            return Err(invalid_input("Invalid number of SOCKS5 authentication methods"));
+        }
+        buf.push(VERSION);
+        buf.push(self.methods.len() as u8);
+        buf.extend_from_slice(&self.methods);
+        Ok(())
+    }
+}
+
+/// The method of authentication the server selects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MethodSelection {
+    pub method: u8,
+}
+
+impl MethodSelection {
+    pub fn parse(data: &[u8]) -> io::Result<Option<(Self, usize)>> {
+        check_version(data, VERSION)?;
+        match data.get(1) {
+            Some(&method) => Ok(Some((MethodSelection { method }, 2))),
+            None => Ok(None),
+        }
+    }
+
+    #[inline]
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(VERSION);
+        buf.push(self.method);
+    }
+}
+
+/// The credentials of the username/password authentication.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthRequest {
+    pub username: String,
+    pub password: String,
+}
+
+impl AuthRequest {
+    pub fn parse(data: &[u8]) -> io::Result<Option<(Self, usize)>> {
+        check_version(data, AUTH_VERSION)?;
+        let ulen = match data.get(1) {
+            Some(&n) => n as usize,
+            None => return Ok(None),
+        };
+        let plen = match data.get(2 + ulen) {
+            Some(&n) => n as usize,
+            None => return Ok(None),
+        };
+        let len = 3 + ulen + plen;
+        if data.len() < len {
+            return Ok(None);
+        }
+        let username = str::from_utf8(&data[2..2 + ulen]);
+        let password = str::from_utf8(&data[3 + ulen..len]);
+        match (username, password) {
+            (Ok(username), Ok(password)) => {
+                let auth = AuthRequest {
+                    username: username.to_string(),
+                    password: password.to_string(),
+                };
+                Ok(Some((auth, len)))
+            }
+            _ => Err(invalid_data("Invalid SOCKS5 credentials")),
+        }
+    }
+
+    pub fn encode(&self, buf: &mut Vec<u8>) -> io::Result<()> {
+        if self.username.is_empty() || self.username.len() > 255 || self.password.len() > 255 {
+            return Err(invalid_input("Invalid SOCKS5 credentials"));
+        }
+        buf.push(AUTH_VERSION);
+        buf.push(self.username.len() as u8);
+        buf.extend_from_slice(self.username.as_bytes());
+        buf.push(self.password.len() as u8);
+        buf.extend_from_slice(self.password.as_bytes());
+        Ok(())
+    }
+}
+
+/// The result of the username/password authentication, where 0 is success.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthStatus {
+    pub status: u8,
+}
+
+impl AuthStatus {
+    pub fn parse(data: &[u8]) -> io::Result<Option<(Self, usize)>> {
+        check_version(data, AUTH_VERSION)?;
+        match data.get(1) {
+            Some(&status) => Ok(Some((AuthStatus { status }, 2))),
+            None => Ok(None),
+        }
+    }
+
+    #[inline]
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(AUTH_VERSION);
+        buf.push(self.status);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub command: Command,
+    pub addr: Addr,
+}
+
+impl Request {
+    /// Parses the request at the start of `data`. Unsupported commands and
+    /// address types are `InvalidData` errors, to be answered with
+    /// `reply::COMMAND_NOT_SUPPORTED` and `reply::ADDRESS_TYPE_NOT_SUPPORTED`.
+    pub fn parse(data: &[u8]) -> io::Result<Option<(Self, usize)>> {
+        check_version(data, VERSION)?;
+        let command = match data.get(1) {
+            Some(&1) => Command::Connect,
+            Some(&2) => Command::Bind,
+            Some(&3) => Command::UdpAssociate,
+            Some(..) => return Err(invalid_data("Unsupported SOCKS5 command")),
+            None => return Ok(None),
+        };
+        if data.len() < 3 {
+            return Ok(None);
+        }
+        match Addr::parse(&data[3..])? {
+            Some((addr, len)) => Ok(Some((Request { command, addr }, 3 + len))),
+            None => Ok(None),
+        }
+    }
+
+    pub fn encode(&self, buf: &mut Vec<u8>) -> io::Result<()> {
+        buf.extend_from_slice(&[VERSION, self.command as u8, 0]);
+        self.addr.encode(buf)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reply {
+    /// One of the codes in `reply`.
+    pub reply: u8,
+    /// The address the server bound, e.g. of its connection to the
+    /// destination, or of its relay for UDP ASSOCIATE.
+    pub addr: Addr,
+}
+
+impl Reply {
+    /// A reply of `reply` with an unspecified address, e.g. of a failure.
+    #[inline]
+    pub fn new(reply: u8) -> Self {
+        Reply {
+            reply,
+            addr: Addr::Ip(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 0)),
+        }
+    }
+
+    pub fn parse(data: &[u8]) -> io::Result<Option<(Self, usize)>> {
+        check_version(data, VERSION)?;
+        if data.len() < 3 {
+            return Ok(None);
+        }
+        match Addr::parse(&data[3..])? {
+            Some((addr, len)) => Ok(Some((
+                Reply {
+                    reply: data[1],
+                    addr,
+                },
+                3 + len,
+            ))),
+            None => Ok(None),
+        }
+    }
+
+    pub fn encode(&self, buf: &mut Vec<u8>) -> io::Result<()> {
+        buf.extend_from_slice(&[VERSION, self.reply, 0]);
+        self.addr.encode(buf)
+    }
+
+    /// Returns the error of a failure reply.
+    pub fn to_error(&self) -> Option<io::Error> {
+        let (kind, msg) = match self.reply {
+            reply::SUCCEEDED => return None,
+            reply::NOT_ALLOWED => (io::ErrorKind::PermissionDenied, "Not allowed by ruleset"),
+            reply::NETWORK_UNREACHABLE => (io::ErrorKind::Other, "Network unreachable"),
+            reply::HOST_UNREACHABLE => (io::ErrorKind::Other, "Host unreachable"),
+            reply::CONNECTION_REFUSED => (io::ErrorKind::ConnectionRefused, "Connection refused"),
+            reply::TTL_EXPIRED => (io::ErrorKind::TimedOut, "TTL expired"),
+            reply::COMMAND_NOT_SUPPORTED => (io::ErrorKind::Other, "Command not supported"),
+            reply::ADDRESS_TYPE_NOT_SUPPORTED => {
+                (io::ErrorKind::Other, "Address type not supported")
+            }
+            _ => (io::ErrorKind::Other, "General SOCKS server failure"),
+        };
+        Some(io::Error::new(kind, msg))
+    }
+}
+
+/// Parses the header of a UDP datagram relayed by the server, and returns
+/// the address and the data. Fragments are not supported.
+pub fn parse_udp(datagram: &[u8]) -> io::Result<(Addr, &[u8])> {
+    if datagram.len() < 4 || datagram[..2] != [0, 0] {
+        return Err(invalid_data("Invalid SOCKS5 UDP header"));
+    }
+    if datagram[2] != 0 {
+        return Err(invalid_data("SOCKS5 UDP fragments are not supported"));
+    }
+    match Addr::parse(&datagram[3..])? {
+        Some((addr, len)) => Ok((addr, &datagram[3 + len..])),
+        None => Err(invalid_data("Invalid SOCKS5 UDP header")),
+    }
+}
+
+/// Encodes a UDP datagram of `data` to `addr` to be relayed by the server.
+pub fn encode_udp(addr: &Addr, data: &[u8], buf: &mut Vec<u8>) -> io::Result<()> {
+    buf.extend_from_slice(&[0, 0, 0]);
+    addr.encode(buf)?;
+    buf.extend_from_slice(data);
+    Ok(())
+}
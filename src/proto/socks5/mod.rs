@@ -0,0 +1,12 @@
+//! SOCKS Protocol Version 5 (RFC 1928) with the username/password
+//! authentication (RFC 1929).
+//!
+//! `Connector` connects through a proxy with CONNECT or UDP ASSOCIATE. The
+//! messages of the handshake parse from and encode to bytes for servers,
+//! e.g. `service::socks5`.
+
+mod message;
+pub use self::message::*;
+
+mod client;
+pub use self::client::*;
@@ -0,0 +1,287 @@
+use std::io;
+
+use buf::ByteBuf;
+use buf::codec::u8s;
+use proto::{frame_too_long, max_frame_len, prefix, put, random, Decoder, Encoder};
+
+// The longest header: 2 bytes, a 64-bit length and a masking key.
+const MAX_HEAD_LEN: usize = 14;
+
+// The longest payload of a control frame.
+const MAX_CONTROL_LEN: usize = 125;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpCode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+/// A frame without its masking, which the codec applies.
+#[derive(Debug)]
+pub struct Frame {
+    pub fin: bool,
+    pub opcode: OpCode,
+    pub payload: ByteBuf,
+}
+
+/// Which end of the connection a codec is for. Clients mask the frames they
+/// send, and servers require them to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Client,
+    Server,
+}
+
+// The header length, opcode, FIN, payload length and masking key of a frame.
+type Head = (usize, OpCode, bool, usize, Option<[u8; 4]>);
+
+/// Decodes and encodes WebSocket frames.
+///
+/// No extension is supported, so frames with reserved bits set are invalid.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameCodec {
+    role: Role,
+    max_frame_len: usize,
+}
+
+#[inline]
+pub(crate) fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[inline]
+fn invalid_input(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+// XORs `data` with `key`, as both masking and unmasking do.
+fn apply_mask(data: &mut ByteBuf, key: [u8; 4]) {
+    let mut i = 0;
+    for bytes in data.as_mut_slices() {
+        for b in bytes {
+            *b ^= key[i & 3];
+            i += 1;
+        }
+    }
+}
+
+fn mask_key() -> io::Result<[u8; 4]> {
+    let mut key = [0u8; 4];
+    random::fill(&mut key)?;
+    Ok(key)
+}
+
+impl OpCode {
+    #[inline]
+    fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            0 => Some(OpCode::Continuation),
+            1 => Some(OpCode::Text),
+            2 => Some(OpCode::Binary),
+            8 => Some(OpCode::Close),
+            9 => Some(OpCode::Ping),
+            10 => Some(OpCode::Pong),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn as_u8(&self) -> u8 {
+        match *self {
+            OpCode::Continuation => 0,
+            OpCode::Text => 1,
+            OpCode::Binary => 2,
+            OpCode::Close => 8,
+            OpCode::Ping => 9,
+            OpCode::Pong => 10,
+        }
+    }
+
+    /// Returns whether frames of this opcode are control frames, which
+    /// cannot be fragmented and carry at most 125 bytes.
+    #[inline]
+    pub fn is_control(&self) -> bool {
+        match *self {
+            OpCode::Close | OpCode::Ping | OpCode::Pong => true,
+            _ => false,
+        }
+    }
+}
+
+impl Frame {
+    /// Creates a final frame.
+    #[inline]
+    pub fn new(opcode: OpCode, payload: ByteBuf) -> Self {
+        Frame {
+            fin: true,
+            opcode,
+            payload,
+        }
+    }
+}
+
+impl FrameCodec {
+    #[inline]
+    pub fn new(role: Role) -> Self {
+        FrameCodec {
+            role,
+            max_frame_len: max_frame_len(),
+        }
+    }
+
+    #[inline]
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    #[inline]
+    pub fn max_frame_len(&self) -> usize {
+        self.max_frame_len
+    }
+
+    /// Sets the maximum payload length of a frame. Longer frames fail with
+    /// `FrameTooLong`.
+    #[inline]
+    pub fn set_max_frame_len(&mut self, max_frame_len: usize) -> &mut Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+
+    // Returns the length of the header, the opcode, FIN, the payload length
+    // and the masking key, or `None` if `data` does not hold all of them.
+    fn head(&self, data: &ByteBuf) -> io::Result<Option<Head>> {
+        let mut bytes = data.bytes();
+        macro_rules! next {
+            () => {
+                match bytes.next() {
+                    Some(b) => b,
+                    None => return Ok(None),
+                }
+            };
+        }
+        let b0 = next!();
+        let b1 = next!();
+        if b0 & 0x70 != 0 {
+            return Err(invalid_data("Reserved bits set"));
+        }
+        let opcode = match OpCode::from_u8(b0 & 0x0F) {
+            Some(opcode) => opcode,
+            None => return Err(invalid_data("Unknown opcode")),
+        };
+        let fin = b0 & 0x80 != 0;
+        let masked = b1 & 0x80 != 0;
+        match (self.role, masked) {
+            (Role::Server, false) => return Err(invalid_data("Unmasked frame from a client")),
+            (Role::Client, true) => return Err(invalid_data("Masked frame from a server")),
+            _ => (),
+        }
+        let (len, mut head) = match b1 & 0x7F {
+            126 => ((next!() as u64) << 8 | next!() as u64, 4),
+            127 => {
+                let mut len = 0u64;
+                for _ in 0..8 {
+                    len = len << 8 | next!() as u64;
+                }
+                if len >> 63 != 0 {
+                    return Err(invalid_data("Invalid payload length"));
+                }
+                (len, 10)
+            }
+            n => (n as u64, 2),
+        };
+        if opcode.is_control() && (!fin || len > MAX_CONTROL_LEN as u64) {
+            return Err(invalid_data("Invalid control frame"));
+        }
+        if len > self.max_frame_len as u64 {
+            let len = if len > usize::max_value() as u64 { usize::max_value() } else { len as usize };
+            return Err(frame_too_long(len, self.max_frame_len));
+        }
+        let key = match masked {
+            true => {
+                head += 4;
+                Some([next!(), next!(), next!(), next!()])
+            }
+            false => None,
+        };
+        Ok(Some((head, opcode, fin, len as usize, key)))
+    }
+}
+
+impl Decoder for FrameCodec {
+    type Item = Frame;
+    type Error = io::Error;
+
+    fn decode(&mut self, data: &mut ByteBuf) -> io::Result<Option<Frame>> {
+        let (head, opcode, fin, len, key) = match self.head(data)? {
+            Some(t) => t,
+            None => return Ok(None),
+        };
+        if data.len() < head + len {
+            return Ok(None);
+        }
+        data.skip(head);
+        let mut payload = match len {
+            0 => ByteBuf::new(),
+            _ => match data.drain_to(len) {
+                Ok(payload) => payload,
+                _ => ::unreachable(),
+            },
+        };
+        if let Some(key) = key {
+            apply_mask(&mut payload, key);
+        }
+        Ok(Some(Frame {
+            fin,
+            opcode,
+            payload,
+        }))
+    }
+}
+
+/// Masks the payload in place if the codec is for a client.
+impl Encoder for FrameCodec {
+    type Item = Frame;
+    type Error = io::Error;
+
+    fn encode(&mut self, frame: Frame, dst: &mut ByteBuf) -> io::Result<()> {
+        let mut payload = frame.payload;
+        let len = payload.len();
+        if frame.opcode.is_control() && (!frame.fin || len > MAX_CONTROL_LEN) {
+            return Err(invalid_input("Invalid control frame"));
+        }
+        if len > self.max_frame_len {
+            return Err(frame_too_long(len, self.max_frame_len));
+        }
+
+        let mut head = [0u8; MAX_HEAD_LEN];
+        head[0] = frame.opcode.as_u8() | if frame.fin { 0x80 } else { 0 };
+        let mut n = 2;
+        if len < 126 {
+            head[1] = len as u8;
+        } else if len <= 0xFFFF {
+            head[1] = 126;
+            head[2] = (len >> 8) as u8;
+            head[3] = len as u8;
+            n = 4;
+        } else {
+            head[1] = 127;
+            for i in 0..8 {
+                head[2 + i] = ((len as u64) >> (8 * (7 - i))) as u8;
+            }
+            n = 10;
+        }
+        if self.role == Role::Client {
+            let key = mask_key()?;
+            head[1] |= 0x80;
+            head[n..n + 4].copy_from_slice(&key);
+            n += 4;
+            apply_mask(&mut payload, key);
+        }
+        put(dst, prefix(payload, &head[..n], n, u8s::prepend));
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+use std::io;
+
+use buf::ByteBuf;
+use buf::codec::base64;
+use proto::http1::{Request, Response};
+use proto::random;
+
+use super::frame::invalid_data;
+
+/// Appended to `Sec-WebSocket-Key` to derive `Sec-WebSocket-Accept`.
+pub const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+// SHA-1, which the handshake requires, over a message short enough to be
+// hashed at once.
+fn sha1(msg: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x6745_2301, 0xEFCD_AB89, 0x98BA_DCFE, 0x1032_5476, 0xC3D2_E1F0];
+    let mut data = msg.to_vec();
+    data.push(0x80);
+    while data.len() % 64 != 56 {
+        data.push(0);
+    }
+    let bits = (msg.len() as u64).wrapping_mul(8);
+    for i in 0..8 {
+        data.push((bits >> (56 - 8 * i)) as u8);
+    }
+    for block in data.chunks(64) {
+        let mut w = [0u32; 80];
+        for i in 0..16 {
+            w[i] = (block[4 * i] as u32) << 24 | (block[4 * i + 1] as u32) << 16
+                | (block[4 * i + 2] as u32) << 8 | block[4 * i + 3] as u32;
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let (mut a, mut b, mut c, mut d, mut e) = (h[0], h[1], h[2], h[3], h[4]);
+        for (i, &wi) in w.iter().enumerate() {
+            let (f, k) = if i < 20 {
+                ((b & c) | (!b & d), 0x5A82_7999)
+            } else if i < 40 {
+                (b ^ c ^ d, 0x6ED9_EBA1)
+            } else if i < 60 {
+                ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC)
+            } else {
+                (b ^ c ^ d, 0xCA62_C1D6)
+            };
+            let t = a.rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(wi);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+        h[0] = h[0].wrapping_add(a);
+        h[1] = h[1].wrapping_add(b);
+        h[2] = h[2].wrapping_add(c);
+        h[3] = h[3].wrapping_add(d);
+        h[4] = h[4].wrapping_add(e);
+    }
+    let mut digest = [0u8; 20];
+    for (i, v) in h.iter().enumerate() {
+        for j in 0..4 {
+            digest[4 * i + j] = (v >> (24 - 8 * j)) as u8;
+        }
+    }
+    digest
+}
+
+fn to_base64(bytes: &[u8]) -> String {
+    let mut buf = ByteBuf::with_capacity(bytes.len() * 4 / 3 + 4);
+    match buf.append(bytes, base64::standard::append) {
+        Ok(..) => String::from_utf8_lossy(&buf.as_bytes()).into_owned(),
+        _ => ::unreachable(),
+    }
+}
+
+/// Returns the `Sec-WebSocket-Accept` value for `key`.
+pub fn accept_key(key: &str) -> String {
+    let mut msg = key.trim().as_bytes().to_vec();
+    msg.extend_from_slice(GUID.as_bytes());
+    to_base64(&sha1(&msg))
+}
+
+/// Returns whether `request` asks to upgrade to WebSocket.
+pub fn is_upgrade(request: &Request) -> bool {
+    request.method == "GET" && request.headers.has_token("Connection", "upgrade")
+        && request.headers.has_token("Upgrade", "websocket")
+}
+
+/// Returns the `101 Switching Protocols` response accepting `request`, or
+/// an `InvalidData` error if it is not a valid WebSocket upgrade.
+pub fn accept_response(request: &Request) -> io::Result<Response> {
+    if !is_upgrade(request) {
+        return Err(invalid_data("Not a WebSocket upgrade"));
+    }
+    if request.headers.get("Sec-WebSocket-Version") != Some("13") {
+        return Err(invalid_data("Unsupported WebSocket version"));
+    }
+    let key = match request.headers.get("Sec-WebSocket-Key") {
+        Some(key) => key,
+        None => return Err(invalid_data("Missing Sec-WebSocket-Key")),
+    };
+    let mut response = Response::new(101);
+    response
+        .headers
+        .insert("Upgrade", "websocket")
+        .insert("Connection", "Upgrade")
+        .insert("Sec-WebSocket-Accept", accept_key(key));
+    Ok(response)
+}
+
+/// Returns a request to upgrade the connection to `host` to WebSocket at
+/// `target`, and the key its response must accept.
+///
+/// # Panics
+///
+/// Panics if the random number generator of the OS fails.
+pub fn upgrade_request(host: &str, target: &str) -> (Request, String) {
+    let mut nonce = [0u8; 16];
+    random::fill(&mut nonce).expect("Failed to generate a WebSocket key");
+    let key = to_base64(&nonce);
+    let mut request = Request::new("GET", target);
+    request
+        .headers
+        .insert("Host", host)
+        .insert("Upgrade", "websocket")
+        .insert("Connection", "Upgrade")
+        .insert("Sec-WebSocket-Key", key.as_str())
+        .insert("Sec-WebSocket-Version", "13");
+    (request, key)
+}
+
+/// Checks that `response` accepts the upgrade request sent with `key`.
+pub fn check_response(response: &Response, key: &str) -> io::Result<()> {
+    if response.status != 101 {
+        return Err(invalid_data("WebSocket upgrade refused"));
+    }
+    if !response.headers.has_token("Upgrade", "websocket")
+        || !response.headers.has_token("Connection", "upgrade")
+    {
+        return Err(invalid_data("Not a WebSocket upgrade"));
+    }
+    match response.headers.get("Sec-WebSocket-Accept") {
+        Some(accept) if accept == accept_key(key) => Ok(()),
+        _ => Err(invalid_data("Invalid Sec-WebSocket-Accept")),
+    }
+}
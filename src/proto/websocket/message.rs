@@ -0,0 +1,41 @@
+use buf::ByteBuf;
+
+/// Status codes of close frames.
+pub mod close_code {
+    pub const NORMAL: u16 = 1000;
+    pub const GOING_AWAY: u16 = 1001;
+    pub const PROTOCOL_ERROR: u16 = 1002;
+    pub const UNSUPPORTED_DATA: u16 = 1003;
+    pub const INVALID_DATA: u16 = 1007;
+    pub const POLICY_VIOLATION: u16 = 1008;
+    pub const TOO_BIG: u16 = 1009;
+    pub const MANDATORY_EXTENSION: u16 = 1010;
+    pub const INTERNAL_ERROR: u16 = 1011;
+
+    /// Returns whether `code` may be sent in a close frame.
+    #[inline]
+    pub fn is_valid(code: u16) -> bool {
+        match code {
+            1000..=1003 | 1007..=1011 | 3000..=4999 => true,
+            _ => false,
+        }
+    }
+}
+
+/// The status code and reason of a close frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloseFrame {
+    pub code: u16,
+    pub reason: String,
+}
+
+/// A whole WebSocket message, reassembled from its fragments.
+#[derive(Debug)]
+pub enum Message {
+    Text(String),
+    Binary(ByteBuf),
+    Ping(ByteBuf),
+    Pong(ByteBuf),
+    /// A close frame, without a body if `None`.
+    Close(Option<CloseFrame>),
+}
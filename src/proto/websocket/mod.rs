@@ -0,0 +1,20 @@
+//! WebSocket (RFC 6455) handshake, frames and messages.
+//!
+//! `accept` and `connect` perform the handshake over a connection, e.g. the
+//! halves of `net::tcp::split` joined by `Duplex`, and yield a `WebSocket`
+//! of messages. `FrameCodec` works at the level of frames instead.
+
+mod frame;
+pub use self::frame::{Frame, FrameCodec, OpCode, Role};
+
+mod handshake;
+pub use self::handshake::*;
+
+mod message;
+pub use self::message::*;
+
+mod socket;
+pub use self::socket::*;
+
+mod upgrade;
+pub use self::upgrade::*;
@@ -0,0 +1,325 @@
+use std::collections::VecDeque;
+use std::io;
+use std::str;
+
+use futures::{Async, AsyncSink, Poll, Sink, StartSend, Stream};
+
+use buf::ByteBuf;
+use proto::{frame_too_long, max_frame_len, put, Framed};
+
+use super::{close_code, CloseFrame, Frame, FrameCodec, Message, OpCode, Role};
+use super::frame::invalid_data;
+
+/// A `Stream` and `Sink` of messages over the frames of `T`.
+///
+/// Pings are answered and the closing handshake is completed automatically,
+/// while still being yielded to the application. The stream ends once a
+/// close frame has been received and answered.
+pub struct WebSocket<T> {
+    framed: Framed<T, FrameCodec>,
+    // The opcode and data so far of a fragmented message.
+    partial: Option<(OpCode, ByteBuf)>,
+    max_message_len: usize,
+    max_fragment_len: usize,
+    // Frames to send, replies included, in order.
+    out: VecDeque<Frame>,
+    close_sent: bool,
+    close_received: bool,
+}
+
+#[inline]
+fn text(data: ByteBuf) -> io::Result<String> {
+    String::from_utf8(data.as_bytes().into_owned()).map_err(|_| invalid_data("Invalid UTF-8 text"))
+}
+
+fn parse_close(payload: ByteBuf) -> io::Result<Option<CloseFrame>> {
+    let payload = payload.as_bytes();
+    match payload.len() {
+        0 => return Ok(None),
+        1 => return Err(invalid_data("Invalid close frame")),
+        _ => (),
+    }
+    let code = (payload[0] as u16) << 8 | payload[1] as u16;
+    if !close_code::is_valid(code) {
+        return Err(invalid_data("Invalid close code"));
+    }
+    let reason = str::from_utf8(&payload[2..]).map_err(|_| invalid_data("Invalid UTF-8 text"))?;
+    Ok(Some(CloseFrame {
+        code,
+        reason: reason.to_owned(),
+    }))
+}
+
+fn close_payload(close: Option<CloseFrame>) -> ByteBuf {
+    match close {
+        Some(close) => {
+            let mut payload = vec![(close.code >> 8) as u8, close.code as u8];
+            payload.extend_from_slice(close.reason.as_bytes());
+            ByteBuf::from(payload)
+        }
+        None => ByteBuf::new(),
+    }
+}
+
+impl<T> WebSocket<T> {
+    #[inline]
+    pub fn new(io: T, role: Role) -> Self {
+        Self::from_framed(Framed::new(io, FrameCodec::new(role)))
+    }
+
+    /// Creates a `WebSocket` over frames, e.g. with data already received
+    /// after the handshake.
+    #[inline]
+    pub fn from_framed(framed: Framed<T, FrameCodec>) -> Self {
+        WebSocket {
+            framed,
+            partial: None,
+            max_message_len: max_frame_len(),
+            max_fragment_len: usize::max_value(),
+            out: VecDeque::new(),
+            close_sent: false,
+            close_received: false,
+        }
+    }
+
+    #[inline]
+    pub fn max_message_len(&self) -> usize {
+        self.max_message_len
+    }
+
+    /// Sets the maximum length of a reassembled message. Longer messages fail
+    /// with `FrameTooLong`.
+    #[inline]
+    pub fn set_max_message_len(&mut self, max_message_len: usize) -> &mut Self {
+        self.max_message_len = max_message_len;
+        self
+    }
+
+    #[inline]
+    pub fn max_fragment_len(&self) -> usize {
+        self.max_fragment_len
+    }
+
+    /// Sets the maximum payload length of the frames of messages sent, which
+    /// are fragmented if longer. By default messages are not fragmented.
+    #[inline]
+    pub fn set_max_fragment_len(&mut self, max_fragment_len: usize) -> &mut Self {
+        if max_fragment_len > 0 {
+            self.max_fragment_len = max_fragment_len;
+        }
+        self
+    }
+
+    #[inline]
+    pub fn codec_mut(&mut self) -> &mut FrameCodec {
+        self.framed.codec_mut()
+    }
+
+    #[inline]
+    pub fn get_ref(&self) -> &T {
+        self.framed.get_ref()
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.framed.get_mut()
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.framed.into_inner()
+    }
+
+    #[inline]
+    fn message(&self, opcode: OpCode, data: ByteBuf) -> io::Result<Message> {
+        match opcode {
+            OpCode::Text => Ok(Message::Text(text(data)?)),
+            _ => Ok(Message::Binary(data)),
+        }
+    }
+
+    #[inline]
+    fn check_len(&self, len: usize) -> io::Result<()> {
+        match len > self.max_message_len {
+            true => Err(frame_too_long(len, self.max_message_len)),
+            false => Ok(()),
+        }
+    }
+
+    // Returns the message completed by `frame`, if any.
+    fn on_frame(&mut self, frame: Frame) -> io::Result<Option<Message>> {
+        match frame.opcode {
+            OpCode::Ping => {
+                let ping = ByteBuf::from(frame.payload.as_bytes().into_owned());
+                if !self.close_sent {
+                    self.out.push_back(Frame::new(OpCode::Pong, frame.payload));
+                }
+                Ok(Some(Message::Ping(ping)))
+            }
+            OpCode::Pong => Ok(Some(Message::Pong(frame.payload))),
+            OpCode::Close => {
+                let close = parse_close(frame.payload)?;
+                self.close_received = true;
+                if !self.close_sent {
+                    self.close_sent = true;
+                    let code = close.as_ref().map(|close| CloseFrame {
+                        code: close.code,
+                        reason: String::new(),
+                    });
+                    self.out.push_back(Frame::new(OpCode::Close, close_payload(code)));
+                }
+                Ok(Some(Message::Close(close)))
+            }
+            OpCode::Continuation => {
+                let (opcode, mut data) = match self.partial.take() {
+                    Some(partial) => partial,
+                    None => return Err(invalid_data("Unexpected continuation frame")),
+                };
+                self.check_len(data.len() + frame.payload.len())?;
+                put(&mut data, frame.payload);
+                match frame.fin {
+                    true => self.message(opcode, data).map(Some),
+                    false => {
+                        self.partial = Some((opcode, data));
+                        Ok(None)
+                    }
+                }
+            }
+            opcode => {
+                if self.partial.is_some() {
+                    return Err(invalid_data("Expected a continuation frame"));
+                }
+                self.check_len(frame.payload.len())?;
+                match frame.fin {
+                    true => self.message(opcode, frame.payload).map(Some),
+                    false => {
+                        self.partial = Some((opcode, frame.payload));
+                        Ok(None)
+                    }
+                }
+            }
+        }
+    }
+
+    // Queues the frames of `msg`.
+    fn queue(&mut self, msg: Message) -> io::Result<()> {
+        let (opcode, mut payload) = match msg {
+            Message::Text(s) => (OpCode::Text, ByteBuf::from(s.into_bytes())),
+            Message::Binary(data) => (OpCode::Binary, data),
+            Message::Ping(data) => (OpCode::Ping, data),
+            Message::Pong(data) => (OpCode::Pong, data),
+            Message::Close(close) => {
+                if let Some(ref close) = close {
+                    if !close_code::is_valid(close.code) {
+                        return Err(io::Error::new(io::ErrorKind::InvalidInput, "Invalid close code"));
+                    }
+                }
+                self.close_sent = true;
+                (OpCode::Close, close_payload(close))
+            }
+        };
+        let mut opcode = opcode;
+        if !opcode.is_control() {
+            while payload.len() > self.max_fragment_len {
+                let fragment = match payload.drain_to(self.max_fragment_len) {
+                    Ok(fragment) => fragment,
+                    _ => ::unreachable(),
+                };
+                self.out.push_back(Frame {
+                    fin: false,
+                    opcode,
+                    payload: fragment,
+                });
+                opcode = OpCode::Continuation;
+            }
+        }
+        self.out.push_back(Frame::new(opcode, payload));
+        Ok(())
+    }
+}
+
+impl<T> WebSocket<T>
+where
+    T: Sink<SinkItem = ByteBuf, SinkError = io::Error>,
+{
+    fn poll_flush(&mut self) -> Poll<(), io::Error> {
+        while let Some(frame) = self.out.pop_front() {
+            if let AsyncSink::NotReady(frame) = self.framed.start_send(frame)? {
+                self.out.push_front(frame);
+                try_ready!(self.framed.poll_complete());
+            }
+        }
+        Ok(Async::Ready(()))
+    }
+}
+
+impl<T> Stream for WebSocket<T>
+where
+    T: Stream<Item = ByteBuf, Error = io::Error> + Sink<SinkItem = ByteBuf, SinkError = io::Error>,
+{
+    type Item = Message;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            // Replies go out even if the application only reads.
+            if !self.out.is_empty() && self.poll_flush()?.is_ready() {
+                self.framed.poll_complete()?;
+            }
+            if self.close_received {
+                try_ready!(self.poll_complete());
+                return Ok(Async::Ready(None));
+            }
+            let frame = match try_ready!(self.framed.poll()) {
+                Some(frame) => frame,
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Connection closed without a close frame",
+                    ))
+                }
+            };
+            if let Some(msg) = self.on_frame(frame)? {
+                return Ok(Async::Ready(Some(msg)));
+            }
+        }
+    }
+}
+
+/// Fails with `BrokenPipe` once a close frame has been sent.
+impl<T> Sink for WebSocket<T>
+where
+    T: Sink<SinkItem = ByteBuf, SinkError = io::Error>,
+{
+    type SinkItem = Message;
+    type SinkError = io::Error;
+
+    fn start_send(&mut self, msg: Message) -> StartSend<Message, io::Error> {
+        if self.poll_flush()?.is_not_ready() {
+            return Ok(AsyncSink::NotReady(msg));
+        }
+        if self.close_sent {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "Close frame already sent"));
+        }
+        self.queue(msg)?;
+        self.poll_flush()?;
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), io::Error> {
+        try_ready!(self.poll_flush());
+        self.framed.poll_complete()
+    }
+
+    /// Sends a normal close frame first, unless one has been sent.
+    fn close(&mut self) -> Poll<(), io::Error> {
+        if !self.close_sent {
+            self.queue(Message::Close(Some(CloseFrame {
+                code: close_code::NORMAL,
+                reason: String::new(),
+            })))?;
+        }
+        try_ready!(self.poll_complete());
+        self.framed.close()
+    }
+}
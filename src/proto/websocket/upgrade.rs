@@ -0,0 +1,177 @@
+use std::io;
+
+use futures::{Async, AsyncSink, Future, Poll, Sink, Stream};
+
+use buf::ByteBuf;
+use proto::{Decoder, Encoder, Framed};
+use proto::http1::{ClientCodec, Request, Response, ServerCodec};
+
+use super::{accept_response, check_response, upgrade_request, FrameCodec, Role, WebSocket};
+
+enum State<I> {
+    Send(Option<I>),
+    Flush,
+    Recv,
+}
+
+/// Completes the server side of the handshake, yielding the `WebSocket` and
+/// the upgrade request.
+pub struct Accept<T> {
+    framed: Option<Framed<T, ServerCodec>>,
+    state: State<Response>,
+    // The outcome to yield once the response has been sent.
+    result: Option<io::Result<Request>>,
+}
+
+/// Completes the client side of the handshake, yielding the `WebSocket` and
+/// the response accepting the upgrade.
+pub struct Connect<T> {
+    framed: Option<Framed<T, ClientCodec>>,
+    state: State<Request>,
+    key: String,
+}
+
+/// Reads an upgrade request from `io` and accepts it. Other requests get
+/// `400 Bad Request` and fail the handshake with `InvalidData`.
+#[inline]
+pub fn accept<T>(io: T) -> Accept<T> {
+    Accept {
+        framed: Some(Framed::new(io, ServerCodec::new())),
+        state: State::Recv,
+        result: None,
+    }
+}
+
+/// Requests an upgrade to WebSocket at `target` of `host` over `io`.
+#[inline]
+pub fn connect<T>(io: T, host: &str, target: &str) -> Connect<T> {
+    let (request, key) = upgrade_request(host, target);
+    Connect {
+        framed: Some(Framed::new(io, ClientCodec::new())),
+        state: State::Send(Some(request)),
+        key,
+    }
+}
+
+// Continues with the data received after the handshake as frames.
+#[inline]
+fn upgrade<T, C>(framed: Framed<T, C>, role: Role) -> WebSocket<T> {
+    let (io, _, data) = framed.into_parts();
+    WebSocket::from_framed(Framed::from_parts(io, FrameCodec::new(role), data))
+}
+
+// Sends the item of `state`, then flushes it.
+fn poll_send<T, C>(framed: &mut Framed<T, C>, state: &mut State<C::Item>) -> Poll<(), io::Error>
+where
+    T: Sink<SinkItem = ByteBuf, SinkError = io::Error>,
+    C: Encoder<Error = io::Error>,
+{
+    loop {
+        match *state {
+            State::Send(ref mut item) => {
+                let it = match item.take() {
+                    Some(it) => it,
+                    None => ::unreachable(),
+                };
+                if let AsyncSink::NotReady(it) = framed.start_send(it)? {
+                    *item = Some(it);
+                    try_ready!(framed.poll_complete());
+                    continue;
+                }
+            }
+            State::Flush => {
+                try_ready!(framed.poll_complete());
+                return Ok(Async::Ready(()));
+            }
+            State::Recv => ::unreachable(),
+        }
+        *state = State::Flush;
+    }
+}
+
+// Receives the next message of `framed`, which must be there.
+fn poll_recv<T, C>(framed: &mut Framed<T, C>) -> Poll<C::Item, io::Error>
+where
+    T: Stream<Item = ByteBuf, Error = io::Error>,
+    C: Decoder<Error = io::Error>,
+{
+    match try_ready!(framed.poll()) {
+        Some(item) => Ok(Async::Ready(item)),
+        None => Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Connection closed during the WebSocket handshake",
+        )),
+    }
+}
+
+impl<T> Future for Accept<T>
+where
+    T: Stream<Item = ByteBuf, Error = io::Error> + Sink<SinkItem = ByteBuf, SinkError = io::Error>,
+{
+    type Item = (WebSocket<T>, Request);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        {
+            let framed = match self.framed.as_mut() {
+                Some(framed) => framed,
+                None => panic!("Poll a finished handshake"),
+            };
+            if let State::Recv = self.state {
+                let request = try_ready!(poll_recv(framed));
+                let response = match accept_response(&request) {
+                    Ok(response) => {
+                        self.result = Some(Ok(request));
+                        response
+                    }
+                    Err(e) => {
+                        self.result = Some(Err(e));
+                        let mut response = Response::new(400);
+                        response.headers.insert("Connection", "close");
+                        response
+                    }
+                };
+                self.state = State::Send(Some(response));
+            }
+            try_ready!(poll_send(framed, &mut self.state));
+        }
+        let request = match self.result.take() {
+            Some(result) => result?,
+            None => ::unreachable(),
+        };
+        match self.framed.take() {
+            Some(framed) => Ok(Async::Ready((upgrade(framed, Role::Server), request))),
+            None => ::unreachable(),
+        }
+    }
+}
+
+impl<T> Future for Connect<T>
+where
+    T: Stream<Item = ByteBuf, Error = io::Error> + Sink<SinkItem = ByteBuf, SinkError = io::Error>,
+{
+    type Item = (WebSocket<T>, Response);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let response = {
+            let framed = match self.framed.as_mut() {
+                Some(framed) => framed,
+                None => panic!("Poll a finished handshake"),
+            };
+            match self.state {
+                State::Recv => (),
+                _ => {
+                    try_ready!(poll_send(framed, &mut self.state));
+                    self.state = State::Recv;
+                }
+            }
+            try_ready!(poll_recv(framed))
+        };
+        check_response(&response, &self.key)?;
+        match self.framed.take() {
+            Some(framed) => Ok(Async::Ready((upgrade(framed, Role::Client), response))),
+            None => ::unreachable(),
+        }
+    }
+}
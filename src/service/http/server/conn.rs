@@ -0,0 +1,130 @@
+use std::io;
+
+use futures::{Async, AsyncSink, Future, Poll, Sink, Stream};
+
+use buf::ByteBuf;
+use proto::{FrameTooLong, Framed};
+use proto::http1::{Response, ServerCodec};
+use service::http::server::Handler;
+
+enum State<F> {
+    Read,
+    Handle(F),
+    Respond(Option<Response>),
+    Flush,
+    Close,
+}
+
+/// Serves the requests of a connection in the order received.
+pub(crate) struct Conn<T, H>
+where
+    H: Handler,
+{
+    framed: Framed<T, ServerCodec>,
+    handler: H,
+    state: State<H::Future>,
+    keep_alive: bool,
+}
+
+// Returns the status of the response to a request that failed to decode.
+fn error_status(e: &io::Error, codec: &ServerCodec) -> u16 {
+    let too_long = e.get_ref().map_or(false, |e| e.is::<FrameTooLong>());
+    match too_long {
+        true if codec.in_body() => 413,
+        true => 431,
+        false => 400,
+    }
+}
+
+// Marks `response` as the last one if either side closes the connection.
+fn last_if_closing(mut response: Response, keep_alive: &mut bool) -> Response {
+    if *keep_alive && !response.keep_alive() {
+        *keep_alive = false;
+    }
+    if !*keep_alive && !response.headers.has_token("Connection", "close") {
+        response.headers.append("Connection", "close");
+    }
+    response
+}
+
+impl<T, H> Conn<T, H>
+where
+    H: Handler,
+{
+    #[inline]
+    pub fn new(framed: Framed<T, ServerCodec>, handler: H) -> Self {
+        Conn {
+            framed,
+            handler,
+            state: State::Read,
+            keep_alive: true,
+        }
+    }
+}
+
+impl<T, H> Future for Conn<T, H>
+where
+    T: Stream<Item = ByteBuf, Error = io::Error> + Sink<SinkItem = ByteBuf, SinkError = io::Error>,
+    H: Handler,
+{
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<(), io::Error> {
+        loop {
+            let next = match self.state {
+                State::Read => match self.framed.poll() {
+                    Ok(Async::Ready(Some(request))) => {
+                        self.keep_alive = request.keep_alive();
+                        State::Handle(self.handler.handle(request))
+                    }
+                    Ok(Async::Ready(None)) => State::Close,
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    Err(e) => match e.kind() {
+                        io::ErrorKind::InvalidData => {
+                            debug!("Bad request: {}", e);
+                            self.keep_alive = false;
+                            let response = Response::new(error_status(&e, self.framed.codec()));
+                            State::Respond(Some(last_if_closing(response, &mut self.keep_alive)))
+                        }
+                        io::ErrorKind::UnexpectedEof => State::Close,
+                        _ => return Err(e),
+                    },
+                },
+                State::Handle(ref mut f) => match f.poll() {
+                    Ok(Async::Ready(response)) => {
+                        State::Respond(Some(last_if_closing(response, &mut self.keep_alive)))
+                    }
+                    Ok(Async::NotReady) => return Ok(Async::NotReady),
+                    Err(e) => {
+                        error!("Failed to handle request: {}", e);
+                        self.keep_alive = false;
+                        let response = Response::new(500);
+                        State::Respond(Some(last_if_closing(response, &mut self.keep_alive)))
+                    }
+                },
+                State::Respond(ref mut response) => {
+                    let r = match response.take() {
+                        Some(r) => r,
+                        None => ::unreachable(),
+                    };
+                    if let AsyncSink::NotReady(r) = self.framed.start_send(r)? {
+                        *response = Some(r);
+                        try_ready!(self.framed.poll_complete());
+                        continue;
+                    }
+                    State::Flush
+                }
+                State::Flush => {
+                    try_ready!(self.framed.poll_complete());
+                    match self.keep_alive {
+                        true => State::Read,
+                        false => State::Close,
+                    }
+                }
+                State::Close => return self.framed.close(),
+            };
+            self.state = next;
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use std::io;
+
+use futures::{Future, IntoFuture};
+
+use proto::http1::{Request, Response};
+
+/// Responds to the requests of a connection, one at a time.
+///
+/// Each connection gets its own clone of the handler given to the server.
+pub trait Handler {
+    type Future: Future<Item = Response, Error = io::Error>;
+
+    fn handle(&mut self, request: Request) -> Self::Future;
+}
+
+impl<F, R> Handler for F
+where
+    F: FnMut(Request) -> R,
+    R: IntoFuture<Item = Response, Error = io::Error>,
+{
+    type Future = R::Future;
+
+    #[inline]
+    fn handle(&mut self, request: Request) -> Self::Future {
+        self(request).into_future()
+    }
+}
@@ -0,0 +1,7 @@
+mod handler;
+pub use self::handler::*;
+
+mod conn;
+
+mod server;
+pub use self::server::Server;
@@ -0,0 +1,159 @@
+use std::io;
+use std::net::SocketAddr;
+
+use futures::Future;
+
+use net::tcp;
+use proto::http1::{ServerCodec, DEFAULT_MAX_HEADERS, DEFAULT_MAX_HEAD_LEN};
+use proto::max_frame_len;
+use service::tcp::server::{self as tcp_server, Session};
+use task::{IntoTask, Task};
+
+use service::http::server::Handler;
+use service::http::server::conn::Conn;
+
+// Frames each connection and serves it with a clone of `handler`.
+#[derive(Clone)]
+struct Dispatch<H> {
+    handler: H,
+    max_head_len: usize,
+    max_headers: usize,
+    max_body_len: usize,
+}
+
+impl<H> tcp_server::Handler for Dispatch<H>
+where
+    H: Handler + Clone + 'static,
+{
+    fn handle(&mut self, session: Session) -> Option<Task> {
+        let mut codec = ServerCodec::new();
+        codec
+            .set_max_head_len(self.max_head_len)
+            .set_max_headers(self.max_headers)
+            .set_max_body_len(self.max_body_len);
+        let peer = format!("{}", session);
+        match tcp::framed(session, codec) {
+            Ok(framed) => Some(
+                Conn::new(framed, self.handler.clone())
+                    .map_err(move |e| error!("{}: {}", peer, e))
+                    .into_task(),
+            ),
+            Err(e) => {
+                error!("{}: {}", peer, e);
+                None
+            }
+        }
+    }
+}
+
+/// An HTTP/1.1 server on top of `service::tcp::Server`, with keep-alive and
+/// pipelining.
+///
+/// ```ignore
+/// let mut server = Server::with_handler(|_: Request| {
+///     let mut response = Response::new(200);
+///     response.body = ByteBuf::from(b"OK".to_vec());
+///     Ok(response)
+/// });
+/// server.port(8080).start()?;
+/// ```
+pub struct Server<H> {
+    inner: tcp_server::Server<Dispatch<H>>,
+}
+
+impl<H> Server<H>
+where
+    H: Handler + Clone + Send + Sync + 'static,
+{
+    #[inline]
+    pub fn with_handler(handler: H) -> Self {
+        let dispatch = Dispatch {
+            handler,
+            max_head_len: DEFAULT_MAX_HEAD_LEN,
+            max_headers: DEFAULT_MAX_HEADERS,
+            max_body_len: max_frame_len(),
+        };
+        Server {
+            inner: tcp_server::Server::with_handler(dispatch),
+        }
+    }
+
+    #[inline]
+    pub fn addr(&mut self, addr: SocketAddr) -> &mut Self {
+        self.inner.addr(addr);
+        self
+    }
+
+    #[inline]
+    pub fn port(&mut self, port: u16) -> &mut Self {
+        self.inner.port(port);
+        self
+    }
+
+    #[inline]
+    pub fn backlog(&mut self, backlog: i32) -> &mut Self {
+        self.inner.backlog(backlog);
+        self
+    }
+
+    #[inline]
+    pub fn num_of_workers(&mut self, num_of_workers: usize) -> &mut Self {
+        self.inner.num_of_workers(num_of_workers);
+        self
+    }
+
+    #[inline]
+    pub fn worker_conns(&mut self, worker_conns: usize) -> &mut Self {
+        self.inner.worker_conns(worker_conns);
+        self
+    }
+
+    /// Expects a PROXY protocol header at the start of each connection, see
+    /// `service::tcp::Server::proxy_protocol`.
+    #[inline]
+    pub fn proxy_protocol(&mut self, proxy_protocol: bool) -> &mut Self {
+        self.inner.proxy_protocol(proxy_protocol);
+        self
+    }
+
+    #[inline]
+    pub fn proxy_timeout(&mut self, secs: u64) -> &mut Self {
+        self.inner.proxy_timeout(secs);
+        self
+    }
+
+    /// Requests with longer heads get `431 Request Header Fields Too Large`.
+    /// Has no effect once the server has started.
+    #[inline]
+    pub fn max_head_len(&mut self, max_head_len: usize) -> &mut Self {
+        if let Some(dispatch) = self.inner.handler_mut() {
+            dispatch.max_head_len = max_head_len;
+        }
+        self
+    }
+
+    /// Requests with more header fields get `400 Bad Request`. Has no effect
+    /// once the server has started.
+    #[inline]
+    pub fn max_headers(&mut self, max_headers: usize) -> &mut Self {
+        if let Some(dispatch) = self.inner.handler_mut() {
+            dispatch.max_headers = max_headers;
+        }
+        self
+    }
+
+    /// Requests with longer bodies get `413 Payload Too Large`. Has no effect
+    /// once the server has started.
+    #[inline]
+    pub fn max_body_len(&mut self, max_body_len: usize) -> &mut Self {
+        if let Some(dispatch) = self.inner.handler_mut() {
+            dispatch.max_body_len = max_body_len;
+        }
+        self
+    }
+
+    #[inline]
+    pub fn start(&mut self) -> io::Result<()> {
+        self.inner.start()
+    }
+}
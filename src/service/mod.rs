@@ -1 +1,3 @@
+pub mod http;
+pub mod socks5;
 pub mod tcp;
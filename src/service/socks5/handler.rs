@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::Shutdown;
+use std::sync::Arc;
+
+use futures::{future, Future, Sink};
+
+use buf::ByteBuf;
+use future::Timeout;
+use net::TcpStream;
+use net::tcp;
+use proto::socks5::{reply, Addr, Reply};
+use service::tcp::server::{self as tcp_server, Session};
+use task::{IntoTask, Task};
+
+use super::handshake::Handshake;
+
+/// Serves the CONNECT command of SOCKS5 clients, to IP addresses only.
+///
+/// Clients authenticate with the username/password authentication if any
+/// user is added, or with none otherwise.
+///
+/// Not supported on Windows, where every handshake fails, for the same
+/// reason as `proto::socks5::Connector`.
+#[derive(Debug, Clone)]
+pub struct Handler {
+    users: Arc<HashMap<String, String>>,
+    handshake_timeout: u64,
+}
+
+impl Default for Handler {
+    #[inline]
+    fn default() -> Self {
+        Handler {
+            users: Default::default(),
+            handshake_timeout: 5,
+        }
+    }
+}
+
+impl Handler {
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Drops connections whose handshake, up to the request, takes longer
+    /// than `secs` seconds. Defaults to 5.
+    #[inline]
+    pub fn handshake_timeout(&mut self, secs: u64) -> &mut Self {
+        self.handshake_timeout = secs;
+        self
+    }
+
+    #[inline]
+    pub fn user(&mut self, username: &str, password: &str) -> &mut Self {
+        Arc::make_mut(&mut self.users).insert(username.to_string(), password.to_string());
+        self
+    }
+}
+
+fn reply_code(e: &io::Error) -> u8 {
+    match e.kind() {
+        io::ErrorKind::ConnectionRefused => reply::CONNECTION_REFUSED,
+        io::ErrorKind::TimedOut => reply::HOST_UNREACHABLE,
+        io::ErrorKind::PermissionDenied => reply::NOT_ALLOWED,
+        _ => reply::GENERAL_FAILURE,
+    }
+}
+
+#[inline]
+fn shutdown_write<T: AsRef<TcpStream>>(io: &T) {
+    io.as_ref().shutdown(Shutdown::Write).ok();
+}
+
+impl tcp_server::Handler for Handler {
+    fn handle(&mut self, session: Session) -> Option<Task> {
+        let peer = format!("{}", session);
+        let task = Handshake::new(session, self.users.clone())
+            .timeout(self.handshake_timeout)
+            .and_then(|(session, addr)| {
+                tcp::connect::<TcpStream>(&addr).then(move |res| {
+                    let conn = res.and_then(|conn| {
+                        let addr = conn.as_ref().local_addr()?;
+                        Ok((conn, addr))
+                    });
+                    let (reply, conn) = match conn {
+                        Ok((conn, addr)) => (
+                            Reply {
+                                reply: reply::SUCCEEDED,
+                                addr: Addr::Ip(addr),
+                            },
+                            Some(conn),
+                        ),
+                        Err(e) => {
+                            debug!("Failed to connect to {}: {}", addr, e);
+                            (Reply::new(reply_code(&e)), None)
+                        }
+                    };
+                    let mut data = Vec::new();
+                    future::result(
+                        reply
+                            .encode(&mut data)
+                            .and_then(|()| tcp::send(session, ByteBuf::from(data))),
+                    ).flatten()
+                        .map(|client| (client, conn))
+                })
+            })
+            .and_then(|(client, conn)| match conn {
+                Some(conn) => {
+                    let (client_r, client_s) = client.into_twoway();
+                    let (conn_r, conn_s) = conn.into_twoway();
+                    let up = conn_s.send_all(client_r).map(|(s, _)| shutdown_write(s.as_ref()));
+                    let down = client_s.send_all(conn_r).map(|(s, _)| shutdown_write(s.as_ref()));
+                    future::Either::A(up.join(down).map(|_| ()))
+                }
+                None => future::Either::B(future::ok(())),
+            })
+            .map_err(move |e| debug!("{}: {}", peer, e))
+            .into_task();
+        Some(task)
+    }
+}
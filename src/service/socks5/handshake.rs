@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use futures::{Async, Future, Poll};
+
+use proto::socks5::{method, reply, Addr, AuthRequest, AuthStatus, Command, Greeting,
+                    MethodSelection, Reply, Request, VERSION};
+use service::tcp::server::Session;
+use sys::net::tcp::Peek;
+
+// A request, or the reply code to refuse it with.
+fn parse_request(data: &[u8]) -> io::Result<Option<(Result<Request, u8>, usize)>> {
+    match Request::parse(data) {
+        Ok(req) => Ok(req.map(|(req, len)| (Ok(req), len))),
+        Err(e) => {
+            if data[0] != VERSION {
+                return Err(e);
+            }
+            let code = match data[1] {
+                1..=3 => reply::ADDRESS_TYPE_NOT_SUPPORTED,
+                _ => reply::COMMAND_NOT_SUPPORTED,
+            };
+            Ok(Some((Err(code), data.len())))
+        }
+    }
+}
+
+#[inline]
+fn closed() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "Client closed the connection during the SOCKS5 handshake",
+    )
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    Greeting,
+    Method(u8),
+    Auth,
+    AuthStatus(bool),
+    Request,
+    Refuse(u8),
+}
+
+/// Negotiates the authentication and reads the request of a client, and
+/// yields the session and the destination to connect to.
+pub(super) struct Handshake {
+    peek: Option<Peek<Session>>,
+    users: Arc<HashMap<String, String>>,
+    step: Step,
+    out: Vec<u8>,
+    error: Option<io::Error>,
+}
+
+impl Handshake {
+    pub(super) fn new(session: Session, users: Arc<HashMap<String, String>>) -> Self {
+        let (peek, error) = match Peek::try_from(session) {
+            Ok(peek) => (Some(peek), None),
+            Err(e) => (None, Some(e)),
+        };
+        Handshake {
+            peek,
+            users,
+            step: Step::Greeting,
+            out: Vec::new(),
+            error,
+        }
+    }
+}
+
+// Selects the username/password authentication if there are users, or none
+// otherwise.
+fn select(users: &HashMap<String, String>, greeting: &Greeting) -> u8 {
+    let method = match users.is_empty() {
+        true => method::NO_AUTH,
+        false => method::USERNAME_PASSWORD,
+    };
+    match greeting.methods.contains(&method) {
+        true => method,
+        false => method::NO_ACCEPTABLE,
+    }
+}
+
+impl Future for Handshake {
+    type Item = (Session, SocketAddr);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        let addr = loop {
+            let peek = match self.peek {
+                Some(ref mut peek) => peek,
+                None => panic!("Attempted to poll a SOCKS5 handshake after completion"),
+            };
+            match self.step {
+                Step::Greeting => {
+                    let greeting = try_ready!(peek.poll_read(Greeting::parse)).ok_or_else(closed)?;
+                    let method = select(&self.users, &greeting);
+                    MethodSelection { method }.encode(&mut self.out);
+                    self.step = Step::Method(method);
+                }
+                Step::Method(method) => {
+                    try_ready!(peek.poll_write(&mut self.out));
+                    self.step = match method {
+                        method::NO_AUTH => Step::Request,
+                        method::USERNAME_PASSWORD => Step::Auth,
+                        _ => {
+                            return Err(io::Error::new(
+                                io::ErrorKind::PermissionDenied,
+                                "No acceptable SOCKS5 authentication method",
+                            ))
+                        }
+                    };
+                }
+                Step::Auth => {
+                    let auth = try_ready!(peek.poll_read(AuthRequest::parse)).ok_or_else(closed)?;
+                    let ok = self.users.get(&auth.username) == Some(&auth.password);
+                    AuthStatus { status: !ok as u8 }.encode(&mut self.out);
+                    self.step = Step::AuthStatus(ok);
+                }
+                Step::AuthStatus(ok) => {
+                    try_ready!(peek.poll_write(&mut self.out));
+                    if !ok {
+                        return Err(io::Error::new(
+                            io::ErrorKind::PermissionDenied,
+                            "SOCKS5 authentication failed",
+                        ));
+                    }
+                    self.step = Step::Request;
+                }
+                Step::Request => {
+                    let code = match try_ready!(peek.poll_read(parse_request)).ok_or_else(closed)? {
+                        Ok(Request {
+                            command: Command::Connect,
+                            addr: Addr::Ip(addr),
+                        }) => break addr,
+                        // Resolving the domain name would block the reactor.
+                        Ok(Request {
+                            command: Command::Connect,
+                            ..
+                        }) => reply::ADDRESS_TYPE_NOT_SUPPORTED,
+                        Ok(..) => reply::COMMAND_NOT_SUPPORTED,
+                        Err(code) => code,
+                    };
+                    Reply::new(code).encode(&mut self.out)?;
+                    self.step = Step::Refuse(code);
+                }
+                Step::Refuse(code) => {
+                    try_ready!(peek.poll_write(&mut self.out));
+                    match Reply::new(code).to_error() {
+                        Some(e) => return Err(e),
+                        None => ::unreachable(),
+                    }
+                }
+            }
+        };
+        match self.peek.take() {
+            Some(peek) => Ok(Async::Ready((peek.into_inner(), addr))),
+            None => ::unreachable(),
+        }
+    }
+}
@@ -0,0 +1,15 @@
+//! A SOCKS5 proxy, which plugs into `service::tcp::Server`:
+//!
+//! ```no_run
+//! use ruyi::service::socks5::Handler;
+//! use ruyi::service::tcp::Server;
+//!
+//! let mut handler = Handler::new();
+//! handler.user("user", "pass");
+//! Server::with_handler(handler).port(1080).start().unwrap();
+//! ```
+
+mod handshake;
+
+mod handler;
+pub use self::handler::*;
@@ -1,16 +1,20 @@
+use std::cell::RefCell;
 use std::fmt;
 use std::io;
 use std::mem;
 use std::net::SocketAddr;
+use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread::{self, JoinHandle};
 
-use futures::{Future, Stream};
+use futures::{future, Future, Stream};
 
+use future::Timeout;
 use sync::err::SendError;
 use sync::spsc::{self, Receiver, SyncSender};
 use net::{TcpListener, TcpListenerBuilder, TcpStream};
+use proto::proxy;
 use reactor;
 use task::{IntoTask, Task};
 
@@ -23,6 +27,43 @@ struct Inner {
     mask: usize,
     idx: usize,
     worker_conns: usize,
+    proxy_timeout: Option<u64>,
+}
+
+// Hands `session` to `handler`, once its PROXY protocol header is read if
+// `proxy_timeout` is set.
+fn dispatch<T>(handler: &Rc<RefCell<T>>, session: Session, proxy_timeout: Option<u64>)
+where
+    T: Handler + 'static,
+{
+    let secs = match proxy_timeout {
+        Some(secs) => secs,
+        None => {
+            if let Some(t) = handler.borrow_mut().handle(session) {
+                reactor::spawn(t);
+            }
+            return;
+        }
+    };
+    let handler = handler.clone();
+    let peer = format!("{}", session);
+    let task = proxy::accept(session)
+        .timeout(secs)
+        .then(move |res| match res {
+            Ok((mut session, header)) => {
+                session.set_proxy_header(header);
+                match handler.borrow_mut().handle(session) {
+                    Some(t) => future::Either::A(t),
+                    None => future::Either::B(future::ok(())),
+                }
+            }
+            Err(e) => {
+                warn!("Failed to read PROXY protocol header from {}: {}", peer, e);
+                future::Either::B(future::ok(()))
+            }
+        })
+        .into_task();
+    reactor::spawn(task);
 }
 
 impl Inner {
@@ -30,6 +71,7 @@ impl Inner {
     fn init<H>(&mut self, to_handler: Arc<H>)
     where
         H: ToHandler + Send + Sync + 'static,
+        H::Handler: 'static,
     {
         for _ in 0..self.mask + 1 {
             let (tx, rx) = spsc::sync_channel(self.worker_conns).unwrap();
@@ -37,8 +79,9 @@ impl Inner {
             let join_handle = {
                 let conn_count = conn_count.clone();
                 let to_handler = to_handler.clone();
+                let proxy_timeout = self.proxy_timeout;
                 thread::spawn(move || {
-                    Self::handle(rx, conn_count, to_handler)
+                    Self::handle(rx, conn_count, to_handler, proxy_timeout)
                         .map_err(|e| error!("{}", e))
                         .ok();
                 })
@@ -53,16 +96,16 @@ impl Inner {
         rx: Receiver<TcpStream>,
         conn_count: Arc<AtomicUsize>,
         to_handler: Arc<H>,
+        proxy_timeout: Option<u64>,
     ) -> io::Result<()>
     where
         H: ToHandler + Send + Sync + 'static,
+        H::Handler: 'static,
     {
-        let mut handler = to_handler.to_handler();
+        let handler = Rc::new(RefCell::new(to_handler.to_handler()));
         let handle = rx.recv()?.for_each(|conn| {
             let session = Session::new(conn, unsafe { mem::transmute(conn_count.as_ref()) });
-            if let Some(t) = handler.handle(session) {
-                reactor::spawn(t);
-            }
+            dispatch(&handler, session, proxy_timeout);
             Ok(())
         });
         reactor::run(handle)
@@ -108,9 +151,10 @@ impl Inner {
     fn run_single<H>(mut self, to_handler: Arc<H>) -> io::Result<Task>
     where
         H: ToHandler + Send + Sync + 'static,
+        H::Handler: 'static,
     {
         info!("{} started", self);
-        let mut handler = to_handler.to_handler();
+        let handler = Rc::new(RefCell::new(to_handler.to_handler()));
         let conn_count = AtomicUsize::new(0);
         let task = self.listener
             .take()
@@ -120,9 +164,7 @@ impl Inner {
                 let n = conn_count.fetch_add(1, Ordering::Relaxed);
                 if self.worker_conns > n {
                     let session = Session::new(conn, unsafe { mem::transmute(&conn_count) });
-                    if let Some(t) = handler.handle(session) {
-                        reactor::spawn(t);
-                    }
+                    dispatch(&handler, session, self.proxy_timeout);
                 } else {
                     warn!(
                         "{} drops {} to not exceed worker_conns {}",
@@ -155,6 +197,8 @@ pub struct Server<H> {
     listener_builder: TcpListenerBuilder,
     num_of_workers: usize,
     worker_conns: usize, // Max number of simultaneous connections per worker
+    proxy_protocol: bool,
+    proxy_timeout: u64,
     tx: Option<SyncSender<(Inner, Arc<H>)>>,
     join_handle: Option<JoinHandle<()>>,
     to_handler: Arc<H>,
@@ -163,6 +207,7 @@ pub struct Server<H> {
 impl<H> Server<H>
 where
     H: ToHandler + Send + Sync + 'static,
+    H::Handler: 'static,
 {
     #[inline]
     pub fn with_handler(to_handler: H) -> Self {
@@ -170,6 +215,8 @@ where
             listener_builder: TcpListenerBuilder::default(),
             num_of_workers: 1,
             worker_conns: 512,
+            proxy_protocol: false,
+            proxy_timeout: 5,
             tx: None,
             join_handle: None,
             to_handler: Arc::new(to_handler),
@@ -222,6 +269,29 @@ where
         self
     }
 
+    /// Expects a PROXY protocol header, v1 or v2, at the start of each
+    /// connection, and drops connections without a valid one. The addresses
+    /// in the header are those of `Session::peer_addr` and `local_addr`.
+    #[inline]
+    pub fn proxy_protocol(&mut self, proxy_protocol: bool) -> &mut Self {
+        self.proxy_protocol = proxy_protocol;
+        self
+    }
+
+    /// Drops connections whose PROXY protocol header takes longer than
+    /// `secs` seconds. Defaults to 5.
+    #[inline]
+    pub fn proxy_timeout(&mut self, secs: u64) -> &mut Self {
+        self.proxy_timeout = secs;
+        self
+    }
+
+    /// Returns the handler, unless the server has been started.
+    #[inline]
+    pub(crate) fn handler_mut(&mut self) -> Option<&mut H> {
+        Arc::get_mut(&mut self.to_handler)
+    }
+
     pub fn start(&mut self) -> io::Result<()> {
         let listener = self.listener_builder.build()?;
         let name = format!("{}", listener.local_addr()?);
@@ -232,6 +302,10 @@ where
             mask: self.num_of_workers - 1,
             idx: 0,
             worker_conns: self.worker_conns,
+            proxy_timeout: match self.proxy_protocol {
+                true => Some(self.proxy_timeout),
+                false => None,
+            },
         };
         let (tx, rx) = spsc::sync_channel(1)?;
         match tx.send((inner, self.to_handler.clone())) {
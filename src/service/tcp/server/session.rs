@@ -1,18 +1,58 @@
 use std::fmt;
+use std::io;
+use std::net::SocketAddr;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 use net::TcpStream;
+use proto::proxy::Header;
 
 #[derive(Debug)]
 pub struct Session {
     conn: TcpStream,
     conn_count: &'static AtomicUsize,
+    proxy_header: Option<Header>,
 }
 
 impl Session {
     #[inline]
     pub fn new(conn: TcpStream, conn_count: &'static AtomicUsize) -> Self {
-        Session { conn, conn_count }
+        Session {
+            conn,
+            conn_count,
+            proxy_header: None,
+        }
+    }
+
+    /// Returns the PROXY protocol header of the connection, if the server
+    /// expects one.
+    #[inline]
+    pub fn proxy_header(&self) -> Option<&Header> {
+        self.proxy_header.as_ref()
+    }
+
+    #[inline]
+    pub(crate) fn set_proxy_header(&mut self, header: Header) {
+        self.proxy_header = Some(header);
+    }
+
+    /// Returns the address of the client, which is the source address in
+    /// the PROXY protocol header if there is one.
+    #[inline]
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        match self.proxy_header.as_ref().and_then(Header::source) {
+            Some(addr) => Ok(addr),
+            None => self.conn.peer_addr(),
+        }
+    }
+
+    /// Returns the address the client connected to, which is the destination
+    /// address in the PROXY protocol header if there is one.
+    #[inline]
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        match self.proxy_header.as_ref().and_then(Header::destination) {
+            Some(addr) => Ok(addr),
+            None => self.conn.local_addr(),
+        }
     }
 }
 
@@ -30,10 +70,14 @@ impl AsMut<TcpStream> for Session {
     }
 }
 
+/// Shows the client as `peer_addr` does, rather than the proxy in front of it.
 impl fmt::Display for Session {
     #[inline]
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Display::fmt(&self.conn, f)
+        match self.proxy_header.as_ref().and_then(Header::source) {
+            Some(addr) => fmt::Display::fmt(&addr, f),
+            None => fmt::Display::fmt(&self.conn, f),
+        }
     }
 }
 
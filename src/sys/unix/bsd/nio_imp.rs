@@ -1,7 +1,9 @@
 use std::fmt;
 use std::io;
 use std::marker::PhantomData;
+use std::mem;
 use std::os::unix::io::AsRawFd;
+use std::ptr;
 
 use reactor::CURRENT_LOOP;
 use sys::{ReadyTasks, Schedule, Token};
@@ -45,6 +47,15 @@ where
         &mut self.io
     }
 
+    /// Deregisters `io` and returns it.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        Self::deregister(self.token);
+        let io = unsafe { ptr::read(&self.io) };
+        mem::forget(self);
+        io
+    }
+
     #[inline]
     pub fn schedule_read(&mut self) -> io::Result<()> {
         if self.read_sched {
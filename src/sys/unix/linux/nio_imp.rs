@@ -1,7 +1,9 @@
 use std::fmt;
 use std::io;
 use std::marker::PhantomData;
+use std::mem;
 use std::os::unix::io::{AsRawFd, RawFd};
+use std::ptr;
 
 use reactor::CURRENT_LOOP;
 use sys::{ReadyTasks, Schedule, Token};
@@ -44,6 +46,16 @@ where
         &mut self.io
     }
 
+    /// Deregisters `io` and returns it.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        Self::deregister(self.io.as_ref().as_raw_fd(), self.token)
+            .unwrap_or_else(|e| error!("Failed to deregister {:?}: {}", self.io.as_ref(), e));
+        let io = unsafe { ptr::read(&self.io) };
+        mem::forget(self);
+        io
+    }
+
     #[inline]
     pub fn schedule_read(&mut self) -> io::Result<()> {
         if self.sched_ops.contains(Ops::READ) {
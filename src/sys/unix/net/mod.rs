@@ -1,4 +1,5 @@
 pub(crate) mod tcp;
+pub(crate) mod udp;
 
 #[inline]
 pub fn init() {}
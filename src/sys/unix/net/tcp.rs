@@ -1,5 +1,6 @@
 use std::cell::UnsafeCell;
-use std::io;
+use std::cmp;
+use std::io::{self, Read, Write};
 use std::mem;
 use std::net::{self, SocketAddr};
 use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
@@ -385,6 +386,11 @@ where
             inner: IStream::from(self.inner.nio),
         }
     }
+
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.inner.nio.into_inner()
+    }
 }
 
 pub struct RecvHalf<T>
@@ -441,6 +447,140 @@ where
     }
 }
 
+// Lends `Nio` a stream that is handed back once it is deregistered.
+struct Shared<T>(Rc<T>);
+
+impl<T> AsRef<TcpStream> for Shared<T>
+where
+    T: AsRef<TcpStream>,
+{
+    #[inline]
+    fn as_ref(&self) -> &TcpStream {
+        (*self.0).as_ref()
+    }
+}
+
+/// Reads the data at the front of a stream without consuming more of it
+/// than asked, e.g. a header before the data of the protocol on top.
+pub(crate) struct Peek<T>
+where
+    T: AsRef<TcpStream>,
+{
+    nio: Nio<TcpStream, Shared<T>>,
+    io: Rc<T>,
+    data: Vec<u8>,
+}
+
+impl<T> Peek<T>
+where
+    T: AsRef<TcpStream>,
+{
+    #[inline]
+    pub(crate) fn try_from(io: T) -> io::Result<Self> {
+        let io = Rc::new(io);
+        Ok(Peek {
+            nio: Nio::try_from(Shared(io.clone()))?,
+            io,
+            data: Vec::new(),
+        })
+    }
+
+    // Copies the data available into `buf` without consuming it. Yields 0
+    // at EOF.
+    fn poll_peek(&mut self, buf: &mut [u8]) -> Poll<usize, io::Error> {
+        if !self.nio.is_read_ready() {
+            return Ok(Async::NotReady);
+        }
+        match (*self.io).as_ref().as_inner().peek(buf) {
+            Ok(n) => Ok(Async::Ready(n)),
+            Err(e) => match e.kind() {
+                io::ErrorKind::WouldBlock => {
+                    self.nio.schedule_read()?;
+                    Ok(Async::NotReady)
+                }
+                _ => Err(e),
+            },
+        }
+    }
+
+    // Consumes the first `n` bytes of the data peeked.
+    fn consume(&mut self, mut n: usize) -> io::Result<()> {
+        let mut conn = (*self.io).as_ref().as_inner();
+        let mut buf = [0; 512];
+        while n > 0 {
+            let len = cmp::min(n, buf.len());
+            match conn.read(&mut buf[..len])? {
+                0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+                m => n -= m,
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the message at the front of the stream, e.g. a header.
+    /// `parse` returns the message and its length once the data holds all
+    /// of it. Yields `None` at EOF.
+    pub(crate) fn poll_read<V, F>(&mut self, parse: F) -> Poll<Option<V>, io::Error>
+    where
+        F: Fn(&[u8]) -> io::Result<Option<(V, usize)>>,
+    {
+        // Peeks to not read past the message, and consumes what is peeked
+        // until the message is complete.
+        let mut buf = [0; 512];
+        loop {
+            let n = match self.poll_peek(&mut buf)? {
+                Async::Ready(0) => return Ok(Async::Ready(None)),
+                Async::Ready(n) => n,
+                Async::NotReady => return Ok(Async::NotReady),
+            };
+            let len = self.data.len();
+            self.data.extend_from_slice(&buf[..n]);
+            match parse(&self.data)? {
+                Some((v, end)) => {
+                    self.consume(end - len)?;
+                    self.data.clear();
+                    return Ok(Async::Ready(Some(v)));
+                }
+                None => self.consume(n)?,
+            }
+        }
+    }
+
+    /// Writes `data`, and removes what is written from it.
+    pub(crate) fn poll_write(&mut self, data: &mut Vec<u8>) -> Poll<(), io::Error> {
+        let mut conn = (*self.io).as_ref().as_inner();
+        while !data.is_empty() {
+            if !self.nio.is_write_ready() {
+                return Ok(Async::NotReady);
+            }
+            match conn.write(data) {
+                Ok(n) => {
+                    data.drain(..n);
+                }
+                Err(e) => match e.kind() {
+                    io::ErrorKind::WouldBlock => {
+                        self.nio.schedule_write()?;
+                        return Ok(Async::NotReady);
+                    }
+                    _ => return Err(e),
+                },
+            }
+        }
+        self.nio.cancel_write()?;
+        Ok(Async::Ready(()))
+    }
+
+    #[inline]
+    pub(crate) fn into_inner(self) -> T {
+        let Peek { nio, io, .. } = self;
+        drop(nio);
+        match Rc::try_unwrap(io) {
+            Ok(io) => io,
+            Err(..) => ::unreachable(),
+        }
+    }
+}
+
 #[inline]
 pub fn split<T>(io: T) -> io::Result<(RecvHalf<T>, SendHalf<T>)>
 where
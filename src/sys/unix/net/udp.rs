@@ -0,0 +1,81 @@
+use std::io;
+use std::net::{self, SocketAddr};
+use std::os::unix::io::{AsRawFd, RawFd};
+
+use futures::{Async, Poll};
+
+use sys::unix::nio::Nio;
+
+#[derive(Debug)]
+struct Socket(net::UdpSocket);
+
+impl AsRawFd for Socket {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl AsRef<Self> for Socket {
+    #[inline]
+    fn as_ref(&self) -> &Self {
+        self
+    }
+}
+
+pub(crate) struct UdpSocket {
+    nio: Nio<Socket>,
+}
+
+impl UdpSocket {
+    #[inline]
+    pub(crate) fn try_from(socket: net::UdpSocket) -> io::Result<Self> {
+        socket.set_nonblocking(true)?;
+        Ok(UdpSocket {
+            nio: Nio::try_from(Socket(socket))?,
+        })
+    }
+
+    #[inline]
+    pub(crate) fn as_inner(&self) -> &net::UdpSocket {
+        &self.nio.get_ref().0
+    }
+
+    pub(crate) fn poll_send_to(&mut self, buf: &[u8], addr: &SocketAddr) -> Poll<usize, io::Error> {
+        if !self.nio.is_write_ready() {
+            return Ok(Async::NotReady);
+        }
+        match self.nio.get_ref().0.send_to(buf, addr) {
+            Ok(n) => {
+                self.nio.cancel_write()?;
+                Ok(Async::Ready(n))
+            }
+            Err(e) => match e.kind() {
+                io::ErrorKind::WouldBlock => {
+                    self.nio.schedule_write()?;
+                    Ok(Async::NotReady)
+                }
+                _ => Err(e),
+            },
+        }
+    }
+
+    pub(crate) fn poll_recv_from(&mut self, buf: &mut [u8]) -> Poll<(usize, SocketAddr), io::Error> {
+        if !self.nio.is_read_ready() {
+            return Ok(Async::NotReady);
+        }
+        match self.nio.get_ref().0.recv_from(buf) {
+            Ok(r) => {
+                self.nio.schedule_read()?;
+                Ok(Async::Ready(r))
+            }
+            Err(e) => match e.kind() {
+                io::ErrorKind::WouldBlock => {
+                    self.nio.schedule_read()?;
+                    Ok(Async::NotReady)
+                }
+                _ => Err(e),
+            },
+        }
+    }
+}
@@ -617,6 +617,11 @@ where
     pub fn into_recv(self) -> Recv<T> {
         Recv(IStream::from(self.0.nio))
     }
+
+    #[inline]
+    pub fn into_inner(self) -> T {
+        (self.0).nio.into_inner()
+    }
 }
 
 pub struct RecvHalf<T>(IStream<T, Rc<UnsafeCell<Nio<TcpStream, T>>>>);
@@ -663,6 +668,43 @@ where
     }
 }
 
+/// Reads the data at the front of a stream without consuming more of it
+/// than asked. Not supported with overlapped I/O.
+pub(crate) struct Peek<T> {
+    _marker: PhantomData<T>,
+}
+
+impl<T> Peek<T>
+where
+    T: AsRef<TcpStream>,
+{
+    #[inline]
+    pub(crate) fn try_from(_io: T) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "Peeking is not supported on Windows",
+        ))
+    }
+
+    #[inline]
+    pub(crate) fn poll_read<V, F>(&mut self, _parse: F) -> Poll<Option<V>, io::Error>
+    where
+        F: Fn(&[u8]) -> io::Result<Option<(V, usize)>>,
+    {
+        ::unreachable()
+    }
+
+    #[inline]
+    pub(crate) fn poll_write(&mut self, _data: &mut Vec<u8>) -> Poll<(), io::Error> {
+        ::unreachable()
+    }
+
+    #[inline]
+    pub(crate) fn into_inner(self) -> T {
+        ::unreachable()
+    }
+}
+
 #[inline]
 pub fn split<T>(io: T) -> io::Result<(RecvHalf<T>, SendHalf<T>)>
 where
@@ -3,6 +3,7 @@ use std::io;
 use std::marker::PhantomData;
 use std::mem;
 use std::os::windows::io::AsRawSocket;
+use std::ptr;
 use std::rc::Rc;
 
 use winapi::shared::minwindef::{FALSE, UCHAR, ULONG};
@@ -172,6 +173,17 @@ impl<H, T> Nio<H, T> {
         &mut self.io
     }
 
+    /// Cancels the schedule of `io` and returns it.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        CURRENT_LOOP.with(|eloop| {
+            unsafe { eloop.as_mut() }.as_mut_inner().cancel(self.token);
+        });
+        let io = unsafe { ptr::read(&self.io) };
+        mem::forget(self);
+        io
+    }
+
     #[inline]
     pub fn is_read_ready(&self) -> bool {
         CURRENT_LOOP.with(|current_loop| {
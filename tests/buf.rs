@@ -1,9 +1,10 @@
+#[macro_use]
 extern crate ruyi;
 
 use std::io::{Read, Write};
 use std::mem;
 
-use ruyi::buf::ByteBuf;
+use ruyi::buf::{ByteBuf, Error};
 use ruyi::buf::codec::{f64, u32, u8, u8s};
 
 #[test]
@@ -513,3 +514,242 @@ fn windows() {
         None
     );
 }
+
+#[test]
+fn hex_dump_parse() {
+    let mut buf = ByteBuf::with_growth(5);
+    let data: Vec<u8> = (0..40u8).map(|i| i.wrapping_mul(37)).collect();
+    buf.append(&data[..], u8s::append).unwrap();
+    let dump = format!("{}", buf.as_hex_dump());
+    let parsed = ruyi::buf::HexDump::parse(&dump).unwrap();
+    assert_eq!(parsed, buf);
+
+    let parsed = hex_dump!(
+        "00000000h: 48 65 6C 6C 6F 2C 20 77 6F 72 6C 64 21 0D 0A 00   Hello, world!...",
+        "00000010h: FF 41                                             .A"
+    );
+    assert_eq!(parsed.as_bytes().as_ref(), &b"Hello, world!\r\n\x00\xffA"[..]);
+
+    let parsed = hex_dump!("de ad be ef\n  01 02");
+    assert_eq!(parsed.as_bytes().as_ref(), &[0xde, 0xad, 0xbe, 0xef, 0x01, 0x02][..]);
+
+    assert!(ruyi::buf::HexDump::parse("00000000h: 4G").is_err());
+}
+
+#[test]
+fn hex_dump_format() {
+    assert_eq!(format!("{}", ByteBuf::new().as_hex_dump()), "");
+
+    let mut buf = ByteBuf::new();
+    buf.append(&b"0123456789ABCDE\xE9"[..], u8s::append).unwrap();
+    let dump = format!("{}", buf.as_hex_dump());
+    let lines: Vec<&str> = dump.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].ends_with("   0123456789ABCDE\u{E9}"));
+    assert_eq!(lines[1], format!("00000010h:{:51}", ""));
+    assert_eq!(ruyi::buf::HexDump::parse(&dump).unwrap(), buf);
+}
+
+#[test]
+fn hex_dump_diff() {
+    let left = hex_dump!("00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F 10 11");
+    let mut right = hex_dump!("00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F 10 11");
+    assert_eq!(format!("{}", left.as_hex_dump().diff(&right)), "");
+
+    right.set(1, 0xFF, u8::set).unwrap();
+    right.append(0x12, u8::append).unwrap();
+    let diff = format!("{}", left.as_hex_dump().diff(&right));
+    let lines: Vec<&str> = diff.lines().collect();
+    assert_eq!(lines.len(), 6);
+    assert!(lines[0].starts_with("- 00000000h: 00 01 02"));
+    assert!(lines[1].starts_with("+ 00000000h: 00 FF 02"));
+    assert_eq!(lines[2], format!("{:15} ^^", ""));
+    assert!(lines[3].starts_with("- 00000010h: 10 11   "));
+    assert!(lines[4].starts_with("+ 00000010h: 10 11 12"));
+    assert_eq!(lines[5], format!("{:18} ^^", ""));
+}
+
+#[test]
+fn checksums() {
+    use ruyi::buf::checksum::{self, Checksum, Xxh32, Xxh64};
+
+    let vectors: [(&[u8], u32, u32, u32); 2] = [
+        (b"", 0, 0, 1),
+        (b"123456789", 0xCBF4_3926, 0xE306_9283, 0x091E_01DE),
+    ];
+    for &(data, crc32, crc32c, adler32) in vectors.iter() {
+        let mut buf = ByteBuf::with_growth(2);
+        buf.append(data, u8s::append).unwrap();
+        buf.append(0xEE, u8::append).unwrap();
+        let len = data.len();
+        assert_eq!(buf.get_exact(0, len, checksum::crc32_exact).unwrap(), crc32);
+        assert_eq!(buf.get_exact(0, len, checksum::crc32c_exact).unwrap(), crc32c);
+        assert_eq!(buf.get_exact(0, len, checksum::adler32_exact).unwrap(), adler32);
+    }
+
+    let text = b"Nobody inspects the spammish repetition";
+    let mut buf = ByteBuf::with_growth(3);
+    buf.append(&text[..], u8s::append).unwrap();
+    assert_eq!(buf.get(0, checksum::xxh32).unwrap(), 0xE229_3B2F);
+    assert_eq!(buf.get(0, checksum::xxh64).unwrap(), 0xFBCE_A83C_8A37_8BF1);
+    assert_eq!(Xxh32::new().sum(), 0x02CC_5D05);
+    assert_eq!(Xxh64::new().sum(), 0xEF46_DB37_51D8_E999);
+
+    let data: Vec<u8> = (0..5000u32).map(|i| (i * 31 % 251) as u8).collect();
+    let mut buf = ByteBuf::with_growth(7);
+    buf.append(&data[..], u8s::append).unwrap();
+    let mut xxh = Xxh64::new();
+    xxh.update(&data);
+    assert_eq!(buf.get(0, checksum::xxh64).unwrap(), xxh.sum());
+    let mut xxh = Xxh32::with_seed(1);
+    xxh.update(&data);
+    assert_ne!(buf.get(0, checksum::xxh32).unwrap(), xxh.sum());
+    assert!(buf.get_exact(0, 5001, checksum::crc32_exact).is_err());
+}
+
+#[test]
+fn checksum_trailer() {
+    use ruyi::buf::checksum;
+
+    let mut buf = ByteBuf::with_growth(5);
+    buf.append(&b"123456789"[..], u8s::append).unwrap();
+    assert_eq!(buf.append(0, checksum::append_crc32).unwrap(), 4);
+    assert_eq!(buf.len(), 13);
+    assert_eq!(buf.get(9, u32::big_endian::get).unwrap(), 0xCBF4_3926);
+    assert!(buf.get(0, checksum::verify_crc32).unwrap());
+
+    buf.set(3, b'x', u8::set).unwrap();
+    assert!(!buf.get(0, checksum::verify_crc32).unwrap());
+
+    let mut buf = ByteBuf::new();
+    buf.append(0, checksum::append_crc32).unwrap();
+    assert!(buf.get(0, checksum::verify_crc32).unwrap());
+    assert!(buf.append(10, checksum::append_crc32).is_err());
+}
+
+#[test]
+fn find_any() {
+    let mut buf = ByteBuf::with_growth(4);
+    buf.append(&b"GET / HTTP/1.1\r\nHost: x\n\r\n"[..], u8s::append).unwrap();
+    assert_eq!(buf.find(b"\r\n"), Some(14));
+    assert_eq!(buf.rfind(b"\r\n"), Some(24));
+    assert_eq!(buf.find(b":"), Some(20));
+    assert_eq!(buf.rfind(b"/"), Some(10));
+
+    let needles: [&[u8]; 3] = [b"\r\n", b"\n", b"Host"];
+    assert_eq!(buf.find_any(&needles), Some((14, 0)));
+    assert_eq!(buf.find_any_from(&needles, 15), Some((15, 1)));
+    assert_eq!(buf.find_any_from(&needles, 16), Some((16, 2)));
+    assert_eq!(buf.find_any_from(&needles, 17), Some((23, 1)));
+    assert_eq!(buf.find_any_from(&needles, 24), Some((24, 0)));
+    assert_eq!(buf.find_any_from(&needles, 25), Some((25, 1)));
+    assert_eq!(buf.find_any(&[b"none", b"\t"]), None);
+
+    let needles: [&[u8]; 5] = [b"xyz", b"1.1", b"HTTP", b"/ ", b"Host: x"];
+    assert_eq!(buf.find_any(&needles), Some((4, 3)));
+    assert_eq!(buf.find_any_from(&needles, 5), Some((6, 2)));
+    assert_eq!(buf.find_any_from(&needles, 7), Some((11, 1)));
+    assert_eq!(buf.find_any_from(&needles, 12), Some((16, 4)));
+    assert_eq!(buf.find_any_from(&needles, 17), None);
+}
+
+#[test]
+fn bits() {
+    use ruyi::buf::BitOrder;
+
+    let mut buf = ByteBuf::with_growth(1);
+    {
+        let mut w = buf.as_bit_writer(BitOrder::Msb);
+        w.write_bits(0b101, 3).unwrap();
+        w.write_bits(0x1234_5678_9ABC_DEF0, 64).unwrap();
+        w.write_bit(true).unwrap();
+        w.align().unwrap();
+        w.write_bits(0xF, 4).unwrap();
+    }
+    assert_eq!(buf.len(), 10);
+    assert_eq!(buf.get(0, u8::get).unwrap(), 0b1010_0010);
+    assert_eq!(buf.get(9, u8::get).unwrap(), 0xF0);
+    {
+        let mut r = buf.as_bit_reader(BitOrder::Msb);
+        assert_eq!(r.read_bits(3).unwrap(), 0b101);
+        assert_eq!(r.read_bits(64).unwrap(), 0x1234_5678_9ABC_DEF0);
+        assert!(r.read_bit().unwrap());
+        assert!(!r.is_aligned());
+        r.align();
+        assert_eq!(r.bits_left(), 8);
+        match r.read_bits(9) {
+            Err(Error::Underflow) => (),
+            v => panic!("Expect underflow, got {:?}", v),
+        }
+        assert_eq!(r.read_bits(8).unwrap(), 0xF0);
+    }
+
+    {
+        let mut w = buf.as_bit_writer(BitOrder::Lsb);
+        w.write_bits(0b01, 2).unwrap();
+        w.write_bits(0x3FF, 10).unwrap();
+    }
+    assert_eq!(buf.get(0, u8::get).unwrap(), 0b1111_1101);
+    assert_eq!(buf.get(1, u8::get).unwrap(), 0b0000_1111);
+    let mut r = buf.as_bit_reader(BitOrder::Lsb);
+    assert_eq!(r.read_bits(2).unwrap(), 0b01);
+    assert_eq!(r.read_bits(10).unwrap(), 0x3FF);
+}
+
+#[test]
+fn exp_golomb() {
+    use ruyi::buf::BitOrder;
+
+    let mut buf = ByteBuf::new();
+    {
+        let mut w = buf.as_bit_writer(BitOrder::Msb);
+        for v in 0..5 {
+            w.write_exp_golomb(v).unwrap();
+        }
+        for v in [0, 1, -1, 2, -2].iter() {
+            w.write_signed_exp_golomb(*v).unwrap();
+        }
+        w.write_exp_golomb(u64::max_value() - 1).unwrap();
+    }
+    // 1 010 011 00100 00101, 1 010 011 00100 00101
+    assert_eq!(buf.get(0, u8::get).unwrap(), 0b1010_0110);
+    assert_eq!(buf.get(1, u8::get).unwrap(), 0b0100_0010);
+    let mut r = buf.as_bit_reader(BitOrder::Msb);
+    for v in 0..5 {
+        assert_eq!(r.read_exp_golomb().unwrap(), v);
+    }
+    for v in [0, 1, -1, 2, -2].iter() {
+        assert_eq!(r.read_signed_exp_golomb().unwrap(), *v);
+    }
+    assert_eq!(r.read_exp_golomb().unwrap(), u64::max_value() - 1);
+}
+
+#[test]
+fn with_limit() {
+    let mut buf = ByteBuf::with_limit(10);
+    assert_eq!(buf.append(0x0102_0304, u32::big_endian::append), Ok(4));
+    assert_eq!(buf.append(&[5, 6, 7, 8][..], u8s::append), Ok(4));
+    // Nothing of a value that does not fit is kept.
+    assert_eq!(buf.append(0x090A_0B0C, u32::big_endian::append), Err(()));
+    assert_eq!(buf.len(), 8);
+    assert_eq!(buf.append(9, u8::append), Ok(1));
+    assert_eq!(buf.prepend(&[1, 2][..], u8s::prepend), Err(()));
+    assert_eq!(buf.len(), 9);
+    assert_eq!(buf.as_bytes().as_ref(), &[1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+    let mut w = buf.as_writer();
+    assert_eq!(w.write(&[10, 11, 12]).unwrap(), 1);
+    assert!(w.write(&[11]).is_err());
+
+    // Consumed blocks no longer count.
+    assert_eq!(buf.skip(10), 10);
+    buf.compact();
+    assert_eq!(buf.append(&[0; 10][..], u8s::append), Ok(10));
+    assert!(buf.try_extend(ByteBuf::from(vec![1])).is_err());
+
+    let mut buf = ByteBuf::with_limit(4);
+    assert!(buf.try_extend(ByteBuf::from(vec![1, 2, 3])).is_ok());
+    let rest = buf.try_extend(ByteBuf::from(vec![4, 5])).unwrap_err();
+    assert_eq!(rest.len(), 2);
+    assert_eq!(buf.len(), 3);
+}
@@ -0,0 +1,274 @@
+extern crate ruyi;
+
+use ruyi::buf::{Appender, ByteBuf, Error, ReadIter};
+use ruyi::buf::codec::{self, str, u32, Be, Decode, Encode, Le, Varint};
+
+#[derive(Debug, PartialEq)]
+struct Header {
+    id: u32,
+    flags: u16,
+    seq: i64,
+}
+
+impl Encode for Header {
+    fn encode(&self, chain: &mut Appender) -> Result<usize, ()> {
+        Ok(Be(self.id).encode(chain)? + Le(self.flags).encode(chain)?
+            + Varint(self.seq).encode(chain)?)
+    }
+}
+
+impl Decode for Header {
+    fn decode(chain: &mut ReadIter) -> Result<Self, Error> {
+        Ok(Header {
+            id: Be::decode(chain)?.0,
+            flags: Le::decode(chain)?.0,
+            seq: Varint::decode(chain)?.0,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Message {
+    header: Header,
+    tag: Option<u8>,
+    body: Vec<Be<u32>>,
+    digest: [u8; 4],
+}
+
+impl Encode for Message {
+    fn encode(&self, chain: &mut Appender) -> Result<usize, ()> {
+        (&self.header, &self.tag, &self.body, &self.digest).encode(chain)
+    }
+}
+
+impl Decode for Message {
+    fn decode(chain: &mut ReadIter) -> Result<Self, Error> {
+        let (header, tag, body, digest) = Decode::decode(chain)?;
+        Ok(Message {
+            header,
+            tag,
+            body,
+            digest,
+        })
+    }
+}
+
+#[test]
+fn encode_wrappers() {
+    let mut buf = ByteBuf::with_growth(1);
+    assert_eq!(4, buf.append(&Be(0x12345678u32), codec::encode).unwrap());
+    assert_eq!(4, buf.append(&Le(0x12345678u32), codec::encode).unwrap());
+    assert_eq!(3, buf.append(&Varint(0x23456u32), codec::encode).unwrap());
+
+    assert_eq!(0x12345678, buf.get(0, u32::big_endian::get).unwrap());
+    assert_eq!(0x12345678, buf.get(4, u32::little_endian::get).unwrap());
+    assert_eq!(0x23456, buf.get(8, u32::varint::get).unwrap());
+
+    let v: (Be<u32>, Le<u32>, Varint<u32>) = buf.read(codec::decode).unwrap();
+    assert_eq!((Be(0x12345678), Le(0x12345678), Varint(0x23456)), v);
+    assert!(buf.is_empty());
+}
+
+#[test]
+fn encode_message() {
+    let msg = Message {
+        header: Header {
+            id: 7,
+            flags: 0x0102,
+            seq: -1,
+        },
+        tag: Some(9),
+        body: vec![Be(1), Be(2), Be(3)],
+        digest: [0xDE, 0xAD, 0xBE, 0xEF],
+    };
+
+    let mut buf = ByteBuf::with_growth(1);
+    let n = buf.append(&msg, codec::encode).unwrap();
+    assert_eq!(n, buf.len());
+    // id + flags + seq + tag + count + body + digest
+    assert_eq!(n, 4 + 2 + 10 + 2 + 1 + 12 + 4);
+
+    let decoded: Message = buf.read(codec::decode).unwrap();
+    assert_eq!(msg, decoded);
+    assert!(buf.is_empty());
+}
+
+#[test]
+fn decode_underflow() {
+    let mut buf = ByteBuf::with_growth(1);
+    buf.append(&vec![Be(1u16), Be(2u16)], codec::encode).unwrap();
+    buf.append(&None::<u8>, codec::encode).unwrap();
+    assert_eq!(6, buf.len());
+
+    let v: (Vec<Be<u16>>, Option<u8>) = buf.read(codec::decode).unwrap();
+    assert_eq!((vec![Be(1), Be(2)], None), v);
+
+    buf.append(&vec![true, false], codec::encode).unwrap();
+    buf.skip(1);
+    match buf.read(codec::decode::<Be<u32>>) {
+        Err(Error::Underflow) => {}
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn str_prefixed() {
+    let mut buf = ByteBuf::with_growth(2);
+    buf.append("你好", str::u8_prefixed::append).unwrap();
+    buf.append("ruyi", str::u16be_prefixed::append).unwrap();
+    buf.prepend("head", str::u32le_prefixed::prepend).unwrap();
+    buf.append("", str::varint_prefixed::append).unwrap();
+    assert_eq!(buf.len(), 8 + 7 + 6 + 1);
+    assert_eq!(buf.get(8, str::u8_prefixed::get).unwrap(), "你好");
+    assert_eq!(buf.get(15, str::u16be_prefixed::get).unwrap(), "ruyi");
+
+    assert_eq!(buf.read(str::u32le_prefixed::read).unwrap(), "head");
+    assert_eq!(buf.read(str::u8_prefixed::read).unwrap(), "你好");
+    assert_eq!(buf.read(str::u16be_prefixed::read).unwrap(), "ruyi");
+    assert_eq!(buf.read(str::varint_prefixed::read).unwrap(), "");
+    assert!(buf.is_empty());
+
+    let long = "x".repeat(256);
+    assert!(buf.append(long.as_str(), str::u8_prefixed::append).is_err());
+
+    // A varint prefix of 2 bytes split across blocks.
+    buf.append(long.as_str(), str::varint_prefixed::append).unwrap();
+    assert_eq!(buf.get(0, str::varint_prefixed::get).unwrap(), long);
+    assert_eq!(buf.read(str::varint_prefixed::read).unwrap(), long);
+}
+
+#[test]
+fn str_terminated() {
+    let mut buf = ByteBuf::with_growth(3);
+    buf.append("GET / HTTP/1.1", str::crlf_terminated::append).unwrap();
+    buf.append("a\rb\nc\r", str::crlf_terminated::append).unwrap();
+    buf.append("key", str::nul_terminated::append).unwrap();
+    assert_eq!(buf.get(0, str::crlf_terminated::get).unwrap(), "GET / HTTP/1.1");
+
+    assert_eq!(buf.read(str::crlf_terminated::read).unwrap(), "GET / HTTP/1.1");
+    assert_eq!(buf.read(str::crlf_terminated::read).unwrap(), "a\rb\nc\r");
+    assert_eq!(buf.read(str::nul_terminated::read).unwrap(), "key");
+    assert!(buf.is_empty());
+
+    assert!(buf.append("a\r\nb", str::crlf_terminated::append).is_err());
+    assert!(buf.append("a\0b", str::nul_terminated::append).is_err());
+    buf.append(&b"no end"[..], codec::u8s::append).unwrap();
+    match buf.read(str::nul_terminated::read) {
+        Err(Error::Underflow) => (),
+        v => panic!("Expect underflow, got {:?}", v),
+    }
+}
+
+#[test]
+fn str_invalid_utf8() {
+    let mut buf = ByteBuf::new();
+    buf.append(&[2u8, 0xc3, 0x28][..], codec::u8s::append).unwrap();
+    match buf.get(0, str::u8_prefixed::get) {
+        Err(Error::InvalidUtf8) => (),
+        v => panic!("Expect invalid UTF-8, got {:?}", v),
+    }
+    match buf.read(str::u8_prefixed::read) {
+        Err(Error::InvalidUtf8) => (),
+        v => panic!("Expect invalid UTF-8, got {:?}", v),
+    }
+}
+
+#[test]
+fn codec_128() {
+    let x = 0x0102_0304_0506_0708_090a_0b0c_0d0e_0f10u128;
+    let mut buf = ByteBuf::with_growth(3);
+    buf.append(x, codec::u128::big_endian::append).unwrap();
+    buf.append(x, codec::u128::little_endian::append).unwrap();
+    buf.append(u128::max_value(), codec::u128::varint::append).unwrap();
+    buf.append(i128::min_value(), codec::i128::zigzag::append).unwrap();
+    buf.prepend(-2, codec::i128::varint::prepend).unwrap();
+    assert_eq!(buf.len(), 19 + 16 + 16 + 19 + 19);
+    assert_eq!(buf.get(19, codec::u8::get).unwrap(), 0x01);
+    assert_eq!(buf.get(35, codec::u8::get).unwrap(), 0x10);
+    assert_eq!(buf.get(19, codec::u128::big_endian::get).unwrap(), x);
+
+    assert_eq!(buf.read(codec::i128::varint::read).unwrap(), -2);
+    assert_eq!(buf.read(codec::u128::big_endian::read).unwrap(), x);
+    assert_eq!(buf.read(codec::u128::little_endian::read).unwrap(), x);
+    assert_eq!(buf.read(codec::u128::varint::read).unwrap(), u128::max_value());
+    assert_eq!(buf.read(codec::i128::zigzag::read).unwrap(), i128::min_value());
+    assert!(buf.is_empty());
+}
+
+#[test]
+fn codec_uuid() {
+    let s = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+    let id = codec::uuid::parse(s).unwrap();
+    assert_eq!(codec::uuid::to_hyphenated(id), s);
+    assert!(codec::uuid::parse("67e55044-10b1-426f-9247_bb680e5fe0c8").is_none());
+    assert!(codec::uuid::parse("67e55044-10b1-426f-9247-bb680e5fe0cg").is_none());
+
+    let mut buf = ByteBuf::new();
+    buf.append(id, codec::uuid::append).unwrap();
+    assert_eq!(buf.get(0, codec::u8::get).unwrap(), 0x67);
+    assert_eq!(buf.read(codec::uuid::read).unwrap(), id);
+}
+
+#[test]
+fn codec_hex() {
+    let data: Vec<u8> = (0..=255).collect();
+    let mut buf = ByteBuf::with_growth(7);
+    assert_eq!(buf.append(&data[..], codec::hex::append).unwrap(), 512);
+    assert_eq!(buf.prepend(&[0xABu8, 0xCD][..], codec::hex::prepend).unwrap(), 4);
+    assert_eq!(buf.get_exact(0, 6, codec::hex::get_exact).unwrap(), vec![0xAB, 0xCD, 0x00]);
+    assert_eq!(buf.read_exact(4, codec::hex::read_exact).unwrap(), vec![0xAB, 0xCD]);
+    assert_eq!(buf.get(0, codec::hex::get).unwrap(), data);
+    assert_eq!(buf.read(codec::hex::read).unwrap(), data);
+
+    buf.append(&b"0A1bFf"[..], codec::u8s::append).unwrap();
+    assert_eq!(buf.read(codec::hex::read).unwrap(), vec![0x0A, 0x1B, 0xFF]);
+    for bad in [&b"0g"[..], &b"abc"[..]].iter() {
+        buf.append(*bad, codec::u8s::append).unwrap();
+        match buf.read(codec::hex::read) {
+            Err(Error::InvalidEncoding) => (),
+            v => panic!("Expect invalid encoding, got {:?}", v),
+        }
+        buf.skip(bad.len());
+    }
+}
+
+#[test]
+fn codec_base64() {
+    use codec::base64::{standard, url_safe};
+
+    let cases: [(&[u8], &str, &str); 4] = [
+        (b"f", "Zg==", "Zg"),
+        (b"fo", "Zm8=", "Zm8"),
+        (b"foo", "Zm9v", "Zm9v"),
+        (b"\xfb\xff\xbf", "+/+/", "-_-_"),
+    ];
+    for &(raw, std, url) in cases.iter() {
+        let mut buf = ByteBuf::with_growth(1);
+        buf.append(raw, standard::append).unwrap();
+        assert_eq!(buf.as_bytes().as_ref(), std.as_bytes());
+        assert_eq!(buf.get(0, standard::get).unwrap(), raw);
+        assert_eq!(buf.read(standard::read).unwrap(), raw);
+
+        buf.append(raw, url_safe::append).unwrap();
+        assert_eq!(buf.as_bytes().as_ref(), url.as_bytes());
+        assert_eq!(buf.read(url_safe::read).unwrap(), raw);
+    }
+
+    let data: Vec<u8> = (0..2000).map(|i| (i * 7) as u8).collect();
+    let mut buf = ByteBuf::with_growth(13);
+    let n = buf.append(&data[..], url_safe::append).unwrap();
+    buf.prepend(&b"ab"[..], standard::prepend).unwrap();
+    assert_eq!(buf.read_exact(4, standard::read_exact).unwrap(), b"ab");
+    assert_eq!(buf.len(), n);
+    assert_eq!(buf.read(url_safe::read).unwrap(), data);
+
+    for bad in ["Z", "Zg=", "Zg==Zg==", "Zm9*", "Zm9_"].iter() {
+        buf.append(bad.as_bytes(), codec::u8s::append).unwrap();
+        match buf.read(standard::read) {
+            Err(Error::InvalidEncoding) => (),
+            v => panic!("Expect invalid encoding for {}, got {:?}", bad, v),
+        }
+        let len = buf.len();
+        buf.skip(len);
+    }
+}
@@ -0,0 +1,53 @@
+// Helpers shared by the tests, not all of which each test uses.
+#![allow(dead_code)]
+
+use std::io;
+use std::vec;
+
+use futures::stream::{self, IterOk};
+
+use ruyi::buf::ByteBuf;
+use ruyi::proto::Decoder;
+use ruyi::proto::dns::{RData, Record};
+
+// Streams `wire` in buffers of `size` bytes.
+pub fn chunks(wire: &[u8], size: usize) -> IterOk<vec::IntoIter<ByteBuf>, io::Error> {
+    let bufs: Vec<_> = wire.chunks(size).map(|c| ByteBuf::from(c.to_vec())).collect();
+    stream::iter_ok(bufs)
+}
+
+// Decodes `wire` fed `size` bytes at a time.
+pub fn decode<D>(codec: &mut D, wire: &[u8], size: usize) -> io::Result<Vec<D::Item>>
+where
+    D: Decoder<Error = io::Error>,
+{
+    let mut data = ByteBuf::new();
+    let mut items = Vec::new();
+    for chunk in wire.chunks(size) {
+        data.extend(ByteBuf::from(chunk.to_vec()));
+        while let Some(item) = codec.decode(&mut data)? {
+            items.push(item);
+        }
+    }
+    if let Some(item) = codec.decode_eof(&mut data)? {
+        items.push(item);
+    }
+    Ok(items)
+}
+
+// The SOA record of the zone `test`.
+pub fn soa() -> Record {
+    Record::new(
+        "test",
+        3600,
+        RData::Soa {
+            mname: "ns.test".to_string(),
+            rname: "admin.test".to_string(),
+            serial: 1,
+            refresh: 7200,
+            retry: 900,
+            expire: 86400,
+            minimum: 300,
+        },
+    )
+}
@@ -0,0 +1,78 @@
+extern crate futures;
+extern crate ruyi;
+
+mod common;
+
+use std::io::ErrorKind;
+
+use ruyi::proto::dns::{rcode, rtype, Edns, Message, Question, RData, Record};
+
+use common::soa;
+
+#[test]
+fn dns_message() {
+    let query = Message::query(0x1234, Question::new("www.example.com.", rtype::A));
+    let mut response = Message::response(&query);
+    response.recursion_available = true;
+    response.answers = vec![
+        Record::new("www.example.com", 300, RData::Cname("web.example.com".to_string())),
+        Record::new("web.example.com", 60, RData::A("10.0.0.1".parse().unwrap())),
+    ];
+    response.authorities = vec![
+        Record::new("example.com", 60, RData::Ns("ns.example.com".to_string())),
+        soa(),
+    ];
+    response.additionals = vec![
+        Record::new(
+            "example.com",
+            60,
+            RData::Mx {
+                preference: 10,
+                exchange: "mail.example.com".to_string(),
+            },
+        ),
+        Record::new(
+            "_sip._tcp.example.com",
+            60,
+            RData::Srv {
+                priority: 1,
+                weight: 2,
+                port: 5060,
+                target: "sip.example.com".to_string(),
+            },
+        ),
+        Record::new("example.com", 60, RData::Txt(vec![b"v=spf1".to_vec(), Vec::new()])),
+        Record::new("ns.example.com", 60, RData::Aaaa("::1".parse().unwrap())),
+    ];
+    let mut edns = Edns::new(1232);
+    edns.dnssec_ok = true;
+    edns.options = vec![(10, vec![1, 2, 3, 4, 5, 6, 7, 8])];
+    response.edns = Some(edns);
+
+    let wire = response.encode().unwrap();
+    assert_eq!(&wire[..4], [0x12, 0x34, 0x81, 0x80]);
+    // The question name is at offset 12, and the answer refers to it.
+    assert_eq!(&wire[12..29], b"\x03www\x07example\x03com\x00");
+    assert_eq!(&wire[33..35], [0xC0, 0x0C]);
+    assert_eq!(Message::decode(&wire).unwrap(), response);
+
+    // The extended response code needs EDNS.
+    response.rcode = rcode::BADVERS;
+    let decoded = Message::decode(&response.encode().unwrap()).unwrap();
+    assert_eq!(decoded.rcode, rcode::BADVERS);
+    response.edns = None;
+    assert_eq!(response.encode().unwrap_err().kind(), ErrorKind::InvalidInput);
+
+    let question = Message::query(1, Question::new("a.b", rtype::AAAA));
+    let wire = question.encode().unwrap();
+    for n in 0..wire.len() {
+        assert!(Message::decode(&wire[..n]).is_err());
+    }
+    // A name pointing to itself.
+    let mut wire = vec![0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0xC0, 12, 0, 1, 0, 1];
+    assert_eq!(Message::decode(&wire).unwrap_err().kind(), ErrorKind::InvalidData);
+    wire[13] = 14;
+    assert_eq!(Message::decode(&wire).unwrap_err().kind(), ErrorKind::InvalidData);
+    let long = Question::new(&"a.".repeat(128), rtype::A);
+    assert!(Message::query(1, long).encode().is_err());
+}
@@ -0,0 +1,235 @@
+// The resolver needs `net::UdpSocket`, which is only on Unix.
+#![cfg(unix)]
+
+extern crate futures;
+extern crate ruyi;
+
+mod common;
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::{self, IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use futures::future;
+
+use ruyi::proto::dns::{rcode, rtype, Config, Edns, Message, Question, RData, Record, Resolver};
+use ruyi::reactor;
+
+use common::soa;
+
+fn addr(s: &str) -> SocketAddr {
+    s.parse().unwrap()
+}
+
+#[test]
+fn dns_config() {
+    let config = Config::parse(
+        "# comment\n\
+         nameserver 10.0.0.1\n\
+         nameserver fe80::1%eth0 ; comment\n\
+         nameserver invalid\n\
+         domain local.\n\
+         search example.com corp.example.com\n\
+         options ndots:2 timeout:3 attempts:4 rotate\n",
+    );
+    assert_eq!(
+        config.nameservers,
+        vec![addr("10.0.0.1:53"), addr("[fe80::1]:53")]
+    );
+    assert_eq!(config.search, vec!["example.com", "corp.example.com"]);
+    assert_eq!(config.ndots, 2);
+    assert_eq!(config.timeout, Duration::from_secs(3));
+    assert_eq!(config.attempts, 4);
+
+    assert_eq!(Config::parse(""), Config::default());
+}
+
+// Answers queries with `answer` on UDP and TCP of the same port, truncating
+// UDP responses for names starting with "big".
+fn serve<F>(answer: F) -> (SocketAddr, Arc<AtomicUsize>)
+where
+    F: Fn(&Message) -> Message + Send + Sync + 'static,
+{
+    let udp = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server = udp.local_addr().unwrap();
+    let tcp = net::TcpListener::bind(server).unwrap();
+    let hits = Arc::new(AtomicUsize::new(0));
+    let answer = Arc::new(answer);
+    {
+        let (hits, answer) = (hits.clone(), answer.clone());
+        thread::spawn(move || {
+            let mut buf = [0; 1500];
+            loop {
+                let (n, from) = udp.recv_from(&mut buf).unwrap();
+                hits.fetch_add(1, Ordering::SeqCst);
+                let query = Message::decode(&buf[..n]).unwrap();
+                let mut response = answer(&query);
+                if query.questions[0].name.starts_with("big") {
+                    response.truncated = true;
+                    response.answers.clear();
+                }
+                udp.send_to(&response.encode().unwrap(), from).unwrap();
+            }
+        });
+    }
+    thread::spawn(move || {
+        for conn in tcp.incoming() {
+            let mut conn = conn.unwrap();
+            let mut len = [0; 2];
+            conn.read_exact(&mut len).unwrap();
+            let mut buf = vec![0; (len[0] as usize) << 8 | len[1] as usize];
+            conn.read_exact(&mut buf).unwrap();
+            let wire = answer(&Message::decode(&buf).unwrap()).encode().unwrap();
+            conn.write_all(&[(wire.len() >> 8) as u8, wire.len() as u8])
+                .unwrap();
+            conn.write_all(&wire).unwrap();
+        }
+    });
+    (server, hits)
+}
+
+fn answer(query: &Message) -> Message {
+    let question = &query.questions[0];
+    let mut response = Message::response(query);
+    match (question.name.as_str(), question.qtype) {
+        ("host.test", rtype::A) => {
+            response.answers = vec![
+                Record::new("host.test", 60, RData::A("10.0.0.1".parse().unwrap())),
+                Record::new("host.test", 30, RData::A("10.0.0.2".parse().unwrap())),
+            ];
+        }
+        ("host.test", rtype::AAAA) => {
+            response.answers = vec![Record::new("host.test", 60, RData::Aaaa("::1".parse().unwrap()))];
+        }
+        ("alias.test", rtype::A) => {
+            response.answers = vec![
+                Record::new("alias.test", 60, RData::Cname("host.test".to_string())),
+                Record::new("host.test", 60, RData::A("10.0.0.1".parse().unwrap())),
+            ];
+        }
+        ("chain.test", rtype::A) => {
+            response.answers = vec![Record::new("chain.test", 60, RData::Cname("alias.test".to_string()))];
+        }
+        ("loop.test", rtype::A) => {
+            response.answers = vec![Record::new("loop.test", 60, RData::Cname("loop.test".to_string()))];
+        }
+        ("big.test", rtype::TXT) => {
+            let txt = vec![vec![b'x'; 255]; 8];
+            response.answers = vec![Record::new("big.test", 60, RData::Txt(txt))];
+        }
+        ("short.corp.test", rtype::A) => {
+            response.answers = vec![Record::new("short.corp.test", 60, RData::A("10.0.0.3".parse().unwrap()))];
+        }
+        ("host.test", _) => {
+            response.authorities = vec![soa()];
+        }
+        _ => {
+            response.rcode = rcode::NX_DOMAIN;
+            response.authorities = vec![soa()];
+        }
+    }
+    response
+}
+
+#[test]
+fn dns_resolver() {
+    ruyi::net::init();
+    let (server, hits) = serve(answer);
+    let mut config = Config::default();
+    config.nameservers = vec![server];
+    config.search = vec!["corp.test".to_string()];
+    let resolver = Resolver::new(config);
+
+    let ips = reactor::run(future::lazy(|| resolver.lookup_ip("host.test"))).unwrap();
+    let expected: Vec<IpAddr> = vec![
+        "10.0.0.1".parse().unwrap(),
+        "10.0.0.2".parse().unwrap(),
+        "::1".parse().unwrap(),
+    ];
+    assert_eq!(ips, expected);
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+    // Answered from the cache.
+    let ips = reactor::run(future::lazy(|| resolver.clone().lookup_ip("HOST.test."))).unwrap();
+    assert_eq!(ips, expected);
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+    let ips = reactor::run(future::lazy(|| resolver.lookup_ip("10.1.1.1"))).unwrap();
+    assert_eq!(ips, vec!["10.1.1.1".parse::<IpAddr>().unwrap()]);
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+    let records = reactor::run(future::lazy(|| resolver.query("alias.test", rtype::A))).unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].data, RData::A("10.0.0.1".parse().unwrap()));
+
+    // The target of an alias left unresolved is looked up, here from the
+    // cache.
+    let hit = hits.load(Ordering::SeqCst);
+    let records = reactor::run(future::lazy(|| resolver.query("chain.test", rtype::A))).unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].data, RData::A("10.0.0.1".parse().unwrap()));
+    assert_eq!(hits.load(Ordering::SeqCst), hit + 1);
+
+    let e = reactor::run(future::lazy(|| resolver.query("loop.test", rtype::A))).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::InvalidData);
+
+    // The search domain is appended to names of fewer dots than `ndots`.
+    let ips = reactor::run(future::lazy(|| resolver.lookup_ip("short"))).unwrap();
+    assert_eq!(ips, vec!["10.0.0.3".parse::<IpAddr>().unwrap()]);
+
+    // No data, but the name exists.
+    let records = reactor::run(future::lazy(|| resolver.query("host.test", rtype::MX))).unwrap();
+    assert!(records.is_empty());
+
+    let hit = hits.load(Ordering::SeqCst);
+    for _ in 0..2 {
+        let e = reactor::run(future::lazy(|| resolver.query("missing.test", rtype::A)))
+            .unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::NotFound);
+    }
+    // Both of the names tried are cached as missing.
+    assert_eq!(hits.load(Ordering::SeqCst), hit + 2);
+
+    // Truncated over UDP, so asked again over TCP.
+    let records = reactor::run(future::lazy(|| resolver.query("big.test", rtype::TXT))).unwrap();
+    match records[0].data {
+        RData::Txt(ref txt) => assert_eq!(txt.len(), 8),
+        ref data => panic!("Unexpected {:?}", data),
+    }
+}
+
+#[test]
+fn dns_resolver_timeout() {
+    ruyi::net::init();
+    // Receives queries but never answers.
+    let silent = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let mut config = Config::default();
+    config.nameservers = vec![silent.local_addr().unwrap()];
+    config.timeout = Duration::from_secs(1);
+    config.attempts = 1;
+    let resolver = Resolver::new(config);
+
+    let start = Instant::now();
+    let e = reactor::run(future::lazy(|| resolver.query("host.test", rtype::A))).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::TimedOut);
+    assert!(start.elapsed() < Duration::from_secs(5));
+    let mut buf = [0; 512];
+    let (n, _) = silent.recv_from(&mut buf).unwrap();
+    let query = Message::decode(&buf[..n]).unwrap();
+    assert_eq!(query.questions, vec![Question::new("host.test", rtype::A)]);
+    assert_eq!(query.edns, Some(Edns::new(1232)));
+
+    // A response to another query is ignored.
+    let (server, _) = serve(|query| {
+        let mut response = answer(query);
+        response.id = response.id.wrapping_add(1);
+        response
+    });
+    let mut config = resolver.config().clone();
+    config.nameservers = vec![server];
+    let resolver = Resolver::new(config);
+    let e = reactor::run(future::lazy(|| resolver.query("host.test", rtype::A))).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::TimedOut);
+}
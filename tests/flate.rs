@@ -0,0 +1,110 @@
+#![cfg(feature = "flate")]
+
+extern crate futures;
+extern crate ruyi;
+
+use std::io;
+
+use futures::{stream, Future, Stream};
+
+use ruyi::buf::ByteBuf;
+use ruyi::proto::flate::{self, Format};
+
+// "hello.txt" containing "hello hello hello\n", compressed by gzip(1).
+const HELLO_GZ: &[u8] = &[
+    0x1f, 0x8b, 0x08, 0x08, 0xfb, 0x3e, 0xd5, 0x6a, 0x00, 0x03, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x2e,
+    0x74, 0x78, 0x74, 0x00, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x5c, 0x00, 0x3b,
+    0x7c, 0x8a, 0xdf, 0x12, 0x00, 0x00, 0x00,
+];
+
+fn bufs(data: &[u8], size: usize) -> Vec<ByteBuf> {
+    data.chunks(size).map(|c| ByteBuf::from(c.to_vec())).collect()
+}
+
+fn concat(bufs: Vec<ByteBuf>) -> Vec<u8> {
+    let mut data = Vec::new();
+    for buf in bufs {
+        data.extend_from_slice(&buf.as_bytes());
+    }
+    data
+}
+
+fn compress(data: &[u8], size: usize, format: Format) -> Vec<ByteBuf> {
+    let s = stream::iter_ok::<_, io::Error>(bufs(data, size));
+    flate::deflate(s, format).collect().wait().unwrap()
+}
+
+fn decompress(data: &[u8], size: usize, format: Format) -> io::Result<Vec<u8>> {
+    let s = stream::iter_ok::<_, io::Error>(bufs(data, size));
+    flate::inflate(s, format).collect().wait().map(concat)
+}
+
+#[test]
+fn flate_round_trip() {
+    let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251 * (i % 13)) as u8).collect();
+    for &format in &[Format::Raw, Format::Zlib, Format::Gzip] {
+        let compressed = concat(compress(&data, 3000, format));
+        assert!(compressed.len() < data.len());
+        assert_eq!(decompress(&compressed, 1, format).unwrap(), data);
+        assert_eq!(decompress(&compressed, 4096, format).unwrap(), data);
+    }
+}
+
+#[test]
+fn flate_flush_per_buf() {
+    let msgs = ["first message", "second message", "third"];
+    let s = stream::iter_ok::<_, io::Error>(msgs.iter().map(|m| ByteBuf::from(m.as_bytes().to_vec())));
+    let compressed = flate::deflate(s, Format::Zlib).collect().wait().unwrap();
+    assert_eq!(compressed.len(), msgs.len() + 1);
+
+    // Each message can be decompressed as soon as its buffer arrives.
+    let mut inflated = flate::inflate(stream::iter_ok::<_, io::Error>(compressed), Format::Zlib).wait();
+    for msg in &msgs {
+        let buf = inflated.next().unwrap().unwrap();
+        assert_eq!(&*buf.as_bytes(), msg.as_bytes());
+    }
+    assert!(inflated.next().is_none());
+}
+
+#[test]
+fn flate_gzip_header() {
+    assert_eq!(decompress(HELLO_GZ, 5, Format::Gzip).unwrap(), b"hello hello hello\n");
+
+    let empty = concat(compress(&[], 1, Format::Gzip));
+    assert_eq!(decompress(&empty, 1, Format::Gzip).unwrap(), b"");
+}
+
+#[test]
+fn flate_errors() {
+    let mut corrupted = HELLO_GZ.to_vec();
+    let n = corrupted.len();
+    corrupted[n - 8] ^= 1;
+    let e = decompress(&corrupted, 7, Format::Gzip).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+    let e = decompress(&HELLO_GZ[..n - 3], 7, Format::Gzip).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+
+    let e = decompress(b"not gzip data", 7, Format::Gzip).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+    let mut trailing = HELLO_GZ.to_vec();
+    trailing.push(0);
+    let e = decompress(&trailing, 100, Format::Gzip).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn flate_max_output_len() {
+    let data = vec![0u8; 1 << 20];
+    let compressed = concat(compress(&data, data.len(), Format::Gzip));
+    assert!(compressed.len() < 2048);
+
+    let s = stream::iter_ok::<_, io::Error>(vec![ByteBuf::from(compressed)]);
+    let mut inflate = flate::inflate(s, Format::Gzip);
+    inflate.set_max_output_len(4096);
+    let items = inflate.collect().wait().unwrap();
+    assert_eq!(items.len(), data.len() / 4096);
+    assert!(items.iter().all(|item| item.len() == 4096));
+    assert_eq!(concat(items), data);
+}
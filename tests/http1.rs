@@ -0,0 +1,223 @@
+extern crate futures;
+extern crate ruyi;
+
+mod common;
+
+use std::io::{self, Read, Write};
+use std::net::{self, SocketAddr};
+use std::thread;
+use std::time::Duration;
+
+use futures::{Future, Stream};
+
+use ruyi::buf::ByteBuf;
+use ruyi::proto::{Decoder, Encoder, FrameTooLong, Framed};
+use ruyi::proto::http1::{ClientCodec, Request, Response, ServerCodec, Version};
+use ruyi::service::http::Server;
+
+use common::chunks;
+
+fn requests(wire: &[u8], size: usize, codec: ServerCodec) -> io::Result<Vec<Request>> {
+    Framed::new(chunks(wire, size), codec).collect().wait()
+}
+
+fn encode<E>(codec: &mut E, item: E::Item) -> Vec<u8>
+where
+    E: Encoder<Error = io::Error>,
+{
+    let mut dst = ByteBuf::new();
+    codec.encode(item, &mut dst).unwrap();
+    dst.as_bytes().into_owned()
+}
+
+#[test]
+fn http1_requests() {
+    let wire = b"\r\nGET /health HTTP/1.1\r\nHost: a\r\n\r\n\
+                 POST /echo HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello\
+                 PUT /up HTTP/1.1\nTransfer-Encoding: chunked\n\n\
+                 3;ext=1\r\nabc\r\n2\r\nde\r\n0\r\nX-Sum: 5\r\n\r\n\
+                 GET / HTTP/1.0\r\n\r\n";
+    for size in 1..wire.len() {
+        let requests = requests(wire, size, ServerCodec::new()).unwrap();
+        assert_eq!(requests.len(), 4);
+
+        assert_eq!(requests[0].method, "GET");
+        assert_eq!(requests[0].target, "/health");
+        assert_eq!(requests[0].headers.get("host"), Some("a"));
+        assert!(requests[0].body.is_empty());
+        assert!(requests[0].keep_alive());
+
+        assert_eq!(requests[1].body.as_bytes().as_ref(), b"hello");
+
+        assert_eq!(requests[2].body.as_bytes().as_ref(), b"abcde");
+        assert_eq!(requests[2].headers.get("X-Sum"), Some("5"));
+
+        assert_eq!(requests[3].version, Version::Http10);
+        assert!(!requests[3].keep_alive());
+    }
+}
+
+#[test]
+fn http1_request_errors() {
+    let invalid: &[&[u8]] = &[
+        b"GET /\r\n\r\n",
+        b"GET / HTTP/2.0\r\n\r\n",
+        b"GET / HTTP/1.1\r\nNo colon\r\n\r\n",
+        b"GET / HTTP/1.1\r\nA: b\r\n folded\r\n\r\n",
+        b"POST / HTTP/1.1\r\nContent-Length: 1\r\nContent-Length: 2\r\n\r\n",
+        b"POST / HTTP/1.1\r\nContent-Length: 3\r\nTransfer-Encoding: chunked\r\n\r\n",
+        b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n",
+    ];
+    for wire in invalid {
+        let e = requests(wire, 3, ServerCodec::new()).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData, "{:?}", wire);
+    }
+
+    let e = requests(b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nab", 4, ServerCodec::new())
+        .unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+
+    let mut codec = ServerCodec::new();
+    codec.set_max_body_len(4).set_max_head_len(64);
+    let wire = b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello";
+    let e = requests(wire, 100, codec).unwrap_err();
+    let too_long = e.get_ref().and_then(|e| e.downcast_ref::<FrameTooLong>());
+    assert_eq!(too_long, Some(&FrameTooLong { len: 5, max: 4 }));
+
+    let mut codec = ServerCodec::new();
+    codec.set_max_body_len(4);
+    let wire = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n2\r\nde\r\n";
+    let e = requests(wire, 1, codec).unwrap_err();
+    assert!(e.get_ref().map_or(false, |e| e.is::<FrameTooLong>()));
+
+    let mut codec = ServerCodec::new();
+    codec.set_max_head_len(64);
+    let wire = format!("GET / HTTP/1.1\r\nX: {}\r\n\r\n", "y".repeat(100));
+    let e = requests(wire.as_bytes(), 1, codec).unwrap_err();
+    assert!(e.get_ref().map_or(false, |e| e.is::<FrameTooLong>()));
+}
+
+#[test]
+fn http1_responses() {
+    let mut codec = ServerCodec::new();
+    let mut data = ByteBuf::from(b"HEAD / HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\n\r\n".to_vec());
+    codec.decode(&mut data).unwrap().unwrap();
+    codec.decode(&mut data).unwrap().unwrap();
+
+    let mut response = Response::new(200);
+    response.headers.insert("Content-Type", "text/plain");
+    response.body = ByteBuf::from(b"hi".to_vec());
+    assert_eq!(
+        encode(&mut codec, response),
+        b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 2\r\n\r\n".to_vec()
+    );
+
+    let mut response = Response::new(200);
+    response.headers.insert("Transfer-Encoding", "chunked");
+    response.body = ByteBuf::from(b"hello".to_vec());
+    assert_eq!(
+        encode(&mut codec, response),
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n".to_vec()
+    );
+
+    assert_eq!(
+        encode(&mut codec, Response::new(204)),
+        b"HTTP/1.1 204 No Content\r\n\r\n".to_vec()
+    );
+
+    let mut response = Response::new(200);
+    response.headers.insert("X-Bad", "a\r\nInjected: yes");
+    let e = codec.encode(response, &mut ByteBuf::new()).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn http1_client() {
+    let mut codec = ClientCodec::new();
+    let mut request = Request::new("POST", "/items");
+    request.headers.insert("Host", "example.com");
+    request.body = ByteBuf::from(b"{}".to_vec());
+    assert_eq!(
+        encode(&mut codec, request),
+        b"POST /items HTTP/1.1\r\nHost: example.com\r\nContent-Length: 2\r\n\r\n{}".to_vec()
+    );
+    encode(&mut codec, Request::new("HEAD", "/"));
+    encode(&mut codec, Request::new("GET", "/"));
+
+    let wire = b"HTTP/1.1 100 Continue\r\n\r\n\
+                 HTTP/1.1 201 Created\r\nContent-Length: 3\r\n\r\nyes\
+                 HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n\
+                 HTTP/1.0 200\r\n\r\nuntil the end";
+    for size in 1..wire.len() {
+        let responses: Vec<_> = Framed::new(chunks(wire, size), {
+            let mut c = ClientCodec::new();
+            encode(&mut c, Request::new("POST", "/items"));
+            encode(&mut c, Request::new("HEAD", "/"));
+            encode(&mut c, Request::new("GET", "/"));
+            c
+        }).collect()
+            .wait()
+            .unwrap();
+        assert_eq!(responses.len(), 4);
+        assert_eq!(responses[0].status, 100);
+        assert_eq!(responses[1].reason, "Created");
+        assert_eq!(responses[1].body.as_bytes().as_ref(), b"yes");
+        assert!(responses[2].body.is_empty());
+        assert_eq!(responses[3].reason, "");
+        assert_eq!(responses[3].body.as_bytes().as_ref(), b"until the end");
+    }
+}
+
+fn free_addr() -> SocketAddr {
+    net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+}
+
+fn connect(addr: &SocketAddr) -> net::TcpStream {
+    for _ in 0..100 {
+        if let Ok(conn) = net::TcpStream::connect(addr) {
+            return conn;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    panic!("Cannot connect to {}", addr);
+}
+
+#[test]
+fn http1_server() {
+    ruyi::net::init();
+    let addr = free_addr();
+    let mut server = Server::with_handler(|request: Request| {
+        let mut response = Response::new(200);
+        response.body = request.body;
+        if response.body.is_empty() {
+            response.body = ByteBuf::from(request.target.into_bytes());
+        }
+        Ok(response)
+    });
+    server.addr(addr).max_body_len(16).start().unwrap();
+
+    // Pipelined requests, answered in order on the same connection.
+    let mut conn = connect(&addr);
+    conn.write_all(
+        b"GET /a HTTP/1.1\r\n\r\nPOST /b HTTP/1.1\r\nContent-Length: 4\r\n\r\nbody\
+          GET /c HTTP/1.1\r\nConnection: close\r\n\r\n",
+    ).unwrap();
+    let mut received = String::new();
+    conn.read_to_string(&mut received).unwrap();
+    assert_eq!(
+        received,
+        "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n/a\
+         HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nbody\
+         HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 2\r\n\r\n/c"
+    );
+
+    let mut conn = connect(&addr);
+    conn.write_all(b"POST / HTTP/1.1\r\nContent-Length: 100\r\n\r\n")
+        .unwrap();
+    let mut received = String::new();
+    conn.read_to_string(&mut received).unwrap();
+    assert!(received.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+}
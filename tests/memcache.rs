@@ -0,0 +1,318 @@
+extern crate futures;
+extern crate ruyi;
+
+mod common;
+
+use std::collections::HashMap;
+use std::io;
+
+use futures::{future, Future, Stream};
+
+use ruyi::buf::ByteBuf;
+use ruyi::net::{tcp, TcpListener, TcpStream};
+use ruyi::proto::{Duplex, Encoder, FrameTooLong};
+use ruyi::proto::memcache::Client;
+use ruyi::proto::memcache::binary::{opcode, status, BinaryCodec, Magic, Packet};
+use ruyi::proto::memcache::text::{ClientCodec, Item, Request, Response, ServerCodec, Store};
+use ruyi::reactor;
+
+use common::decode;
+
+fn buf(data: &[u8]) -> ByteBuf {
+    ByteBuf::from(data.to_vec())
+}
+
+fn encode<E: Encoder>(codec: &mut E, item: E::Item) -> Vec<u8>
+where
+    E::Error: std::fmt::Debug,
+{
+    let mut data = ByteBuf::new();
+    codec.encode(item, &mut data).unwrap();
+    data.as_bytes().into_owned()
+}
+
+fn set(key: &str, data: &[u8], mode: Store, noreply: bool) -> Request {
+    Request::Store {
+        mode,
+        key: key.to_owned(),
+        flags: 5,
+        exptime: 0,
+        data: buf(data),
+        noreply,
+    }
+}
+
+#[test]
+fn memcache_text_requests() {
+    let mut client = ClientCodec::new();
+    let wire: Vec<u8> = vec![
+        Request::Get(vec!["a".to_owned(), "b".to_owned()]),
+        Request::Gets(vec!["a".to_owned()]),
+        set("a", b"hello", Store::Set, false),
+        set("a", b"", Store::Cas(42), true),
+        Request::Delete {
+            key: "a".to_owned(),
+            noreply: false,
+        },
+        Request::Incr {
+            key: "n".to_owned(),
+            delta: 3,
+            noreply: false,
+        },
+    ].into_iter()
+        .flat_map(|req| encode(&mut client, req))
+        .collect();
+    assert_eq!(
+        String::from_utf8_lossy(&wire),
+        "get a b\r\ngets a\r\nset a 5 0 5\r\nhello\r\ncas a 5 0 0 42 noreply\r\n\r\n\
+         delete a\r\nincr n 3\r\n"
+    );
+
+    for &size in &[1, 4, wire.len()] {
+        let reqs = decode(&mut ServerCodec::new(), &wire, size).unwrap();
+        assert_eq!(reqs.len(), 6);
+        assert_eq!(reqs[2], set("a", b"hello", Store::Set, false));
+        assert_eq!(reqs[3], set("a", b"", Store::Cas(42), true));
+        assert!(reqs[3].noreply());
+    }
+
+    let e = client
+        .encode(Request::Get(vec!["bad key".to_owned()]), &mut ByteBuf::new())
+        .unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+
+    for wire in &[&b"nope\r\n"[..], b"set a 0 0 x\r\n", b"set a 0 0 1\r\nab\r\n", b"get\r\n"] {
+        let e = decode(&mut ServerCodec::new(), wire, 1).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+    let e = decode(&mut ServerCodec::new(), b"set a 0 0 3\r\nab", 1).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+
+    let mut server = ServerCodec::new();
+    server.set_max_data_len(4);
+    let e = decode(&mut server, b"set a 0 0 5\r\n", 100).unwrap_err();
+    let too_long = e.get_ref().and_then(|e| e.downcast_ref::<FrameTooLong>());
+    assert_eq!(too_long, Some(&FrameTooLong { len: 5, max: 4 }));
+}
+
+#[test]
+fn memcache_text_responses() {
+    let mut server = ServerCodec::new();
+    let items = vec![
+        Item {
+            key: "a".to_owned(),
+            flags: 1,
+            data: buf(b"hello"),
+            cas: Some(7),
+        },
+        Item {
+            key: "b".to_owned(),
+            flags: 0,
+            data: buf(b""),
+            cas: None,
+        },
+    ];
+    let wire: Vec<u8> = vec![
+        Response::Values(items),
+        Response::Values(vec![]),
+        Response::Stored,
+        Response::Exists,
+        Response::Number(10),
+        Response::ServerError("out of memory".to_owned()),
+    ].into_iter()
+        .flat_map(|res| encode(&mut server, res))
+        .collect();
+    assert_eq!(
+        String::from_utf8_lossy(&wire),
+        "VALUE a 1 5 7\r\nhello\r\nVALUE b 0 0\r\n\r\nEND\r\nEND\r\nSTORED\r\nEXISTS\r\n\
+         10\r\nSERVER_ERROR out of memory\r\n"
+    );
+
+    for &size in &[1, 5, wire.len()] {
+        let responses = decode(&mut ClientCodec::new(), &wire, size).unwrap();
+        assert_eq!(responses.len(), 6);
+        match responses[0] {
+            Response::Values(ref items) => {
+                assert_eq!(items.len(), 2);
+                assert_eq!(items[0].cas, Some(7));
+                assert_eq!(items[0].data, buf(b"hello"));
+                assert_eq!(items[1].key, "b");
+            }
+            ref res => panic!("Unexpected {:?}", res),
+        }
+        assert_eq!(responses[1], Response::Values(vec![]));
+        assert_eq!(responses[4], Response::Number(10));
+    }
+
+    for wire in &[&b"WHAT\r\n"[..], b"VALUE a 0 1\r\nx\r\nSTORED\r\n"] {
+        let e = decode(&mut ClientCodec::new(), wire, 1).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+    let e = decode(&mut ClientCodec::new(), b"VALUE a 0 1\r\nx\r\n", 1).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn memcache_binary() {
+    // The GET request for "Hello" in the binary protocol specification.
+    let get: &[u8] = &[
+        0x80, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x48, 0x65, 0x6c, 0x6c, 0x6f,
+    ];
+    let mut req = Packet::request(opcode::GET);
+    req.key = buf(b"Hello");
+    assert_eq!(encode(&mut BinaryCodec::new(), req), get);
+    let packets = decode(&mut BinaryCodec::new(), get, 1).unwrap();
+    assert_eq!(packets.len(), 1);
+    assert_eq!(packets[0].magic, Magic::Request);
+    assert_eq!(packets[0].key, buf(b"Hello"));
+
+    let mut res = Packet::response(opcode::GET, status::NO_ERROR);
+    res.opaque = 0xDEAD_BEEF;
+    res.cas = 1;
+    res.extras = buf(&[0xde, 0xad, 0xbe, 0xef]);
+    res.value = buf(b"World");
+    let wire = encode(&mut BinaryCodec::new(), res);
+    assert_eq!(&wire[8..12], &[0, 0, 0, 9]);
+    let packets = decode(&mut BinaryCodec::new(), &wire, 3).unwrap();
+    assert_eq!(packets[0].magic, Magic::Response);
+    assert_eq!(packets[0].opaque, 0xDEAD_BEEF);
+    assert_eq!(packets[0].cas, 1);
+    assert_eq!(packets[0].extras, buf(&[0xde, 0xad, 0xbe, 0xef]));
+    assert!(packets[0].key.is_empty());
+    assert_eq!(packets[0].value, buf(b"World"));
+
+    let mut bad = get.to_vec();
+    bad[0] = 0x42;
+    let e = decode(&mut BinaryCodec::new(), &bad, 100).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+    let mut codec = BinaryCodec::new();
+    codec.set_max_body_len(4);
+    let e = decode(&mut codec, get, 100).unwrap_err();
+    let too_long = e.get_ref().and_then(|e| e.downcast_ref::<FrameTooLong>());
+    assert_eq!(too_long, Some(&FrameTooLong { len: 5, max: 4 }));
+
+    let e = decode(&mut BinaryCodec::new(), &get[..27], 100).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+// Serves the text protocol from a map to the first connection.
+fn fake_memcached(listener: TcpListener) -> impl Future<Item = (), Error = ()> {
+    listener
+        .incoming()
+        .unwrap()
+        .take(1)
+        .for_each(|(conn, _)| {
+            let (sink, stream) = tcp::framed(conn, ServerCodec::new()).unwrap().split();
+            let mut store: HashMap<String, (u32, Vec<u8>, u64)> = HashMap::new();
+            let mut next_cas = 0;
+            let responses = stream.filter_map(move |req| {
+                let noreply = req.noreply();
+                let res = match req {
+                    Request::Get(keys) | Request::Gets(keys) => Response::Values(
+                        keys.into_iter()
+                            .filter_map(|key| {
+                                store.get(&key).map(|&(flags, ref data, cas)| Item {
+                                    key: key.clone(),
+                                    flags,
+                                    data: ByteBuf::from(data.clone()),
+                                    cas: Some(cas),
+                                })
+                            })
+                            .collect(),
+                    ),
+                    Request::Store {
+                        mode, key, flags, data, ..
+                    } => {
+                        let stored = match (mode, store.get(&key)) {
+                            (Store::Cas(cas), Some(&(_, _, current))) if cas != current => {
+                                Response::Exists
+                            }
+                            (Store::Cas(..), None) => Response::NotFound,
+                            _ => Response::Stored,
+                        };
+                        if stored == Response::Stored {
+                            next_cas += 1;
+                            store.insert(key, (flags, data.as_bytes().into_owned(), next_cas));
+                        }
+                        stored
+                    }
+                    Request::Delete { key, .. } => match store.remove(&key) {
+                        Some(..) => Response::Deleted,
+                        None => Response::NotFound,
+                    },
+                    Request::Incr { key, delta, .. } | Request::Decr { key, delta, .. } => {
+                        match store.get_mut(&key) {
+                            Some(&mut (_, ref mut data, _)) => {
+                                let n = String::from_utf8_lossy(data).parse::<u64>().unwrap() + delta;
+                                *data = n.to_string().into_bytes();
+                                Response::Number(n)
+                            }
+                            None => Response::NotFound,
+                        }
+                    }
+                };
+                match noreply {
+                    true => None,
+                    false => Some(res),
+                }
+            });
+            responses.forward(sink).map(|_| ())
+        })
+        .map_err(|e| panic!("Fake server failed: {}", e))
+}
+
+#[test]
+fn memcache_client() {
+    ruyi::net::init();
+    let listener = TcpListener::builder()
+        .addr("127.0.0.1:0".parse().unwrap())
+        .build()
+        .unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = fake_memcached(listener);
+    let client = future::lazy(move || tcp::connect::<TcpStream>(&addr))
+        .and_then(|sender| {
+            let (r, s) = sender.into_twoway();
+            let reqs = vec![
+                set("k", b"v1", Store::Set, false),
+                set("n", b"1", Store::Set, true),
+                Request::Incr {
+                    key: "n".to_owned(),
+                    delta: 41,
+                    noreply: false,
+                },
+                Request::Gets(vec!["k".to_owned(), "missing".to_owned()]),
+            ];
+            Client::new(Duplex::new(r, s)).pipeline(reqs)
+        })
+        .and_then(|(client, responses)| {
+            assert_eq!(responses.len(), 3);
+            assert_eq!(responses[0], Response::Stored);
+            assert_eq!(responses[1], Response::Number(42));
+            let cas = match responses[2] {
+                Response::Values(ref items) if items.len() == 1 => items[0].cas.unwrap(),
+                ref res => panic!("Unexpected {:?}", res),
+            };
+            client
+                .call(set("k", b"v2", Store::Cas(cas), false))
+                .and_then(move |(client, res)| {
+                    assert_eq!(res, Some(Response::Stored));
+                    client.call(set("k", b"v3", Store::Cas(cas), false))
+                })
+        })
+        .and_then(|(client, res)| {
+            assert_eq!(res, Some(Response::Exists));
+            client.call(Request::Get(vec!["k".to_owned()]))
+        })
+        .map(|(_, res)| res);
+    let test = server.map_err(|()| unreachable!()).join(client);
+    let ((), res) = reactor::run(test).unwrap();
+
+    match res {
+        Some(Response::Values(ref items)) => assert_eq!(items[0].data, buf(b"v2")),
+        ref res => panic!("Unexpected {:?}", res),
+    }
+}
@@ -0,0 +1,433 @@
+extern crate futures;
+extern crate ruyi;
+
+mod common;
+
+use std::io;
+
+use ruyi::buf::ByteBuf;
+use ruyi::proto::{Encoder, FrameTooLong};
+use ruyi::proto::mqtt::{self, reason, Ack, ConnAck, Connect, MqttCodec, Packet, Property,
+                        Protocol, Publish, QoS, Reason, Session, SubAck, Subscribe, Subscription,
+                        Unsubscribe, Will};
+
+use common::decode;
+
+fn encode(codec: &mut MqttCodec, packet: Packet) -> Vec<u8> {
+    let mut data = ByteBuf::new();
+    codec.encode(packet, &mut data).unwrap();
+    data.as_bytes().into_owned()
+}
+
+fn round_trip(protocol: Protocol, packets: Vec<Packet>) {
+    let mut codec = MqttCodec::new();
+    codec.set_protocol(protocol);
+    let mut wire = Vec::new();
+    for packet in packets.clone() {
+        wire.extend(encode(&mut codec, packet));
+    }
+    for &size in &[1, 3, wire.len()] {
+        let mut codec = MqttCodec::new();
+        codec.set_protocol(protocol);
+        assert_eq!(decode(&mut codec, &wire, size).unwrap(), packets);
+    }
+}
+
+fn publish(topic: &str, qos: QoS, payload: &[u8]) -> Publish {
+    Publish::new(topic, qos, ByteBuf::from(payload.to_vec()))
+}
+
+#[test]
+fn mqtt_v311() {
+    let wire = b"\x10\x0F\x00\x04MQTT\x04\x02\x00\x3C\x00\x03abc\
+                 \x82\x08\x00\x01\x00\x03a/b\x01\
+                 \x32\x0C\x00\x03a/b\x00\x07hello\
+                 \xC0\x00";
+    for &size in &[1, 2, wire.len()] {
+        let mut codec = MqttCodec::new();
+        codec.set_protocol(Protocol::V5);
+        let packets = decode(&mut codec, wire, size).unwrap();
+        assert_eq!(codec.protocol(), Protocol::V311);
+        let mut p = publish("a/b", QoS::AtLeastOnce, b"hello");
+        p.packet_id = 7;
+        assert_eq!(
+            packets,
+            vec![
+                Packet::Connect(Connect::new(Protocol::V311, "abc")),
+                Packet::Subscribe(Subscribe {
+                    packet_id: 1,
+                    subscriptions: vec![Subscription::new("a/b", QoS::AtLeastOnce)],
+                    properties: Vec::new(),
+                }),
+                Packet::Publish(p),
+                Packet::PingReq,
+            ]
+        );
+        let mut codec = MqttCodec::new();
+        let mut encoded = Vec::new();
+        for packet in packets {
+            encoded.extend(encode(&mut codec, packet));
+        }
+        assert_eq!(encoded, &wire[..]);
+    }
+
+    let connect = Connect {
+        protocol: Protocol::V311,
+        clean_start: false,
+        keep_alive: 0,
+        client_id: String::new(),
+        will: Some(Will {
+            topic: "status".to_string(),
+            payload: b"offline".to_vec(),
+            qos: QoS::ExactlyOnce,
+            retain: true,
+            properties: Vec::new(),
+        }),
+        username: Some("user".to_string()),
+        password: Some(b"\x00pass".to_vec()),
+        properties: Vec::new(),
+    };
+    let mut p = publish("t", QoS::ExactlyOnce, &[0xAB; 200]);
+    p.packet_id = 0xFFFF;
+    p.dup = true;
+    p.retain = true;
+    round_trip(
+        Protocol::V311,
+        vec![
+            Packet::Connect(connect),
+            Packet::ConnAck(ConnAck {
+                session_present: true,
+                code: 0,
+                properties: Vec::new(),
+            }),
+            Packet::Publish(p),
+            Packet::Publish(publish("big", QoS::AtMostOnce, &vec![1; 20000])),
+            Packet::Publish(publish("empty", QoS::AtMostOnce, b"")),
+            Packet::PubAck(Ack::new(1)),
+            Packet::PubRec(Ack::new(2)),
+            Packet::PubRel(Ack::new(3)),
+            Packet::PubComp(Ack::new(4)),
+            Packet::SubAck(SubAck {
+                packet_id: 5,
+                codes: vec![0, 2, reason::V3_SUBSCRIBE_FAILURE],
+                properties: Vec::new(),
+            }),
+            Packet::Unsubscribe(Unsubscribe {
+                packet_id: 6,
+                filters: vec!["a/+".to_string(), "#".to_string()],
+                properties: Vec::new(),
+            }),
+            Packet::UnsubAck(SubAck {
+                packet_id: 6,
+                codes: Vec::new(),
+                properties: Vec::new(),
+            }),
+            Packet::PingResp,
+            Packet::Disconnect(Reason::default()),
+        ],
+    );
+
+    // The remaining length takes 2 and 3 bytes.
+    let mut codec = MqttCodec::new();
+    let wire = encode(&mut codec, Packet::Publish(publish("t", QoS::AtMostOnce, &[0; 200])));
+    assert_eq!(&wire[..3], [0x30, 0xCB, 0x01]);
+    let wire = encode(&mut codec, Packet::Publish(publish("t", QoS::AtMostOnce, &[0; 20000])));
+    assert_eq!(&wire[..4], [0x30, 0xA3, 0x9C, 0x01]);
+}
+
+#[test]
+fn mqtt_v5() {
+    let properties = vec![
+        Property::PayloadFormatIndicator(1),
+        Property::MessageExpiryInterval(3600),
+        Property::ContentType("text/plain".to_string()),
+        Property::ResponseTopic("reply".to_string()),
+        Property::CorrelationData(vec![1, 2, 3]),
+        Property::SubscriptionIdentifier(268_435_455),
+        Property::TopicAlias(2),
+        Property::UserProperty("k".to_string(), "v".to_string()),
+        Property::UserProperty("k".to_string(), "w".to_string()),
+    ];
+    let mut p = publish("a/b", QoS::AtLeastOnce, b"data");
+    p.packet_id = 9;
+    p.properties = properties;
+    let mut connect = Connect::new(Protocol::V5, "client");
+    connect.properties = vec![
+        Property::SessionExpiryInterval(60),
+        Property::ReceiveMaximum(10),
+        Property::MaximumPacketSize(1 << 20),
+        Property::AuthenticationMethod("SCRAM-SHA-1".to_string()),
+        Property::AuthenticationData(vec![0; 8]),
+    ];
+    connect.will = Some(Will {
+        topic: "will".to_string(),
+        payload: Vec::new(),
+        qos: QoS::AtLeastOnce,
+        retain: false,
+        properties: vec![Property::WillDelayInterval(5)],
+    });
+    let mut subscription = Subscription::new("s/#", QoS::ExactlyOnce);
+    subscription.no_local = true;
+    subscription.retain_as_published = true;
+    subscription.retain_handling = 2;
+    let packets = vec![
+        Packet::Connect(connect),
+        Packet::ConnAck(ConnAck {
+            session_present: false,
+            code: reason::SUCCESS,
+            properties: vec![
+                Property::AssignedClientIdentifier("auto-1".to_string()),
+                Property::ServerKeepAlive(30),
+                Property::MaximumQoS(1),
+                Property::RetainAvailable(0),
+                Property::TopicAliasMaximum(8),
+                Property::WildcardSubscriptionAvailable(1),
+                Property::SubscriptionIdentifierAvailable(1),
+                Property::SharedSubscriptionAvailable(0),
+                Property::ResponseInformation("info".to_string()),
+                Property::ServerReference("other".to_string()),
+            ],
+        }),
+        Packet::Publish(p),
+        Packet::PubAck(Ack::new(9)),
+        Packet::PubRec(Ack {
+            packet_id: 10,
+            code: reason::NO_MATCHING_SUBSCRIBERS,
+            properties: Vec::new(),
+        }),
+        Packet::PubComp(Ack {
+            packet_id: 11,
+            code: reason::PACKET_IDENTIFIER_NOT_FOUND,
+            properties: vec![Property::ReasonString("unknown".to_string())],
+        }),
+        Packet::Subscribe(Subscribe {
+            packet_id: 12,
+            subscriptions: vec![subscription, Subscription::new("t", QoS::AtMostOnce)],
+            properties: vec![Property::SubscriptionIdentifier(1)],
+        }),
+        Packet::SubAck(SubAck {
+            packet_id: 12,
+            codes: vec![reason::GRANTED_QOS_2, reason::NOT_AUTHORIZED],
+            properties: Vec::new(),
+        }),
+        Packet::UnsubAck(SubAck {
+            packet_id: 13,
+            codes: vec![reason::NO_SUBSCRIPTION_EXISTED],
+            properties: Vec::new(),
+        }),
+        Packet::Disconnect(Reason {
+            code: reason::DISCONNECT_WITH_WILL,
+            properties: Vec::new(),
+        }),
+        Packet::Auth(Reason {
+            code: reason::CONTINUE_AUTHENTICATION,
+            properties: vec![
+                Property::RequestProblemInformation(0),
+                Property::RequestResponseInformation(1),
+            ],
+        }),
+        Packet::Disconnect(Reason::default()),
+    ];
+    round_trip(Protocol::V5, packets);
+
+    // Reason codes and properties are left out when they can be.
+    let mut codec = MqttCodec::new();
+    codec.set_protocol(Protocol::V5);
+    assert_eq!(encode(&mut codec, Packet::PubAck(Ack::new(1))), [0x40, 2, 0, 1]);
+    let ack = Ack {
+        packet_id: 1,
+        code: reason::QUOTA_EXCEEDED,
+        properties: Vec::new(),
+    };
+    assert_eq!(encode(&mut codec, Packet::PubAck(ack.clone())), [0x40, 3, 0, 1, 0x97]);
+    assert_eq!(
+        decode(&mut codec, &[0x40, 4, 0, 1, 0x97, 0], 1).unwrap(),
+        vec![Packet::PubAck(ack)]
+    );
+    assert_eq!(encode(&mut codec, Packet::Disconnect(Reason::default())), [0xE0, 0]);
+
+    // The version of the CONNECT decoded is followed.
+    let mut server = MqttCodec::new();
+    let wire = encode(&mut codec, Packet::Connect(Connect::new(Protocol::V5, "c")));
+    decode(&mut server, &wire, wire.len()).unwrap();
+    assert_eq!(server.protocol(), Protocol::V5);
+    let wire = encode(&mut MqttCodec::new(), Packet::Connect(Connect::new(Protocol::V311, "c")));
+    decode(&mut codec, &wire, wire.len()).unwrap();
+    assert_eq!(codec.protocol(), Protocol::V311);
+}
+
+#[test]
+fn mqtt_invalid() {
+    let invalid: &[&[u8]] = &[
+        // A remaining length of 5 bytes.
+        b"\x30\xFF\xFF\xFF\xFF\x01",
+        // Invalid flags.
+        b"\x80\x00",
+        b"\xC1\x00",
+        b"\x36\x00",
+        // A DUP of QoS 0.
+        b"\x38\x03\x00\x01a",
+        b"\x00\x00",
+        // AUTH of MQTT 3.1.1.
+        b"\xF0\x00",
+        // An unsupported protocol level.
+        b"\x10\x0C\x00\x04MQTT\x03\x02\x00\x3C\x00\x00",
+        b"\x10\x0C\x00\x04MQTS\x04\x02\x00\x3C\x00\x00",
+        // The reserved connect flag.
+        b"\x10\x0C\x00\x04MQTT\x04\x03\x00\x3C\x00\x00",
+        // Trailing bytes.
+        b"\xC0\x01\x00",
+        b"\x40\x03\x00\x01\x00",
+        // SUBSCRIBE without topic filters and with reserved options.
+        b"\x82\x02\x00\x01",
+        b"\x82\x06\x00\x01\x00\x01a\x04",
+        // A QoS 1 PUBLISH of packet identifier 0.
+        b"\x32\x05\x00\x01a\x00\x00",
+        // Invalid UTF-8 and a null character.
+        b"\x30\x04\x00\x02\xC3\x28",
+        b"\x30\x03\x00\x01\x00",
+        // Truncated.
+        b"\x40\x01\x00",
+        b"\x30\x02\x00\x02",
+    ];
+    for wire in invalid {
+        let e = decode(&mut MqttCodec::new(), wire, 1).unwrap_err();
+        assert!(
+            e.kind() == io::ErrorKind::InvalidData || e.kind() == io::ErrorKind::UnexpectedEof,
+            "{:?}: {}",
+            wire,
+            e
+        );
+    }
+    let mut codec = MqttCodec::new();
+    codec.set_protocol(Protocol::V5);
+    // An unknown property.
+    assert!(decode(&mut codec, b"\x40\x05\x00\x01\x00\x01\x7F", 1).is_err());
+
+    let mut codec = MqttCodec::new();
+    codec.set_max_packet_len(10);
+    let e = decode(&mut codec, b"\x30\x0B", 2).unwrap_err();
+    let too_long = e.get_ref().and_then(|e| e.downcast_ref::<FrameTooLong>());
+    assert_eq!(too_long, Some(&FrameTooLong { len: 11, max: 10 }));
+
+    for packet in vec![
+        Packet::Publish(publish("t", QoS::AtLeastOnce, b"")),
+        Packet::Publish(publish(&"t".repeat(70000), QoS::AtMostOnce, b"")),
+        Packet::Auth(Reason::default()),
+        Packet::Unsubscribe(Unsubscribe {
+            packet_id: 1,
+            filters: Vec::new(),
+            properties: Vec::new(),
+        }),
+    ] {
+        let e = MqttCodec::new().encode(packet, &mut ByteBuf::new()).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+    }
+}
+
+#[test]
+fn mqtt_topic() {
+    for &(filter, topic, matches) in &[
+        ("a/b", "a/b", true),
+        ("a/b", "a/c", false),
+        ("a/+", "a/b", true),
+        ("a/+", "a/b/c", false),
+        ("a/+/c", "a//c", true),
+        ("a/#", "a", true),
+        ("a/#", "a/b/c", true),
+        ("#", "a/b", true),
+        ("+", "/a", false),
+        ("+/+", "/a", true),
+        ("#", "$SYS/uptime", false),
+        ("+/uptime", "$SYS/uptime", false),
+        ("$SYS/#", "$SYS/uptime", true),
+    ] {
+        assert_eq!(mqtt::topic_matches(filter, topic), matches, "{} {}", filter, topic);
+    }
+    for filter in &["#", "+", "a/+/b", "/", "a/#", "+/+/#"] {
+        assert!(mqtt::is_valid_filter(filter), "{}", filter);
+    }
+    for filter in &["", "a#", "a/#/b", "a+/b", "a/b+"] {
+        assert!(!mqtt::is_valid_filter(filter), "{}", filter);
+    }
+    assert!(mqtt::is_valid_topic("a/b"));
+    assert!(!mqtt::is_valid_topic("a/+"));
+    assert!(!mqtt::is_valid_topic(""));
+}
+
+#[test]
+fn mqtt_session() {
+    let mut sender = Session::new();
+    let mut receiver = Session::new();
+
+    // QoS 0 is passed on without packet identifiers.
+    let packet = sender.publish(publish("t", QoS::AtMostOnce, b"0")).unwrap();
+    assert_eq!(sender.inflight(), 0);
+    assert_eq!(receiver.recv(packet.clone()), (Some(packet), None));
+
+    // QoS 1.
+    let packet = sender.publish(publish("t", QoS::AtLeastOnce, b"1")).unwrap();
+    let id = match packet {
+        Packet::Publish(ref p) => p.packet_id,
+        _ => unreachable!(),
+    };
+    assert_eq!(id, 1);
+    assert_eq!(sender.inflight(), 1);
+    let (delivered, ack) = receiver.recv(packet.clone());
+    assert_eq!(delivered, Some(packet));
+    let ack = ack.unwrap();
+    assert_eq!(ack, Packet::PubAck(Ack::new(id)));
+    assert_eq!(sender.recv(ack.clone()), (Some(ack), None));
+    assert_eq!(sender.inflight(), 0);
+
+    // QoS 2, of which the duplicate is not passed on again.
+    let packet = sender.publish(publish("t", QoS::ExactlyOnce, b"2")).unwrap();
+    let mut dup = sender.resend();
+    assert_eq!(dup.len(), 1);
+    let dup = dup.pop().unwrap();
+    match dup {
+        Packet::Publish(ref p) => assert!(p.dup && p.packet_id == 2),
+        _ => panic!("Unexpected {}", dup),
+    }
+    let (delivered, rec) = receiver.recv(packet.clone());
+    assert_eq!(delivered, Some(packet));
+    let rec = rec.unwrap();
+    assert_eq!(receiver.recv(dup), (None, Some(rec.clone())));
+    let (delivered, rel) = sender.recv(rec);
+    assert_eq!(delivered, None);
+    let rel = rel.unwrap();
+    assert_eq!(rel, Packet::PubRel(Ack::new(2)));
+    assert_eq!(sender.resend(), vec![rel.clone()]);
+    let (delivered, comp) = receiver.recv(rel.clone());
+    assert_eq!(delivered, None);
+    let comp = comp.unwrap();
+    assert_eq!(comp, Packet::PubComp(Ack::new(2)));
+    assert_eq!(sender.recv(comp.clone()), (Some(comp), None));
+    assert_eq!(sender.inflight(), 0);
+    assert!(sender.resend().is_empty());
+    // The identifier is released.
+    match receiver.recv(rel).1 {
+        Some(Packet::PubComp(ack)) => assert_eq!(ack.code, reason::PACKET_IDENTIFIER_NOT_FOUND),
+        p => panic!("Unexpected {:?}", p),
+    }
+
+    // Identifiers in use are skipped.
+    let id = sender.packet_id().unwrap();
+    assert_eq!(id, 3);
+    sender.publish(publish("t", QoS::AtLeastOnce, b"")).unwrap();
+    assert_eq!(sender.packet_id().unwrap(), 5);
+    let ack = Packet::SubAck(SubAck {
+        packet_id: id,
+        codes: vec![0],
+        properties: Vec::new(),
+    });
+    assert_eq!(sender.recv(ack.clone()), (Some(ack), None));
+
+    assert_eq!(receiver.recv(Packet::PingReq), (None, Some(Packet::PingResp)));
+    assert_eq!(sender.recv(Packet::PingResp), (None, None));
+    assert_eq!(sender.ping(), None);
+    assert!(!sender.is_expired());
+    sender.set_keep_alive(60);
+    assert_eq!(sender.ping(), None);
+    assert!(!sender.is_expired());
+}
@@ -0,0 +1,351 @@
+extern crate futures;
+extern crate ruyi;
+
+mod common;
+
+use std::io;
+
+use futures::{stream, Async, AsyncSink, Future, Poll, Sink, StartSend, Stream};
+
+use ruyi::buf::ByteBuf;
+use ruyi::buf::codec::{u16, u8s};
+use ruyi::proto::{Decoder, DelimiterCodec, Duplex, Encoder, Endian, Framed, FrameTooLong,
+                  LengthFieldCodec, LineCodec, U16bePrefix, U32lePrefix, U8Prefix, Width};
+
+use common::chunks;
+
+// Accepts one item per call to `poll_complete`.
+#[derive(Default)]
+struct Frames {
+    sent: Vec<ByteBuf>,
+    busy: bool,
+}
+
+impl Sink for Frames {
+    type SinkItem = ByteBuf;
+    type SinkError = io::Error;
+
+    fn start_send(&mut self, item: ByteBuf) -> StartSend<ByteBuf, io::Error> {
+        if self.busy {
+            return Ok(AsyncSink::NotReady(item));
+        }
+        self.busy = true;
+        self.sent.push(item);
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), io::Error> {
+        self.busy = false;
+        Ok(Async::Ready(()))
+    }
+}
+
+fn frame(len: u16) -> ByteBuf {
+    let mut buf = ByteBuf::new();
+    buf.append(len, u16::big_endian::append).unwrap();
+    buf.append(&vec![0xAB; len as usize][..], u8s::append).unwrap();
+    buf
+}
+
+#[test]
+fn max_frame_len() {
+    let frames = vec![frame(100), frame(300)];
+    let mut s = U16bePrefix::from(stream::iter_ok::<_, io::Error>(frames));
+    s.set_max_frame_len(200);
+    let mut s = s.wait();
+
+    assert_eq!(s.next().unwrap().unwrap().len(), 100);
+    let e = s.next().unwrap().unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    let too_long = e.get_ref().and_then(|e| e.downcast_ref::<FrameTooLong>());
+    assert_eq!(too_long, Some(&FrameTooLong { len: 300, max: 200 }));
+    assert!(s.next().is_none());
+}
+
+#[test]
+fn prefix_sink() {
+    let mut reserved = ByteBuf::with_capacity(64);
+    reserved.try_reserve_in_head(4);
+    reserved.append(&b"hello"[..], u8s::append).unwrap();
+    let plain = ByteBuf::from(b"world!".to_vec());
+    let empty = ByteBuf::new();
+
+    let msgs = stream::iter_ok::<_, io::Error>(vec![reserved, plain, empty]);
+    let (sink, _) = U32lePrefix::from(Frames::default())
+        .send_all(msgs)
+        .wait()
+        .unwrap();
+    let frames = sink.into_inner().sent;
+    assert_eq!(frames[0].as_bytes().as_ref(), b"\x05\0\0\0hello");
+    assert_eq!(frames[1].as_bytes().as_ref(), b"\x06\0\0\0world!");
+    assert_eq!(frames[2].as_bytes().as_ref(), b"\0\0\0\0");
+
+    let mut decoded = U32lePrefix::from(stream::iter_ok::<_, io::Error>(frames)).wait();
+    assert_eq!(decoded.next().unwrap().unwrap().as_bytes().as_ref(), b"hello");
+    assert_eq!(decoded.next().unwrap().unwrap().as_bytes().as_ref(), b"world!");
+
+    let mut sink = U8Prefix::from(Frames::default());
+    let e = sink.start_send(ByteBuf::from(vec![0; 256])).unwrap_err();
+    let too_long = e.get_ref().and_then(|e| e.downcast_ref::<FrameTooLong>());
+    assert_eq!(too_long, Some(&FrameTooLong { len: 256, max: 255 }));
+}
+
+fn encode<E>(codec: &mut E, item: E::Item) -> Result<ByteBuf, E::Error>
+where
+    E: Encoder,
+{
+    let mut dst = ByteBuf::new();
+    codec.encode(item, &mut dst)?;
+    Ok(dst)
+}
+
+fn decode_all(codec: &LengthFieldCodec, wire: &[u8]) -> Vec<Vec<u8>> {
+    // One byte at a time, so that every header is seen incomplete first.
+    let bufs: Vec<_> = wire.iter().map(|&b| ByteBuf::from(vec![b])).collect();
+    codec
+        .clone()
+        .frames(stream::iter_ok::<_, io::Error>(bufs))
+        .map(|frame| frame.as_bytes().into_owned())
+        .collect()
+        .wait()
+        .unwrap()
+}
+
+#[test]
+fn length_field_decode() {
+    // A type byte, a 2-byte big-endian length of the whole frame, the body.
+    let codec = LengthFieldCodec::builder()
+        .offset(1)
+        .width(Width::Two)
+        .adjustment(-3)
+        .strip(3)
+        .build();
+    let wire = b"\x07\0\x06abc\x08\0\x03";
+    assert_eq!(decode_all(&codec, wire), vec![b"abc".to_vec(), vec![]]);
+
+    let codec = LengthFieldCodec::builder()
+        .width(Width::Three)
+        .endian(Endian::Little)
+        .build();
+    let wire = b"\x02\0\0hi";
+    assert_eq!(decode_all(&codec, wire), vec![wire.to_vec()]);
+
+    let codec = LengthFieldCodec::builder().width(Width::Varint).strip(2).build();
+    let mut wire = vec![0x80, 0x01];
+    wire.extend_from_slice(&[b'x'; 128]);
+    assert_eq!(decode_all(&codec, &wire), vec![vec![b'x'; 128]]);
+
+    // Bits past 64 are not dropped to make a length of 0.
+    let mut codec = LengthFieldCodec::builder().width(Width::Varint).build();
+    let mut data = ByteBuf::from(b"\x80\x80\x80\x80\x80\x80\x80\x80\x80\x02".to_vec());
+    let e = codec.decode(&mut data).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    assert!(e.get_ref().and_then(|e| e.downcast_ref::<FrameTooLong>()).is_none());
+}
+
+#[test]
+fn length_field_encode() {
+    let builders = vec![
+        LengthFieldCodec::builder()
+            .offset(1)
+            .width(Width::Two)
+            .adjustment(-3)
+            .strip(3)
+            .build(),
+        LengthFieldCodec::builder()
+            .width(Width::Eight)
+            .endian(Endian::Little)
+            .strip(8)
+            .build(),
+        LengthFieldCodec::builder().width(Width::Varint).build(),
+    ];
+    let mut body = b"\x07".to_vec();
+    body.extend_from_slice(&[b'y'; 200]);
+
+    let mut codec = builders[0];
+    let frame = encode(&mut codec, ByteBuf::from(body.clone())).unwrap();
+    assert_eq!(&frame.as_bytes()[..4], b"\x07\0\xCBy");
+    let mut frame = frame;
+    assert_eq!(codec.decode(&mut frame).unwrap().unwrap().as_bytes(), &body[1..]);
+
+    let mut codec = builders[1];
+    let (sink, _) = codec
+        .frames(Frames::default())
+        .send_all(stream::iter_ok::<_, io::Error>(vec![ByteBuf::from(body.clone())]))
+        .wait()
+        .unwrap();
+    let frames = sink.into_inner().sent;
+    let mut wire = frames.into_iter().next().unwrap();
+    assert_eq!(&wire.as_bytes()[..9], b"\xC9\0\0\0\0\0\0\0\x07");
+    assert_eq!(codec.decode(&mut wire).unwrap().unwrap().as_bytes(), &body[..]);
+
+    let mut codec = builders[2];
+    let frame = encode(&mut codec, ByteBuf::from(body.clone())).unwrap();
+    assert_eq!(&frame.as_bytes()[..3], b"\xC9\x01\x07");
+}
+
+#[test]
+fn length_field_errors() {
+    let mut codec = LengthFieldCodec::builder()
+        .width(Width::One)
+        .max_frame_len(10)
+        .build();
+    let e = encode(&mut codec, ByteBuf::from(vec![0; 10])).unwrap_err();
+    let too_long = e.get_ref().and_then(|e| e.downcast_ref::<FrameTooLong>());
+    assert_eq!(too_long, Some(&FrameTooLong { len: 11, max: 10 }));
+
+    let mut wire = ByteBuf::from(vec![20]);
+    let e = codec.decode(&mut wire).unwrap_err();
+    let too_long = e.get_ref().and_then(|e| e.downcast_ref::<FrameTooLong>());
+    assert_eq!(too_long, Some(&FrameTooLong { len: 21, max: 10 }));
+
+    let mut codec = LengthFieldCodec::builder().width(Width::One).build();
+    let e = encode(&mut codec, ByteBuf::from(vec![0; 256])).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+
+    let mut codec = LengthFieldCodec::builder()
+        .width(Width::One)
+        .adjustment(-2)
+        .build();
+    let e = codec.decode(&mut ByteBuf::from(vec![1, 0])).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+    let codec = LengthFieldCodec::builder().width(Width::Two).build();
+    let wire = vec![ByteBuf::from(vec![0, 5, 1])];
+    let mut frames = codec.frames(stream::iter_ok::<_, io::Error>(wire)).wait();
+    assert_eq!(frames.next().unwrap().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+}
+
+fn collect<S>(frames: S) -> io::Result<Vec<Vec<u8>>>
+where
+    S: Stream<Item = ByteBuf, Error = io::Error>,
+{
+    frames.map(|frame| frame.as_bytes().into_owned()).collect().wait()
+}
+
+#[test]
+fn lines() {
+    let wire = b"HELO example.com\r\nMAIL FROM:<a@b>\n\r\nQUIT";
+    for size in 1..wire.len() {
+        let lines = collect(LineCodec::new().frames(chunks(wire, size))).unwrap();
+        let expected: Vec<&[u8]> = vec![b"HELO example.com", b"MAIL FROM:<a@b>", b"", b"QUIT"];
+        assert_eq!(lines, expected);
+    }
+
+    let mut codec = LineCodec::new();
+    codec.set_keep_line_end(true);
+    let lines = collect(codec.frames(chunks(b"a\r\nb\n", 1))).unwrap();
+    assert_eq!(lines, vec![b"a\r\n".to_vec(), b"b\n".to_vec()]);
+
+    let mut codec = LineCodec::new();
+    codec.set_max_len(4);
+    let lines = codec.clone().frames(chunks(b"1234\r\n", 1));
+    assert_eq!(collect(lines).unwrap(), vec![b"1234".to_vec()]);
+    let e = collect(codec.frames(chunks(b"12345678\r\n", 1))).unwrap_err();
+    let too_long = e.get_ref().and_then(|e| e.downcast_ref::<FrameTooLong>());
+    assert_eq!(too_long, Some(&FrameTooLong { len: 6, max: 4 }));
+
+    let mut codec = LineCodec::new();
+    let line = encode(&mut codec, ByteBuf::from(b"PING".to_vec())).unwrap();
+    assert_eq!(line.as_bytes().as_ref(), b"PING\r\n");
+    codec.set_crlf(false);
+    let line = encode(&mut codec, ByteBuf::from(b"PING".to_vec())).unwrap();
+    assert_eq!(line.as_bytes().as_ref(), b"PING\n");
+    let e = encode(&mut codec, ByteBuf::from(b"a\nb".to_vec())).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn delimiters() {
+    let wire = b"a;bb||ccc;";
+    for size in 1..wire.len() {
+        let codec = DelimiterCodec::any(&[b";", b"||"]);
+        let frames = collect(codec.frames(chunks(wire, size))).unwrap();
+        assert_eq!(frames, vec![b"a".to_vec(), b"bb".to_vec(), b"ccc".to_vec()]);
+    }
+
+    let mut codec = DelimiterCodec::new(b"\0\0");
+    codec.set_keep_delimiter(true);
+    let frames = collect(codec.clone().frames(chunks(b"x\0y\0\0z", 2))).unwrap();
+    assert_eq!(frames, vec![b"x\0y\0\0".to_vec(), b"z".to_vec()]);
+
+    let frame = encode(&mut codec, ByteBuf::from(b"abc".to_vec())).unwrap();
+    assert_eq!(frame.as_bytes().as_ref(), b"abc\0\0");
+    let e = encode(&mut codec, ByteBuf::from(b"a\0\0".to_vec())).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+
+    codec.set_max_len(3);
+    let e = collect(codec.frames(chunks(b"abcdef", 1))).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+}
+
+// Commands of a one-byte opcode and a big-endian u16 operand.
+#[derive(Debug, PartialEq)]
+enum Cmd {
+    Get(u16),
+    Put(u16),
+}
+
+struct CmdCodec;
+
+impl Decoder for CmdCodec {
+    type Item = Cmd;
+    type Error = io::Error;
+
+    fn decode(&mut self, data: &mut ByteBuf) -> io::Result<Option<Cmd>> {
+        if data.len() < 3 {
+            return Ok(None);
+        }
+        let op = data.read(ruyi::buf::codec::u8::read).unwrap();
+        let n = data.read(u16::big_endian::read).unwrap();
+        match op {
+            b'G' => Ok(Some(Cmd::Get(n))),
+            b'P' => Ok(Some(Cmd::Put(n))),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "Unknown opcode")),
+        }
+    }
+}
+
+impl Encoder for CmdCodec {
+    type Item = Cmd;
+    type Error = io::Error;
+
+    fn encode(&mut self, cmd: Cmd, dst: &mut ByteBuf) -> io::Result<()> {
+        let (op, n) = match cmd {
+            Cmd::Get(n) => (b'G', n),
+            Cmd::Put(n) => (b'P', n),
+        };
+        dst.append(&[op][..], u8s::append).unwrap();
+        dst.append(n, u16::big_endian::append).unwrap();
+        Ok(())
+    }
+}
+
+#[test]
+fn framed() {
+    let wire = b"G\x00\x01P\x01\x00G";
+    let framed = Framed::new(Duplex::new(chunks(wire, 2), Frames::default()), CmdCodec);
+    let (cmd, framed) = framed.into_future().wait().map_err(|(e, _)| e).unwrap();
+    assert_eq!(cmd, Some(Cmd::Get(1)));
+
+    let framed = framed.send(Cmd::Put(2)).wait().unwrap();
+    assert_eq!(framed.get_ref().get_ref().1.sent[0].as_bytes().as_ref(), b"P\x00\x02");
+
+    let mut cmds = Stream::wait(framed);
+    assert_eq!(cmds.next().unwrap().unwrap(), Cmd::Put(256));
+    let e = cmds.next().unwrap().unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+    assert!(cmds.next().is_none());
+
+    let mut cmds = Framed::new(chunks(b"X\0\0G\0\0", 3), CmdCodec).wait();
+    assert_eq!(cmds.next().unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert!(cmds.next().is_none());
+
+    // At most 3 bytes received but not decoded.
+    let mut framed = Framed::new(chunks(wire, 2), CmdCodec);
+    framed.set_limit(3);
+    let mut cmds = framed.wait();
+    let e = cmds.next().unwrap().unwrap_err();
+    let too_long = e.get_ref().and_then(|e| e.downcast_ref::<FrameTooLong>());
+    assert_eq!(too_long, Some(&FrameTooLong { len: 4, max: 3 }));
+}
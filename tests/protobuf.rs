@@ -0,0 +1,201 @@
+extern crate ruyi;
+
+use ruyi::buf::{ByteBuf, Error};
+use ruyi::buf::codec::protobuf::{self, bytes, int32, sint64, string, tag, uint32, Message, Tag,
+                                 WireType};
+
+// message Test {
+//   uint32 a = 1;
+//   string b = 2;
+//   Test c = 3;
+//   repeated int32 d = 4 [packed = true];
+//   repeated sint64 e = 5;
+// }
+#[derive(Debug, Default, PartialEq)]
+struct Test {
+    a: u32,
+    b: String,
+    c: Option<Box<Test>>,
+    d: Vec<i32>,
+    e: Vec<i64>,
+}
+
+impl Message for Test {
+    fn encode(&self, buf: &mut ByteBuf) -> Result<(), ()> {
+        if self.a != 0 {
+            buf.append(Tag::new(1, WireType::Varint), tag::append)?;
+            buf.append(self.a, uint32::append)?;
+        }
+        if !self.b.is_empty() {
+            buf.append(Tag::new(2, WireType::LengthDelimited), tag::append)?;
+            buf.append(self.b.as_str(), string::append)?;
+        }
+        if let Some(ref c) = self.c {
+            protobuf::append_message(3, c.as_ref(), buf)?;
+        }
+        protobuf::append_packed(4, &self.d, int32::append, buf)?;
+        for &e in &self.e {
+            buf.append(Tag::new(5, WireType::Varint), tag::append)?;
+            buf.append(e, sint64::append)?;
+        }
+        Ok(())
+    }
+
+    fn merge_field(&mut self, tag: Tag, buf: &mut ByteBuf) -> Result<(), Error> {
+        match (tag.field, tag.wire_type) {
+            (1, WireType::Varint) => self.a = buf.read(uint32::read)?,
+            (2, WireType::LengthDelimited) => self.b = buf.read(string::read)?,
+            (3, WireType::LengthDelimited) => {
+                let c = self.c.get_or_insert_with(Default::default);
+                protobuf::merge_message(c.as_mut(), buf)?;
+            }
+            (4, WireType::LengthDelimited) => protobuf::read_packed(buf, int32::read, &mut self.d)?,
+            (4, WireType::Varint) => self.d.push(buf.read(int32::read)?),
+            (5, WireType::Varint) => self.e.push(buf.read(sint64::read)?),
+            _ => protobuf::skip(tag, buf)?,
+        }
+        Ok(())
+    }
+}
+
+fn encode(msg: &Test) -> Vec<u8> {
+    let mut buf = ByteBuf::new();
+    msg.encode(&mut buf).unwrap();
+    buf.as_bytes().into_owned()
+}
+
+fn decode(data: &[u8]) -> Result<Test, Error> {
+    let mut buf = ByteBuf::with_capacity(data.len());
+    buf.append(data, ruyi::buf::codec::u8s::append).unwrap();
+    Test::decode(&mut buf)
+}
+
+#[test]
+fn protobuf_encoding() {
+    let a = Test {
+        a: 150,
+        ..Test::default()
+    };
+    assert_eq!(encode(&a), [0x08, 0x96, 0x01]);
+
+    let b = Test {
+        b: "testing".to_string(),
+        ..Test::default()
+    };
+    assert_eq!(encode(&b), b"\x12\x07testing");
+
+    let c = Test {
+        c: Some(Box::new(a)),
+        ..Test::default()
+    };
+    assert_eq!(encode(&c), [0x1A, 0x03, 0x08, 0x96, 0x01]);
+
+    let d = Test {
+        d: vec![3, 270, 86942],
+        ..Test::default()
+    };
+    assert_eq!(
+        encode(&d),
+        [0x22, 0x06, 0x03, 0x8E, 0x02, 0x9E, 0xA7, 0x05]
+    );
+
+    let e = Test {
+        e: vec![0, -1, 1, -2, ::std::i64::MIN],
+        ..Test::default()
+    };
+    let data = encode(&e);
+    assert_eq!(&data[..8], [0x28, 0x00, 0x28, 0x01, 0x28, 0x02, 0x28, 0x03]);
+    assert_eq!(decode(&data).unwrap(), e);
+
+    let neg = Test {
+        d: vec![-1],
+        ..Test::default()
+    };
+    assert_eq!(encode(&neg).len(), 12);
+
+    let all = Test {
+        a: 1,
+        b: "x".to_string(),
+        c: Some(Box::new(Test {
+            d: vec![-1, 0, 1],
+            ..Test::default()
+        })),
+        d: vec![7],
+        e: vec![-7],
+    };
+    assert_eq!(decode(&encode(&all)).unwrap(), all);
+}
+
+#[test]
+fn protobuf_decoding() {
+    // Unknown fields of every wire type, including a nested group.
+    let data = [
+        0x08, 0x96, 0x01, // a = 150
+        0x30, 0xAC, 0x02, // 6: varint
+        0x39, 1, 2, 3, 4, 5, 6, 7, 8, // 7: fixed64
+        0x42, 0x02, 0xFF, 0xFF, // 8: bytes
+        0x4B, 0x53, 0x54, 0x50, 0x01, 0x4C, // 9: group { 10: group {} 10: 1 }
+        0x5D, 1, 2, 3, 4, // 11: fixed32
+        0x12, 0x01, b'x', // b = "x"
+    ];
+    let t = decode(&data).unwrap();
+    assert_eq!(t.a, 150);
+    assert_eq!(t.b, "x");
+
+    // Unpacked and packed values of a packed field both decode, as does a
+    // message merged from two occurrences.
+    let data = [
+        0x20, 0x01, 0x22, 0x02, 0x02, 0x03, 0x1A, 0x02, 0x08, 0x01, 0x1A, 0x03, 0x12, 0x01, b'y',
+    ];
+    let t = decode(&data).unwrap();
+    assert_eq!(t.d, [1, 2, 3]);
+    let c = t.c.unwrap();
+    assert_eq!((c.a, c.b.as_str()), (1, "y"));
+
+    match decode(&[0x12, 0x07, b't']) {
+        Err(Error::Underflow) => (),
+        r => panic!("Unexpected {:?}", r),
+    }
+    match decode(&[0x4B, 0x54]) {
+        Err(Error::InvalidEncoding) => (),
+        r => panic!("Unexpected {:?}", r),
+    }
+    match decode(&[0x0C]) {
+        Err(Error::InvalidEncoding) => (),
+        r => panic!("Unexpected {:?}", r),
+    }
+    match decode(&[0x0F, 0x00]) {
+        Err(Error::InvalidEncoding) => (),
+        r => panic!("Unexpected {:?}", r),
+    }
+    match decode(&[0x00, 0x00]) {
+        Err(Error::InvalidEncoding) => (),
+        r => panic!("Unexpected {:?}", r),
+    }
+
+    let mut buf = ByteBuf::new();
+    buf.append(&b"\xFF\x00"[..], bytes::append).unwrap();
+    assert_eq!(buf.read(bytes::read).unwrap(), b"\xFF\x00");
+    assert!(buf.append(Tag::new(0, WireType::Varint), tag::append).is_err());
+    assert!(
+        buf.append(Tag::new(protobuf::MAX_FIELD + 1, WireType::Varint), tag::append)
+            .is_err()
+    );
+}
+
+#[test]
+fn protobuf_skip_nested_groups() {
+    // Groups nested deeper than the stack could recurse.
+    let mut buf = ByteBuf::from(vec![0x0B; 4_000_000]);
+    match protobuf::skip(Tag::new(1, WireType::StartGroup), &mut buf) {
+        Err(Error::Underflow) => (),
+        r => panic!("Unexpected {:?}", r),
+    }
+
+    let mut data = vec![0x0B; 100_000];
+    data.extend(vec![0x0C; 100_001]);
+    data.push(0x08);
+    let mut buf = ByteBuf::from(data);
+    protobuf::skip(Tag::new(1, WireType::StartGroup), &mut buf).unwrap();
+    assert_eq!(buf.as_bytes().as_ref(), [0x08]);
+}
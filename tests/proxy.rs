@@ -0,0 +1,223 @@
+extern crate futures;
+extern crate ruyi;
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::{self, Shutdown, SocketAddr};
+use std::thread;
+use std::time::Duration;
+
+use futures::{Future, Sink};
+
+use ruyi::{IntoTask, Task};
+use ruyi::buf::ByteBuf;
+use ruyi::net::tcp;
+use ruyi::proto::proxy::{self, tlv, Command, Header, Tlv, Version};
+use ruyi::service::tcp::server::{Handler, Server, Session};
+
+fn addr(s: &str) -> SocketAddr {
+    s.parse().unwrap()
+}
+
+#[test]
+fn proxy_v1() {
+    let wire = b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\nGET /";
+    for n in 0..47 {
+        assert!(proxy::parse(&wire[..n]).unwrap().is_none());
+    }
+    let (header, len) = proxy::parse(wire).unwrap().unwrap();
+    assert_eq!(len, 47);
+    assert_eq!(
+        header,
+        Header {
+            version: Version::V1,
+            command: Command::Proxy,
+            addrs: Some((addr("192.168.0.1:56324"), addr("192.168.0.11:443"))),
+            tlvs: Vec::new(),
+        }
+    );
+
+    let wire = b"PROXY TCP6 2001:db8::1 ::1 65535 80\r\n";
+    let (header, _) = proxy::parse(wire).unwrap().unwrap();
+    assert_eq!(header.source(), Some(addr("[2001:db8::1]:65535")));
+    assert_eq!(header.destination(), Some(addr("[::1]:80")));
+
+    let wire = b"PROXY UNKNOWN ffff::1 ffff::2 1 2\r\n";
+    let (header, len) = proxy::parse(wire).unwrap().unwrap();
+    assert_eq!((header.addrs, len), (None, wire.len()));
+
+    for wire in &[
+        &b"GET / HTTP/1.1\r\n"[..],
+        b"PROXY TCP5 1.1.1.1 2.2.2.2 1 2\r\n",
+        b"PROXY TCP4 1.1.1.1 2.2.2.2 1\r\n",
+        b"PROXY TCP4 1.1.1.1 2.2.2.2 1 2 3\r\n",
+        b"PROXY TCP4 ::1 2.2.2.2 1 2\r\n",
+        b"PROXY TCP4 1.1.1.1 2.2.2.2 01 2\r\n",
+        b"PROXY TCP4 1.1.1.1 2.2.2.2 65536 2\r\n",
+        b"PROXY TCP4  1.1.1.1 2.2.2.2 1 2\r\n",
+    ] {
+        let e = proxy::parse(wire).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidData);
+    }
+    let long: Vec<u8> = b"PROXY ".iter().cycle().take(108).cloned().collect();
+    let e = proxy::parse(&long).unwrap_err();
+    assert_eq!(e.to_string(), "PROXY v1 header too long");
+}
+
+#[test]
+fn proxy_v2() {
+    let mut wire = proxy::V2_SIGNATURE.to_vec();
+    wire.extend_from_slice(&[0x21, 0x11, 0x00, 0x14]);
+    wire.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2, 0x1F, 0x90, 0x00, 0x50]);
+    wire.extend_from_slice(&[tlv::AUTHORITY, 0x00, 0x05]);
+    wire.extend_from_slice(b"a.com");
+    wire.extend_from_slice(b"data");
+    for n in 0..36 {
+        assert!(proxy::parse(&wire[..n]).unwrap().is_none());
+    }
+    let (header, len) = proxy::parse(&wire).unwrap().unwrap();
+    assert_eq!(len, 36);
+    assert_eq!(
+        header,
+        Header {
+            version: Version::V2,
+            command: Command::Proxy,
+            addrs: Some((addr("10.0.0.1:8080"), addr("10.0.0.2:80"))),
+            tlvs: vec![
+                Tlv {
+                    kind: tlv::AUTHORITY,
+                    value: b"a.com".to_vec(),
+                },
+            ],
+        }
+    );
+    assert_eq!(header.tlv(tlv::AUTHORITY), Some(&b"a.com"[..]));
+    assert_eq!(header.tlv(tlv::ALPN), None);
+
+    let mut wire = proxy::V2_SIGNATURE.to_vec();
+    wire.extend_from_slice(&[0x21, 0x21, 0x00, 0x24]);
+    wire.extend_from_slice(&[0x20, 0x01, 0x0D, 0xB8]);
+    wire.extend_from_slice(&[0; 11]);
+    wire.push(1);
+    wire.extend_from_slice(&[0; 15]);
+    wire.push(1);
+    wire.extend_from_slice(&[0x01, 0xBB, 0x00, 0x50]);
+    let (header, _) = proxy::parse(&wire).unwrap().unwrap();
+    assert_eq!(
+        header.addrs,
+        Some((addr("[2001:db8::1]:443"), addr("[::1]:80")))
+    );
+
+    // The addresses of a health check from the proxy are ignored.
+    let mut wire = proxy::V2_SIGNATURE.to_vec();
+    wire.extend_from_slice(&[0x20, 0x11, 0x00, 0x0C]);
+    wire.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2, 0x1F, 0x90, 0x00, 0x50]);
+    let (header, _) = proxy::parse(&wire).unwrap().unwrap();
+    assert_eq!((header.command, header.addrs), (Command::Local, None));
+
+    for &(head, body) in &[
+        (&[0x31, 0x11, 0x00, 0x0C][..], &[0; 12][..]),
+        (&[0x22, 0x11, 0x00, 0x0C], &[0; 12]),
+        (&[0x21, 0x41, 0x00, 0x0C], &[0; 12]),
+        (&[0x21, 0x13, 0x00, 0x0C], &[0; 12]),
+        (&[0x21, 0x11, 0x00, 0x0B], &[0; 11]),
+        (&[0x21, 0x11, 0x00, 0x0E], &[0; 14]),
+        (&[0x21, 0x11, 0x00, 0x0F], &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1]),
+    ] {
+        let mut wire = proxy::V2_SIGNATURE.to_vec();
+        wire.extend_from_slice(head);
+        wire.extend_from_slice(body);
+        let e = proxy::parse(&wire).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidData);
+    }
+    let e = proxy::parse(b"\r\n\r\n\0\r\nQUIT\r").unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::InvalidData);
+}
+
+// Replies with the addresses of the session, then echoes what it receives.
+#[derive(Clone)]
+struct Echo;
+
+impl Handler for Echo {
+    fn handle(&mut self, session: Session) -> Option<Task> {
+        if session.proxy_header().and_then(|h| h.source()).is_some() {
+            assert_eq!(format!("{}", session), session.peer_addr().unwrap().to_string());
+        }
+        let addrs = format!(
+            "{} {}\n",
+            session.peer_addr().unwrap(),
+            session.local_addr().unwrap()
+        );
+        let (r, s) = tcp::split(session).unwrap();
+        Some(
+            s.send(ByteBuf::from(addrs.into_bytes()))
+                .and_then(|s| s.send_all(r))
+                .map_err(|e| panic!("{}", e))
+                .into_task(),
+        )
+    }
+}
+
+fn free_addr() -> SocketAddr {
+    net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+}
+
+fn connect(addr: &SocketAddr) -> net::TcpStream {
+    for _ in 0..100 {
+        if let Ok(conn) = net::TcpStream::connect(addr) {
+            return conn;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    panic!("Cannot connect to {}", addr);
+}
+
+fn exchange(addr: &SocketAddr, parts: &[&[u8]]) -> String {
+    let mut conn = connect(addr);
+    for part in parts {
+        conn.write_all(part).unwrap();
+        thread::sleep(Duration::from_millis(20));
+    }
+    conn.shutdown(Shutdown::Write).ok();
+    let mut received = String::new();
+    match conn.read_to_string(&mut received) {
+        Ok(..) => received,
+        Err(e) => match e.kind() {
+            ErrorKind::ConnectionReset => received,
+            _ => panic!("{}", e),
+        },
+    }
+}
+
+#[test]
+fn proxy_server() {
+    ruyi::net::init();
+    let addr = free_addr();
+    let mut server = Server::with_handler(Echo);
+    server.addr(addr).proxy_protocol(true).start().unwrap();
+
+    // The data in the same segment as the header is left to the handler.
+    let received = exchange(
+        &addr,
+        &[b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\nhello"],
+    );
+    assert_eq!(received, "192.168.0.1:56324 192.168.0.11:443\nhello");
+
+    // A header split across segments.
+    let mut v2 = proxy::V2_SIGNATURE.to_vec();
+    v2.extend_from_slice(&[0x21, 0x11, 0x00, 0x0C]);
+    v2.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2, 0x1F, 0x90, 0x00, 0x50]);
+    v2.extend_from_slice(b"bye");
+    let received = exchange(&addr, &[&v2[..5], &v2[5..20], &v2[20..]]);
+    assert_eq!(received, "10.0.0.1:8080 10.0.0.2:80\nbye");
+
+    // A v1 header of unknown addresses falls back to those of the connection.
+    let received = exchange(&addr, &[b"PROXY UNKNOWN\r\n"]);
+    assert!(received.starts_with("127.0.0.1:"));
+    assert!(received.ends_with(&format!(" {}\n", addr)));
+
+    // Connections without a valid header are dropped.
+    assert_eq!(exchange(&addr, &[b"GET / HTTP/1.1\r\n\r\n"]), "");
+}
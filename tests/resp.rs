@@ -0,0 +1,230 @@
+extern crate futures;
+extern crate ruyi;
+
+mod common;
+
+use std::collections::HashMap;
+use std::io;
+
+use futures::{future, Future, Stream};
+
+use ruyi::buf::ByteBuf;
+use ruyi::net::{tcp, TcpListener, TcpStream};
+use ruyi::proto::{Duplex, Encoder, FrameTooLong};
+use ruyi::proto::resp::{Client, RespCodec, Value};
+use ruyi::reactor;
+
+use common::decode;
+
+fn bulk(s: &str) -> Value {
+    Value::from(s)
+}
+
+fn encode(codec: &mut RespCodec, value: Value) -> Vec<u8> {
+    let mut data = ByteBuf::new();
+    codec.encode(value, &mut data).unwrap();
+    data.as_bytes().into_owned()
+}
+
+#[test]
+fn resp2() {
+    let wire = b"+OK\r\n-ERR unknown\r\n:-42\r\n$5\r\nhello\r\n$0\r\n\r\n$-1\r\n*-1\r\n\
+                 *3\r\n:1\r\n*2\r\n+a\r\n$1\r\nb\r\n*0\r\n";
+    for &size in &[1, 2, 7, wire.len()] {
+        let values = decode(&mut RespCodec::new(), wire, size).unwrap();
+        assert_eq!(
+            values,
+            vec![
+                Value::Simple("OK".to_owned()),
+                Value::Error("ERR unknown".to_owned()),
+                Value::Integer(-42),
+                bulk("hello"),
+                bulk(""),
+                Value::Null,
+                Value::Null,
+                Value::Array(vec![
+                    Value::Integer(1),
+                    Value::Array(vec![Value::Simple("a".to_owned()), bulk("b")]),
+                    Value::Array(vec![]),
+                ]),
+            ]
+        );
+    }
+
+    let mut codec = RespCodec::new();
+    let cmd = Value::command(&["SET", "key", "value"]);
+    assert_eq!(
+        encode(&mut codec, cmd),
+        &b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nvalue\r\n"[..]
+    );
+    assert_eq!(encode(&mut codec, Value::Null), b"$-1\r\n");
+    assert_eq!(encode(&mut codec, Value::Integer(7)), b":7\r\n");
+    assert_eq!(encode(&mut codec, Value::Error("ERR x".to_owned())), b"-ERR x\r\n");
+}
+
+#[test]
+fn resp3() {
+    let wire = b"_\r\n,1.5\r\n,-inf\r\n#t\r\n(3492890328409238509324850943850943825024385\r\n\
+                 !9\r\nERR oops!\r\n=15\r\ntxt:Some string\r\n%2\r\n+a\r\n:1\r\n+b\r\n:2\r\n\
+                 ~1\r\n#f\r\n|1\r\n+ttl\r\n:3600\r\n:42\r\n>2\r\n+message\r\n$2\r\nhi\r\n";
+    let expected = || {
+        vec![
+            Value::Null,
+            Value::Double(1.5),
+            Value::Double(std::f64::NEG_INFINITY),
+            Value::Boolean(true),
+            Value::BigNumber("3492890328409238509324850943850943825024385".to_owned()),
+            Value::BulkError(ByteBuf::from(b"ERR oops!".to_vec())),
+            Value::Verbatim("txt".to_owned(), ByteBuf::from(b"Some string".to_vec())),
+            Value::Map(vec![
+                (Value::Simple("a".to_owned()), Value::Integer(1)),
+                (Value::Simple("b".to_owned()), Value::Integer(2)),
+            ]),
+            Value::Set(vec![Value::Boolean(false)]),
+            Value::Attribute(
+                vec![(Value::Simple("ttl".to_owned()), Value::Integer(3600))],
+                Box::new(Value::Integer(42)),
+            ),
+            Value::Push(vec![Value::Simple("message".to_owned()), bulk("hi")]),
+        ]
+    };
+    for &size in &[1, 3, wire.len()] {
+        assert_eq!(decode(&mut RespCodec::new(), wire, size).unwrap(), expected());
+    }
+
+    let mut codec = RespCodec::new();
+    let e = codec
+        .encode(Value::Boolean(true), &mut ByteBuf::new())
+        .unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+
+    codec.set_resp3(true);
+    let mut encoded = Vec::new();
+    for value in expected() {
+        encoded.extend(encode(&mut codec, value));
+    }
+    assert_eq!(&encoded[..], &wire[..]);
+}
+
+#[test]
+fn resp_errors() {
+    let errors: &[&[u8]] = &[
+        b"?\r\n",
+        b"\r\n",
+        b":12a\r\n",
+        b"$-2\r\n",
+        b"$3\r\nabcd\r\n",
+        b"#x\r\n",
+        b"=3\r\ntxt\r\n",
+        b"(12.5\r\n",
+        b"%-1\r\n",
+    ];
+    for wire in errors {
+        let e = decode(&mut RespCodec::new(), wire, 1).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData, "{:?}", wire);
+    }
+
+    for wire in &[&b"*2\r\n:1\r\n"[..], b"$5\r\nhel", b"+OK"] {
+        let e = decode(&mut RespCodec::new(), wire, 1).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    let mut codec = RespCodec::new();
+    codec.set_max_bulk_len(10);
+    let e = decode(&mut codec, b"$11\r\n", 100).unwrap_err();
+    let too_long = e.get_ref().and_then(|e| e.downcast_ref::<FrameTooLong>());
+    assert_eq!(too_long, Some(&FrameTooLong { len: 11, max: 10 }));
+    assert!(decode(&mut codec, b"+0123456789ab", 1).is_err());
+
+    let e = codec
+        .encode(Value::Simple("a\r\nb".to_owned()), &mut ByteBuf::new())
+        .unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+}
+
+// Serves GET, SET and INCR from a map to the first connection.
+fn fake_redis(listener: TcpListener) -> impl Future<Item = (), Error = ()> {
+    listener
+        .incoming()
+        .unwrap()
+        .take(1)
+        .for_each(|(conn, _)| {
+            let (sink, stream) = tcp::framed(conn, RespCodec::new()).unwrap().split();
+            let mut store = HashMap::new();
+            let replies = stream.map(move |cmd| {
+                let args: Vec<Vec<u8>> = match cmd {
+                    Value::Array(args) => args.into_iter()
+                        .map(|arg| match arg {
+                            Value::Bulk(arg) => arg.as_bytes().into_owned(),
+                            _ => Vec::new(),
+                        })
+                        .collect(),
+                    _ => return Value::Error("ERR protocol error".to_owned()),
+                };
+                match (&args[0][..], args.len()) {
+                    (b"GET", 2) => match store.get(&args[1]) {
+                        Some(v) => Value::Bulk(ByteBuf::from(Vec::clone(v))),
+                        None => Value::Null,
+                    },
+                    (b"SET", 3) => {
+                        store.insert(args[1].clone(), args[2].clone());
+                        Value::Simple("OK".to_owned())
+                    }
+                    (b"INCR", 2) => {
+                        let v = store.entry(args[1].clone()).or_insert_with(|| b"0".to_vec());
+                        let n = String::from_utf8_lossy(v).parse::<i64>().unwrap() + 1;
+                        *v = n.to_string().into_bytes();
+                        Value::Integer(n)
+                    }
+                    _ => Value::Error("ERR unknown command".to_owned()),
+                }
+            });
+            replies.forward(sink).map(|_| ())
+        })
+        .map_err(|e| panic!("Fake server failed: {}", e))
+}
+
+#[test]
+fn resp_client() {
+    ruyi::net::init();
+    let listener = TcpListener::builder()
+        .addr("127.0.0.1:0".parse().unwrap())
+        .build()
+        .unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = fake_redis(listener);
+    let client = future::lazy(move || tcp::connect::<TcpStream>(&addr))
+        .and_then(|sender| {
+            let (r, s) = sender.into_twoway();
+            let cmds = (0..100)
+                .map(|_| Value::command(&["INCR", "n"]))
+                .chain(vec![
+                    Value::command(&["SET", "k", "v"]),
+                    Value::command(&["GET", "k"]),
+                    Value::command(&["GET", "missing"]),
+                ]);
+            Client::new(Duplex::new(r, s)).pipeline(cmds)
+        })
+        .and_then(|(client, mut replies)| {
+            client.call(Value::command(&["NOPE"])).map(move |(_, reply)| {
+                // The server is done once the client is dropped.
+                replies.push(reply);
+                replies
+            })
+        });
+    let test = server
+        .map_err(|()| unreachable!())
+        .join(client)
+        .map(|((), replies)| replies);
+    let replies = reactor::run(test).unwrap();
+
+    assert_eq!(replies.len(), 104);
+    for (i, reply) in replies.iter().take(100).enumerate() {
+        assert_eq!(*reply, Value::Integer(i as i64 + 1));
+    }
+    assert_eq!(replies[100], Value::Simple("OK".to_owned()));
+    assert_eq!(replies[101], bulk("v"));
+    assert_eq!(replies[102], Value::Null);
+    assert!(replies[103].is_error());
+}
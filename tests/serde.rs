@@ -0,0 +1,122 @@
+#![cfg(feature = "serde")]
+
+extern crate ruyi;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use serde::{Serialize, Serializer};
+
+use ruyi::buf::ByteBuf;
+use ruyi::buf::codec::u8s;
+use ruyi::buf::serde::{append, from_buf, read, Error};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Kind {
+    Ping,
+    Data(Vec<u8>),
+    Move { x: i32, y: i32 },
+    Pair(char, f64),
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Message {
+    id: u64,
+    delta: i16,
+    flag: bool,
+    name: String,
+    tags: Option<Vec<String>>,
+    attrs: HashMap<String, u32>,
+    kinds: Vec<Kind>,
+    unit: (),
+}
+
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Serialize for Bytes<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+fn message() -> Message {
+    let mut attrs = HashMap::new();
+    attrs.insert("ttl".to_string(), 300);
+    attrs.insert("weight".to_string(), 7);
+    Message {
+        id: 1 << 40,
+        delta: -3,
+        flag: true,
+        name: "ruyi 如意".to_string(),
+        tags: Some(vec!["a".to_string(), "".to_string(), "bc".to_string()]),
+        attrs,
+        kinds: vec![
+            Kind::Ping,
+            Kind::Data(vec![0, 1, 2, 0xff]),
+            Kind::Move { x: -1, y: 1 << 20 },
+            Kind::Pair('✓', 0.5),
+        ],
+        unit: (),
+    }
+}
+
+#[test]
+fn serde_layout() {
+    let mut buf = ByteBuf::new();
+    let n = buf.append(&(300u32, -2i32, "hi", Kind::Ping), append).unwrap();
+    assert_eq!(n, 7);
+    assert_eq!(buf.as_bytes().as_ref(), &[0xac, 0x02, 0x03, 0x02, b'h', b'i', 0x00]);
+}
+
+#[test]
+fn serde_round_trip() {
+    for growth in [1, 3, 4096].iter() {
+        let msg = message();
+        let mut buf = ByteBuf::with_growth(*growth);
+        let n = buf.append(&msg, append).unwrap();
+        assert_eq!(n, buf.len());
+        let decoded: Message = buf.read(read).unwrap();
+        assert_eq!(decoded, msg);
+        assert!(buf.is_empty());
+    }
+}
+
+#[test]
+fn serde_borrow() {
+    #[derive(Deserialize)]
+    struct Borrowed<'a> {
+        name: &'a str,
+        #[serde(borrow)]
+        data: Cow<'a, [u8]>,
+    }
+
+    let mut buf = ByteBuf::with_capacity(64);
+    buf.append(&("borrowed", Bytes(b"data")), append).unwrap();
+    let v: Borrowed = from_buf(&mut buf).unwrap();
+    assert_eq!(v.name, "borrowed");
+    match v.data {
+        Cow::Borrowed(data) => assert_eq!(data, b"data"),
+        Cow::Owned(..) => panic!("data should be borrowed"),
+    }
+}
+
+#[test]
+fn serde_errors() {
+    let mut buf = ByteBuf::new();
+    buf.append(&5u32, append).unwrap();
+    buf.append(&b"abc"[..], u8s::append).unwrap();
+    match buf.read::<String, _, _>(read) {
+        Err(Error::Buf(..)) => (),
+        v => panic!("Expect underflow, got {:?}", v),
+    }
+
+    let mut buf = ByteBuf::new();
+    buf.append(&[0xffu8, 0xfe][..], append).unwrap();
+    match buf.read::<String, _, _>(read) {
+        Err(Error::InvalidUtf8) => (),
+        v => panic!("Expect invalid UTF-8, got {:?}", v),
+    }
+}
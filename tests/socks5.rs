@@ -0,0 +1,313 @@
+extern crate futures;
+extern crate ruyi;
+
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{self, Shutdown, SocketAddr};
+use std::thread;
+use std::time::Duration;
+
+use futures::{future, Future, Sink, Stream};
+
+use ruyi::buf::ByteBuf;
+use ruyi::proto::socks5::{self, method, reply, Addr, AuthRequest, Command, Connector, Greeting,
+                          Reply, Request};
+use ruyi::reactor;
+use ruyi::service::socks5::Handler;
+use ruyi::service::tcp::Server;
+
+fn addr(s: &str) -> SocketAddr {
+    s.parse().unwrap()
+}
+
+fn encode<F>(encode: F) -> Vec<u8>
+where
+    F: FnOnce(&mut Vec<u8>) -> io::Result<()>,
+{
+    let mut buf = Vec::new();
+    encode(&mut buf).unwrap();
+    buf
+}
+
+#[test]
+fn socks5_messages() {
+    let wire = [5, 2, method::NO_AUTH, method::USERNAME_PASSWORD];
+    for n in 0..wire.len() {
+        assert!(Greeting::parse(&wire[..n]).unwrap().is_none());
+    }
+    let (greeting, len) = Greeting::parse(&wire).unwrap().unwrap();
+    assert_eq!(len, 4);
+    assert_eq!(encode(|buf| greeting.encode(buf)), wire);
+
+    let auth = AuthRequest {
+        username: "user".to_string(),
+        password: "pass".to_string(),
+    };
+    let wire = encode(|buf| auth.encode(buf));
+    assert_eq!(wire, b"\x01\x04user\x04pass");
+    for n in 0..wire.len() {
+        assert!(AuthRequest::parse(&wire[..n]).unwrap().is_none());
+    }
+    assert_eq!(AuthRequest::parse(&wire).unwrap(), Some((auth, 11)));
+
+    let req = Request {
+        command: Command::Connect,
+        addr: Addr::Domain("example.com".to_string(), 443),
+    };
+    let wire = encode(|buf| req.encode(buf));
+    assert_eq!(wire, b"\x05\x01\x00\x03\x0Bexample.com\x01\xBB");
+    for n in 0..wire.len() {
+        assert!(Request::parse(&wire[..n]).unwrap().is_none());
+    }
+    assert_eq!(Request::parse(&wire).unwrap(), Some((req, wire.len())));
+
+    let reply = Reply {
+        reply: reply::SUCCEEDED,
+        addr: Addr::from(addr("[::1]:1080")),
+    };
+    let wire = encode(|buf| reply.encode(buf));
+    assert_eq!(wire.len(), 22);
+    assert_eq!(Reply::parse(&wire).unwrap(), Some((reply, 22)));
+    assert!(Reply::new(reply::SUCCEEDED).to_error().is_none());
+    let e = Reply::new(reply::CONNECTION_REFUSED).to_error().unwrap();
+    assert_eq!(e.kind(), ErrorKind::ConnectionRefused);
+
+    let to = Addr::from(addr("10.0.0.1:53"));
+    let datagram = encode(|buf| socks5::encode_udp(&to, b"query", buf));
+    assert_eq!(
+        datagram,
+        b"\x00\x00\x00\x01\x0A\x00\x00\x01\x00\x35query"
+    );
+    assert_eq!(socks5::parse_udp(&datagram).unwrap(), (to, &b"query"[..]));
+
+    for wire in &[&[4, 1, 0][..], &[5, 0], &[5, 9, 0, 1, 1, 1, 1, 1, 0, 80], &[5, 1, 0, 2]] {
+        let e = Request::parse(wire).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidData);
+    }
+    let e = socks5::parse_udp(b"\x00\x00\x01\x01\x0A\x00\x00\x01\x00\x35").unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::InvalidData);
+    let long = Addr::Domain("a".repeat(256), 80);
+    assert!(long.encode(&mut Vec::new()).is_err());
+}
+
+fn read_exact(conn: &mut net::TcpStream, n: usize) -> Vec<u8> {
+    let mut buf = vec![0; n];
+    conn.read_exact(&mut buf).unwrap();
+    buf
+}
+
+fn append(mut data: Vec<u8>, buf: ByteBuf) -> io::Result<Vec<u8>> {
+    data.extend_from_slice(&buf.as_bytes());
+    Ok(data)
+}
+
+#[test]
+fn socks5_client() {
+    ruyi::net::init();
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        {
+            let mut conn = listener.accept().unwrap().0;
+            assert_eq!(read_exact(&mut conn, 4), [5, 2, 0, 2]);
+            conn.write_all(&[5, 2]).unwrap();
+            assert_eq!(read_exact(&mut conn, 7), b"\x01\x01u\x03pwd");
+            conn.write_all(&[1, 0]).unwrap();
+            assert_eq!(read_exact(&mut conn, 18), b"\x05\x01\x00\x03\x0Bexample.com\x00\x50");
+            // The data after the reply is left to the connection.
+            conn.write_all(b"\x05\x00\x00\x01\x0A\x00\x00\x01\x1F\x90hello")
+                .unwrap();
+            assert_eq!(read_exact(&mut conn, 4), b"ping");
+        }
+        {
+            let mut conn = listener.accept().unwrap().0;
+            assert_eq!(read_exact(&mut conn, 3), [5, 1, 0]);
+            conn.write_all(&[5, 0]).unwrap();
+            assert_eq!(read_exact(&mut conn, 10), [5, 3, 0, 1, 0, 0, 0, 0, 0, 0]);
+            conn.write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0x30, 0x39])
+                .unwrap();
+        }
+        {
+            let mut conn = listener.accept().unwrap().0;
+            read_exact(&mut conn, 3);
+            conn.write_all(&[5, 0]).unwrap();
+            read_exact(&mut conn, 10);
+            conn.write_all(&[5, 5, 0, 1, 0, 0, 0, 0, 0, 0]).unwrap();
+        }
+        {
+            let mut conn = listener.accept().unwrap().0;
+            read_exact(&mut conn, 4);
+            conn.write_all(&[5, 2]).unwrap();
+            read_exact(&mut conn, 7);
+            conn.write_all(&[1, 1]).unwrap();
+        }
+        {
+            let mut conn = listener.accept().unwrap().0;
+            read_exact(&mut conn, 3);
+            conn.write_all(&[5, 0xFF]).unwrap();
+        }
+    });
+
+    let mut connector = Connector::new(proxy);
+    connector.auth("u", "pwd");
+    let received = reactor::run(future::lazy(|| {
+        connector
+            .connect(Addr::Domain("example.com".to_string(), 80))
+            .and_then(|sender| sender.send(ByteBuf::from(b"ping".to_vec())))
+            .and_then(|sender| sender.into_recv().fold(Vec::new(), append))
+    })).unwrap();
+    assert_eq!(received, b"hello");
+
+    let connector = Connector::new(proxy);
+    let (_conn, relay) =
+        reactor::run(future::lazy(|| connector.udp_associate(addr("0.0.0.0:0")))).unwrap();
+    assert_eq!(relay, Addr::from(addr("127.0.0.1:12345")));
+
+    let e = reactor::run(future::lazy(|| connector.connect(addr("10.0.0.1:80"))))
+        .err()
+        .unwrap();
+    assert_eq!(e.kind(), ErrorKind::ConnectionRefused);
+
+    let mut connector = Connector::new(proxy);
+    connector.auth("u", "pwd");
+    let e = reactor::run(future::lazy(|| connector.connect(addr("10.0.0.1:80"))))
+        .err()
+        .unwrap();
+    assert_eq!(e.kind(), ErrorKind::PermissionDenied);
+
+    let connector = Connector::new(proxy);
+    let e = reactor::run(future::lazy(|| connector.connect(addr("10.0.0.1:80"))))
+        .err()
+        .unwrap();
+    assert_eq!(e.kind(), ErrorKind::PermissionDenied);
+
+    server.join().unwrap();
+}
+
+fn free_addr() -> SocketAddr {
+    net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+}
+
+fn connect(addr: &SocketAddr) -> net::TcpStream {
+    for _ in 0..100 {
+        if let Ok(conn) = net::TcpStream::connect(addr) {
+            return conn;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    panic!("Cannot connect to {}", addr);
+}
+
+// Writes `data` to the proxy and returns all it replies.
+fn exchange(proxy: &SocketAddr, data: &[u8]) -> Vec<u8> {
+    let mut conn = connect(proxy);
+    conn.write_all(data).unwrap();
+    conn.shutdown(Shutdown::Write).unwrap();
+    let mut received = Vec::new();
+    conn.read_to_end(&mut received).unwrap();
+    received
+}
+
+#[test]
+fn socks5_server() {
+    ruyi::net::init();
+    let echo = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let target = echo.local_addr().unwrap();
+    thread::spawn(move || {
+        for conn in echo.incoming() {
+            let mut conn = conn.unwrap();
+            let mut data = Vec::new();
+            conn.read_to_end(&mut data).unwrap();
+            conn.write_all(&data).unwrap();
+        }
+    });
+
+    let proxy = free_addr();
+    let mut handler = Handler::new();
+    handler.user("u", "pwd");
+    let mut server = Server::with_handler(handler);
+    server.addr(proxy).start().unwrap();
+    connect(&proxy);
+
+    let mut connector = Connector::new(proxy);
+    connector.auth("u", "pwd");
+    let received = reactor::run(future::lazy(move || {
+        connector
+            .connect(target)
+            .and_then(|sender| sender.send(ByteBuf::from(b"hello".to_vec())))
+            .and_then(|sender| {
+                sender.as_ref().shutdown(Shutdown::Write)?;
+                Ok(sender)
+            })
+            .and_then(|sender| sender.into_recv().fold(Vec::new(), append))
+    })).unwrap();
+    assert_eq!(received, b"hello");
+
+    // The request without the greeting and authentication of a client that
+    // knows them in advance.
+    let mut request = vec![5, 1, 2, 1, 1, b'u', 3, b'p', b'w', b'd'];
+    request.extend_from_slice(&[5, 1, 0, 1, 127, 0, 0, 1]);
+    request.push((target.port() >> 8) as u8);
+    request.push(target.port() as u8);
+    request.extend_from_slice(b"data");
+    let received = exchange(&proxy, &request);
+    assert_eq!(&received[..6], [5, 2, 1, 0, 5, 0]);
+    assert_eq!(&received[received.len() - 4..], b"data");
+
+    // No acceptable authentication method.
+    assert_eq!(exchange(&proxy, &[5, 1, 0]), [5, 0xFF]);
+    // A wrong password.
+    assert_eq!(
+        exchange(&proxy, &[5, 1, 2, 1, 1, b'u', 1, b'p']),
+        [5, 2, 1, 1]
+    );
+
+    let closed = free_addr();
+    for &(ref request, code) in &[
+        (Request {
+            command: Command::Connect,
+            addr: Addr::Domain("localhost".to_string(), 80),
+        }, reply::ADDRESS_TYPE_NOT_SUPPORTED),
+        (Request {
+            command: Command::Bind,
+            addr: Addr::from(target),
+        }, reply::COMMAND_NOT_SUPPORTED),
+        (Request {
+            command: Command::Connect,
+            addr: Addr::from(closed),
+        }, reply::CONNECTION_REFUSED),
+    ] {
+        let mut data = vec![5, 1, 2, 1, 1, b'u', 3, b'p', b'w', b'd'];
+        request.encode(&mut data).unwrap();
+        let received = exchange(&proxy, &data);
+        assert_eq!(&received[..5], [5, 2, 1, 0, 5]);
+        assert_eq!(received[5], code);
+    }
+}
+
+#[test]
+fn socks5_handshake_timeout() {
+    ruyi::net::init();
+    let proxy = free_addr();
+    let mut handler = Handler::new();
+    handler.handshake_timeout(1);
+    let mut server = Server::with_handler(handler);
+    server.addr(proxy).start().unwrap();
+
+    // A client that sends nothing is dropped.
+    let mut conn = connect(&proxy);
+    conn.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    let mut data = Vec::new();
+    conn.read_to_end(&mut data).unwrap();
+    assert!(data.is_empty());
+
+    // So is one that stops after the greeting.
+    let mut conn = connect(&proxy);
+    conn.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    conn.write_all(&[5, 1, 0]).unwrap();
+    let mut data = Vec::new();
+    conn.read_to_end(&mut data).unwrap();
+    assert_eq!(data, [5, 0]);
+}
@@ -0,0 +1,187 @@
+extern crate futures;
+extern crate ruyi;
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::mpsc;
+use std::thread;
+
+use futures::{Future, Sink, Stream};
+
+use ruyi::buf::ByteBuf;
+use ruyi::net::{tcp, TcpListener, TcpStream};
+use ruyi::proto::{Decoder, Duplex, Encoder, FrameTooLong};
+use ruyi::proto::http1::Request;
+use ruyi::proto::websocket::{self, close_code, CloseFrame, Frame, FrameCodec, Message, OpCode,
+                             Role};
+
+fn decode(codec: &mut FrameCodec, wire: &[u8]) -> io::Result<Vec<Frame>> {
+    let mut data = ByteBuf::from(wire.to_vec());
+    let mut frames = Vec::new();
+    while let Some(frame) = codec.decode(&mut data)? {
+        frames.push(frame);
+    }
+    assert!(data.is_empty());
+    Ok(frames)
+}
+
+#[test]
+fn websocket_handshake() {
+    assert_eq!(
+        websocket::accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+        "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+    );
+
+    let (request, key) = websocket::upgrade_request("example.com", "/chat");
+    assert!(websocket::is_upgrade(&request));
+    let response = websocket::accept_response(&request).unwrap();
+    assert_eq!(response.status, 101);
+    websocket::check_response(&response, &key).unwrap();
+    assert!(websocket::check_response(&response, "dGhlIHNhbXBsZSBub25jZQ==").is_err());
+
+    let e = websocket::accept_response(&Request::new("GET", "/chat")).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn websocket_frames() {
+    // The examples of RFC 6455, section 5.7.
+    let mut client = FrameCodec::new(Role::Client);
+    let frames = decode(&mut client, b"\x81\x05Hello\x01\x03Hel\x80\x02lo\x89\x05Hello").unwrap();
+    assert_eq!(frames.len(), 4);
+    assert_eq!((frames[0].fin, frames[0].opcode), (true, OpCode::Text));
+    assert_eq!(frames[0].payload.as_bytes().as_ref(), b"Hello");
+    assert_eq!((frames[1].fin, frames[1].opcode), (false, OpCode::Text));
+    assert_eq!((frames[2].fin, frames[2].opcode), (true, OpCode::Continuation));
+    assert_eq!(frames[3].opcode, OpCode::Ping);
+
+    let mut server = FrameCodec::new(Role::Server);
+    let frames = decode(&mut server, b"\x81\x85\x37\xfa\x21\x3d\x7f\x9f\x4d\x51\x58").unwrap();
+    assert_eq!(frames[0].payload.as_bytes().as_ref(), b"Hello");
+
+    let mut wire = ByteBuf::new();
+    server
+        .encode(Frame::new(OpCode::Binary, ByteBuf::from(vec![1; 256])), &mut wire)
+        .unwrap();
+    server
+        .encode(Frame::new(OpCode::Binary, ByteBuf::from(vec![2; 65536])), &mut wire)
+        .unwrap();
+    let wire = wire.as_bytes();
+    assert_eq!(&wire[..4], b"\x82\x7E\x01\x00");
+    assert_eq!(&wire[260..270], b"\x82\x7F\0\0\0\0\0\x01\0\0");
+    let frames = decode(&mut client, &wire).unwrap();
+    assert_eq!(frames[1].payload.len(), 65536);
+
+    // Masked by the client, unmasked by the server.
+    let mut wire = ByteBuf::new();
+    client
+        .encode(Frame::new(OpCode::Text, ByteBuf::from(b"Hello".to_vec())), &mut wire)
+        .unwrap();
+    assert_eq!(wire.len(), 11);
+    let frames = decode(&mut server, &wire.as_bytes()).unwrap();
+    assert_eq!(frames[0].payload.as_bytes().as_ref(), b"Hello");
+}
+
+#[test]
+fn websocket_frame_errors() {
+    let invalid: &[(Role, &[u8])] = &[
+        (Role::Server, b"\x81\x05Hello"),
+        (Role::Client, b"\x81\x85\x37\xfa\x21\x3d\x7f\x9f\x4d\x51\x58"),
+        (Role::Client, b"\xC1\x00"),
+        (Role::Client, b"\x83\x00"),
+        (Role::Client, b"\x09\x00"),
+        (Role::Client, b"\x89\x7E\x00\x7E"),
+    ];
+    for &(role, wire) in invalid {
+        let e = decode(&mut FrameCodec::new(role), wire).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData, "{:?}", wire);
+    }
+
+    let mut codec = FrameCodec::new(Role::Client);
+    codec.set_max_frame_len(100);
+    let e = decode(&mut codec, b"\x82\x7E\x01\x00").unwrap_err();
+    let too_long = e.get_ref().and_then(|e| e.downcast_ref::<FrameTooLong>());
+    assert_eq!(too_long, Some(&FrameTooLong { len: 256, max: 100 }));
+
+    let ping = Frame::new(OpCode::Ping, ByteBuf::from(vec![0; 126]));
+    let e = codec.encode(ping, &mut ByteBuf::new()).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+}
+
+// Echoes the data messages of the first connection to `tx`'s listener.
+fn echo_server(tx: mpsc::Sender<SocketAddr>) {
+    let listener = TcpListener::builder()
+        .addr("127.0.0.1:0".parse().unwrap())
+        .build()
+        .unwrap();
+    tx.send(listener.local_addr().unwrap()).unwrap();
+    let server = listener
+        .incoming()
+        .unwrap()
+        .into_future()
+        .map_err(|(e, _)| e)
+        .and_then(|(conn, _)| {
+            let (r, w) = tcp::split(conn.unwrap().0).unwrap();
+            websocket::accept(Duplex::new(r, w))
+        })
+        .and_then(|(ws, request)| {
+            assert_eq!(request.target, "/echo");
+            let (sink, stream) = ws.split();
+            stream
+                .filter(|msg| match *msg {
+                    Message::Text(..) | Message::Binary(..) => true,
+                    _ => false,
+                })
+                .forward(sink)
+                .map(|_| ())
+        });
+    ruyi::reactor::run(server).unwrap();
+}
+
+#[test]
+fn websocket_echo() {
+    ruyi::net::init();
+    let (tx, rx) = mpsc::channel();
+    let server = thread::spawn(move || echo_server(tx));
+    let addr = rx.recv().unwrap();
+
+    let client = tcp::connect::<TcpStream>(&addr)
+        .and_then(|sender| {
+            let (r, s) = sender.into_twoway();
+            websocket::connect(Duplex::new(r, s), "localhost", "/echo")
+        })
+        .and_then(|(mut ws, response)| {
+            assert_eq!(response.status, 101);
+            ws.set_max_fragment_len(1000);
+            ws.send(Message::Text("hello".to_owned()))
+        })
+        .and_then(|ws| ws.send(Message::Ping(ByteBuf::from(b"?".to_vec()))))
+        .and_then(|ws| ws.send(Message::Binary(ByteBuf::from(vec![7; 5000]))))
+        .and_then(|ws| {
+            ws.send(Message::Close(Some(CloseFrame {
+                code: close_code::NORMAL,
+                reason: "bye".to_owned(),
+            })))
+        })
+        .and_then(|ws| ws.collect());
+    let msgs = ruyi::reactor::run(client).unwrap();
+    server.join().unwrap();
+
+    assert_eq!(msgs.len(), 4);
+    match msgs[0] {
+        Message::Text(ref text) => assert_eq!(text, "hello"),
+        ref msg => panic!("Unexpected {:?}", msg),
+    }
+    match msgs[1] {
+        Message::Pong(ref data) => assert_eq!(data.as_bytes().as_ref(), b"?"),
+        ref msg => panic!("Unexpected {:?}", msg),
+    }
+    match msgs[2] {
+        Message::Binary(ref data) => assert_eq!(data.as_bytes().as_ref(), &[7; 5000][..]),
+        ref msg => panic!("Unexpected {:?}", msg),
+    }
+    match msgs[3] {
+        Message::Close(Some(ref close)) => assert_eq!(close.code, close_code::NORMAL),
+        ref msg => panic!("Unexpected {:?}", msg),
+    }
+}